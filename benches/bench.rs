@@ -1,17 +1,127 @@
-#![feature(test)]
-
-extern crate test;
+#[macro_use]
+extern crate criterion;
 extern crate piston_truetype;
 
+use criterion::{black_box, BenchmarkId, Criterion, Throughput};
 use piston_truetype::*;
+use std::ptr::null_mut;
+
+static FONT: &[u8] = include_bytes!("../assets/Tuffy_Bold.ttf");
 
-#[bench]
-fn font_initialization(bencher: &mut test::Bencher) {
-    let bs = include_bytes!("../tests/Tuffy_Bold.ttf");
-    let data = test::black_box(&bs[..]);
-    bencher.bytes = data.len() as u64;
-    bencher.iter(|| {
-        let f = FontInfo::new_with_offset(data, 0).ok().expect("Failed to load font");
-        test::black_box(f)
-    })
+fn font() -> FontInfo<'static> {
+    FontInfo::new_with_offset(FONT, 0).expect("Failed to load font")
 }
+
+// The glyphs of the printable ASCII characters.
+fn ascii_glyphs(font: &FontInfo) -> Vec<GlyphId> {
+    (32..127u8).map(|c| font.glyph_index_for_code(c as char)).collect()
+}
+
+fn font_initialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("font_initialization");
+    group.throughput(Throughput::Bytes(FONT.len() as u64));
+    group.bench_function("new_with_offset", |b| {
+        b.iter(|| FontInfo::new_with_offset(black_box(FONT), 0).unwrap())
+    });
+    group.finish();
+}
+
+fn glyph_decoding(c: &mut Criterion) {
+    let font = font();
+    let glyphs = ascii_glyphs(&font);
+    c.bench_function("glyph_outline", |b| {
+        b.iter(|| {
+            for &i in &glyphs {
+                black_box(font.glyph_outline(i).unwrap());
+            }
+        })
+    });
+    c.bench_function("glyph_path", |b| {
+        b.iter(|| {
+            for &i in &glyphs {
+                black_box(font.glyph_path(i, 0.05).unwrap());
+            }
+        })
+    });
+}
+
+fn rasterization(c: &mut Criterion) {
+    let font = font();
+    let i = font.glyph_index_for_code('@');
+    let mut group = c.benchmark_group("render_glyph");
+    for &pixel_height in &[12.0, 32.0, 96.0, 256.0] {
+        let scale = font.scale_for_pixel_height(pixel_height);
+        let size = pixel_height as usize * 2;
+        let mut target = vec![0; size * size];
+        group.bench_with_input(BenchmarkId::from_parameter(pixel_height), &scale, |b, &scale| {
+            b.iter(|| font.render_into(i, scale, &mut target, size, size, size, 0, size as i32 / 2))
+        });
+    }
+    group.finish();
+
+    let path = font.glyph_path(i, font.scale_for_pixel_height(96.0)).unwrap();
+    c.bench_function("rasterize_path", |b| {
+        b.iter(|| rasterize_path(black_box(&path), &RasterOptions::default()))
+    });
+}
+
+fn kerning(c: &mut Criterion) {
+    let font = font();
+    let glyphs = ascii_glyphs(&font);
+    let mut group = c.benchmark_group("kerning");
+    group.throughput(Throughput::Elements((glyphs.len() * glyphs.len()) as u64));
+    group.bench_function("ascii_pairs", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            for &left in &glyphs {
+                for &right in &glyphs {
+                    total += font.kerning(left, right) as i32;
+                }
+            }
+            total
+        })
+    });
+    let cache = font.build_kern_cache();
+    group.bench_function("ascii_pairs_cached", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            for &left in &glyphs {
+                for &right in &glyphs {
+                    total += cache.get(&(left, right)).cloned().unwrap_or(0) as i32;
+                }
+            }
+            total
+        })
+    });
+    group.finish();
+}
+
+fn packing(c: &mut Criterion) {
+    c.bench_function("bake_atlas", |b| {
+        b.iter(|| Atlas::bake(FONT, 0, 48.0, 1024, 1024, ' ', 95).unwrap())
+    });
+
+    let (w, h) = (1024, 1024);
+    let mut pixels = vec![0; w * h];
+    let mut chars = vec![PackedChar::default(); 95];
+    let mut group = c.benchmark_group("pack_ascii");
+    for &oversample in &[1, 2, 3] {
+        let settings = PackSettings { h_oversample: oversample, v_oversample: oversample,
+            ..PackSettings::default() };
+        group.bench_with_input(BenchmarkId::from_parameter(oversample), &settings, |b, &settings| {
+            b.iter(|| unsafe {
+                let mut range = PackRange::new(48.0, ' ', 95, chars.as_mut_ptr())
+                    .with_settings(settings);
+                let mut spc = PackContext::default();
+                pack_begin(&mut spc, pixels.as_mut_ptr(), w as isize, h as isize, 0, 1,
+                    null_mut());
+                pack_font_ranges(&mut spc, FONT, 0, &mut range, 1).unwrap();
+                pack_end(&mut spc);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, font_initialization, glyph_decoding, rasterization, kerning, packing);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+//! Bakes the printable ASCII characters of a font into a PNG texture and a
+//! metadata file, see `Atlas::bake_to`.
+//!
+//! ```sh
+//! cargo run --features image --example bake -- font.ttf 32 atlas
+//! ```
+//!
+//! writes `atlas.png` and `atlas.txt`.
+
+extern crate piston_truetype;
+
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use piston_truetype::Atlas;
+
+/// Prints `message` and `error` to stderr and exits with an error status.
+fn fail<E: fmt::Display>(message: &str, error: E) -> ! {
+    eprintln!("bake: {}: {}", message, error);
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() != 4 {
+        eprintln!("usage: bake <font.ttf> <pixel height> <output name>");
+        process::exit(2);
+    }
+    let pixel_height: f32 = args[2].parse()
+        .unwrap_or_else(|e| fail("the pixel height is not a number", e));
+
+    let mut data = vec![];
+    File::open(&args[1]).and_then(|mut file| file.read_to_end(&mut data))
+        .unwrap_or_else(|e| fail("can't read the font", e));
+    let atlas = Atlas::bake(&data, 0, pixel_height, 512, 512, ' ', 95)
+        .unwrap_or_else(|e| fail("can't load the font", e));
+    if atlas.len() < 95 {
+        eprintln!("bake: only {} characters fit into the atlas", atlas.len());
+    }
+
+    let name = &args[3];
+    atlas.bake_to(format!("{}.png", name), format!("{}.txt", name))
+        .unwrap_or_else(|e| fail("can't write the atlas", e));
+}
@@ -0,0 +1,119 @@
+//! Renders glyphs with FreeType and with the crate, and reports how much
+//! their coverage differs, as a measure of the quality of anti-aliasing.
+//!
+//! Needs FreeType installed, run from the crate root:
+//!
+//! ```sh
+//! cargo test --manifest-path freetype-comparison/Cargo.toml -- --nocapture
+//! ```
+
+extern crate freetype;
+extern crate piston_truetype;
+
+use freetype::face::LoadFlag;
+use piston_truetype::*;
+
+static FONT: &[u8] = include_bytes!("../../assets/Tuffy_Bold.ttf");
+
+/// Sizes in pixels per em, from hinted sizes to display ones.
+const SIZES: &[u32] = &[9, 12, 16, 24, 32, 48, 64, 128];
+
+/// How much the coverage of a size differs from FreeType.
+#[derive(Debug, Default)]
+struct Difference {
+    /// The pixels of either bitmap.
+    pixels: usize,
+    /// The sum of the differences of the pixels, from 0 to 255.
+    total: u64,
+    /// The largest difference of a pixel.
+    max: u8,
+    /// The pixels that differ by more than 1/16th.
+    visible: usize,
+    /// The coverage of all pixels of the crate and of FreeType.
+    coverage: (u64, u64),
+}
+
+impl Difference {
+    fn mean(&self) -> f64 {
+        self.total as f64 / self.pixels.max(1) as f64
+    }
+
+    /// Returns how much more the crate covers than FreeType, as a fraction.
+    fn coverage_error(&self) -> f64 {
+        (self.coverage.0 as f64 - self.coverage.1 as f64) / self.coverage.1.max(1) as f64
+    }
+
+    /// Adds the differences of a glyph, with the bitmaps placed at their
+    /// offsets from the origin.
+    fn add(&mut self, ours: &GrayBitmap, theirs: &GrayBitmap) {
+        let x0 = ours.x.min(theirs.x);
+        let y0 = ours.y.min(theirs.y);
+        let x1 = (ours.x + ours.width() as i32).max(theirs.x + theirs.width() as i32);
+        let y1 = (ours.y + ours.height() as i32).max(theirs.y + theirs.height() as i32);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (a, b) = (pixel(ours, x, y), pixel(theirs, x, y));
+                let d = if a > b { a - b } else { b - a };
+                self.pixels += 1;
+                self.total += d as u64;
+                self.max = self.max.max(d);
+                self.visible += (d > 16) as usize;
+                self.coverage.0 += a as u64;
+                self.coverage.1 += b as u64;
+            }
+        }
+    }
+}
+
+/// Returns the pixel of `bitmap` at `(x, y)` from the origin, 0 outside.
+fn pixel(bitmap: &GrayBitmap, x: i32, y: i32) -> u8 {
+    let (x, y) = (x - bitmap.x, y - bitmap.y);
+    if x < 0 || y < 0 || x as usize >= bitmap.width() || y as usize >= bitmap.height() {
+        return 0;
+    }
+    bitmap.row(y as usize)[x as usize]
+}
+
+/// Renders `glyph` of `face` without hinting.
+fn render_freetype(face: &freetype::Face, glyph: GlyphId) -> GrayBitmap {
+    face.load_glyph(glyph.0 as u32, LoadFlag::NO_HINTING | LoadFlag::RENDER).unwrap();
+    let slot = face.glyph();
+    let bitmap = slot.bitmap();
+    let (width, height) = (bitmap.width() as usize, bitmap.rows() as usize);
+    let mut result = GrayBitmap::new(slot.bitmap_left(), -slot.bitmap_top(), width, height);
+    for y in 0..height {
+        let row = y * bitmap.pitch() as usize;
+        result.as_mut_slice()[y * width..(y + 1) * width]
+            .copy_from_slice(&bitmap.buffer()[row..row + width]);
+    }
+    result
+}
+
+#[test]
+fn compare_with_freetype() {
+    let font = FontInfo::new_with_offset(FONT, 0).unwrap();
+    let library = freetype::Library::init().unwrap();
+    let face = library.new_memory_face(FONT.to_vec(), 0).unwrap();
+    let glyphs: Vec<GlyphId> = (33..127u8).map(|c| font.glyph_index_for_code(c as char)).collect();
+
+    println!("\n{:>5} {:>8} {:>5} {:>9} {:>10}", "ppem", "mean", "max", "visible", "coverage");
+    for &ppem in SIZES {
+        face.set_char_size(ppem as isize * 64, 0, 72, 72).unwrap();
+        let scale = font.scale_for_mapping_em_to_pixels(ppem as f32);
+        let mut difference = Difference::default();
+        for &glyph in &glyphs {
+            let ours = rasterize_path(&font.glyph_path(glyph, scale).unwrap(),
+                &RasterOptions::default());
+            difference.add(&ours, &render_freetype(&face, glyph));
+        }
+        println!("{:>5} {:>8.3} {:>5} {:>8.2}% {:>+9.3}%", ppem, difference.mean(),
+            difference.max, difference.visible as f64 * 100.0 / difference.pixels as f64,
+            difference.coverage_error() * 100.0);
+
+        // Both compute the exact area of the outline in each pixel, they
+        // differ by curves, which the crate flattens to lines at most 0.35
+        // pixels away like stb_truetype, so it covers up to about 1% less.
+        assert!(difference.mean() < 3.0);
+        assert!(difference.coverage_error().abs() < 0.02);
+    }
+}
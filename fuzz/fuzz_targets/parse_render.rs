@@ -0,0 +1,33 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate piston_truetype;
+
+use std::ptr::null_mut;
+use piston_truetype::*;
+
+// Parses the input as a font and renders some of its glyphs,
+// `parse_strict` must reject anything that could make rendering panic.
+fuzz_target!(|data: &[u8]| {
+    let font = match FontInfo::parse_strict(data) {
+        Ok(font) => font,
+        Err(_) => return,
+    };
+
+    unsafe {
+        let glyphs = "Ag0л".chars()
+            .map(|c| font.glyph_index_for_code(c))
+            .chain((0..64).map(GlyphId));
+        for glyph in glyphs {
+            let mut vertices = null_mut();
+            get_glyph_shape(&font, glyph, &mut vertices);
+            free_shape(&font, vertices);
+
+            let (mut w, mut h) = (0, 0);
+            let bitmap = get_glyph_bitmap_subpixel(&font, 0.05, 0.05, 0.25, 0.5, glyph,
+                &mut w, &mut h, null_mut(), null_mut());
+            free_bitmap(bitmap);
+
+            get_glyph_kern_advance(&font, glyph, GlyphId(glyph.0.wrapping_add(1)));
+        }
+    }
+});
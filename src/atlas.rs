@@ -0,0 +1,242 @@
+//! A safe owned atlas of baked characters.
+
+use prelude::*;
+use {aligned_stride, bake_font_bitmap, AlignedQuad, BakedChar, FontInfo, GlyphId, GrayBitmap,
+    PackedChar, Result};
+
+/// A range of characters baked into a single bitmap with
+/// `bake_font_bitmap`, for drawing text from a texture.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Atlas {
+    /// The bitmap with the glyphs, at the position `(0, 0)`.
+    pub bitmap: GrayBitmap,
+    pixel_height: f32,
+    first_char: u32,
+    chars: Vec<BakedChar>,
+    // Where the next glyph goes, the left and top of the free space in the
+    // current row and the bottom of the row.
+    shelf: (usize, usize, usize),
+}
+
+impl Atlas {
+    /// Bakes `num_chars` characters from `first_char` of the font at
+    /// `offset` in `data`, `pixel_height` pixels high, into a bitmap of
+    /// `width` by `height` pixels.
+    ///
+    /// The characters that don't fit are missing, see `len`.
+    pub fn bake(data: &[u8], offset: usize, pixel_height: f32, width: usize, height: usize,
+        first_char: char, num_chars: usize) -> Result<Atlas>
+    {
+        let mut bitmap = GrayBitmap::new(0, 0, width, height);
+        let mut chars = vec![BakedChar::default(); num_chars];
+        let baked = unsafe {
+            try!(bake_font_bitmap(data, offset, pixel_height, bitmap.as_mut_slice().as_mut_ptr(),
+                width as isize, height as isize, first_char, num_chars as isize,
+                chars.as_mut_ptr()))
+        };
+        // The number of characters that fit, negated, if not all did.
+        if baked <= 0 {
+            chars.truncate((-baked) as usize);
+        }
+        // The glyphs are baked in rows, continue after the last one.
+        let shelf = match chars.last() {
+            Some(last) => (last.x1 as usize + 1, last.y0 as usize,
+                chars.iter().map(|b| b.y1 as usize + 1).max().unwrap_or(1)),
+            None => (1, 1, 1),
+        };
+        Ok(Atlas {
+            bitmap: bitmap,
+            pixel_height: pixel_height,
+            first_char: first_char as u32,
+            chars: chars,
+            shelf: shelf,
+        })
+    }
+
+    /// Renders `glyph` of `font`, `pixel_height` pixels high like in `bake`,
+    /// into the free space of the atlas.
+    ///
+    /// Lets text renderers add the glyphs they encounter at runtime. The
+    /// atlas doesn't remember the glyph, keep the returned character to draw
+    /// it with `PackedChar::quad`.
+    ///
+    /// Returns `None` if the glyph doesn't fit.
+    pub fn insert_glyph(&mut self, font: &FontInfo, glyph: GlyphId, pixel_height: f32)
+        -> Option<PackedChar>
+    {
+        let width = self.bitmap.width();
+        insert_glyph(&mut self.bitmap, width, &mut self.shelf, font, glyph, pixel_height)
+    }
+
+    /// Returns the number of baked characters.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Returns `true` if no characters fit into the atlas.
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Returns the baked character `c`, if it is in the atlas.
+    pub fn baked_char(&self, c: char) -> Option<&BakedChar> {
+        (c as u32).checked_sub(self.first_char).and_then(|i| self.chars.get(i as usize))
+    }
+
+    /// Returns the baked characters with their character codes, in order.
+    pub fn chars<'a>(&'a self) -> impl Iterator<Item = (char, &'a BakedChar)> + 'a {
+        let first_char = self.first_char;
+        self.chars.iter().enumerate().filter_map(move |(i, b)| {
+            ::core::char::from_u32(first_char + i as u32).map(|c| (c, b))
+        })
+    }
+
+    /// Returns the quad to draw character `c` with its origin at
+    /// `(xpos, ypos)` with OpenGL and the `xpos` of the next character, see
+    /// `BakedChar::quad`.
+    pub fn quad(&self, c: char, xpos: f32, ypos: f32) -> Option<(AlignedQuad, f32)> {
+        let (width, height) = (self.bitmap.width(), self.bitmap.height());
+        self.baked_char(c).map(|b| b.quad(width, height, xpos, ypos, true))
+    }
+
+    /// Returns the quads to draw `text` on a line starting at `(xpos, ypos)`
+    /// with OpenGL, kerned with the kerning tables of `font`, the font the
+    /// atlas was baked from.
+    ///
+    /// The position of the pen keeps the fractions of the advances and the
+    /// kerning, only the quads are aligned to whole pixels. Characters that
+    /// aren't in the atlas are skipped.
+    pub fn quads<'a>(&'a self, font: &'a FontInfo, text: &'a str, xpos: f32, ypos: f32)
+        -> impl Iterator<Item = AlignedQuad> + 'a
+    {
+        let scale = font.scale_for_pixel_height(self.pixel_height);
+        let (width, height) = (self.bitmap.width(), self.bitmap.height());
+        let (mut xpos, mut previous) = (xpos, None);
+        text.chars().filter_map(move |c| {
+            let b = match self.baked_char(c) {
+                Some(b) => b,
+                None => return None,
+            };
+            let glyph = font.glyph_index_for_code(c);
+            if let Some(left) = previous {
+                xpos += scale * font.kerning(left, glyph) as f32;
+            }
+            previous = Some(glyph);
+            let (quad, next) = b.quad(width, height, xpos, ypos, true);
+            xpos = next;
+            Some(quad)
+        })
+    }
+}
+
+/// A texture of a `PagedAtlas`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AtlasPage {
+    /// The bitmap with the glyphs, at the position `(0, 0)`.
+    pub bitmap: GrayBitmap,
+    shelf: (usize, usize, usize),
+}
+
+/// An atlas of glyphs added on demand, that starts a new page when a page
+/// is full.
+///
+/// Every page is a bitmap of the same size, bind the texture of the page
+/// of a character, `PackedChar::page`, to draw it.
+///
+/// The rows of the pages can be aligned for uploading them to the GPU
+/// directly, see `with_stride_alignment`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PagedAtlas {
+    width: usize,
+    height: usize,
+    stride: usize,
+    pages: Vec<AtlasPage>,
+}
+
+impl PagedAtlas {
+    /// Returns an atlas without pages, whose pages are `width` by `height`
+    /// pixels.
+    pub fn new(width: usize, height: usize) -> PagedAtlas {
+        PagedAtlas { width: width, height: height, stride: width, pages: vec![] }
+    }
+
+    /// Returns the atlas with the rows of new pages starting at multiples of
+    /// `alignment` bytes, see `aligned_stride`.
+    ///
+    /// The bitmaps of the pages are as wide as the rows, with empty columns
+    /// on the right, so they can be uploaded as they are.
+    pub fn with_stride_alignment(self, alignment: usize) -> PagedAtlas {
+        PagedAtlas { stride: aligned_stride(self.width, alignment), ..self }
+    }
+
+    /// Returns the pages, in the order they were added.
+    pub fn pages(&self) -> &[AtlasPage] {
+        &self.pages
+    }
+
+    /// Renders `glyph` of `font`, `pixel_height` pixels high, into the last
+    /// page, or a new page if it is full, see `Atlas::insert_glyph`.
+    ///
+    /// Returns `None` if the glyph doesn't fit into an empty page.
+    pub fn insert_glyph(&mut self, font: &FontInfo, glyph: GlyphId, pixel_height: f32)
+        -> Option<PackedChar>
+    {
+        let width = self.width;
+        if let Some(c) = self.pages.last_mut().and_then(|page| {
+            insert_glyph(&mut page.bitmap, width, &mut page.shelf, font, glyph, pixel_height)
+        }) {
+            return Some(PackedChar { page: (self.pages.len() - 1) as u16, ..c });
+        }
+
+        let mut page = AtlasPage {
+            bitmap: GrayBitmap::new(0, 0, self.stride, self.height),
+            shelf: (1, 1, 1),
+        };
+        insert_glyph(&mut page.bitmap, width, &mut page.shelf, font, glyph, pixel_height).map(|c| {
+            self.pages.push(page);
+            PackedChar { page: (self.pages.len() - 1) as u16, ..c }
+        })
+    }
+}
+
+/// Renders a glyph into the free space of the left `width` columns of
+/// `bitmap`, the glyphs are put in rows with a pixel between them, like
+/// `bake_font_bitmap` does.
+fn insert_glyph(bitmap: &mut GrayBitmap, width: usize, shelf: &mut (usize, usize, usize),
+    font: &FontInfo, glyph: GlyphId, pixel_height: f32) -> Option<PackedChar>
+{
+    let scale = font.scale_for_pixel_height(pixel_height);
+    let bbox = font.glyph_data_for_glyph_at_index(glyph).bitmap_box(scale, scale)
+        .unwrap_or_default();
+    let (w, h) = ((bbox.x1 - bbox.x0) as usize, (bbox.y1 - bbox.y0) as usize);
+    let (height, stride) = (bitmap.height(), bitmap.width());
+
+    let (mut x, mut y, mut bottom) = *shelf;
+    if x + w + 1 >= width {
+        x = 1;
+        y = bottom;
+    }
+    if x + w + 1 >= width || y + h + 1 >= height {
+        return None;
+    }
+    font.render_into(glyph, scale, bitmap.as_mut_slice(), width, height, stride,
+        x as i32 - bbox.x0, y as i32 - bbox.y0);
+    bottom = bottom.max(y + h + 1);
+    *shelf = (x + w + 1, y, bottom);
+
+    Some(PackedChar {
+        x0: x as u16,
+        y0: y as u16,
+        x1: (x + w) as u16,
+        y1: (y + h) as u16,
+        xoff: bbox.x0 as f32,
+        yoff: bbox.y0 as f32,
+        xadvance: scale * font.hmetric_for_glyph_at_index(glyph).advance_width as f32,
+        xoff2: bbox.x1 as f32,
+        yoff2: bbox.y1 as f32,
+        page: 0,
+    })
+}
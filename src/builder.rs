@@ -0,0 +1,541 @@
+//! Building fonts from glyph outlines.
+
+use prelude::*;
+use alloc::collections::BTreeMap;
+use Error;
+use GlyphId;
+use Result;
+use subset::build_cmap;
+use utils;
+use byteorder::BigEndian;
+use utils::WriteBytesExt;
+
+/// A point of a glyph outline.
+///
+/// Two consecutive off-curve points have an implied on-curve point
+/// in the middle, like in the `glyf` table.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OutlinePoint {
+    pub x: i16,
+    pub y: i16,
+    /// `false` for the control point of a quadratic curve.
+    pub on_curve: bool,
+}
+
+impl OutlinePoint {
+    /// Returns a point on the outline.
+    pub fn on(x: i16, y: i16) -> OutlinePoint {
+        OutlinePoint { x: x, y: y, on_curve: true }
+    }
+
+    /// Returns a control point of a curve.
+    pub fn off(x: i16, y: i16) -> OutlinePoint {
+        OutlinePoint { x: x, y: y, on_curve: false }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Glyph {
+    contours: Vec<Vec<OutlinePoint>>,
+    advance_width: u16,
+}
+
+impl Glyph {
+    fn bounding_box(&self) -> Option<(i16, i16, i16, i16)> {
+        self.contours.iter().flat_map(|c| c.iter()).fold(None, |b, p| Some(match b {
+            None => (p.x, p.y, p.x, p.y),
+            Some((x0, y0, x1, y1)) => (x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)),
+        }))
+    }
+
+    fn num_points(&self) -> usize {
+        self.contours.iter().map(|c| c.len()).sum()
+    }
+
+    fn num_contours(&self) -> usize {
+        self.contours.iter().filter(|c| !c.is_empty()).count()
+    }
+
+    /// Returns the glyph in the `glyf` table format, without instructions.
+    /// Empty contours are left out.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if consecutive points are more than 32767
+    /// units apart.
+    fn bytes(&self) -> Result<Vec<u8>> {
+        const ON_CURVE_POINT: u8 = 1 << 0;
+        const X_SHORT_VECTOR: u8 = 1 << 1;
+        const Y_SHORT_VECTOR: u8 = 1 << 2;
+        const X_IS_SAME_OR_POSITIVE: u8 = 1 << 4;
+        const Y_IS_SAME_OR_POSITIVE: u8 = 1 << 5;
+
+        let (x0, y0, x1, y1) = match self.bounding_box() {
+            Some(b) => b,
+            None => return Ok(vec![]),
+        };
+
+        let contours: Vec<_> = self.contours.iter().filter(|c| !c.is_empty()).collect();
+        let mut data = vec![];
+        // `build` rejects glyphs with more contours than fit.
+        data.write_i16::<BigEndian>(contours.len() as i16).unwrap();
+        data.write_i16::<BigEndian>(x0).unwrap();
+        data.write_i16::<BigEndian>(y0).unwrap();
+        data.write_i16::<BigEndian>(x1).unwrap();
+        data.write_i16::<BigEndian>(y1).unwrap();
+        let mut end = 0;
+        for contour in &contours {
+            end += contour.len();
+            data.write_u16::<BigEndian>((end - 1) as u16).unwrap();
+        }
+        data.write_u16::<BigEndian>(0).unwrap(); // instructionLength
+
+        // Coordinates are stored as deltas from the previous point.
+        fn coordinate(delta: i32, short: u8, same_or_positive: u8,
+                      flag: &mut u8, out: &mut Vec<u8>) -> Result<()> {
+            if delta < i16::min_value() as i32 || delta > i16::max_value() as i32 {
+                return Err(Error::Malformed);
+            }
+            if delta == 0 {
+                *flag |= same_or_positive;
+            } else if delta.abs() < 256 {
+                *flag |= short;
+                if delta > 0 {
+                    *flag |= same_or_positive;
+                }
+                out.push(delta.abs() as u8);
+            } else {
+                out.write_i16::<BigEndian>(delta as i16).unwrap();
+            }
+            Ok(())
+        }
+
+        let (mut flags, mut xs, mut ys) = (vec![], vec![], vec![]);
+        let (mut x, mut y) = (0, 0);
+        for p in contours.iter().flat_map(|c| c.iter()) {
+            let mut flag = if p.on_curve { ON_CURVE_POINT } else { 0 };
+            try!(coordinate(p.x as i32 - x, X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE, &mut flag, &mut xs));
+            try!(coordinate(p.y as i32 - y, Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE, &mut flag, &mut ys));
+            flags.push(flag);
+            x = p.x as i32;
+            y = p.y as i32;
+        }
+        data.extend_from_slice(&flags);
+        data.extend_from_slice(&xs);
+        data.extend_from_slice(&ys);
+        Ok(data)
+    }
+}
+
+/// Builds a TrueType font out of glyph outlines.
+///
+/// Glyph 0 is displayed for characters the font doesn't map, so the first
+/// glyph added should be the `.notdef` glyph.
+#[derive(Debug, Clone)]
+pub struct FontBuilder {
+    family_name: String,
+    units_per_em: u16,
+    ascent: i16,
+    descent: i16,
+    line_gap: i16,
+    glyphs: Vec<Glyph>,
+    mapping: BTreeMap<u32, GlyphId>,
+}
+
+impl FontBuilder {
+    /// Returns a builder of a font named `family_name` with `units_per_em`
+    /// units in the em square.
+    ///
+    /// Ascent and descent default to 80% and 20% of the em.
+    pub fn new(family_name: &str, units_per_em: u16) -> FontBuilder {
+        FontBuilder {
+            family_name: family_name.to_owned(),
+            units_per_em: units_per_em,
+            ascent: (units_per_em as i32 * 4 / 5) as i16,
+            descent: -((units_per_em / 5) as i16),
+            line_gap: 0,
+            glyphs: vec![],
+            mapping: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the ascent, descent (typically negative) and line gap
+    /// of the font.
+    pub fn set_vertical_metrics(&mut self, ascent: i16, descent: i16, line_gap: i16) {
+        self.ascent = ascent;
+        self.descent = descent;
+        self.line_gap = line_gap;
+    }
+
+    /// Adds a glyph made of closed `contours` and returns it.
+    ///
+    /// The outer contours should go clockwise and holes counter-clockwise.
+    pub fn add_glyph(&mut self, contours: Vec<Vec<OutlinePoint>>, advance_width: u16) -> GlyphId {
+        self.glyphs.push(Glyph { contours: contours, advance_width: advance_width });
+        // Fonts with more glyphs than fit in a `GlyphId` fail to build.
+        GlyphId((self.glyphs.len() - 1) as u16)
+    }
+
+    /// Maps the character `code` to `glyph`.
+    pub fn map_code(&mut self, code: char, glyph: GlyphId) {
+        self.mapping.insert(code as u32, glyph);
+    }
+
+    /// Returns the font file.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if there are no glyphs, more than 65535
+    /// glyphs or points in a glyph, more than 32767 contours in a glyph,
+    /// consecutive points of a glyph more than 32767 units apart, or a
+    /// character is mapped to a glyph that doesn't exist.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        let num_glyphs = self.glyphs.len();
+        if num_glyphs == 0 || num_glyphs > 0xffff
+            || self.glyphs.iter().any(|g| g.num_points() > 0xffff || g.num_contours() > 0x7fff)
+            || self.mapping.values().any(|g| g.index() >= num_glyphs) {
+            return Err(Error::Malformed);
+        }
+
+        // glyf, loca & hmtx
+        let mut glyf = vec![];
+        let mut offsets = vec![];
+        let mut hmtx = vec![];
+        for glyph in &self.glyphs {
+            offsets.push(glyf.len() as u32);
+            glyf.extend_from_slice(&try!(glyph.bytes()));
+            while glyf.len() % 4 != 0 {
+                glyf.push(0);
+            }
+            let lsb = glyph.bounding_box().map_or(0, |b| b.0);
+            hmtx.write_u16::<BigEndian>(glyph.advance_width).unwrap();
+            hmtx.write_i16::<BigEndian>(lsb).unwrap();
+        }
+        offsets.push(glyf.len() as u32);
+
+        let long_offsets = glyf.len() > 0x1fffe;
+        let mut loca = vec![];
+        for &offset in &offsets {
+            if long_offsets {
+                loca.write_u32::<BigEndian>(offset).unwrap();
+            } else {
+                loca.write_u16::<BigEndian>((offset / 2) as u16).unwrap();
+            }
+        }
+
+        let bbox = self.glyphs.iter().filter_map(|g| g.bounding_box())
+            .fold(None, |a: Option<(i16, i16, i16, i16)>, b| Some(match a {
+                None => b,
+                Some(a) => (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)),
+            })).unwrap_or((0, 0, 0, 0));
+
+        let tables = vec![
+            (*b"cmap", self.cmap()),
+            (*b"glyf", glyf),
+            (*b"head", self.head(bbox, long_offsets)),
+            (*b"hhea", self.hhea()),
+            (*b"hmtx", hmtx),
+            (*b"loca", loca),
+            (*b"maxp", self.maxp()),
+            (*b"name", self.name()),
+            (*b"OS/2", self.os2()),
+            (*b"post", self.post()),
+        ];
+        Ok(utils::assemble_font(tables))
+    }
+
+    fn cmap(&self) -> Vec<u8> {
+        let mapping: Vec<_> = self.mapping.iter().map(|(&c, g)| (c, g.0)).collect();
+        build_cmap(&mapping)
+    }
+
+    fn head(&self, bbox: (i16, i16, i16, i16), long_offsets: bool) -> Vec<u8> {
+        let mut data = vec![];
+        data.write_u32::<BigEndian>(0x00010000).unwrap(); // version
+        data.write_u32::<BigEndian>(0x00010000).unwrap(); // fontRevision
+        data.write_u32::<BigEndian>(0).unwrap(); // checkSumAdjustment
+        data.write_u32::<BigEndian>(0x5F0F3CF5).unwrap(); // magicNumber
+        data.write_u16::<BigEndian>(0b11).unwrap(); // Baseline and left sidebearing at 0.
+        data.write_u16::<BigEndian>(self.units_per_em).unwrap();
+        data.write_i64::<BigEndian>(0).unwrap(); // created
+        data.write_i64::<BigEndian>(0).unwrap(); // modified
+        data.write_i16::<BigEndian>(bbox.0).unwrap();
+        data.write_i16::<BigEndian>(bbox.1).unwrap();
+        data.write_i16::<BigEndian>(bbox.2).unwrap();
+        data.write_i16::<BigEndian>(bbox.3).unwrap();
+        data.write_u16::<BigEndian>(0).unwrap(); // macStyle
+        data.write_u16::<BigEndian>(8).unwrap(); // lowestRecPPEM
+        data.write_i16::<BigEndian>(2).unwrap(); // fontDirectionHint
+        data.write_i16::<BigEndian>(if long_offsets { 1 } else { 0 }).unwrap();
+        data.write_i16::<BigEndian>(0).unwrap(); // glyphDataFormat
+        data
+    }
+
+    fn hhea(&self) -> Vec<u8> {
+        let mut advance_width_max = 0;
+        let mut min_lsb = 0;
+        let mut min_rsb = 0;
+        let mut x_max_extent = 0;
+        for (i, glyph) in self.glyphs.iter().filter(|g| g.bounding_box().is_some()).enumerate() {
+            let (x0, _, x1, _) = glyph.bounding_box().unwrap();
+            let rsb = glyph.advance_width as i32 - x1 as i32;
+            if i == 0 {
+                min_lsb = x0;
+                min_rsb = rsb;
+                x_max_extent = x1;
+            }
+            min_lsb = min_lsb.min(x0);
+            min_rsb = min_rsb.min(rsb);
+            x_max_extent = x_max_extent.max(x1);
+        }
+        for glyph in &self.glyphs {
+            advance_width_max = advance_width_max.max(glyph.advance_width);
+        }
+
+        let mut data = vec![];
+        data.write_u32::<BigEndian>(0x00010000).unwrap(); // version
+        data.write_i16::<BigEndian>(self.ascent).unwrap();
+        data.write_i16::<BigEndian>(self.descent).unwrap();
+        data.write_i16::<BigEndian>(self.line_gap).unwrap();
+        data.write_u16::<BigEndian>(advance_width_max).unwrap();
+        data.write_i16::<BigEndian>(min_lsb).unwrap();
+        data.write_i16::<BigEndian>(min_rsb as i16).unwrap();
+        data.write_i16::<BigEndian>(x_max_extent).unwrap();
+        data.write_i16::<BigEndian>(1).unwrap(); // caretSlopeRise
+        data.write_i16::<BigEndian>(0).unwrap(); // caretSlopeRun
+        data.write_i16::<BigEndian>(0).unwrap(); // caretOffset
+        data.extend_from_slice(&[0; 8]); // reserved
+        data.write_i16::<BigEndian>(0).unwrap(); // metricDataFormat
+        data.write_u16::<BigEndian>(self.glyphs.len() as u16).unwrap();
+        data
+    }
+
+    fn maxp(&self) -> Vec<u8> {
+        let max_points = self.glyphs.iter().map(|g| g.num_points()).max().unwrap_or(0);
+        let max_contours = self.glyphs.iter()
+            .map(|g| g.contours.iter().filter(|c| !c.is_empty()).count()).max().unwrap_or(0);
+
+        let mut data = vec![];
+        data.write_u32::<BigEndian>(0x00010000).unwrap(); // version
+        data.write_u16::<BigEndian>(self.glyphs.len() as u16).unwrap();
+        data.write_u16::<BigEndian>(max_points as u16).unwrap();
+        data.write_u16::<BigEndian>(max_contours as u16).unwrap();
+        data.write_u16::<BigEndian>(0).unwrap(); // maxCompositePoints
+        data.write_u16::<BigEndian>(0).unwrap(); // maxCompositeContours
+        data.write_u16::<BigEndian>(1).unwrap(); // maxZones, no twilight zone.
+        data.extend_from_slice(&[0; 2 * 8]); // No instructions.
+        data
+    }
+
+    /// Family, subfamily, full and PostScript names for Windows.
+    fn name(&self) -> Vec<u8> {
+        let postscript_name: String = self.family_name.chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+        let names = [
+            (1, self.family_name.clone()),
+            (2, "Regular".to_owned()),
+            (4, self.family_name.clone()),
+            (6, postscript_name),
+        ];
+
+        let mut strings = vec![];
+        let mut data = vec![];
+        data.write_u16::<BigEndian>(0).unwrap(); // format
+        data.write_u16::<BigEndian>(names.len() as u16).unwrap();
+        data.write_u16::<BigEndian>((6 + 12 * names.len()) as u16).unwrap();
+        for &(id, ref name) in &names {
+            let offset = strings.len();
+            for unit in name.encode_utf16() {
+                strings.write_u16::<BigEndian>(unit).unwrap();
+            }
+            data.write_u16::<BigEndian>(3).unwrap(); // Windows
+            data.write_u16::<BigEndian>(1).unwrap(); // Unicode BMP
+            data.write_u16::<BigEndian>(0x409).unwrap(); // English (US)
+            data.write_u16::<BigEndian>(id).unwrap();
+            data.write_u16::<BigEndian>((strings.len() - offset) as u16).unwrap();
+            data.write_u16::<BigEndian>(offset as u16).unwrap();
+        }
+        data.extend_from_slice(&strings);
+        data
+    }
+
+    /// Version 4 of the `OS/2` table.
+    fn os2(&self) -> Vec<u8> {
+        let advances: Vec<_> = self.glyphs.iter()
+            .filter(|g| g.advance_width > 0).map(|g| g.advance_width as u32).collect();
+        let avg_char_width = if advances.is_empty() {
+            0
+        } else {
+            advances.iter().sum::<u32>() / advances.len() as u32
+        };
+        let first_char = self.mapping.keys().next().map_or(0, |&c| c.min(0xffff));
+        let last_char = self.mapping.keys().last().map_or(0, |&c| c.min(0xffff));
+        let em = self.units_per_em as i32;
+
+        let mut data = vec![];
+        data.write_u16::<BigEndian>(4).unwrap(); // version
+        data.write_i16::<BigEndian>(avg_char_width as i16).unwrap();
+        data.write_u16::<BigEndian>(400).unwrap(); // usWeightClass, normal
+        data.write_u16::<BigEndian>(5).unwrap(); // usWidthClass, medium
+        data.write_u16::<BigEndian>(0).unwrap(); // fsType, installable
+        // Subscript and superscript sizes and offsets.
+        for &(size, offset) in &[(em * 13 / 20, -em / 10), (em * 13 / 20, em * 9 / 20)] {
+            data.write_i16::<BigEndian>(size as i16).unwrap();
+            data.write_i16::<BigEndian>(size as i16).unwrap();
+            data.write_i16::<BigEndian>(0).unwrap();
+            data.write_i16::<BigEndian>(offset as i16).unwrap();
+        }
+        data.write_i16::<BigEndian>((em / 20) as i16).unwrap(); // yStrikeoutSize
+        data.write_i16::<BigEndian>((em / 4) as i16).unwrap(); // yStrikeoutPosition
+        data.write_i16::<BigEndian>(0).unwrap(); // sFamilyClass
+        data.extend_from_slice(&[0; 10]); // panose
+        data.extend_from_slice(&[0; 16]); // ulUnicodeRange1-4
+        data.extend_from_slice(b"NONE"); // achVendID
+        data.write_u16::<BigEndian>(1 << 6).unwrap(); // fsSelection, regular
+        data.write_u16::<BigEndian>(first_char as u16).unwrap();
+        data.write_u16::<BigEndian>(last_char as u16).unwrap();
+        data.write_i16::<BigEndian>(self.ascent).unwrap();
+        data.write_i16::<BigEndian>(self.descent).unwrap();
+        data.write_i16::<BigEndian>(self.line_gap).unwrap();
+        data.write_u16::<BigEndian>(self.ascent.max(0) as u16).unwrap(); // usWinAscent
+        data.write_u16::<BigEndian>((-(self.descent as i32)).max(0) as u16).unwrap();
+        data.write_u32::<BigEndian>(1).unwrap(); // ulCodePageRange1, Latin 1
+        data.write_u32::<BigEndian>(0).unwrap(); // ulCodePageRange2
+        data.write_i16::<BigEndian>(0).unwrap(); // sxHeight
+        data.write_i16::<BigEndian>(0).unwrap(); // sCapHeight
+        data.write_u16::<BigEndian>(0).unwrap(); // usDefaultChar
+        data.write_u16::<BigEndian>(0x20).unwrap(); // usBreakChar
+        data.write_u16::<BigEndian>(1).unwrap(); // usMaxContext
+        data
+    }
+
+    /// Version 3.0 of the `post` table, without glyph names.
+    fn post(&self) -> Vec<u8> {
+        let mut data = vec![];
+        data.write_u32::<BigEndian>(0x00030000).unwrap(); // version
+        data.write_u32::<BigEndian>(0).unwrap(); // italicAngle
+        data.write_i16::<BigEndian>(-((self.units_per_em / 10) as i16)).unwrap();
+        data.write_i16::<BigEndian>((self.units_per_em / 20) as i16).unwrap();
+        data.write_u32::<BigEndian>(0).unwrap(); // isFixedPitch
+        data.extend_from_slice(&[0; 4 * 4]); // Memory usage is unknown.
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use FontInfo;
+    use FontCollection;
+    use tables::{HEAD, HHEA, MAXP, Serialize};
+    use expectest::prelude::*;
+
+    fn builder() -> FontBuilder {
+        let mut builder = FontBuilder::new("Test Icons", 1000);
+        builder.add_glyph(vec![], 300);
+        let square = builder.add_glyph(vec![vec![
+            OutlinePoint::on(100, 0), OutlinePoint::on(100, 700),
+            OutlinePoint::on(700, 700), OutlinePoint::on(700, 0),
+        ], vec![
+            OutlinePoint::on(300, 200), OutlinePoint::on(500, 200),
+            OutlinePoint::on(500, 500), OutlinePoint::on(300, 500),
+        ]], 800);
+        let drop = builder.add_glyph(vec![vec![
+            OutlinePoint::on(-20, 0), OutlinePoint::off(-20, 1200),
+            OutlinePoint::off(600, -400),
+        ]], 600);
+        builder.map_code('a', square);
+        builder.map_code('\u{e000}', square);
+        builder.map_code('\u{1f4a7}', drop);
+        builder
+    }
+
+    #[test]
+    fn smoke() {
+        let data = builder().build().unwrap();
+
+        let collection = FontCollection::from_data(&data).unwrap();
+        expect!(collection.validate_font_at_index(0)).to(be_ok().value(0));
+
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        expect!(font.glyph_index_for_code('a')).to(be_equal_to(GlyphId(1)));
+        expect!(font.glyph_index_for_code('\u{e000}')).to(be_equal_to(GlyphId(1)));
+        expect!(font.glyph_index_for_code('\u{1f4a7}')).to(be_equal_to(GlyphId(2)));
+        expect!(font.glyph_index_for_code('b')).to(be_equal_to(GlyphId(0)));
+
+        expect!(font.offset_for_glyph_at_index(GlyphId(0))).to(be_none());
+        let glyph = font.glyph_data_for_glyph_at_index(GlyphId(1));
+        expect!(glyph.number_of_contours()).to(be_equal_to(2));
+        let bbox = glyph.bounding_box().unwrap();
+        expect!((bbox.x0, bbox.y0, bbox.x1, bbox.y1)).to(be_equal_to((100, 0, 700, 700)));
+
+        let hhea = utils::find_table_offset(&data, 0, b"hhea").unwrap().unwrap();
+        let hhea = HHEA::from_data(&data, hhea).unwrap();
+        expect!(hhea.ascent()).to(be_equal_to(800));
+        expect!(hhea.descent()).to(be_equal_to(-200));
+        expect!(hhea.num_of_long_hor_metrics()).to(be_equal_to(3));
+
+        let maxp = utils::find_table_offset(&data, 0, b"maxp").unwrap().unwrap();
+        let maxp = MAXP::from_data(&data, maxp).unwrap();
+        expect!(maxp.num_glyphs()).to(be_equal_to(3));
+        expect!(maxp.bytes().len()).to(be_equal_to(32));
+
+        let head = utils::find_table_offset(&data, 0, b"head").unwrap().unwrap();
+        let head = HEAD::from_data(&data, head).unwrap();
+        expect!(head.bounding_box().x0).to(be_equal_to(-20));
+        expect!(head.bounding_box().y1).to(be_equal_to(1200));
+    }
+
+    #[test]
+    fn outline_round_trip() {
+        let data = builder().build().unwrap();
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+
+        unsafe {
+            let mut vertices = ::std::ptr::null_mut();
+            let n = ::get_glyph_shape(&font, GlyphId(1), &mut vertices);
+            let points: Vec<_> = (0..n).map(|i| {
+                let v = *vertices.offset(i);
+                (v.type_ as u8, v.x, v.y)
+            }).collect();
+            ::free_shape(&font, vertices);
+            expect!(points).to(be_equal_to(vec![
+                (1, 100, 0), (2, 100, 700), (2, 700, 700), (2, 700, 0), (2, 100, 0),
+                (1, 300, 200), (2, 500, 200), (2, 500, 500), (2, 300, 500), (2, 300, 200),
+            ]));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        expect!(FontBuilder::new("Empty", 1000).build()).to(be_err().value(Malformed));
+
+        let mut builder = builder();
+        builder.map_code('b', GlyphId(3));
+        expect!(builder.build()).to(be_err().value(Malformed));
+
+        let mut far = FontBuilder::new("Far", 1000);
+        far.add_glyph(vec![vec![OutlinePoint::on(-20000, 0), OutlinePoint::on(20000, 0)]], 0);
+        expect!(far.build()).to(be_err().value(Malformed));
+
+        // The number of contours is stored as an i16, empty ones don't count.
+        let mut dots = FontBuilder::new("Dots", 1000);
+        let mut contours = vec![vec![OutlinePoint::on(0, 0)]; 0x7fff];
+        contours.push(vec![]);
+        dots.add_glyph(contours.clone(), 0);
+        expect!(dots.build()).to(be_ok());
+        contours.push(vec![OutlinePoint::on(0, 0)]);
+        dots.add_glyph(contours, 0);
+        expect!(dots.build()).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn empty_contours() {
+        let mut builder = FontBuilder::new("Gaps", 1000);
+        builder.add_glyph(vec![vec![], vec![OutlinePoint::on(0, 0), OutlinePoint::on(100, 100),
+            OutlinePoint::on(100, 0)], vec![]], 100);
+        let data = builder.build().unwrap();
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        expect!(font.glyph_data_for_glyph_at_index(GlyphId(0)).number_of_contours()).to(be_equal_to(1));
+        expect!(font.glyph_path(GlyphId(0), 1.0).unwrap().is_empty()).to(be_false());
+    }
+}
@@ -0,0 +1,317 @@
+//! A cache of rendered glyphs with a budget in bytes.
+
+#[cfg(feature = "std")]
+use prelude::*;
+use core::mem::size_of;
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::thread::{self, JoinHandle};
+use {FontInfo, GlyphId, GrayBitmap, RasterOptions, Result, rasterize_path};
+#[cfg(feature = "std")]
+use OwnedFontInfo;
+
+/// The counters of a `GlyphCache`, see `GlyphCache::stats`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheStats {
+    /// The number of glyphs found in the cache.
+    pub hits: u64,
+    /// The number of glyphs that had to be rendered.
+    pub misses: u64,
+    /// The bytes the cached bitmaps take now.
+    pub bytes: usize,
+    /// The number of bitmaps dropped to stay within the budget.
+    pub evictions: u64,
+}
+
+/// Keeps the bitmaps of the glyphs of a font rendered at some sizes, and
+/// drops those used least recently when they take more than a budget.
+///
+/// The glyphs are looked up by index and scale only, so a cache must
+/// only be used with one font.
+#[derive(Debug, Clone)]
+pub struct GlyphCache {
+    budget: usize,
+    // The bitmaps by glyph and the bits of the scale, with the tick they
+    // were last used at.
+    glyphs: BTreeMap<(GlyphId, u32), (u64, GrayBitmap)>,
+    // The keys of the bitmaps by the tick they were last used at.
+    ticks: BTreeMap<u64, (GlyphId, u32)>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl GlyphCache {
+    /// Returns an empty cache keeping bitmaps of up to `budget` bytes.
+    pub fn new(budget: usize) -> GlyphCache {
+        GlyphCache {
+            budget: budget,
+            glyphs: BTreeMap::new(),
+            ticks: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the budget in bytes.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Sets the budget in bytes, dropping bitmaps if they take more.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict(0);
+    }
+
+    /// Returns the number of bitmaps in the cache.
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    /// Returns `true` if the cache has no bitmaps.
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Returns the number of hits, misses and evictions so far, and the
+    /// bytes the bitmaps take.
+    ///
+    /// The bytes count the pixels and the bookkeeping of every bitmap, so
+    /// they can be compared with the budget.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Sets the hits, misses and evictions back to 0, keeping the bitmaps.
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats { bytes: self.stats.bytes, ..CacheStats::default() };
+    }
+
+    /// Drops all bitmaps. The counters are kept.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.ticks.clear();
+        self.stats.bytes = 0;
+    }
+
+    /// Returns the bitmap of `glyph` of `font` rendered at `scale`,
+    /// rendering it if it isn't in the cache.
+    ///
+    /// A bitmap larger than the budget is returned, but all others are
+    /// dropped to make room for it, and it's dropped by the next miss.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if the glyph can't be read, which isn't
+    /// cached.
+    pub fn get(&mut self, font: &FontInfo, glyph: GlyphId, scale: f32) -> Result<&GrayBitmap> {
+        let key = (glyph, scale.to_bits());
+        self.tick += 1;
+        if let Some(&mut (ref mut tick, _)) = self.glyphs.get_mut(&key) {
+            self.ticks.remove(tick);
+            *tick = self.tick;
+            self.ticks.insert(self.tick, key);
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+            let bitmap = try!(render(font, glyph, scale));
+            self.insert(key, bitmap);
+        }
+        Ok(&self.glyphs[&key].1)
+    }
+
+    /// Renders the glyphs of `chars` in `font` at `scale` that aren't in
+    /// the cache, so that drawing them later doesn't stall, e.g. the
+    /// glyphs of a game's HUD before a level starts.
+    ///
+    /// Rendering them doesn't count as misses. If the glyphs take more
+    /// than the budget, those rendered first are dropped.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if a glyph can't be read, keeping the
+    /// glyphs rendered before it.
+    pub fn prewarm<I>(&mut self, font: &FontInfo, scale: f32, chars: I) -> Result<()>
+        where I: IntoIterator<Item = char>
+    {
+        for c in chars {
+            let key = (font.glyph_index_for_code(c), scale.to_bits());
+            if !self.glyphs.contains_key(&key) {
+                self.tick += 1;
+                let bitmap = try!(render(font, key.0, scale));
+                self.insert(key, bitmap);
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts rendering the glyphs of `chars` in `font` at `scale` on
+    /// another thread, like `prewarm`, for `add_prewarmed` to add them to
+    /// a cache.
+    #[cfg(feature = "std")]
+    pub fn prewarm_in_background<I>(font: Arc<OwnedFontInfo>, scale: f32, chars: I) -> Prewarm
+        where I: IntoIterator<Item = char>
+    {
+        let mut glyphs: Vec<GlyphId> = chars.into_iter().map(|c| font.glyph_index_for_code(c))
+            .collect();
+        glyphs.sort();
+        glyphs.dedup();
+        let thread = thread::spawn(move || {
+            let mut bitmaps = Vec::with_capacity(glyphs.len());
+            for glyph in glyphs {
+                bitmaps.push((glyph, try!(render(&font, glyph, scale))));
+            }
+            Ok(bitmaps)
+        });
+        Prewarm { scale: scale, thread: thread }
+    }
+
+    /// Adds the glyphs `prewarm` rendered, waiting for it to finish if it
+    /// hasn't, see `Prewarm::is_finished`. Glyphs already in the cache
+    /// are kept, and the glyphs don't count as misses.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if a glyph couldn't be read, adding none.
+    #[cfg(feature = "std")]
+    pub fn add_prewarmed(&mut self, prewarm: Prewarm) -> Result<()> {
+        let bitmaps = try!(prewarm.thread.join().unwrap_or_else(|e| ::std::panic::resume_unwind(e)));
+        for (glyph, bitmap) in bitmaps {
+            let key = (glyph, prewarm.scale.to_bits());
+            if !self.glyphs.contains_key(&key) {
+                self.tick += 1;
+                self.insert(key, bitmap);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds `bitmap` as used at the current tick, dropping others to make
+    /// room for it.
+    fn insert(&mut self, key: (GlyphId, u32), bitmap: GrayBitmap) {
+        let bytes = entry_bytes(&bitmap);
+        self.evict(bytes);
+        self.glyphs.insert(key, (self.tick, bitmap));
+        self.ticks.insert(self.tick, key);
+        self.stats.bytes += bytes;
+    }
+
+    /// Drops the bitmaps used least recently until `bytes` more fit into
+    /// the budget, or the cache is empty.
+    fn evict(&mut self, bytes: usize) {
+        while self.stats.bytes + bytes > self.budget {
+            let (tick, key) = match self.ticks.iter().next() {
+                Some((&tick, &key)) => (tick, key),
+                None => break,
+            };
+            self.ticks.remove(&tick);
+            if let Some((_, bitmap)) = self.glyphs.remove(&key) {
+                self.stats.bytes -= entry_bytes(&bitmap);
+            }
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+/// Glyphs being rendered on another thread, see
+/// `GlyphCache::prewarm_in_background`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Prewarm {
+    scale: f32,
+    thread: JoinHandle<Result<Vec<(GlyphId, GrayBitmap)>>>,
+}
+
+#[cfg(feature = "std")]
+impl Prewarm {
+    /// Returns `true` if all glyphs were rendered, so that
+    /// `GlyphCache::add_prewarmed` won't wait.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
+
+/// Returns the bitmap of `glyph` of `font` at `scale`.
+fn render(font: &FontInfo, glyph: GlyphId, scale: f32) -> Result<GrayBitmap> {
+    Ok(rasterize_path(&try!(font.glyph_path(glyph, scale)), &RasterOptions::default()))
+}
+
+/// Returns the bytes a cached `bitmap` takes.
+fn entry_bytes(bitmap: &GrayBitmap) -> usize {
+    bitmap.as_slice().len() + size_of::<((GlyphId, u32), (u64, GrayBitmap))>()
+        + size_of::<(u64, (GlyphId, u32))>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+    use utils::read_file;
+
+    #[test]
+    fn hits_misses_and_evictions() {
+        let data = read_file("assets/Tuffy_Bold.ttf");
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        let (a, b) = (font.glyph_index_for_code('a'), font.glyph_index_for_code('b'));
+
+        let mut cache = GlyphCache::new(1 << 20);
+        let bitmap = cache.get(&font, a, 0.02).unwrap().clone();
+        expect!(cache.get(&font, a, 0.02).unwrap()).to(be_equal_to(&bitmap));
+        cache.get(&font, b, 0.02).unwrap();
+        cache.get(&font, a, 0.03).unwrap();
+        cache.get(&font, a, 0.02).unwrap();
+        let stats = cache.stats();
+        expect!((stats.hits, stats.misses, stats.evictions)).to(be_equal_to((2, 3, 0)));
+        expect!(stats.bytes > 2 * entry_bytes(&bitmap)).to(be_true());
+        expect!(cache.len()).to(be_equal_to(3));
+
+        // 'b' goes first, being used least recently.
+        cache.set_budget(stats.bytes - 1);
+        expect!(cache.stats().evictions).to(be_equal_to(1));
+        cache.get(&font, a, 0.02).unwrap();
+        cache.get(&font, a, 0.03).unwrap();
+        expect!(cache.stats().hits).to(be_equal_to(4));
+        cache.get(&font, b, 0.02).unwrap();
+        expect!(cache.stats().misses).to(be_equal_to(4));
+        expect!(cache.stats().bytes <= cache.budget()).to(be_true());
+
+        // Too large for the budget, but returned anyway.
+        cache.set_budget(0);
+        expect!(cache.is_empty()).to(be_true());
+        expect!(cache.get(&font, a, 0.02).unwrap()).to(be_equal_to(&bitmap));
+        expect!(cache.len()).to(be_equal_to(1));
+
+        cache.reset_stats();
+        expect!(cache.stats().hits).to(be_equal_to(0));
+        cache.clear();
+        expect!(cache.stats().bytes).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn prewarm() {
+        let data = read_file("assets/Tuffy_Bold.ttf");
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        let mut cache = GlyphCache::new(1 << 20);
+        cache.prewarm(&font, 0.02, "Score: 0".chars()).unwrap();
+        expect!(cache.len()).to(be_equal_to(8));
+        expect!(cache.stats().misses).to(be_equal_to(0));
+        for c in "Score: 0".chars() {
+            cache.get(&font, font.glyph_index_for_code(c), 0.02).unwrap();
+        }
+        expect!(cache.stats().hits).to(be_equal_to(8));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn prewarm_in_background() {
+        let data = read_file("assets/Tuffy_Bold.ttf");
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        let mut cache = GlyphCache::new(1 << 20);
+        cache.prewarm(&font, 0.02, "Score: 0".chars()).unwrap();
+        let prewarm = GlyphCache::prewarm_in_background(Arc::new(font.clone().into_owned()), 0.03,
+            "Score: 0".chars());
+        cache.add_prewarmed(prewarm).unwrap();
+        expect!(cache.len()).to(be_equal_to(16));
+        expect!(cache.stats().misses).to(be_equal_to(0));
+    }
+}
@@ -0,0 +1,468 @@
+use prelude::*;
+use Error;
+use Result;
+use tables::{HEAD, MacStyle, NAME, OS2, TTCF};
+use types::FontKind;
+use utils;
+
+/// A font of a family in a collection, see `FontCollection::family_members`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FaceInfo {
+    /// The index of the font in the collection.
+    pub index: usize,
+    /// The weight from 1 to 1000, 400 is regular and 700 bold.
+    pub weight: u16,
+    /// Whether the font is italic or oblique.
+    pub italic: bool,
+}
+
+impl FaceInfo {
+    /// Returns `true` if the font is bold or heavier, so that it needn't be
+    /// emboldened.
+    pub fn is_bold(&self) -> bool {
+        self.weight >= 600
+    }
+}
+
+/// What a font picker lists about a font, see `FontSummary::scan`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct FontSummary {
+    /// The index of the font in the collection, 0 for a single font.
+    pub index: usize,
+    /// The typographic family name, or the family name of fonts without one.
+    pub family: String,
+    /// The typographic style name like "Semibold Italic", or the style name
+    /// of fonts without one.
+    pub style: String,
+    /// The weight from 1 to 1000, 400 is regular and 700 bold.
+    pub weight: u16,
+    /// Whether the font is italic or oblique.
+    pub italic: bool,
+    /// The number of glyphs.
+    pub glyphs: u32,
+    /// The ISO 15924 tags of the scripts the font claims to support in the
+    /// Unicode ranges of its `OS/2` table, like `*b"Latn"`, empty if it
+    /// has none.
+    pub scripts: Vec<[u8; 4]>,
+}
+
+/// The bits of the Unicode ranges of `OS/2` of the blocks of scripts.
+const SCRIPT_RANGES: &[(usize, [u8; 4])] = &[
+    (0, *b"Latn"), (7, *b"Grek"), (9, *b"Cyrl"), (10, *b"Armn"), (11, *b"Hebr"),
+    (13, *b"Arab"), (15, *b"Deva"), (16, *b"Beng"), (17, *b"Guru"), (18, *b"Gujr"),
+    (19, *b"Orya"), (20, *b"Taml"), (21, *b"Telu"), (22, *b"Knda"), (23, *b"Mlym"),
+    (24, *b"Thai"), (25, *b"Laoo"), (26, *b"Geor"), (28, *b"Hang"), (49, *b"Hira"),
+    (50, *b"Kana"), (51, *b"Bopo"), (56, *b"Hang"), (59, *b"Hani"), (70, *b"Tibt"),
+    (71, *b"Syrc"), (72, *b"Thaa"), (73, *b"Sinh"), (74, *b"Mymr"), (75, *b"Ethi"),
+    (76, *b"Cher"), (77, *b"Cans"), (80, *b"Khmr"), (81, *b"Mong"),
+];
+
+impl FontSummary {
+    /// Returns the summaries of the fonts in `data`, a font or a collection,
+    /// for listing many font files quickly.
+    ///
+    /// Only the table directory and the `head`, `maxp`, `OS/2` and `name`
+    /// tables are read, neither the glyphs nor the checksums. Fonts whose
+    /// tables can't be read are skipped.
+    ///
+    /// # Errors
+    /// Returns error if `data` is neither a font nor a valid collection,
+    /// `Error::Type1NotSupported` for Type 1 fonts.
+    pub fn scan(data: &[u8]) -> Result<Vec<FontSummary>> {
+        let collection = try!(FontCollection::from_data(data));
+        Ok((0..collection.len()).filter_map(|index| {
+            collection.offset_for_font_at_index(index).and_then(|offset| summarize(data, offset).ok())
+                .map(|summary| FontSummary { index: index, ..summary })
+        }).collect())
+    }
+}
+
+/// A TrueType Collection.
+///
+/// A plain font file is treated as a collection containing a single font
+/// at offset 0, so the same code can be used for both kinds of files.
+#[derive(Debug, Clone)]
+pub struct FontCollection<'a> {
+    data: &'a [u8],
+    ttcf: Option<TTCF>,
+}
+
+impl<'a> FontCollection<'a> {
+    /// Returns a collection of fonts stored in `data`.
+    ///
+    /// Only the collection header is parsed here, member fonts are validated
+    /// when they are looked up.
+    ///
+    /// # Errors
+    /// Returns error if `data` is neither a font nor a valid collection,
+    /// `Error::Type1NotSupported` for Type 1 fonts.
+    pub fn from_data(data: &'a [u8]) -> Result<FontCollection<'a>> {
+        let ttcf = match FontKind::detect(data) {
+            Some(FontKind::TrueTypeCollection) => Some(try!(TTCF::from_data(data, 0))),
+            Some(kind) if kind.is_font() => None,
+            Some(FontKind::Type1) => return Err(Error::Type1NotSupported),
+            _ => return Err(Error::Malformed),
+        };
+
+        Ok(FontCollection { data: data, ttcf: ttcf })
+    }
+
+    /// Returns the number of entries in the collection, including corrupt ones.
+    pub fn len(&self) -> usize {
+        self.ttcf.as_ref().map_or(1, |ttcf| ttcf.num_fonts())
+    }
+
+    /// Returns the offset of the font at `index` after validating it.
+    ///
+    /// The font's table directory and all of its tables must lie within
+    /// the data, it must have a `head` table and its `checkSumAdjustment`
+    /// must match the checksum of the font. Fonts of collections may have
+    /// an adjustment of 0 instead, then the checksums of their tables must
+    /// match.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if `index` is out of bounds or the font is
+    /// truncated, `Error::MissingTable` if there is no `head` table and
+    /// `Error::CheckSumMismatch` if the font's checksum is wrong.
+    pub fn validate_font_at_index(&self, index: usize) -> Result<usize> {
+        let offset = match self.ttcf {
+            Some(ref ttcf) => try!(ttcf.offset_for_font_at_index(index).ok_or(Error::Malformed)),
+            None if index == 0 => 0,
+            None => return Err(Error::Malformed),
+        };
+        try!(validate_font(self.data, offset, self.ttcf.is_some()));
+        Ok(offset)
+    }
+
+    /// Returns the offset of the font at `index` without validating it,
+    /// `None` if `index` is out of bounds.
+    ///
+    /// Many fonts have a wrong `checkSumAdjustment`, and those of the fonts
+    /// of collections are meaningless, so rendering needn't check it.
+    pub fn offset_for_font_at_index(&self, index: usize) -> Option<usize> {
+        match self.ttcf {
+            Some(ref ttcf) => ttcf.offset_for_font_at_index(index),
+            None if index == 0 => Some(0),
+            None => None,
+        }
+    }
+
+    /// Returns the offset of the font at `index`.
+    ///
+    /// Returns `None` if `index` is out of bounds or the font is corrupt,
+    /// see `validate_font_at_index` for details.
+    pub fn font_offset_for_index(&self, index: usize) -> Option<usize> {
+        self.validate_font_at_index(index).ok()
+    }
+
+    /// Returns indices and offsets of all fonts in the collection,
+    /// skipping the corrupt ones.
+    ///
+    /// Corrupt fonts are those `validate_font_at_index` rejects, so also
+    /// fonts with wrong checksums, which render fine. To go through the
+    /// fonts that can be loaded, use `offset_for_font_at_index` for the
+    /// indices up to `len` instead.
+    pub fn valid_fonts(&self) -> Vec<(usize, usize)> {
+        (0..self.len()).filter_map(|index| {
+            self.font_offset_for_index(index).map(|offset| (index, offset))
+        }).collect()
+    }
+
+    /// Returns the index of the first font of `family` with `style`.
+    ///
+    /// Only `MacStyle::BOLD` and `MacStyle::ITALIC` of `style` are compared,
+    /// the default style finds the regular font. `family` is compared
+    /// ignoring case with the family names of the fonts, then with their
+    /// typographic family names and their full names, like "Arial Bold".
+    /// Corrupt fonts are skipped.
+    pub fn find(&self, family: &str, style: MacStyle) -> Option<usize> {
+        let checked = MacStyle::BOLD | MacStyle::ITALIC;
+        let fonts: Vec<_> = self.faces().into_iter()
+            .filter(|&(_, ref head, _, _)| head.mac_style() & checked == style & checked)
+            .collect();
+
+        for &name_id in &[NAME::FAMILY, NAME::TYPOGRAPHIC_FAMILY, NAME::FULL_NAME] {
+            let font = fonts.iter().find(|&&(_, _, _, ref name)| name.has_string(name_id, family));
+            if let Some(&(index, _, _, _)) = font {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Returns the fonts of `family` in the collection, lightest first and
+    /// upright before italic, e.g. to embolden text only if the family has
+    /// no bold font.
+    ///
+    /// `family` is compared ignoring case with the typographic family names
+    /// of the fonts, which include all weights, and with their family names.
+    /// The weights and slopes are taken from the `OS/2` tables, or from the
+    /// `head` tables of fonts without one. Corrupt fonts are skipped.
+    pub fn family_members(&self, family: &str) -> Vec<FaceInfo> {
+        let mut members: Vec<_> = self.faces().into_iter()
+            .filter(|&(_, _, _, ref name)| name.has_string(NAME::TYPOGRAPHIC_FAMILY, family)
+                || name.has_string(NAME::FAMILY, family))
+            .map(|(index, head, os2, _)| match os2 {
+                Some(os2) => FaceInfo {
+                    index: index,
+                    weight: os2.weight_class(),
+                    italic: os2.is_italic(),
+                },
+                None => FaceInfo {
+                    index: index,
+                    weight: if head.mac_style().contains(MacStyle::BOLD) { 700 } else { 400 },
+                    italic: head.mac_style().contains(MacStyle::ITALIC),
+                },
+            })
+            .collect();
+        members.sort_by_key(|face| (face.weight, face.italic, face.index));
+        members
+    }
+
+    /// Returns the index and the tables telling the style and the names of
+    /// the fonts whose tables can be read.
+    ///
+    /// The `checkSumAdjustment` isn't checked, it may be 0 in collections.
+    fn faces(&self) -> Vec<(usize, HEAD, Option<OS2>, NAME)> {
+        (0..self.len()).filter_map(|index| {
+            let offset = match self.offset_for_font_at_index(index) {
+                Some(offset) => offset,
+                None => return None,
+            };
+            read_face_tables(self.data, offset).ok().map(|(head, os2, name)| (index, head, os2, name))
+        }).collect()
+    }
+}
+
+fn read_face_tables(data: &[u8], fontstart: usize) -> Result<(HEAD, Option<OS2>, NAME)> {
+    let head = try!(HEAD::from_data(data,
+        try!(utils::find_required_table_offset(data, fontstart, b"head"))));
+    let os2 = match try!(utils::find_table_with_length(data, fontstart, b"OS/2")) {
+        // Like for a font, a malformed OS/2 table is as if there were none.
+        Some((offset, length)) => OS2::from_data(data, offset, length).ok(),
+        None => None,
+    };
+    let name = try!(NAME::from_data(data,
+        try!(utils::find_required_table_offset(data, fontstart, b"name"))));
+    Ok((head, os2, name))
+}
+
+fn summarize(data: &[u8], fontstart: usize) -> Result<FontSummary> {
+    let (head, os2, name) = try!(read_face_tables(data, fontstart));
+    let maxp = try!(utils::find_required_table_offset(data, fontstart, b"maxp"));
+    let name_or = |preferred: u16, fallback: u16| {
+        name.string(preferred).or_else(|| name.string(fallback)).unwrap_or("").to_owned()
+    };
+    let ranges = os2.as_ref().and_then(|os2| os2.unicode_ranges()).unwrap_or([0; 4]);
+    let mut scripts = vec![];
+    for &(bit, tag) in SCRIPT_RANGES {
+        if ranges[bit / 32] & 1 << (bit % 32) != 0 && !scripts.contains(&tag) {
+            scripts.push(tag);
+        }
+    }
+    Ok(FontSummary {
+        index: 0,
+        family: name_or(NAME::TYPOGRAPHIC_FAMILY, NAME::FAMILY),
+        style: name_or(NAME::TYPOGRAPHIC_SUBFAMILY, NAME::SUBFAMILY),
+        weight: match os2 {
+            Some(ref os2) => os2.weight_class(),
+            None if head.mac_style().contains(MacStyle::BOLD) => 700,
+            None => 400,
+        },
+        italic: match os2 {
+            Some(ref os2) => os2.is_italic(),
+            None => head.mac_style().contains(MacStyle::ITALIC),
+        },
+        glyphs: try!(utils::read_u16_at(data, maxp + 4)) as u32,
+        scripts: scripts,
+    })
+}
+
+fn validate_font(data: &[u8], fontstart: usize, in_collection: bool) -> Result<()> {
+    if fontstart >= data.len() || !utils::is_font(&data[fontstart..]) {
+        return Err(Error::Malformed);
+    }
+
+    let head_offset = try!(utils::find_required_table_offset(data, fontstart, b"head"));
+    let head = try!(HEAD::from_data(data, head_offset));
+    // The adjustment is unused in collections, and may be 0.
+    if in_collection && head.check_sum_adjustment() == 0 {
+        let check_sums = try!(utils::verify_check_sums(data, fontstart));
+        if !check_sums.corrupt_tables.is_empty() {
+            return Err(Error::CheckSumMismatch);
+        }
+        return Ok(());
+    }
+    // Also checks that all tables are within bounds.
+    let adjustment = try!(utils::check_sum_adjustment(data, fontstart));
+    if adjustment != head.check_sum_adjustment() {
+        return Err(Error::CheckSumMismatch);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use byteorder::{BigEndian, ByteOrder};
+    use utils::WriteBytesExt;
+    use expectest::prelude::*;
+
+    /// Builds a version 2.0 collection out of `fonts` copies of the font
+    /// in `data`, relocating table offsets and fixing up checksums.
+    fn make_collection(data: &[u8], fonts: usize) -> Vec<u8> {
+        let header_size = 12 + 4 * fonts + 12;
+        let mut ttc = vec![];
+        ttc.extend_from_slice(b"ttcf");
+        ttc.write_u32::<BigEndian>(0x00020000).unwrap();
+        ttc.write_u32::<BigEndian>(fonts as u32).unwrap();
+        for i in 0..fonts {
+            ttc.write_u32::<BigEndian>((header_size + i * data.len()) as u32).unwrap();
+        }
+        ttc.extend_from_slice(&[0; 12]); // Not signed.
+
+        for _ in 0..fonts {
+            let start = ttc.len();
+            ttc.extend_from_slice(data);
+            let num_tables = BigEndian::read_u16(&data[4..]) as usize;
+            for t in 0..num_tables {
+                let z = start + 12 + t * 16 + 8;
+                let offset = BigEndian::read_u32(&ttc[z..]) as usize + start;
+                BigEndian::write_u32(&mut ttc[z..], offset as u32);
+            }
+            let head = utils::find_table_offset(&ttc, start, b"head").unwrap().unwrap();
+            let adjustment = utils::check_sum_adjustment(&ttc, start).unwrap();
+            BigEndian::write_u32(&mut ttc[head + 8..], adjustment);
+        }
+        ttc
+    }
+
+    /// Sets the `u16` at `position` in the table `tag` of the font at `index`
+    /// to `value`, fixing up the checksum.
+    fn patch(ttc: &mut [u8], index: usize, tag: &[u8; 4], position: usize, value: u16) {
+        let start = FontCollection::from_data(ttc).unwrap().font_offset_for_index(index).unwrap();
+        let table = utils::find_table_offset(ttc, start, tag).unwrap().unwrap();
+        BigEndian::write_u16(&mut ttc[table + position..], value);
+        let head = utils::find_table_offset(ttc, start, b"head").unwrap().unwrap();
+        let adjustment = utils::check_sum_adjustment(ttc, start).unwrap();
+        BigEndian::write_u32(&mut ttc[head + 8..], adjustment);
+    }
+
+    /// Sets the `checkSumAdjustment` of the font at `index` to 0, which the
+    /// fonts of collections may have.
+    fn clear_adjustment(ttc: &mut [u8], index: usize) {
+        let start = FontCollection::from_data(ttc).unwrap().offset_for_font_at_index(index).unwrap();
+        let head = utils::find_table_offset(ttc, start, b"head").unwrap().unwrap();
+        BigEndian::write_u32(&mut ttc[head + 8..], 0);
+    }
+
+    #[test]
+    fn single_font() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let collection = FontCollection::from_data(&data).unwrap();
+        expect!(collection.len()).to(be_equal_to(1));
+        expect!(collection.font_offset_for_index(0)).to(be_some().value(0));
+        expect!(collection.font_offset_for_index(1)).to(be_none());
+
+        expect!(FontCollection::from_data(b"abcd")).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn skips_corrupt_fonts() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let mut ttc = make_collection(&data, 3);
+
+        let collection = FontCollection::from_data(&ttc).unwrap();
+        expect!(collection.len()).to(be_equal_to(3));
+        expect!(collection.valid_fonts().len()).to(be_equal_to(3));
+
+        // Corrupt a glyph of the second font.
+        let second = FontCollection::from_data(&ttc).unwrap().font_offset_for_index(1).unwrap();
+        let glyf = utils::find_table_offset(&ttc, second, b"glyf").unwrap().unwrap();
+        ttc[glyf + 100] ^= 0xff;
+
+        // Fonts of collections without an adjustment are valid unless a
+        // table is corrupt.
+        clear_adjustment(&mut ttc, 0);
+        clear_adjustment(&mut ttc, 1);
+
+        // Point the third font past the end of the data.
+        let len = ttc.len() as u32;
+        BigEndian::write_u32(&mut ttc[12 + 2 * 4..], len - 8);
+
+        let collection = FontCollection::from_data(&ttc).unwrap();
+        expect!(collection.validate_font_at_index(1)).to(be_err().value(CheckSumMismatch));
+        expect!(collection.validate_font_at_index(2)).to(be_err().value(Malformed));
+        expect!(collection.validate_font_at_index(3)).to(be_err().value(Malformed));
+        expect!(collection.valid_fonts()).to(be_equal_to(vec![(0, 36)]));
+    }
+
+    #[test]
+    fn find() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let mut ttc = make_collection(&data, 2);
+        // Make the second font regular, without a checksum adjustment.
+        patch(&mut ttc, 1, b"head", 44, 0);
+        clear_adjustment(&mut ttc, 1);
+
+        let collection = FontCollection::from_data(&ttc).unwrap();
+        expect!(collection.find("Tuffy", MacStyle::BOLD)).to(be_some().value(0));
+        expect!(collection.find("tUFFY", MacStyle::default())).to(be_some().value(1));
+        expect!(collection.find("Tuffy", MacStyle::BOLD | MacStyle::UNDERLINE)).to(be_some().value(0));
+        expect!(collection.find("Tuffy", MacStyle::ITALIC)).to(be_none());
+        // The full name.
+        expect!(collection.find("tuffy bold", MacStyle::BOLD)).to(be_some().value(0));
+        expect!(collection.find("Tuffy-Bold", MacStyle::BOLD)).to(be_none());
+    }
+
+    #[test]
+    fn family_members() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let mut ttc = make_collection(&data, 3);
+        // A regular and an italic font, as told by weight and fsSelection.
+        patch(&mut ttc, 1, b"OS/2", 4, 400);
+        patch(&mut ttc, 1, b"OS/2", 62, 0x40);
+        patch(&mut ttc, 2, b"OS/2", 4, 400);
+        patch(&mut ttc, 2, b"OS/2", 62, 0x01);
+        // Collections needn't have checksum adjustments.
+        clear_adjustment(&mut ttc, 1);
+
+        let collection = FontCollection::from_data(&ttc).unwrap();
+        let members = collection.family_members("tuffy");
+        expect!(members.clone()).to(be_equal_to(vec![
+            FaceInfo { index: 1, weight: 400, italic: false },
+            FaceInfo { index: 2, weight: 400, italic: true },
+            FaceInfo { index: 0, weight: 700, italic: false },
+        ]));
+        expect!(members.iter().any(|face| face.is_bold() && !face.italic)).to(be_true());
+        expect!(members.iter().any(|face| face.is_bold() && face.italic)).to(be_false());
+        expect!(collection.family_members("Tuffy Bold").is_empty()).to(be_true());
+    }
+
+    #[test]
+    fn summaries() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let tuffy = FontSummary {
+            index: 0,
+            family: "Tuffy".to_owned(),
+            style: "Bold".to_owned(),
+            weight: 700,
+            italic: false,
+            glyphs: 890,
+            scripts: vec![*b"Latn", *b"Grek", *b"Cyrl"],
+        };
+        expect!(FontSummary::scan(&data).unwrap()).to(be_equal_to(vec![tuffy]));
+
+        // The second font is italic and the third one points past the end.
+        let mut ttc = make_collection(&data, 3);
+        patch(&mut ttc, 1, b"OS/2", 62, 0x01);
+        let len = ttc.len() as u32;
+        BigEndian::write_u32(&mut ttc[12 + 2 * 4..], len - 8);
+        let summaries = FontSummary::scan(&ttc).unwrap();
+        expect!(summaries.iter().map(|s| (s.index, s.italic)).collect::<Vec<_>>())
+            .to(be_equal_to(vec![(0, false), (1, true)]));
+
+        expect!(FontSummary::scan(b"abcd")).to(be_err().value(Malformed));
+    }
+}
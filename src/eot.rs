@@ -0,0 +1,56 @@
+//! Embedded OpenType, the web font container of old Internet Explorers.
+
+use prelude::*;
+use Error;
+use Result;
+use byteorder::{ByteOrder, LittleEndian};
+
+/// The size of the fixed fields of the header, up to the family name.
+const HEADER_SIZE: usize = 82;
+const MAGIC_NUMBER: u16 = 0x504C;
+
+// Flags of the header.
+const TTEMBED_TTCOMPRESSED: u32 = 0x4;
+const TTEMBED_XORENCRYPTDATA: u32 = 0x10000000;
+
+/// Checks that `data` starts with the header of an EOT file.
+///
+/// EOT files have no tag at the start, only a magic number in the header.
+pub fn is_eot(data: &[u8]) -> bool {
+    data.len() >= HEADER_SIZE && LittleEndian::read_u16(&data[34..]) == MAGIC_NUMBER
+}
+
+/// Returns the font in the Embedded OpenType file `data`, to be read with
+/// `FontInfo` or `FontCollection`.
+///
+/// The font is borrowed from `data` unless it's obfuscated with XOR, then
+/// it's decoded into a copy.
+///
+/// # Errors
+/// Returns `Error::Malformed` if `data` isn't an EOT file or the font
+/// doesn't fit in it, `Error::CompressedFontNotSupported` if the font is
+/// compressed with MicroType Express.
+pub fn unwrap_eot<'a>(data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    if !is_eot(data) {
+        return Err(Error::Malformed);
+    }
+    let eot_size = LittleEndian::read_u32(&data[0..]) as usize;
+    let font_data_size = LittleEndian::read_u32(&data[4..]) as usize;
+    let flags = LittleEndian::read_u32(&data[12..]);
+    // The font is at the end, after the names of the variable part of
+    // the header.
+    if eot_size > data.len() || eot_size < HEADER_SIZE
+        || font_data_size > eot_size - HEADER_SIZE
+    {
+        return Err(Error::Malformed);
+    }
+    if flags & TTEMBED_TTCOMPRESSED != 0 {
+        return Err(Error::CompressedFontNotSupported);
+    }
+    let font = &data[eot_size - font_data_size..eot_size];
+    if flags & TTEMBED_XORENCRYPTDATA != 0 {
+        Ok(Cow::Owned(font.iter().map(|b| b ^ 0x50).collect()))
+    } else {
+        Ok(Cow::Borrowed(font))
+    }
+}
@@ -1,6 +1,5 @@
 
-use std::fmt;
-use byteorder;
+use core::fmt;
 
 /// An Error type.
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -13,17 +12,16 @@ pub enum Error {
     CMAPEncodingSubtableIsNotSupported,
     CMAPFormatIsNotSupported,
     UnknownLocationFormat,
+    TTCFVersionIsNotSupported,
+    CheckSumMismatch,
+    Type1NotSupported,
+    CompressedFontNotSupported,
+    /// Reading the font from a file or another reader failed.
+    Io,
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use std::error::Error;
-        f.write_str(self.description())
-    }
-}
-
-impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
+impl Error {
+    fn message(&self) -> &'static str {
         match *self {
             Error::Malformed => "malformed data",
             Error::MissingTable => "missing table",
@@ -33,12 +31,24 @@ impl ::std::error::Error for Error {
             Error::CMAPEncodingSubtableIsNotSupported => "cmap encoding subtable is not supported",
             Error::CMAPFormatIsNotSupported => "cmap format is not supported",
             Error::UnknownLocationFormat => "unknown index to glyph map format",
+            Error::TTCFVersionIsNotSupported => "font collection version is not supported",
+            Error::CheckSumMismatch => "checksum mismatch",
+            Error::Type1NotSupported => "type 1 fonts are not supported",
+            Error::CompressedFontNotSupported => "compressed fonts are not supported",
+            Error::Io => "reading the font failed",
         }
     }
 }
 
-impl From<byteorder::Error> for Error {
-    fn from(_: byteorder::Error) -> Self {
-        Error::Malformed
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {
+    fn description(&self) -> &str {
+        self.message()
     }
 }
@@ -0,0 +1,193 @@
+//! Filters for rendered coverage, for effects like outlines and shadows.
+
+use prelude::*;
+use GrayBitmap;
+
+impl GrayBitmap {
+    /// Returns the bitmap with shapes grown by `radius` pixels, every
+    /// pixel taking the largest coverage of the pixels within the radius.
+    ///
+    /// The bitmap grows by `radius` on every side so nothing is cut off.
+    /// Drawing the dilated bitmap of text in another color under the text
+    /// outlines it.
+    pub fn dilated(&self, radius: usize) -> GrayBitmap {
+        let r = radius as i32;
+        let mut result = GrayBitmap::new(self.x - r, self.y - r, self.width() + 2 * radius,
+            self.height() + 2 * radius);
+        let disk = disk(radius);
+        let width = result.width();
+        let pixels = result.as_mut_slice();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let c = self.as_slice()[y * self.width() + x];
+                if c == 0 {
+                    continue;
+                }
+                // The pixel moves by the radius in the result.
+                for &(dx, dy) in &disk {
+                    let i = (y as i32 + r + dy) as usize * width + (x as i32 + r + dx) as usize;
+                    if pixels[i] < c {
+                        pixels[i] = c;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the bitmap with shapes shrunk by `radius` pixels, every
+    /// pixel taking the smallest coverage of the pixels within the radius,
+    /// those outside of the bitmap being empty.
+    pub fn eroded(&self, radius: usize) -> GrayBitmap {
+        let mut result = GrayBitmap::new(self.x, self.y, self.width(), self.height());
+        let disk = disk(radius);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                result.as_mut_slice()[y * self.width() + x] = disk.iter().map(|&(dx, dy)| {
+                    let (x, y) = (x as i32 + dx, y as i32 + dy);
+                    if x < 0 || y < 0 || x as usize >= self.width() || y as usize >= self.height() {
+                        0
+                    } else {
+                        self.as_slice()[y as usize * self.width() + x as usize]
+                    }
+                }).min().unwrap_or(0);
+            }
+        }
+        result
+    }
+
+    /// Returns the bitmap blurred by three box blurs, which approximate
+    /// a Gaussian blur reaching `radius` pixels away, for shadows and glows.
+    ///
+    /// The bitmap grows by `radius` on every side so nothing is cut off.
+    pub fn blurred(&self, radius: usize) -> GrayBitmap {
+        let r = radius as i32;
+        let (width, height) = (self.width() + 2 * radius, self.height() + 2 * radius);
+        let mut values = vec![0.0f32; width * height];
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                values[(y + radius) * width + x + radius] = self.as_slice()[y * self.width() + x] as f32;
+            }
+        }
+        // The boxes add up to the radius, so the blur stays in the bitmap.
+        let mut scratch = vec![0.0f32; width.max(height)];
+        for i in 0..3 {
+            let box_radius = (radius + 2 - i) / 3;
+            for y in 0..height {
+                box_blur(&mut values[y * width..], 1, width, box_radius, &mut scratch);
+            }
+            for x in 0..width {
+                box_blur(&mut values[x..], width, height, box_radius, &mut scratch);
+            }
+        }
+        let mut result = GrayBitmap::new(self.x - r, self.y - r, width, height);
+        for (p, &v) in result.as_mut_slice().iter_mut().zip(&values) {
+            *p = (v + 0.5).min(255.0) as u8;
+        }
+        result
+    }
+}
+
+/// Replaces the `len` values `stride` apart from the start of `values` by
+/// their average with the `radius` values on each side, those outside
+/// being 0.
+fn box_blur(values: &mut [f32], stride: usize, len: usize, radius: usize, scratch: &mut [f32]) {
+    if radius == 0 {
+        return;
+    }
+    for i in 0..len {
+        scratch[i] = values[i * stride];
+    }
+    let scale = 1.0 / (2 * radius + 1) as f32;
+    // The sum of the window around the value at i.
+    let mut sum: f32 = scratch[..radius.min(len)].iter().sum();
+    for i in 0..len {
+        if i + radius < len {
+            sum += scratch[i + radius];
+        }
+        values[i * stride] = sum * scale;
+        if i >= radius {
+            sum -= scratch[i - radius];
+        }
+    }
+}
+
+/// Returns the offsets of the pixels whose center is within `radius` of
+/// the center of a pixel.
+fn disk(radius: usize) -> Vec<(i32, i32)> {
+    let r = radius as i32;
+    let mut offsets = vec![];
+    for dy in -r..r + 1 {
+        for dx in -r..r + 1 {
+            if dx * dx + dy * dy <= r * r {
+                offsets.push((dx, dy));
+            }
+        }
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn morphology() {
+        expect!(disk(0)).to(be_equal_to(vec![(0, 0)]));
+        expect!(disk(1).len()).to(be_equal_to(5));
+        expect!(disk(2).len()).to(be_equal_to(13));
+
+        let mut dot = GrayBitmap::new(3, -2, 3, 3);
+        dot.as_mut_slice()[4] = 200;
+        dot.as_mut_slice()[5] = 100;
+        let dilated = dot.dilated(1);
+        expect!((dilated.x, dilated.y, dilated.width(), dilated.height()))
+            .to(be_equal_to((2, -3, 5, 5)));
+        expect!(dilated.as_slice().to_vec()).to(be_equal_to(vec![
+            0, 0, 0, 0, 0,
+            0, 0, 200, 100, 0,
+            0, 200, 200, 200, 100,
+            0, 0, 200, 100, 0,
+            0, 0, 0, 0, 0,
+        ]));
+        expect!(dot.dilated(0)).to(be_equal_to(dot.clone()));
+
+        // Eroding undoes dilating a shape without holes.
+        let eroded = dilated.eroded(1);
+        expect!(eroded.as_slice().iter().filter(|&&p| p != 0).count()).to(be_equal_to(2));
+        expect!((eroded.as_slice()[12], eroded.as_slice()[13])).to(be_equal_to((200, 100)));
+        expect!(dot.eroded(1).into_vec()).to(be_equal_to(vec![0; 9]));
+        expect!(GrayBitmap::default().dilated(2)).to(be_equal_to(GrayBitmap::new(-2, -2, 4, 4)));
+    }
+
+    #[test]
+    fn blur() {
+        let mut values = [0.0, 3.0, 0.0, 0.0, 6.0];
+        let mut scratch = [0.0; 5];
+        box_blur(&mut values, 1, 5, 1, &mut scratch);
+        expect!(values).to(be_equal_to([1.0, 1.0, 1.0, 2.0, 2.0]));
+
+        let mut dot = GrayBitmap::new(0, 0, 1, 1);
+        dot.as_mut_slice()[0] = 255;
+        let blurred = dot.blurred(3);
+        expect!((blurred.x, blurred.y, blurred.width(), blurred.height()))
+            .to(be_equal_to((-3, -3, 7, 7)));
+        // Symmetric, decreasing away from the center and reaching the edges.
+        let pixel = |x: usize, y: usize| blurred.as_slice()[y * 7 + x];
+        expect!((0..7).all(|i| pixel(i, 3) == pixel(3, i) && pixel(i, 3) == pixel(6 - i, 3)))
+            .to(be_true());
+        expect!((0..3).all(|i| pixel(i, 3) < pixel(i + 1, 3))).to(be_true());
+        expect!(pixel(0, 3)).to(be_greater_than(0));
+        expect!(pixel(0, 0)).to(be_equal_to(0));
+
+        // Blurring keeps the total coverage, up to rounding.
+        let mut square = GrayBitmap::new(0, 0, 8, 8);
+        for p in &mut square.as_mut_slice()[8 * 2..8 * 6] {
+            *p = 255;
+        }
+        let sum = |b: &GrayBitmap| b.as_slice().iter().map(|&p| p as i32).sum::<i32>();
+        expect!((sum(&square.blurred(4)) - sum(&square)).abs() < 255).to(be_true());
+        expect!(square.blurred(0)).to(be_equal_to(square.clone()));
+    }
+}
@@ -0,0 +1,176 @@
+//! Packs glyph outlines into buffers for rasterizing them on the GPU.
+
+use prelude::*;
+use byteorder::LittleEndian;
+use utils::WriteBytesExt;
+use {FontInfo, GlyphId, PathSegment, Result};
+
+impl<'a> FontInfo<'a> {
+    /// Returns the outlines of `glyphs` packed for a GPU storage buffer, so
+    /// that compute shaders can rasterize them without parsing the font.
+    ///
+    /// All numbers are 32 bits and little-endian, floats or unsigned
+    /// integers, and every table starts at a multiple of 16 bytes:
+    ///
+    /// - The header of 16 bytes: the number of glyphs, the offsets in bytes
+    ///   of the contour table and of the point table, and 0.
+    /// - The glyph table, 32 bytes for every glyph of `glyphs` in order: the
+    ///   index of its first contour, its number of contours, its advance
+    ///   width, 0, and the smallest x and y and the largest x and y of its
+    ///   points as floats, which bound the outline.
+    /// - The contour table: the index of the first point of every contour,
+    ///   and the number of points, where the last contour ends.
+    /// - The point table, 8 bytes for every point: x and y as floats in
+    ///   font units, with the y axis pointing up.
+    ///
+    /// Every contour is a closed chain of quadratic curves: the points at
+    /// even indices from its first point are on the outline, the others are
+    /// the control points of the curves to the next, the last curve going
+    /// back to the first point. Lines have their control point in the
+    /// middle.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if a glyph is truncated.
+    pub fn outline_buffer(&self, glyphs: &[GlyphId]) -> Result<Vec<u8>> {
+        let mut records = vec![];
+        let mut contours: Vec<u32> = vec![];
+        let mut points: Vec<(f32, f32)> = vec![];
+        for &glyph in glyphs {
+            let first = contours.len() as u32;
+            let start = points.len();
+            for contour in quadratic_contours(&try!(self.glyph_path(glyph, 1.0))) {
+                contours.push(points.len() as u32);
+                points.extend(contour);
+            }
+            let bounds = points[start..].iter().fold(None, |b: Option<(f32, f32, f32, f32)>, p| {
+                Some(b.map_or((p.0, p.1, p.0, p.1), |b| {
+                    (b.0.min(p.0), b.1.min(p.1), b.2.max(p.0), b.3.max(p.1))
+                }))
+            }).unwrap_or((0.0, 0.0, 0.0, 0.0));
+            records.push((first, contours.len() as u32 - first, self.advance_width(glyph), bounds));
+        }
+        contours.push(points.len() as u32);
+
+        let align = |offset: usize| (offset + 15) & !15;
+        let contour_table = 16 + 32 * records.len();
+        let point_table = align(contour_table + 4 * contours.len());
+        let mut data = Vec::with_capacity(point_table + align(8 * points.len()));
+        for &v in &[records.len(), contour_table, point_table, 0] {
+            try!(data.write_u32::<LittleEndian>(v as u32));
+        }
+        for &(first, count, advance, (x0, y0, x1, y1)) in &records {
+            for &v in &[first, count, advance as u32, 0] {
+                try!(data.write_u32::<LittleEndian>(v));
+            }
+            for &v in &[x0, y0, x1, y1] {
+                try!(data.write_f32::<LittleEndian>(v));
+            }
+        }
+        for &first in &contours {
+            try!(data.write_u32::<LittleEndian>(first));
+        }
+        data.resize(point_table, 0);
+        for &(x, y) in &points {
+            try!(data.write_f32::<LittleEndian>(x));
+            try!(data.write_f32::<LittleEndian>(y));
+        }
+        let end = align(data.len());
+        data.resize(end, 0);
+        Ok(data)
+    }
+}
+
+/// Returns the contours of `path` as chains of quadratic curves, see
+/// `FontInfo::outline_buffer`, with the y axis flipped.
+fn quadratic_contours(path: &[PathSegment]) -> Vec<Vec<(f32, f32)>> {
+    let mut contours: Vec<Vec<(f32, f32)>> = vec![];
+    let close = |contour: &mut Vec<(f32, f32)>| {
+        let (first, last) = (contour[0], contour[contour.len() - 1]);
+        if contour.len() > 1 && first == last {
+            contour.pop();
+        } else {
+            contour.push(((first.0 + last.0) / 2.0, (first.1 + last.1) / 2.0));
+        }
+    };
+    for segment in path {
+        match *segment {
+            PathSegment::MoveTo(x, y) => {
+                if let Some(contour) = contours.last_mut() {
+                    close(contour);
+                }
+                contours.push(vec![(x, -y)]);
+            }
+            PathSegment::LineTo(x, y) => {
+                if contours.is_empty() {
+                    contours.push(vec![(0.0, 0.0)]);
+                }
+                let contour = contours.last_mut().unwrap();
+                let last = contour[contour.len() - 1];
+                contour.push(((last.0 + x) / 2.0, (last.1 - y) / 2.0));
+                contour.push((x, -y));
+            }
+            PathSegment::QuadTo(cx, cy, x, y) => {
+                if contours.is_empty() {
+                    contours.push(vec![(0.0, 0.0)]);
+                }
+                let contour = contours.last_mut().unwrap();
+                contour.push((cx, -cy));
+                contour.push((x, -y));
+            }
+        }
+    }
+    if let Some(contour) = contours.last_mut() {
+        close(contour);
+    }
+    // A single point has no outline.
+    contours.retain(|contour| contour.len() > 2);
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+    use byteorder::ByteOrder;
+    use {FontBuilder, OutlinePoint};
+
+    #[test]
+    fn outline_buffers() {
+        let mut builder = FontBuilder::new("Buffers", 1000);
+        builder.add_glyph(vec![], 500);
+        builder.add_glyph(vec![vec![OutlinePoint::on(0, 0), OutlinePoint::on(0, 100),
+            OutlinePoint::on(100, 100), OutlinePoint::on(100, 0)]], 600);
+        builder.add_glyph(vec![vec![OutlinePoint::on(0, 0), OutlinePoint::off(50, 100),
+            OutlinePoint::on(100, 0)], vec![OutlinePoint::on(10, 10), OutlinePoint::on(90, 10),
+            OutlinePoint::on(50, 50)]], 700);
+        let font = FontInfo::from_vec(builder.build().unwrap(), 0).unwrap();
+        let data = font.outline_buffer(&[GlyphId(1), GlyphId(0), GlyphId(2)]).unwrap();
+        let u32_at = |i: usize| LittleEndian::read_u32(&data[i..]);
+        let f32_at = |i: usize| LittleEndian::read_f32(&data[i..]);
+
+        expect!(data.len() % 16).to(be_equal_to(0));
+        let (contours, points) = (u32_at(4) as usize, u32_at(8) as usize);
+        expect!((u32_at(0), contours, points % 16)).to(be_equal_to((3, 16 + 3 * 32, 0)));
+        let glyph = |g: usize| {
+            let record = 16 + g * 32;
+            ((u32_at(record), u32_at(record + 4), u32_at(record + 8)),
+                (f32_at(record + 16), f32_at(record + 20), f32_at(record + 24), f32_at(record + 28)))
+        };
+        expect!(glyph(0)).to(be_equal_to(((0, 1, 600), (0.0, 0.0, 100.0, 100.0))));
+        expect!(glyph(1)).to(be_equal_to(((1, 0, 500), (0.0, 0.0, 0.0, 0.0))));
+        expect!(glyph(2)).to(be_equal_to(((1, 2, 700), (0.0, 0.0, 100.0, 100.0))));
+        let firsts: Vec<u32> = (0..4).map(|i| u32_at(contours + i * 4)).collect();
+        expect!(firsts).to(be_equal_to(vec![0, 8, 12, 18]));
+
+        let point = |i: usize| (f32_at(points + i * 8), f32_at(points + i * 8 + 4));
+        expect!((0..8).map(&point).collect::<Vec<_>>()).to(be_equal_to(vec![(0.0, 0.0),
+            (0.0, 50.0), (0.0, 100.0), (50.0, 100.0), (100.0, 100.0), (100.0, 50.0),
+            (100.0, 0.0), (50.0, 0.0)]));
+        expect!((8..12).map(&point).collect::<Vec<_>>()).to(be_equal_to(vec![(0.0, 0.0),
+            (50.0, 100.0), (100.0, 0.0), (50.0, 0.0)]));
+        expect!(points + 18 * 8 <= data.len()).to(be_true());
+
+        expect!(font.outline_buffer(&[]).unwrap()).to(be_equal_to(vec![0, 0, 0, 0, 16, 0, 0, 0,
+            32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,175 @@
+//! Writing grayscale bitmaps as PGM and PNG images, for looking at glyphs
+//! and atlases while debugging. Needs the `image` feature.
+//!
+//! The PNG encoder doesn't compress, the files are only slightly larger
+//! than the pixels.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use prelude::*;
+use {Atlas, GrayBitmap};
+
+impl GrayBitmap {
+    /// Saves the bitmap as a binary PGM image at `path`.
+    pub fn save_pgm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(try!(File::create(path)));
+        try!(write_pgm(&mut file, self.as_slice(), self.width(), self.height()));
+        file.flush()
+    }
+
+    /// Saves the bitmap as a grayscale PNG image at `path`.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(try!(File::create(path)));
+        try!(write_png(&mut file, self.as_slice(), self.width(), self.height()));
+        file.flush()
+    }
+}
+
+impl Atlas {
+    /// Saves the bitmap of the atlas as a PNG image at `png` and the
+    /// positions of its characters as text at `meta`, for baking atlases
+    /// offline.
+    ///
+    /// See `write_metadata` for the format of the metadata.
+    pub fn bake_to<P: AsRef<Path>, Q: AsRef<Path>>(&self, png: P, meta: Q) -> io::Result<()> {
+        try!(self.bitmap.save_png(png));
+        let mut file = BufWriter::new(try!(File::create(meta)));
+        try!(self.write_metadata(&mut file));
+        file.flush()
+    }
+
+    /// Writes the size of the bitmap and the baked characters as text.
+    ///
+    /// The first line is `atlas <width> <height> <characters>`, followed by
+    /// a line of `<code> <x0> <y0> <x1> <y1> <xoff> <yoff> <xadvance>` for
+    /// every character, with the fields of `BakedChar`. Codes that aren't
+    /// characters, the surrogates, are left out and not counted.
+    pub fn write_metadata<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        try!(writeln!(w, "atlas {} {} {}", self.bitmap.width(), self.bitmap.height(),
+            self.chars().count()));
+        for (c, b) in self.chars() {
+            try!(writeln!(w, "{} {} {} {} {} {} {} {}", c as u32, b.x0, b.y0, b.x1, b.y1,
+                b.xoff, b.yoff, b.xadvance));
+        }
+        Ok(())
+    }
+}
+
+/// Writes `height` rows of `width` pixels from `pixels`, such as an atlas
+/// from `bake_font_bitmap`, as a binary PGM image.
+///
+/// # Panics
+///
+/// If `pixels` has less than `width * height` pixels.
+pub fn write_pgm<W: Write>(w: &mut W, pixels: &[u8], width: usize, height: usize)
+    -> io::Result<()>
+{
+    try!(write!(w, "P5\n{} {}\n255\n", width, height));
+    w.write_all(&pixels[..width * height])
+}
+
+/// Writes `height` rows of `width` pixels from `pixels` as a grayscale PNG
+/// image.
+///
+/// # Panics
+///
+/// If `pixels` has less than `width * height` pixels.
+pub fn write_png<W: Write>(w: &mut W, pixels: &[u8], width: usize, height: usize)
+    -> io::Result<()>
+{
+    let pixels = &pixels[..width * height];
+    try!(w.write_all(b"\x89PNG\r\n\x1a\n"));
+
+    let mut header = vec![];
+    header.extend_from_slice(&be32(width as u32));
+    header.extend_from_slice(&be32(height as u32));
+    // 8 bit grayscale, no interlacing.
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+    try!(write_chunk(w, b"IHDR", &header));
+
+    // Every row starts with filter type 0, stored in uncompressed zlib blocks.
+    let mut raw = Vec::with_capacity((width + 1) * height);
+    for row in 0..height {
+        raw.push(0);
+        raw.extend_from_slice(&pixels[row * width..(row + 1) * width]);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(if blocks.peek().is_none() { 1 } else { 0 });
+        zlib.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&be32(adler32(&raw)));
+    try!(write_chunk(w, b"IDAT", &zlib));
+
+    write_chunk(w, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    try!(w.write_all(&be32(data.len() as u32)));
+    try!(w.write_all(kind));
+    try!(w.write_all(data));
+    let crc = crc32(crc32(!0, kind), data);
+    w.write_all(&be32(!crc))
+}
+
+fn be32(x: u32) -> [u8; 4] {
+    [(x >> 24) as u8, (x >> 16) as u8, (x >> 8) as u8, x as u8]
+}
+
+/// Updates the CRC-32 `crc` of PNG chunks with `data`.
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn checksums() {
+        expect!(!crc32(!0, b"IEND")).to(be_equal_to(0xae426082));
+        expect!(adler32(b"Wikipedia")).to(be_equal_to(0x11e60398));
+    }
+
+    #[test]
+    fn pgm() {
+        let mut data = vec![];
+        write_pgm(&mut data, &[1, 2, 3, 4, 5, 6, 7], 3, 2).unwrap();
+        expect!(data).to(be_equal_to(b"P5\n3 2\n255\n\x01\x02\x03\x04\x05\x06".to_vec()));
+    }
+
+    #[test]
+    fn png() {
+        let mut data = vec![];
+        write_png(&mut data, &[1, 2, 3, 4], 2, 2).unwrap();
+        expect!(data.len()).to(be_equal_to(8 + 25 + 12 + 2 + 5 + 6 + 4 + 12));
+        expect!(&data[12..16]).to(be_equal_to(&b"IHDR"[..]));
+        // One final stored block with the filter type of every row.
+        expect!(&data[41..54])
+            .to(be_equal_to(&[0x78, 0x01, 1, 6, 0, 0xf9, 0xff, 0, 1, 2, 0, 3, 4][..]));
+        expect!(&data[data.len() - 8..]).to(be_equal_to(&b"IEND\xae\x42\x60\x82"[..]));
+    }
+}
@@ -0,0 +1,530 @@
+//! Lays out a line of text as positioned glyphs.
+
+use prelude::*;
+use {FontInfo, GlyphId, PathSegment, Result};
+
+/// The direction glyphs advance in on a line.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    /// Latin, Greek, Cyrillic and most other scripts.
+    LeftToRight,
+    /// Hebrew and Arabic, whose brackets are mirrored.
+    RightToLeft,
+}
+
+impl Default for Direction {
+    fn default() -> Direction {
+        Direction::LeftToRight
+    }
+}
+
+/// The OpenType features `FontInfo::layout` applies unless they are turned
+/// off: ligatures, kerning and the attachment of marks, and `trak` for the
+/// tracking of Apple fonts.
+pub const DEFAULT_FEATURES: &[[u8; 4]] = &[*b"ccmp", *b"rlig", *b"liga", *b"clig", *b"kern",
+    *b"mark", *b"mkmk", *b"trak"];
+
+/// How `FontInfo::layout` lays out text.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct LayoutOptions {
+    pub direction: Direction,
+    /// The features turned on or off, the last of a tag counts, see
+    /// `features`.
+    pub features: Vec<([u8; 4], bool)>,
+    /// Puts the glyphs on whole pixels, like Windows does: the advances
+    /// are those of the `hdmx` table at the nearest whole number of pixels
+    /// per em, rounded scaled advances for sizes the table doesn't have,
+    /// and kerning, tracking and the offsets of marks are rounded.
+    pub pixel_grid: bool,
+}
+
+impl LayoutOptions {
+    /// Returns the options with OpenType `features` turned on or off, e.g.
+    /// `&[("smcp", true), ("liga", false)]` for small capitals without
+    /// ligatures. Tags shorter than four characters are padded with spaces,
+    /// longer ones can't name a feature and are ignored.
+    ///
+    /// Only the substitutions of single glyphs and ligatures and the
+    /// attachment of marks are applied, so features made of other lookups
+    /// have no effect. Turning off `kern` turns off the kerning tables, and
+    /// `trak` the tracking table.
+    pub fn features(mut self, features: &[(&str, bool)]) -> LayoutOptions {
+        for &(tag, on) in features {
+            if tag.len() > 4 {
+                continue;
+            }
+            let mut bytes = *b"    ";
+            bytes[..tag.len()].copy_from_slice(tag.as_bytes());
+            self.features.push((bytes, on));
+        }
+        self
+    }
+
+    /// Returns `true` if the feature `tag` is applied, by default those of
+    /// `DEFAULT_FEATURES`.
+    pub fn is_enabled(&self, tag: &[u8; 4]) -> bool {
+        self.features.iter().rev().find(|&&(ref t, _)| t == tag)
+            .map_or(DEFAULT_FEATURES.contains(tag), |&(_, on)| on)
+    }
+}
+
+/// A glyph laid out on a line.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PositionedGlyph {
+    /// The glyph.
+    pub glyph: GlyphId,
+    /// The byte offset of the character of the glyph in the text.
+    pub cluster: usize,
+    /// The origin of the glyph from the start of the line, in pixels, with
+    /// the y axis pointing down like in bitmaps.
+    pub x: f32,
+    pub y: f32,
+    /// The advance of the glyph, in pixels.
+    pub advance: f32,
+}
+
+/// A glyph positioned by a shaper like HarfBuzz, in font units.
+///
+/// The fields are those of the glyph infos and positions of HarfBuzz, so
+/// its output converts field by field, the `codepoint` of an info being the
+/// glyph. Fonts shaped at their default scale give positions in font units.
+/// Glyphs past 65535, which fonts can't have, are taken as glyph 0.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ShapedGlyph {
+    /// The index of the glyph.
+    pub glyph: u32,
+    /// The offset of the first character of the glyph in the text.
+    pub cluster: u32,
+    /// How far the pen moves after the glyph.
+    pub x_advance: i32,
+    pub y_advance: i32,
+    /// How far the glyph is moved from the pen, without moving the pen, with
+    /// the y axis pointing up.
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// Returns the shaped `glyphs` scaled by `scale` as laid out glyphs, with
+/// the line starting at 0.
+///
+/// The glyphs are drawn where the shaper put them, so they can be rendered
+/// with `FontInfo::line_path` and put into atlases like the glyphs of
+/// `FontInfo::layout`.
+pub fn from_shaped(glyphs: &[ShapedGlyph], scale: f32) -> Vec<PositionedGlyph> {
+    let (mut x, mut y) = (0, 0);
+    glyphs.iter().map(|g| {
+        let glyph = PositionedGlyph {
+            glyph: if g.glyph <= 0xffff { GlyphId(g.glyph as u16) } else { GlyphId(0) },
+            cluster: g.cluster as usize,
+            x: scale * (x + g.x_offset) as f32,
+            y: -scale * (y + g.y_offset) as f32,
+            advance: scale * g.x_advance as f32,
+        };
+        x += g.x_advance;
+        y += g.y_advance;
+        glyph
+    }).collect()
+}
+
+impl<'a> FontInfo<'a> {
+    /// Returns the glyphs of `text` on a line scaled by `scale`, kerned with
+    /// the kerning tables, from left to right with the line starting at 0.
+    ///
+    /// The glyphs are substituted by the features of the `GSUB` table that
+    /// are enabled, see `LayoutOptions::features`, e.g. into ligatures,
+    /// which take the offset of their first character, then by the
+    /// non-contextual substitutions of the `morx` table of Apple fonts.
+    /// Marks like combining accents are attached to the letters before them
+    /// by the `mark` and `mkmk` features of the `GPOS` table, and don't
+    /// advance.
+    ///
+    /// The other glyphs are spaced by the normal track of the `trak` table
+    /// at the size of the text, taking a pixel for a point like macOS does:
+    /// half of the tracking goes before a glyph and half after it.
+    ///
+    /// Right-to-left text is reversed and its brackets are replaced by
+    /// their mirrored glyphs, if the font has them. The whole text goes in
+    /// one direction: there is neither bidirectional reordering nor
+    /// shaping, so Arabic has to be given in presentation forms and text
+    /// mixing directions has to be laid out in runs.
+    pub fn layout(&self, text: &str, scale: f32, options: &LayoutOptions) -> Vec<PositionedGlyph> {
+        let mut chars: Vec<(usize, char)> = text.char_indices().collect();
+        if options.direction == Direction::RightToLeft {
+            for &mut (_, ref mut c) in &mut chars {
+                if let Some(m) = mirrored(*c) {
+                    if self.cmap().index_for_code_in(&self.data, m as usize).is_some() {
+                        *c = m;
+                    }
+                }
+            }
+        }
+        let enabled = |features: Vec<[u8; 4]>| -> Vec<[u8; 4]> {
+            features.into_iter().filter(|tag| options.is_enabled(tag)).collect()
+        };
+        let glyphs: Vec<usize> = chars.iter().map(|&(_, c)| self.glyph_index_for_code(c).index())
+            .collect();
+        let lookups = self.gsub().feature_lookups(&enabled(self.gsub().features()));
+        let substituted = self.gsub().substitute(&glyphs, &lookups);
+        let mut glyphs: Vec<usize> = substituted.iter().map(|&(glyph, _)| glyph).collect();
+        self.morx().substitute(&mut glyphs);
+        // The substitutes are read from 16-bit fields, so they all fit.
+        let ids: Vec<GlyphId> = glyphs.iter().map(|&g| GlyphId(g as u16)).collect();
+        let lookups = self.gpos().feature_lookups(&enabled(self.gpos().features()));
+        let kern = options.is_enabled(b"kern");
+        let round = |value: f32| if options.pixel_grid { libm::roundf(value) } else { value };
+        let ppem = libm::roundf(scale * self.head.units_per_em()) as u16;
+        let tracking = if options.is_enabled(b"trak") {
+            round(scale * self.trak().tracking(0.0, scale * self.head.units_per_em()))
+        } else {
+            0.0
+        };
+        let attachments = self.gpos().attach_marks(&glyphs, &lookups,
+            |g| self.is_mark(GlyphId(g as u16)));
+
+        let mut order: Vec<usize> = (0..glyphs.len()).collect();
+        if options.direction == Direction::RightToLeft {
+            order.reverse();
+        }
+        let mut positioned = vec![PositionedGlyph::default(); glyphs.len()];
+        let (mut x, mut left) = (0.0, None);
+        for &i in &order {
+            positioned[i].glyph = ids[i];
+            positioned[i].cluster = chars[substituted[i].1].0;
+            if attachments[i].is_some() {
+                continue;
+            }
+            if let Some(left) = left {
+                if kern {
+                    x += round(scale * self.kerning(left, ids[i]) as f32);
+                }
+            }
+            let advance = if options.pixel_grid {
+                self.pixel_advance_width(ids[i], ppem) as f32
+            } else {
+                scale * self.advance_width(ids[i]) as f32
+            } + tracking;
+            positioned[i].x = x + round(tracking / 2.0);
+            positioned[i].advance = advance;
+            x += advance;
+            left = Some(ids[i]);
+        }
+        // The glyphs marks are attached to come before them in the text.
+        for (i, attachment) in attachments.into_iter().enumerate() {
+            if let Some(attachment) = attachment {
+                let base = positioned[attachment.base];
+                positioned[i].x = base.x + round(scale * attachment.x as f32);
+                positioned[i].y = base.y - round(scale * attachment.y as f32);
+            }
+        }
+        order.into_iter().map(|i| positioned[i]).collect()
+    }
+
+    /// Returns `true` if the digits of the font are as wide, by default or
+    /// with the `tnum` feature of `GSUB`, `false` for fonts missing a digit.
+    pub fn has_tabular_figures(&self) -> bool {
+        let digits: Vec<GlyphId> = (b'0'..b'9' + 1)
+            .map(|c| self.glyph_index_for_code(c as char)).collect();
+        if digits.contains(&GlyphId(0)) {
+            return false;
+        }
+        let zero = self.advance_width(digits[0]);
+        // Only substitutions are applied, a `tnum` of `GPOS` changes nothing.
+        self.gsub().features().contains(b"tnum")
+            || digits.iter().all(|&digit| self.advance_width(digit) == zero)
+    }
+
+    /// Returns the glyphs of `text` like `layout` with all digits as wide,
+    /// so numbers line up in columns, e.g. of scores.
+    ///
+    /// The `tnum` feature is turned on. Fonts without tabular figures get
+    /// their digits centered in the advance of the widest digit, without
+    /// kerning and tracking.
+    pub fn layout_tabular(&self, text: &str, scale: f32, options: &LayoutOptions)
+        -> Vec<PositionedGlyph>
+    {
+        let options = options.clone().features(&[("tnum", true), ("pnum", false)]);
+        if self.has_tabular_figures() {
+            return self.layout(text, scale, &options);
+        }
+
+        let mut glyphs = self.layout(text, scale, &options.features(&[("kern", false), ("trak", false)]));
+        let widest = (b'0'..b'9' + 1)
+            .map(|c| self.advance_width(self.glyph_index_for_code(c as char)))
+            .max().unwrap_or(0);
+        let widest = scale * widest as f32;
+        let mut shift = 0.0;
+        for g in &mut glyphs {
+            g.x += shift;
+            if text[g.cluster..].starts_with(|c: char| c.is_ascii_digit()) && g.advance > 0.0 {
+                let padding = widest - g.advance;
+                g.x += padding / 2.0;
+                g.advance = widest;
+                shift += padding;
+            }
+        }
+        glyphs
+    }
+
+    /// Returns the outlines of laid out `glyphs` scaled by `scale`, each
+    /// moved to its position, to render the line with `rasterize_path`.
+    pub fn line_path(&self, glyphs: &[PositionedGlyph], scale: f32) -> Result<Vec<PathSegment>> {
+        let mut path = vec![];
+        for g in glyphs {
+            let (dx, dy) = (g.x, g.y);
+            path.extend(try!(self.glyph_path(g.glyph, scale)).into_iter().map(|segment| {
+                match segment {
+                    PathSegment::MoveTo(x, y) => PathSegment::MoveTo(x + dx, y + dy),
+                    PathSegment::LineTo(x, y) => PathSegment::LineTo(x + dx, y + dy),
+                    PathSegment::QuadTo(cx, cy, x, y) =>
+                        PathSegment::QuadTo(cx + dx, cy + dy, x + dx, y + dy),
+                }
+            }));
+        }
+        Ok(path)
+    }
+}
+
+/// Returns the character that looks like `c` mirrored, for the brackets and
+/// the comparison signs that right-to-left text mirrors.
+fn mirrored(c: char) -> Option<char> {
+    static PAIRS: &[(char, char)] = &[
+        ('(', ')'), ('<', '>'), ('[', ']'), ('{', '}'), ('\u{ab}', '\u{bb}'),
+        ('\u{2039}', '\u{203a}'), ('\u{2045}', '\u{2046}'), ('\u{207d}', '\u{207e}'),
+        ('\u{208d}', '\u{208e}'), ('\u{2264}', '\u{2265}'), ('\u{2308}', '\u{2309}'),
+        ('\u{230a}', '\u{230b}'), ('\u{2329}', '\u{232a}'), ('\u{27e8}', '\u{27e9}'),
+        ('\u{3008}', '\u{3009}'), ('\u{300a}', '\u{300b}'), ('\u{300c}', '\u{300d}'),
+        ('\u{300e}', '\u{300f}'), ('\u{3010}', '\u{3011}'), ('\u{ff08}', '\u{ff09}'),
+        ('\u{ff3b}', '\u{ff3d}'), ('\u{ff5b}', '\u{ff5d}'),
+    ];
+    PAIRS.iter().filter_map(|&(open, close)| {
+        if c == open {
+            Some(close)
+        } else if c == close {
+            Some(open)
+        } else {
+            None
+        }
+    }).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+    use {get_codepoint_kern_advance, get_glyph_kern_advance, FontBuilder, GlyphId, OutlinePoint};
+    use tables::{mark_table, substitution_table, with_tables};
+
+    #[test]
+    fn mirroring() {
+        expect!(mirrored('(')).to(be_some().value(')'));
+        expect!(mirrored(']')).to(be_some().value('['));
+        expect!(mirrored('\u{ab}')).to(be_some().value('\u{bb}'));
+        expect!(mirrored('\u{2265}')).to(be_some().value('\u{2264}'));
+        expect!(mirrored('a')).to(be_none());
+        expect!(mirrored('\u{5d0}')).to(be_none());
+    }
+
+    #[test]
+    fn shaped() {
+        let shaped = [
+            ShapedGlyph { glyph: 3, cluster: 0, x_advance: 1000, y_advance: 0, x_offset: 0, y_offset: 0 },
+            // A mark above the previous glyph, which doesn't advance.
+            ShapedGlyph { glyph: 7, cluster: 0, x_advance: 0, y_advance: 0, x_offset: -600, y_offset: 200 },
+            ShapedGlyph { glyph: 5, cluster: 2, x_advance: 500, y_advance: 0, x_offset: 0, y_offset: 0 },
+        ];
+        let glyphs = from_shaped(&shaped, 0.01);
+        expect!(glyphs.iter().map(|g| (g.glyph, g.cluster)).collect::<Vec<_>>())
+            .to(be_equal_to(vec![(GlyphId(3), 0), (GlyphId(7), 0), (GlyphId(5), 2)]));
+        expect!(glyphs.iter().map(|g| (g.x, g.y, g.advance)).collect::<Vec<_>>())
+            .to(be_equal_to(vec![(0.0, 0.0, 10.0), (4.0, -2.0, 0.0), (10.0, 0.0, 5.0)]));
+        expect!(from_shaped(&[], 1.0)).to(be_equal_to(vec![]));
+    }
+
+    #[test]
+    fn marks() {
+        let square = |x0: i16, y0: i16, x1: i16, y1: i16| vec![vec![OutlinePoint::on(x0, y0),
+            OutlinePoint::on(x0, y1), OutlinePoint::on(x1, y1), OutlinePoint::on(x1, y0)]];
+        let mut builder = FontBuilder::new("Marks", 1000);
+        builder.add_glyph(vec![], 500);
+        let e = builder.add_glyph(square(50, 0, 450, 500), 500);
+        let acute = builder.add_glyph(square(-50, 50, 50, 150), 200);
+        builder.map_code('e', e);
+        builder.map_code('\u{301}', acute);
+        let font = builder.build().unwrap();
+        let data = with_tables(&font, vec![(*b"GPOS", mark_table(&[(e.0, 250)], &[acute.0]))]);
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        expect!((font.is_mark(e), font.is_mark(acute))).to(be_equal_to((false, true)));
+
+        // The accents go above the middle of the 'e', one over the other,
+        // and the next 'e' follows right after the first.
+        let glyphs = font.layout("e\u{301}\u{301}e", 0.1, &LayoutOptions::default());
+        expect!(glyphs.iter().map(|g| (g.glyph, g.cluster, g.x, g.y, g.advance)).collect::<Vec<_>>())
+            .to(be_equal_to(vec![(e, 0, 0.0, 0.0, 50.0), (acute, 1, 25.0, -50.0, 0.0),
+                (acute, 3, 25.0, -70.0, 0.0), (e, 5, 50.0, 0.0, 50.0)]));
+
+        let options = LayoutOptions { direction: Direction::RightToLeft, ..LayoutOptions::default() };
+        let glyphs = font.layout("e\u{301}e", 0.1, &options);
+        expect!(glyphs.iter().map(|g| (g.glyph, g.x, g.y)).collect::<Vec<_>>())
+            .to(be_equal_to(vec![(e, 0.0, 0.0), (acute, 75.0, -50.0), (e, 50.0, 0.0)]));
+
+        // Glyph classes of `GDEF` tell the marks instead, here none, so the
+        // second accent can't be attached to the first.
+        let data = with_tables(&data, vec![(*b"GDEF", vec![
+            0, 1, 0, 0, 0, 12, 0, 0, 0, 22, 0, 0,
+            0, 1, 0, 1, 0, 2, 0, 1, 0, 1,
+            // Carets of 'e' at 100 and on its third point.
+            0, 6, 0, 1, 0, 12, 0, 1, 0, 1, 0, 1, 0, 2, 0, 6, 0, 10, 0, 1, 0, 100, 0, 2, 0, 2,
+        ])]);
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        expect!((font.is_mark(e), font.is_mark(acute))).to(be_equal_to((false, false)));
+        let glyphs = font.layout("e\u{301}\u{301}", 0.1, &LayoutOptions::default());
+        expect!(glyphs.iter().map(|g| (g.x, g.y)).collect::<Vec<_>>())
+            .to(be_equal_to(vec![(0.0, 0.0), (25.0, -50.0), (50.0, 0.0)]));
+        expect!(font.ligature_carets(e)).to(be_equal_to(vec![100, 450]));
+        expect!(font.ligature_carets(acute)).to(be_equal_to(vec![]));
+    }
+
+    #[test]
+    fn features() {
+        let options = LayoutOptions::default().features(&[("smcp", true), ("liga", false),
+            ("cv1", true), ("smcp", false), ("kerning", false)]);
+        expect!(options.features.len()).to(be_equal_to(4));
+        expect!(options.is_enabled(b"cv1 ")).to(be_true());
+        expect!(options.is_enabled(b"smcp")).to(be_false());
+        expect!(options.is_enabled(b"liga")).to(be_false());
+        expect!(options.is_enabled(b"kern")).to(be_true());
+        expect!(LayoutOptions::default().is_enabled(b"smcp")).to(be_false());
+
+        let mut builder = FontBuilder::new("Features", 1000);
+        for &advance in &[500, 300, 200, 450, 250] {
+            builder.add_glyph(vec![], advance);
+        }
+        builder.map_code('f', GlyphId(1));
+        builder.map_code('i', GlyphId(2));
+        let font = builder.build().unwrap();
+        let gsub = substitution_table(&[(2, 4)], &[(&[1, 2], 3)]);
+        let data = with_tables(&font, vec![(*b"GSUB", gsub)]);
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+
+        let layout = |options: LayoutOptions| -> Vec<(u16, usize, f32)> {
+            font.layout("fif", 0.1, &options).iter().map(|g| (g.glyph.0, g.cluster, g.x)).collect()
+        };
+        expect!(layout(LayoutOptions::default()))
+            .to(be_equal_to(vec![(3, 0, 0.0), (1, 2, 45.0)]));
+        expect!(layout(LayoutOptions::default().features(&[("liga", false)])))
+            .to(be_equal_to(vec![(1, 0, 0.0), (2, 1, 30.0), (1, 2, 50.0)]));
+        // Small capitals come first and break the ligature.
+        expect!(layout(LayoutOptions::default().features(&[("smcp", true)])))
+            .to(be_equal_to(vec![(1, 0, 0.0), (4, 1, 30.0), (1, 2, 55.0)]));
+    }
+
+    #[test]
+    fn pixel_grid() {
+        let mut builder = FontBuilder::new("Pixels", 1000);
+        for &advance in &[500, 500, 333] {
+            builder.add_glyph(vec![], advance);
+        }
+        builder.map_code('a', GlyphId(1));
+        builder.map_code('b', GlyphId(2));
+        let font = builder.build().unwrap();
+        // The hinted widths at 12 pixels per em.
+        let hdmx = vec![0, 0, 0, 1, 0, 0, 0, 8, 12, 7, 0, 7, 4, 0, 0, 0];
+        let data = with_tables(&font, vec![(*b"hdmx", hdmx)]);
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+
+        let layout = |scale: f32, pixel_grid: bool| -> Vec<(f32, f32)> {
+            let options = LayoutOptions { pixel_grid: pixel_grid, ..LayoutOptions::default() };
+            font.layout("abb", scale, &options).iter().map(|g| (g.x, g.advance)).collect()
+        };
+        expect!(layout(0.012, false)).to(be_equal_to(vec![(0.0, 6.0), (6.0, 3.996), (9.996, 3.996)]));
+        expect!(layout(0.012, true)).to(be_equal_to(vec![(0.0, 7.0), (7.0, 4.0), (11.0, 4.0)]));
+        // Sizes without widths in the table have the scaled widths rounded.
+        expect!(layout(0.013, true)).to(be_equal_to(vec![(0.0, 7.0), (7.0, 4.0), (11.0, 4.0)]));
+        expect!(layout(0.011, true)).to(be_equal_to(vec![(0.0, 6.0), (6.0, 4.0), (10.0, 4.0)]));
+    }
+
+    #[test]
+    fn tracking() {
+        let mut builder = FontBuilder::new("Tracking", 1024);
+        builder.add_glyph(vec![], 512);
+        let a = builder.add_glyph(vec![], 512);
+        builder.map_code('a', a);
+        let font = builder.build().unwrap();
+        // The normal track at 16 points is 64 units.
+        let data = with_tables(&font, vec![(*b"trak", vec![0, 1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0,
+            0, 1, 0, 1, 0, 0, 0, 28, 0, 0, 0, 0, 0, 0, 0, 32, 0, 16, 0, 0, 0, 64])]);
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+
+        let layout = |options: LayoutOptions| -> Vec<(f32, f32)> {
+            font.layout("aa", 1.0 / 64.0, &options).iter().map(|g| (g.x, g.advance)).collect()
+        };
+        expect!(layout(LayoutOptions::default())).to(be_equal_to(vec![(0.5, 9.0), (9.5, 9.0)]));
+        expect!(layout(LayoutOptions::default().features(&[("trak", false)])))
+            .to(be_equal_to(vec![(0.0, 8.0), (8.0, 8.0)]));
+    }
+
+    #[test]
+    fn apple_kerning() {
+        let mut builder = FontBuilder::new("Kerning", 1000);
+        for _ in 0..3 {
+            builder.add_glyph(vec![], 500);
+        }
+        builder.map_code('a', GlyphId(1));
+        builder.map_code('v', GlyphId(2));
+        let font = builder.build().unwrap();
+        // Glyphs 1 and 2 are kerned by -50.
+        let data = with_tables(&font, vec![(*b"kerx", vec![0, 2, 0, 0, 0, 0, 0, 1,
+            0, 0, 0, 34, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 1, 0, 2, 0xff, 0xce])]);
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+
+        expect!(font.kern().is_empty()).to(be_true());
+        expect!(font.kerning(GlyphId(1), GlyphId(2))).to(be_equal_to(-50));
+        expect!(font.layout("av", 0.1, &LayoutOptions::default())[1].x).to(be_equal_to(45.0));
+        unsafe {
+            expect!(get_glyph_kern_advance(&font, GlyphId(1), GlyphId(2))).to(be_equal_to(-50));
+            expect!(get_codepoint_kern_advance(&font, 'a', 'v')).to(be_equal_to(-50));
+        }
+    }
+
+    #[test]
+    fn tabular_figures() {
+        let mut builder = FontBuilder::new("Figures", 1000);
+        builder.add_glyph(vec![], 500);
+        for (i, c) in "0123456789.".chars().enumerate() {
+            let glyph = builder.add_glyph(vec![], if c == '1' { 300 } else { 500 + i as u16 * 10 });
+            builder.map_code(c, glyph);
+        }
+        let font = FontInfo::from_vec(builder.build().unwrap(), 0).unwrap();
+        expect!(font.has_tabular_figures()).to(be_false());
+
+        // '9' is the widest, the others are centered in its advance.
+        let glyphs = font.layout_tabular("1.9", 0.1, &LayoutOptions::default());
+        expect!(glyphs.iter().map(|g| (g.x, g.advance)).collect::<Vec<_>>())
+            .to(be_equal_to(vec![(14.5, 59.0), (59.0, 60.0), (119.0, 59.0)]));
+        expect!(font.layout_tabular("", 0.1, &LayoutOptions::default())).to(be_equal_to(vec![]));
+
+        // The digits are still centered if only GPOS has the feature.
+        let mut gpos = mark_table(&[(1, 0)], &[2]);
+        gpos[14..18].copy_from_slice(b"tnum");
+        let data = with_tables(font.data(), vec![(*b"GPOS", gpos)]);
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        expect!(font.has_feature(b"tnum")).to(be_true());
+        expect!(font.has_tabular_figures()).to(be_false());
+        expect!(font.layout_tabular("1.9", 0.1, &LayoutOptions::default())[0].x).to(be_equal_to(14.5));
+
+        // Missing digits, all glyph 0, aren't tabular figures.
+        let mut builder = FontBuilder::new("Letters", 1000);
+        builder.add_glyph(vec![], 500);
+        let a = builder.add_glyph(vec![], 500);
+        builder.map_code('a', a);
+        let font = FontInfo::from_vec(builder.build().unwrap(), 0).unwrap();
+        expect!(font.has_tabular_figures()).to(be_false());
+
+        let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+        let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+        expect!(font.has_tabular_figures()).to(be_true());
+        expect!(font.layout_tabular("12.5", 0.1, &LayoutOptions::default()))
+            .to(be_equal_to(font.layout("12.5", 0.1, &LayoutOptions::default())));
+    }
+}
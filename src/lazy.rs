@@ -0,0 +1,158 @@
+//! Tables read from the font data the first time they are needed.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use Error;
+use Result;
+use utils::HeapSize;
+
+const EMPTY: usize = 0;
+const READING: usize = 1;
+const READY: usize = 2;
+
+/// A table of a font that is read the first time it's needed rather than
+/// when the font is loaded, so that fonts which are only listed or
+/// measured don't pay for their character maps and layout tables.
+///
+/// A missing or malformed table is empty, but keeps the error so that
+/// `FontInfo::load_tables` can report it. Threads that need the table
+/// while another one reads it wait for it.
+pub struct Lazy<T> {
+    // The offset of the table in the font data, `None` if it's missing.
+    offset: Option<usize>,
+    state: AtomicUsize,
+    value: UnsafeCell<Option<(T, Option<Error>)>>,
+}
+
+// The value is only written once, before `state` becomes `READY`, and only
+// read after.
+unsafe impl<T: Send + Sync> Sync for Lazy<T> {}
+
+impl<T: Default> Lazy<T> {
+    /// Returns a table at `offset` that hasn't been read yet.
+    pub fn new(offset: Option<usize>) -> Lazy<T> {
+        Lazy { offset: offset, state: AtomicUsize::new(EMPTY), value: UnsafeCell::new(None) }
+    }
+
+    /// Returns the table, reading it with `read` from its offset the first
+    /// time. If `read` panics, the table is read again the next time.
+    pub fn get<F>(&self, read: F) -> &T where F: FnOnce(usize) -> Result<T> {
+        &self.load(read).0
+    }
+
+    fn load<F>(&self, read: F) -> &(T, Option<Error>) where F: FnOnce(usize) -> Result<T> {
+        loop {
+            match self.state.compare_exchange(EMPTY, READING, Ordering::Acquire, Ordering::Acquire) {
+                Ok(_) => {
+                    let reset = Reset(&self.state);
+                    let value = match self.offset.map(read) {
+                        Some(Ok(table)) => (table, None),
+                        Some(Err(e)) => (T::default(), Some(e)),
+                        None => (T::default(), None),
+                    };
+                    unsafe {
+                        *self.value.get() = Some(value);
+                    }
+                    ::core::mem::forget(reset);
+                    self.state.store(READY, Ordering::Release);
+                    break;
+                }
+                Err(READY) => break,
+                Err(_) => ::core::hint::spin_loop(),
+            }
+        }
+        unsafe { (*self.value.get()).as_ref().expect("the table was read") }
+    }
+
+    /// Returns the table if it was read.
+    pub fn loaded(&self) -> Option<&T> {
+        self.value().map(|value| &value.0)
+    }
+
+    /// Returns the error reading the table, `None` if it was read or it
+    /// wasn't yet.
+    pub fn error(&self) -> Option<Error> {
+        self.value().and_then(|value| value.1)
+    }
+
+    fn value(&self) -> Option<&(T, Option<Error>)> {
+        if self.state.load(Ordering::Acquire) == READY {
+            unsafe { (*self.value.get()).as_ref() }
+        } else {
+            None
+        }
+    }
+}
+
+// Puts a table back to `EMPTY` if reading it panics, so that other threads
+// don't wait for it forever.
+struct Reset<'a>(&'a AtomicUsize);
+
+impl<'a> Drop for Reset<'a> {
+    fn drop(&mut self) {
+        self.0.store(EMPTY, Ordering::Release);
+    }
+}
+
+impl<T: Default + Clone> Clone for Lazy<T> {
+    fn clone(&self) -> Lazy<T> {
+        let (state, value) = match self.loaded() {
+            Some(_) => (READY, unsafe { (*self.value.get()).clone() }),
+            None => (EMPTY, None),
+        };
+        Lazy { offset: self.offset, state: AtomicUsize::new(state), value: UnsafeCell::new(value) }
+    }
+}
+
+impl<T: Default + fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lazy").field("offset", &self.offset).field("table", &self.loaded()).finish()
+    }
+}
+
+/// Only tables that were read count.
+impl<T: Default + HeapSize> HeapSize for Lazy<T> {
+    fn heap_size(&self) -> usize {
+        self.loaded().map_or(0, HeapSize::heap_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn reads_once() {
+        let table: Lazy<Vec<usize>> = Lazy::new(Some(4));
+        expect!(table.loaded()).to(be_none());
+        expect!(table.get(|offset| Ok(vec![offset]))).to(be_equal_to(&vec![4]));
+        expect!(table.get(|_| Err(Malformed))).to(be_equal_to(&vec![4]));
+        expect!(table.clone().loaded()).to(be_some().value(&vec![4]));
+
+        expect!(table.error()).to(be_none());
+
+        let malformed: Lazy<Vec<usize>> = Lazy::new(Some(4));
+        expect!(malformed.error()).to(be_none());
+        expect!(malformed.get(|_| Err(Malformed)).is_empty()).to(be_true());
+        expect!(malformed.error()).to(be_some().value(Malformed));
+        expect!(malformed.clone().error()).to(be_some().value(Malformed));
+
+        let missing: Lazy<Vec<usize>> = Lazy::new(None);
+        expect!(missing.get(|offset| Ok(vec![offset])).is_empty()).to(be_true());
+        expect!(missing.error()).to(be_none());
+    }
+
+    #[test]
+    fn reads_again_after_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let table: Lazy<Vec<usize>> = Lazy::new(Some(4));
+        let read = catch_unwind(AssertUnwindSafe(|| table.get(|_| panic!("malformed")).len()));
+        expect!(read.is_err()).to(be_true());
+        expect!(table.loaded()).to(be_none());
+        expect!(table.get(|offset| Ok(vec![offset]))).to(be_equal_to(&vec![4]));
+    }
+}
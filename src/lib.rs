@@ -241,32 +241,135 @@
 ////   The following sections allow you to supply alternate definitions
 ////   of C library functions used by stb_truetype.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[macro_use]
+extern crate alloc;
 extern crate byteorder;
-extern crate libc;
+extern crate libm;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "piston")]
+extern crate texture;
 
 #[cfg(test)]
 #[macro_use(expect)]
 extern crate expectest;
 
-use std::ptr::{ null, null_mut };
-use std::mem::size_of;
-use std::slice;
+use prelude::*;
+use core::ffi::c_void;
+use core::ptr::{ null, null_mut };
+use core::mem::size_of;
+use core::slice;
+use alloc::alloc::{alloc, dealloc, Layout};
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 use byteorder::{BigEndian, ByteOrder};
-use libc::{ c_void, free, malloc, size_t, c_char };
-use tables::{HHEA, HEAD, MAXP, HMTX, LOCA, CMAP, GLYF, GlyphData};
 
+/// Allocation types that are not in the prelude of `no_std` crates.
+mod prelude {
+    pub use alloc::borrow::{Cow, ToOwned};
+    pub use alloc::string::String;
+    pub use alloc::vec::Vec;
+}
+
+mod atlas;
+mod builder;
+mod cache;
+mod collection;
+mod eot;
 mod error;
+mod filter;
+mod gpu;
+mod lazy;
+#[cfg(feature = "image")]
+mod image;
+mod layout;
+mod outline;
+mod packed;
+#[cfg(feature = "piston")]
+mod piston;
+mod raster;
+#[cfg(feature = "std")]
+mod reader;
+mod simd;
+mod specimen;
+mod subset;
+#[cfg(feature = "system-fonts")]
+mod system;
 mod tables;
+#[cfg(feature = "tessellation")]
+mod tessellation;
 mod types;
 mod utils;
 
+pub use atlas::{Atlas, AtlasPage, PagedAtlas};
+pub use builder::{FontBuilder, OutlinePoint};
+pub use cache::{CacheStats, GlyphCache};
+#[cfg(feature = "std")]
+pub use cache::Prewarm;
+pub use collection::{FaceInfo, FontCollection, FontSummary};
+pub use eot::unwrap_eot;
 pub use error::Error;
-
-pub type Result<T> = ::std::result::Result<T, Error>;
+#[cfg(feature = "image")]
+pub use image::{write_pgm, write_png};
+pub use layout::{DEFAULT_FEATURES, Direction, LayoutOptions, PositionedGlyph, ShapedGlyph, from_shaped};
+pub use outline::{embolden, oblique};
+pub use packed::PackedBitmap;
+#[cfg(feature = "piston")]
+pub use piston::GlyphImage;
+pub use raster::{FillRule, GrayBitmap, PathSegment, RasterMode, RasterOptions, path_contains,
+    rasterize_bands, rasterize_path, rasterize_spans, rasterize_stroked_path};
+pub use subset::{subset, Subset};
+#[cfg(feature = "system-fonts")]
+pub use system::{find_default_font, find_system_font, load_system_font, system_font_dirs};
+pub use types::{CheckSums, DecorationMetrics, FontKind, GlyphId, GlyphStats, LineMetrics, MemoryUsage, MetricsSource,
+    Orientation};
+#[cfg(feature = "tessellation")]
+pub use tessellation::{CurveMesh, CurveVertex, Mesh, Mesh3D, curve_mesh, tessellate_path};
+pub use tables::{HHEA, HEAD, MacStyle, MAXP, HMTX, LongHorizontalMetric, LOCA, CMAP, GLYF, GlyphData, KERN,
+    Outline, Component, GASP, RenderingBehavior, HDMX, VDMX,
+    OS2, Embedding, Permissions, ScriptMetrics, META, NAME, Name, POST, BASE, ScriptBaselines,
+    GDEF, GlyphClass, Caret, GPOS, MarkAttachment, GSUB, KERX, MORX, TRAK,
+    Serialize};
+use lazy::Lazy;
+use tables::{TTCF, check_glyph};
+
+pub type Result<T> = ::core::result::Result<T, Error>;
 
 //   #define STBTT_ifloor(x)   ((int) floor(x))
 fn ifloor(x: f32) -> isize {
-    x.floor() as isize
+    libm::floorf(x) as isize
+}
+
+// Size of the header `malloc` stores the size of the block in, also
+// the alignment of the returned pointers.
+const MALLOC_HEADER: usize = 16;
+
+// Allocates with the global allocator, so it works without libc.
+unsafe fn malloc(size: usize) -> *mut c_void {
+    let layout = match Layout::from_size_align(size + MALLOC_HEADER, MALLOC_HEADER) {
+        Ok(layout) => layout,
+        Err(_) => return null_mut(),
+    };
+    let p = alloc(layout);
+    if p.is_null() {
+        return null_mut();
+    }
+    *(p as *mut usize) = layout.size();
+    p.offset(MALLOC_HEADER as isize) as *mut c_void
+}
+
+unsafe fn free(p: *mut c_void) {
+    if p.is_null() {
+        return;
+    }
+    let p = (p as *mut u8).offset(-(MALLOC_HEADER as isize));
+    let size = *(p as *mut usize);
+    dealloc(p, Layout::from_size_align_unchecked(size, MALLOC_HEADER));
 }
 
 macro_rules! STBTT_malloc {
@@ -293,11 +396,18 @@ macro_rules! STBTT_assert {
 
 //   #define STBTT_assert(x)    assert(x)
 
-use libc::strlen as STBTT_strlen;
+#[allow(non_snake_case)]
+unsafe fn STBTT_strlen(s: *const u8) -> usize {
+    let mut n = 0;
+    while *s.offset(n as isize) != 0 {
+        n += 1;
+    }
+    n
+}
 
 //   #define STBTT_strlen(x)    strlen(x)
 
-use std::ptr::copy as STBTT_memcpy;
+use core::ptr::copy as STBTT_memcpy;
 
 //   #define STBTT_memcpy       memcpy
 
@@ -326,28 +436,74 @@ fn memset(buf: *mut c_void, b: u8, count: usize) {
 // If you use this API, you only have to call two functions ever.
 //
 
+/// A character baked into a bitmap by `bake_font_bitmap`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BakedChar {
-    // coordinates of bbox in bitmap
-    x0: u16,
-    y0: u16,
-    x1: u16,
-    y1: u16,
-    xoff: f32,
-    yoff: f32,
-    xadvance: f32,
+    /// Left of the character in the bitmap.
+    pub x0: u16,
+    /// Top of the character in the bitmap.
+    pub y0: u16,
+    /// Right of the character in the bitmap.
+    pub x1: u16,
+    /// Bottom of the character in the bitmap.
+    pub y1: u16,
+    /// Offset from the origin of the character to the left of the quad.
+    pub xoff: f32,
+    /// Offset from the baseline to the top of the quad.
+    pub yoff: f32,
+    /// Distance to the origin of the next character.
+    pub xadvance: f32,
+}
+
+impl BakedChar {
+    /// Returns the quad to draw the character baked into a bitmap of `pw` by
+    /// `ph` pixels with its origin at `(xpos, ypos)`, and the `xpos` of the
+    /// next character.
+    ///
+    /// The quad is aligned to whole pixels, for OpenGL if `opengl_fillrule`
+    /// is `true` and for Direct3D 9 otherwise.
+    pub fn quad(&self, pw: usize, ph: usize, xpos: f32, ypos: f32, opengl_fillrule: bool)
+        -> (AlignedQuad, f32)
+    {
+        let (ipw, iph) = (1.0 / pw as f32, 1.0 / ph as f32);
+        let d3d_bias: f32 = if opengl_fillrule { 0.0 } else { -0.5 };
+        let round_x = ifloor((xpos + self.xoff) + 0.5) as f32;
+        let round_y = ifloor((ypos + self.yoff) + 0.5) as f32;
+        let quad = AlignedQuad {
+            x0: round_x + d3d_bias,
+            y0: round_y + d3d_bias,
+            x1: round_x + self.x1 as f32 - self.x0 as f32 + d3d_bias,
+            y1: round_y + self.y1 as f32 - self.y0 as f32 + d3d_bias,
+            s0: self.x0 as f32 * ipw,
+            t0: self.y0 as f32 * iph,
+            s1: self.x1 as f32 * ipw,
+            t1: self.y1 as f32 * iph,
+        };
+        (quad, xpos + self.xadvance)
+    }
 }
 
+/// A quad to draw a character from a texture with.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AlignedQuad {
-    // top-left
-    x0: f32,
-    y0: f32,
-    s0: f32,
-    t0: f32,
-    // bottom-right
-    x1: f32,
-    y1: f32,
-    s1: f32,
-    t1: f32,
+    /// Left of the quad on the screen.
+    pub x0: f32,
+    /// Top of the quad on the screen.
+    pub y0: f32,
+    /// Left of the character in the texture, from 0 to 1.
+    pub s0: f32,
+    /// Top of the character in the texture, from 0 to 1.
+    pub t0: f32,
+    /// Right of the quad on the screen.
+    pub x1: f32,
+    /// Bottom of the quad on the screen.
+    pub y1: f32,
+    /// Right of the character in the texture, from 0 to 1.
+    pub s1: f32,
+    /// Bottom of the character in the texture, from 0 to 1.
+    pub t1: f32,
 }
 
 //////////////////////////////////////////////////////////////////////////////
@@ -357,34 +513,192 @@ pub struct AlignedQuad {
 // This provides options for packing multiple fonts into one atlas, not
 // perfectly but better than nothing.
 
+/// A character packed into a bitmap by `pack_font_ranges`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PackedChar {
-    // coordinates of bbox in bitmap
-    x0: u16,
-    y0: u16,
-    x1: u16,
-    y1: u16,
-    xoff: f32,
-    yoff: f32,
-    xadvance: f32,
-    xoff2: f32,
-    yoff2: f32,
+    /// Left of the character in the bitmap.
+    pub x0: u16,
+    /// Top of the character in the bitmap.
+    pub y0: u16,
+    /// Right of the character in the bitmap.
+    pub x1: u16,
+    /// Bottom of the character in the bitmap.
+    pub y1: u16,
+    /// Offset from the origin of the character to the left of the quad.
+    pub xoff: f32,
+    /// Offset from the baseline to the top of the quad.
+    pub yoff: f32,
+    /// Distance to the origin of the next character.
+    pub xadvance: f32,
+    /// Offset from the origin of the character to the right of the quad.
+    pub xoff2: f32,
+    /// Offset from the baseline to the bottom of the quad.
+    pub yoff2: f32,
+    /// The page of a `PagedAtlas` the character is in, 0 for other bitmaps.
+    pub page: u16,
+}
+
+impl PackedChar {
+    /// Returns the quad to draw the character packed into a bitmap of `pw` by
+    /// `ph` pixels with its origin at `(xpos, ypos)`, and the `xpos` of the
+    /// next character.
+    ///
+    /// If `align_to_integer` is `true`, the quad is moved to whole pixels.
+    pub fn quad(&self, pw: usize, ph: usize, xpos: f32, ypos: f32, align_to_integer: bool)
+        -> (AlignedQuad, f32)
+    {
+        let (ipw, iph) = (1.0 / pw as f32, 1.0 / ph as f32);
+        let (x0, y0, x1, y1) = if align_to_integer {
+            let x = libm::floorf((xpos + self.xoff) + 0.5);
+            let y = libm::floorf((ypos + self.yoff) + 0.5);
+            (x, y, x + self.xoff2 - self.xoff, y + self.yoff2 - self.yoff)
+        } else {
+            (xpos + self.xoff, ypos + self.yoff, xpos + self.xoff2, ypos + self.yoff2)
+        };
+        let quad = AlignedQuad {
+            x0: x0,
+            y0: y0,
+            x1: x1,
+            y1: y1,
+            s0: self.x0 as f32 * ipw,
+            t0: self.y0 as f32 * iph,
+            s1: self.x1 as f32 * ipw,
+            t1: self.y1 as f32 * iph,
+        };
+        (quad, xpos + self.xadvance)
+    }
 }
 
 // TODO: Macro
 // #define STBTT_POINT_SIZE(x)   (-(x))
 
+/// How to pack the characters of a `PackRange`.
+///
+/// Oversampling a font increases the quality by allowing higher-quality
+/// subpixel positioning, and is especially valuable at smaller text sizes.
+/// The characters take `h_oversample * v_oversample` times as many pixels,
+/// render them with bilinear filtering. Every range has its own settings, so
+/// one atlas can mix qualities.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PackSettings {
+    /// Horizontal oversampling, from 1 (none) to 8.
+    pub h_oversample: u8,
+    /// Vertical oversampling, from 1 (none) to 8.
+    pub v_oversample: u8,
+    /// Empty pixels between the characters, `None` for the padding passed
+    /// to `pack_begin`.
+    pub padding: Option<u8>,
+    /// Fills the padding on all sides of a character with the pixels at its
+    /// edges, so that mipmaps and bilinear filtering at the boundaries don't
+    /// blend in the background as dark halos.
+    pub bleed: bool,
+    /// Renders every character this many times, each shifted right by
+    /// another fraction of a pixel, so that small text can be placed at
+    /// subpixel positions without rendering it every frame. 1 (or 0) renders
+    /// every character once.
+    ///
+    /// The characters of a range then take `num_chars * subpixel_phases`
+    /// `PackedChar`s, the phases of every character one after the other.
+    /// `get_packed_quad_phased` picks the phase nearest to the position.
+    pub subpixel_phases: u8,
+    /// The filter averaging the oversampled pixels.
+    pub filter: Prefilter,
+}
+
+/// The filter averaging oversampled pixels into the pixels of a packed
+/// character.
+///
+/// Both average as many pixels as the oversampling, so characters have the
+/// same size and position with either.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Prefilter {
+    /// Weighs the pixels equally, like stb_truetype, which blurs edges the
+    /// most.
+    Box,
+    /// Weighs the pixels less the further they are from the middle, e.g.
+    /// 1, 2, 1 with 3 times oversampling, which keeps small text sharper.
+    Tent,
+}
+
+impl Default for Prefilter {
+    fn default() -> Prefilter {
+        Prefilter::Box
+    }
+}
+
+impl Default for PackSettings {
+    /// No oversampling and the padding of the context, without bleeding or
+    /// subpixel phases, with the box filter.
+    fn default() -> PackSettings {
+        PackSettings { h_oversample: 1, v_oversample: 1, padding: None, bleed: false,
+            subpixel_phases: 1, filter: Prefilter::Box }
+    }
+}
+
+impl PackSettings {
+    // The oversampling, limited to what the prefilters support.
+    fn oversample(&self) -> (usize, usize) {
+        let limit = |o: u8| (o as usize).max(1).min(STBTT_MAX_OVERSAMPLE);
+        (limit(self.h_oversample), limit(self.v_oversample))
+    }
+
+    // The number of times every character is rendered.
+    fn phases(&self) -> usize {
+        (self.subpixel_phases as usize).max(1)
+    }
+
+    // The padding of the characters, the one of `spc` unless it's set.
+    fn padding(&self, spc: &PackContext) -> isize {
+        self.padding.map_or(spc.padding, |padding| padding as isize)
+    }
+}
+
 pub struct PackRange {
    font_size: f32,
    // if non-zero, then the chars are continuous, and this is the first codepoint
-   first_unicode_codepoint_in_range: isize,
+   first_unicode_codepoint_in_range: char,
    // if non-zero, then this is an array of unicode codepoints
-   array_of_unicode_codepoints: *const isize,
+   array_of_unicode_codepoints: *const char,
    num_chars: isize,
    // output
    chardata_for_range: *mut PackedChar,
-   // don't set these, they're used internally
-   h_oversample: u8,
-   v_oversample: u8,
+   settings: PackSettings,
+}
+
+impl PackRange {
+    /// Returns a range of `num_chars` characters from `first_codepoint`,
+    /// packed into `chardata_for_range` with the default settings.
+    ///
+    /// A positive `font_size` is the height from ascender to descender in
+    /// pixels, a negative one the size of the em in pixels.
+    pub fn new(font_size: f32, first_codepoint: char, num_chars: isize,
+        chardata_for_range: *mut PackedChar) -> PackRange
+    {
+        PackRange {
+            font_size: font_size,
+            first_unicode_codepoint_in_range: first_codepoint,
+            array_of_unicode_codepoints: null(),
+            num_chars: num_chars,
+            chardata_for_range: chardata_for_range,
+            settings: PackSettings::default(),
+        }
+    }
+
+    /// Returns the range packed with `settings`.
+    pub fn with_settings(self, settings: PackSettings) -> PackRange {
+        PackRange { settings: settings, ..self }
+    }
+
+    // The codepoint of the character at index `j` of the range, which may
+    // be a surrogate if the range spans them.
+    unsafe fn codepoint(&self, j: isize) -> usize {
+        if self.array_of_unicode_codepoints.is_null() {
+            self.first_unicode_codepoint_in_range as usize + j as usize
+        } else {
+            *self.array_of_unicode_codepoints.offset(j) as usize
+        }
+    }
 }
 
 // this is an opaque structure that you shouldn't mess with which holds
@@ -396,12 +710,26 @@ pub struct PackContext {
    height: isize,
    stride_in_bytes: isize,
    padding: isize,
-   h_oversample: usize,
-   v_oversample: usize,
    pixels: *mut u8,
    nodes: *mut c_void,
 }
 
+impl Default for PackContext {
+    /// An empty context to pass to `pack_begin`.
+    fn default() -> PackContext {
+        PackContext {
+            user_allocator_context: null(),
+            pack_info: null_mut(),
+            width: 0,
+            height: 0,
+            stride_in_bytes: 0,
+            padding: 0,
+            pixels: null_mut(),
+            nodes: null_mut(),
+        }
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////
 //
 // FONT LOADING
@@ -410,30 +738,80 @@ pub struct PackContext {
 
 // The following structure is defined publically so you can declare one on
 // the stack or as a global or etc, but you should treat it as opaque.
+//
+// A parsed font only changes to read tables on first use, which is
+// synchronized, and contains no raw pointers, so it is `Send` and `Sync`:
+// a single `FontInfo` (e.g. an `OwnedFontInfo` in an `Arc`) can be shared
+// by threads rasterizing glyphs in parallel. The rasterizer keeps
+// all of its state on the stack or in buffers it allocates per call.
+#[derive(Debug, Clone)]
 pub struct FontInfo<'a> {
-   // pointer to .ttf file
-   data: &'a [u8],
+   // .ttf file, either borrowed or owned
+   data: Cow<'a, [u8]>,
    // offset of start of font
    fontstart: usize,
 
    hhea: HHEA,
    head: HEAD,
    hmtx: HMTX,
-   loca: LOCA,
-   cmap: CMAP,
-   glyf: GLYF,
-
-   // table locations as offset from start of .ttf
-   _glyf: usize,
-   kern: usize,
+   num_glyphs: u32,
+
+   // the other tables are read when they are first needed, see `Lazy`
+   loca: Lazy<LOCA>,
+   os2: Lazy<Option<OS2>>,
+   post: Lazy<Option<POST>>,
+   cmap: Lazy<CMAP<'static>>,
+   kern: Lazy<KERN>,
+   kerx: Lazy<KERX>,
+   gasp: Lazy<Option<GASP>>,
+   hdmx: Lazy<Option<HDMX>>,
+   vdmx: Lazy<Option<VDMX>>,
+   base: Lazy<BASE>,
+   gdef: Lazy<GDEF>,
+   gpos: Lazy<GPOS>,
+   gsub: Lazy<GSUB>,
+   morx: Lazy<MORX>,
+   trak: Lazy<TRAK>,
+   meta: Lazy<META>,
+   name: Lazy<NAME>,
+   // the reverse of the cmap, built the first time it's asked for
+   codepoints_by_glyph: Lazy<BTreeMap<GlyphId, Vec<char>>>,
+
+   // offset of the glyf table from start of .ttf, the glyf table
+   // borrows the data so it's created on demand
+   glyf: usize,
+
+   max_component_depth: usize,
 }
 
+/// The data of the font returned by `FontInfo::builtin`.
+#[cfg(feature = "builtin-font")]
+pub static BUILTIN_FONT: &[u8] = include_bytes!("../assets/Tuffy_Bold.ttf");
+
+/// The default of `FontInfo::max_component_depth`.
+pub const DEFAULT_MAX_COMPONENT_DEPTH: usize = 16;
+
+/// A font that owns its data, see `FontInfo::from_vec`.
+pub type OwnedFontInfo = FontInfo<'static>;
+
 impl<'a> FontInfo<'a> {
     // Given an offset into the file that defines a font, this function builds
-    // the necessary cached info for the rest of the system.
-    pub fn new_with_offset(data: &[u8], fontstart: usize) -> Result<FontInfo> {
+    // the necessary cached info for the rest of the system. Only the headers
+    // and the metrics are read, the other tables on first use.
+    //
+    // So a malformed table other than those, even the character map or the
+    // glyph locations, doesn't make this fail but is read as empty, and the
+    // font has no glyphs for characters or no outlines. `parse_strict` or
+    // `load_tables` find such tables up front.
+    pub fn new_with_offset(data: &'a [u8], fontstart: usize) -> Result<FontInfo<'a>> {
         use utils::{find_table_offset, find_required_table_offset};
 
+        // Its tables look like those of TrueType, but the glyphs are Type 1
+        // CharStrings.
+        if fontstart < data.len() && FontKind::detect(&data[fontstart..]) == Some(FontKind::Type1) {
+            return Err(Error::Type1NotSupported);
+        }
+
         let hhea = try!(HHEA::from_data(&data,
                         try!(find_required_table_offset(data, fontstart, b"hhea"))));
 
@@ -448,35 +826,396 @@ impl<'a> FontInfo<'a> {
                         hhea.num_of_long_hor_metrics(),
                         maxp.num_glyphs()));
 
-        let loca = try!(LOCA::from_data(&data,
-                        try!(find_required_table_offset(data, fontstart, b"loca")),
-                        maxp.num_glyphs(),
-                        head.location_format()));
-
-        let cmap = try!(CMAP::from_data(&data,
-                        try!(find_required_table_offset(data, fontstart, b"cmap"))));
-
-        let _glyf = try!(find_required_table_offset(data, fontstart, b"glyf"));
-        let glyf = try!(GLYF::from_data(&data, _glyf, loca.size_of_glyf_table()));
-
-        let kern = try!(find_table_offset(data, fontstart, b"kern")).unwrap_or(0);
-
+        // Only the tables themselves are found, they are read on demand.
+        let table = |tag: &[u8; 4]| find_table_offset(data, fontstart, tag);
         let info = FontInfo {
-            data: data,
+            data: Cow::Borrowed(data),
             fontstart: fontstart,
             hhea: hhea,
             head: head,
             hmtx: hmtx,
-            loca: loca,
-            cmap: cmap,
-            glyf: glyf,
-            _glyf: _glyf,
-            kern: kern,
+            num_glyphs: maxp.num_glyphs(),
+            loca: Lazy::new(Some(try!(find_required_table_offset(data, fontstart, b"loca")))),
+            cmap: Lazy::new(Some(try!(find_required_table_offset(data, fontstart, b"cmap")))),
+            os2: Lazy::new(try!(table(b"OS/2"))),
+            post: Lazy::new(try!(table(b"post"))),
+            kern: Lazy::new(try!(table(b"kern"))),
+            kerx: Lazy::new(try!(table(b"kerx"))),
+            gasp: Lazy::new(try!(table(b"gasp"))),
+            hdmx: Lazy::new(try!(table(b"hdmx"))),
+            vdmx: Lazy::new(try!(table(b"VDMX"))),
+            base: Lazy::new(try!(table(b"BASE"))),
+            gdef: Lazy::new(try!(table(b"GDEF"))),
+            gpos: Lazy::new(try!(table(b"GPOS"))),
+            gsub: Lazy::new(try!(table(b"GSUB"))),
+            morx: Lazy::new(try!(table(b"morx"))),
+            trak: Lazy::new(try!(table(b"trak"))),
+            meta: Lazy::new(try!(table(b"meta"))),
+            name: Lazy::new(try!(table(b"name"))),
+            codepoints_by_glyph: Lazy::new(Some(0)),
+            glyf: try!(find_required_table_offset(data, fontstart, b"glyf")),
+            max_component_depth: DEFAULT_MAX_COMPONENT_DEPTH,
         };
 
         Ok(info)
     }
 
+    /// Same as `new_with_offset`, but the font takes ownership of `data`,
+    /// so it isn't tied to the lifetime of a buffer.
+    pub fn from_vec(data: Vec<u8>, fontstart: usize) -> Result<FontInfo<'static>> {
+        let info = try!(FontInfo::new_with_offset(&data, fontstart)).with_data(Cow::Owned(vec![]));
+        Ok(FontInfo { data: Cow::Owned(data), ..info })
+    }
+
+    /// Returns Tuffy Bold, a public domain font built into the crate with
+    /// the `builtin-font` feature, to show text before loading a font.
+    #[cfg(feature = "builtin-font")]
+    pub fn builtin() -> FontInfo<'static> {
+        FontInfo::new_with_offset(BUILTIN_FONT, 0).expect("the built in font is valid")
+    }
+
+    /// Same as `new_with_offset`, but meant for untrusted data.
+    ///
+    /// Uses the first font of a collection. Besides the tables read by
+    /// `new_with_offset`, reads the tables it leaves for later, and checks
+    /// the table directory and the outlines of all glyphs, so that getting
+    /// glyph shapes and rendering them never reads out of bounds or panics.
+    ///
+    /// # Errors
+    /// Returns the errors of `new_with_offset` and `load_tables`, so also
+    /// those of a malformed character map or glyph locations, or
+    /// `Error::Malformed` if a table or a glyph is truncated, glyphs overlap
+    /// or composite glyphs are nested too deep.
+    pub fn parse_strict(data: &'a [u8]) -> Result<FontInfo<'a>> {
+        let fontstart = if FontKind::detect(data) == Some(FontKind::TrueTypeCollection) {
+            let ttcf = try!(TTCF::from_data(data, 0));
+            try!(ttcf.offset_for_font_at_index(0).ok_or(Error::Malformed))
+        } else {
+            0
+        };
+        match FontKind::detect(data.get(fontstart..).unwrap_or(&[])) {
+            Some(FontKind::Type1) => return Err(Error::Type1NotSupported),
+            Some(kind) if kind.is_font() => {}
+            _ => return Err(Error::Malformed),
+        }
+        // Checks that all tables are within bounds.
+        try!(utils::read_table_records(data, fontstart));
+
+        let info = try!(FontInfo::new_with_offset(data, fontstart));
+        try!(info.load_tables());
+        try!(info.check_glyphs());
+        Ok(info)
+    }
+
+    fn check_glyphs(&self) -> Result<()> {
+        let glyf = self.glyf_data();
+        let offsets = self.loca().offsets();
+        if offsets.windows(2).any(|range| range[0] > range[1]) {
+            return Err(Error::Malformed);
+        }
+        let mut components = vec![];
+        for range in offsets.windows(2) {
+            components.push(try!(check_glyph(&glyf[range[0] as usize..range[1] as usize])));
+        }
+
+        // Composite glyphs are rendered recursively, limiting the depth
+        // also rejects glyphs that contain themselves.
+        fn depth(components: &[Vec<usize>], depths: &mut [Option<usize>],
+            i: usize, level: usize, max: usize) -> Result<usize>
+        {
+            if level > max {
+                return Err(Error::Malformed);
+            }
+            if let Some(d) = depths[i] {
+                return Ok(d);
+            }
+            let mut d = 0;
+            for &c in components[i].iter().filter(|&&c| c < components.len()) {
+                d = ::core::cmp::max(d, 1 + try!(depth(components, depths, c, level + 1, max)));
+            }
+            if d > max {
+                return Err(Error::Malformed);
+            }
+            depths[i] = Some(d);
+            Ok(d)
+        }
+        let mut depths = vec![None; components.len()];
+        for i in 0..components.len() {
+            try!(depth(&components, &mut depths, i, 0, self.max_component_depth));
+        }
+        Ok(())
+    }
+
+    /// Reads the tables that are otherwise read when they are first needed,
+    /// like the character map and the layout tables.
+    ///
+    /// Fonts load faster without them, and a table that can't be read is
+    /// empty, as if the font didn't have it. Call this to read them up
+    /// front, or to find out if they are malformed.
+    ///
+    /// # Errors
+    /// Returns the first error reading a table, the tables are read even if
+    /// one of them is malformed.
+    pub fn load_tables(&self) -> Result<()> {
+        self.loca();
+        self.cmap();
+        self.os2();
+        self.post();
+        self.gasp();
+        self.hdmx();
+        self.vdmx();
+        self.kern();
+        self.kerx();
+        self.base();
+        self.gdef();
+        self.gpos();
+        self.gsub();
+        self.morx();
+        self.trak();
+        self.meta();
+        self.name();
+        let errors = [self.loca.error(), self.cmap.error(), self.os2.error(), self.post.error(),
+            self.gasp.error(), self.hdmx.error(), self.vdmx.error(), self.kern.error(),
+            self.kerx.error(), self.base.error(), self.gdef.error(), self.gpos.error(),
+            self.gsub.error(), self.morx.error(), self.trak.error(), self.meta.error(),
+            self.name.error()];
+        match errors.iter().filter_map(|&error| error).next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Recomputes the checksums of all tables of the font and the
+    /// `checkSumAdjustment` of its `head` table.
+    ///
+    /// Useful to validate downloaded or patched fonts, the checksums are
+    /// not verified when a font is loaded.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if a table doesn't fit into the data and
+    /// `Error::MissingTable` if the font has no `head` table.
+    pub fn verify_checksums(&self) -> Result<CheckSums> {
+        utils::verify_check_sums(&self.data, self.fontstart)
+    }
+
+    /// Returns a copy of the font that owns its data.
+    ///
+    /// Doesn't copy the data if the font already owns it.
+    pub fn into_owned(mut self) -> FontInfo<'static> {
+        let data = ::core::mem::replace(&mut self.data, Cow::Borrowed(&[])).into_owned();
+        self.with_data(Cow::Owned(data))
+    }
+
+    fn with_data<'b>(self, data: Cow<'b, [u8]>) -> FontInfo<'b> {
+        FontInfo {
+            data: data,
+            fontstart: self.fontstart,
+            hhea: self.hhea,
+            head: self.head,
+            hmtx: self.hmtx,
+            num_glyphs: self.num_glyphs,
+            loca: self.loca,
+            cmap: self.cmap,
+            os2: self.os2,
+            post: self.post,
+            kern: self.kern,
+            kerx: self.kerx,
+            gasp: self.gasp,
+            hdmx: self.hdmx,
+            vdmx: self.vdmx,
+            base: self.base,
+            gdef: self.gdef,
+            gpos: self.gpos,
+            gsub: self.gsub,
+            morx: self.morx,
+            trak: self.trak,
+            meta: self.meta,
+            name: self.name,
+            codepoints_by_glyph: self.codepoints_by_glyph,
+            glyf: self.glyf,
+            max_component_depth: self.max_component_depth,
+        }
+    }
+
+    /// Returns the maximum nesting of compound glyphs.
+    ///
+    /// Compound glyphs nested deeper can't be read, as if the font was
+    /// corrupt. Defaults to `DEFAULT_MAX_COMPONENT_DEPTH`.
+    pub fn max_component_depth(&self) -> usize {
+        self.max_component_depth
+    }
+
+    /// Sets the maximum nesting of compound glyphs, see `max_component_depth`.
+    pub fn set_max_component_depth(&mut self, depth: usize) {
+        self.max_component_depth = depth;
+    }
+
+    /// Returns the font file data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the bytes the font holds on to, the font file if it owns it
+    /// and the tables it has read so far.
+    ///
+    /// Most tables are read when they are first needed, so the usage grows
+    /// once text is mapped and laid out, and `load_tables` reads them all.
+    /// Outlines are read from the file when they are needed, so rendering
+    /// doesn't add to it.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        use utils::HeapSize;
+
+        let data = match self.data {
+            Cow::Borrowed(_) => 0,
+            Cow::Owned(ref data) => data.capacity(),
+        };
+        let tables = self.hmtx.heap_size() + self.loca.heap_size() + self.cmap.heap_size()
+            + self.kern.heap_size() + self.kerx.heap_size() + self.gasp.heap_size()
+            + self.hdmx.heap_size() + self.vdmx.heap_size() + self.base.heap_size()
+            + self.gdef.heap_size() + self.gpos.heap_size() + self.gsub.heap_size()
+            + self.morx.heap_size() + self.trak.heap_size() + self.meta.heap_size()
+            + self.name.heap_size() + self.codepoints_by_glyph.heap_size();
+        MemoryUsage { data: data, tables: size_of::<FontInfo>() + tables }
+    }
+
+    /// Returns the header of the font.
+    pub fn head(&self) -> &HEAD {
+        &self.head
+    }
+
+    /// Returns the horizontal header of the font, with its vertical
+    /// metrics and the slope of the caret.
+    pub fn hhea(&self) -> &HHEA {
+        &self.hhea
+    }
+
+    /// Returns the `OS/2` table of the font, if it has one that can be read.
+    pub fn os2(&self) -> Option<&OS2> {
+        self.os2.get(|offset| {
+            // The fields the table has depend on its length.
+            let length = try!(utils::find_table_with_length(&self.data, self.fontstart, b"OS/2"))
+                .map_or(0, |(_, length)| length);
+            OS2::from_data(&self.data, offset, length).map(Some)
+        }).as_ref()
+    }
+
+    /// Returns the `post` table of the font, if it has one that can be read.
+    pub fn post(&self) -> Option<&POST> {
+        self.post.get(|offset| POST::from_data(&self.data, offset).map(Some)).as_ref()
+    }
+
+    /// Returns the baseline table of the font, empty if it has none.
+    pub fn base(&self) -> &BASE {
+        self.base.get(|offset| BASE::from_data(&self.data, offset))
+    }
+
+    /// Returns the position of the baseline `tag`, e.g. `BASE::IDEOGRAPHIC`,
+    /// for `script` written horizontally, in font units from the baseline
+    /// glyphs are drawn on.
+    ///
+    /// `None` if the font has no `BASE` table or it doesn't give the
+    /// baseline for the script.
+    pub fn baseline_offset(&self, script: &[u8; 4], tag: &[u8; 4]) -> Option<i16> {
+        self.base().script(script).and_then(|baselines| baselines.offset(tag))
+    }
+
+    /// Returns the glyph definition table of the font, empty if it has
+    /// none.
+    pub fn gdef(&self) -> &GDEF {
+        self.gdef.get(|offset| GDEF::from_data(&self.data, offset))
+    }
+
+    /// Returns `true` if `glyph` is a mark, like a combining accent, that is
+    /// attached to the glyph before it.
+    ///
+    /// Fonts without glyph classes in their `GDEF` table are taken to mark
+    /// the glyphs their `GPOS` table attaches as marks.
+    pub fn is_mark(&self, glyph: GlyphId) -> bool {
+        if self.gdef().has_glyph_classes() {
+            self.gdef().glyph_class(glyph.index()) == Some(GlyphClass::Mark)
+        } else {
+            self.gpos().is_mark(glyph.index())
+        }
+    }
+
+    /// Returns the x coordinates of the carets between the components of
+    /// the ligature `glyph` in font units, from left to right, empty for
+    /// other glyphs.
+    ///
+    /// Carets on points of the outline of a composite glyph are skipped.
+    pub fn ligature_carets(&self, glyph: GlyphId) -> Vec<i16> {
+        let points: Vec<OutlinePoint> = match self.glyph_outline(glyph) {
+            Ok(Outline::Simple(contours)) => contours.into_iter().flat_map(|c| c.into_iter()).collect(),
+            _ => vec![],
+        };
+        self.gdef().ligature_carets(glyph.index()).iter().filter_map(|caret| match *caret {
+            Caret::Coordinate(x) => Some(x),
+            Caret::Point(point) => points.get(point as usize).map(|p| p.x),
+        }).collect()
+    }
+
+    /// Returns the glyph positioning table of the font, empty if it has
+    /// none.
+    pub fn gpos(&self) -> &GPOS {
+        self.gpos.get(|offset| GPOS::from_data(&self.data, offset))
+    }
+
+    /// Returns the glyph substitution table of the font, empty if it has
+    /// none.
+    pub fn gsub(&self) -> &GSUB {
+        self.gsub.get(|offset| GSUB::from_data(&self.data, offset))
+    }
+
+    /// Returns the extended glyph metamorphosis table of Apple fonts, empty
+    /// if the font has none.
+    pub fn morx(&self) -> &MORX {
+        self.morx.get(|offset| MORX::from_data(&self.data, offset, self.num_glyphs))
+    }
+
+    /// Returns the tracking table of Apple fonts, empty if the font has
+    /// none.
+    pub fn trak(&self) -> &TRAK {
+        self.trak.get(|offset| TRAK::from_data(&self.data, offset))
+    }
+
+    /// Returns `true` if the `GSUB` or the `GPOS` table of the font has the
+    /// OpenType feature `tag`, for any script.
+    pub fn has_feature(&self, tag: &[u8; 4]) -> bool {
+        self.gsub().features().contains(tag) || self.gpos().features().contains(tag)
+    }
+
+    /// Returns the `meta` table of the font, empty if it has none.
+    pub fn meta(&self) -> &META {
+        self.meta.get(|offset| META::from_data(&self.data, offset))
+    }
+
+    /// Returns the naming table of the font, empty if it has none.
+    pub fn name(&self) -> &NAME {
+        self.name.get(|offset| NAME::from_data(&self.data, offset))
+    }
+
+    /// Returns how the font may be embedded in documents, from the `fsType`
+    /// field of its `OS/2` table.
+    ///
+    /// Fonts without the table have no restrictions.
+    pub fn permissions(&self) -> Permissions {
+        self.os2().map(|os2| os2.permissions()).unwrap_or_default()
+    }
+
+    /// Returns the ScriptLangTags of the languages the font supports, from
+    /// its `meta` table.
+    ///
+    /// Falls back to the languages the font was designed for, and is empty
+    /// if the font has neither.
+    pub fn script_tags(&self) -> Vec<&str> {
+        let languages = if self.meta().supported_languages().is_empty() {
+            self.meta().design_languages()
+        } else {
+            self.meta().supported_languages()
+        };
+        languages.iter().map(|tag| tag.as_str()).collect()
+    }
+
     // computes a scale factor to produce a font whose "height" is 'pixels' tall.
     // Height is measured as the distance from the highest ascender to the lowest
     // descender; in other words, it's equivalent to calling stbtt_GetFontVMetrics
@@ -494,25 +1233,521 @@ impl<'a> FontInfo<'a> {
        pixels / self.head.units_per_em()
     }
 
+    /// Returns the scale for a font size of `px` pixels, the size of the
+    /// em like the font sizes of CSS and most text APIs.
+    ///
+    /// Same as `scale_for_mapping_em_to_pixels`, see `scale_for_pixel_height`
+    /// to fit the ascenders and descenders in `px` instead.
+    pub fn scale_for_px(&self, px: f32) -> f32 {
+        self.scale_for_mapping_em_to_pixels(px)
+    }
+
+    /// Returns the scale for a font size of `pt` points on a display with
+    /// `dpi` pixels per inch, with 72 points per inch.
+    ///
+    /// Windows assumes 96 pixels per inch, so 12 points are 16 pixels,
+    /// macOS assumes 72, so points are pixels.
+    pub fn scale_for_pt(&self, pt: f32, dpi: f32) -> f32 {
+        self.scale_for_px(pt * dpi / 72.0)
+    }
+
+    /// Returns the pixels per em at `scale`, the font size in pixels, e.g.
+    /// for `rendering_behavior`.
+    pub fn px_per_em(&self, scale: f32) -> f32 {
+        scale * self.head.units_per_em()
+    }
+
+    /// Returns whether to hint and antialias the font at `ppem` pixels per
+    /// em, from its `gasp` table.
+    ///
+    /// Fonts without the table, or with a malformed one, are hinted and
+    /// antialiased at all sizes, like on Windows.
+    pub fn rendering_behavior(&self, ppem: u16) -> RenderingBehavior {
+        match self.gasp() {
+            Some(gasp) => gasp.rendering_behavior(ppem),
+            None => RenderingBehavior::default(),
+        }
+    }
+
+    // Returns the advance width of `glyph` in whole pixels at `ppem` pixels
+    // per em, for `LayoutOptions::pixel_grid`.
+    //
+    // Prefers the hinted width of the `hdmx` table, like Windows does, and
+    // rounds the scaled advance width for sizes the table doesn't have.
+    fn pixel_advance_width(&self, glyph: GlyphId, ppem: u16) -> i32 {
+        match self.hdmx().and_then(|hdmx| hdmx.advance_width(glyph.index(), ppem)) {
+            Some(width) => width as i32,
+            None => {
+                let scale = self.scale_for_mapping_em_to_pixels(ppem as f32);
+                libm::roundf(self.advance_width(glyph) as f32 * scale) as i32
+            }
+        }
+    }
+
+    /// Returns the ascent and descent of the font in whole pixels at `ppem`
+    /// pixels per em, for lines of text laid out with
+    /// `LayoutOptions::pixel_grid`.
+    ///
+    /// Prefers the hinted extents of the `VDMX` table, like Windows does,
+    /// and rounds the scaled `hhea` ascent and descent for sizes the table
+    /// doesn't have. The descent is negative below the baseline.
+    pub fn pixel_vertical_extents(&self, ppem: u16) -> (i32, i32) {
+        match self.vdmx().and_then(|vdmx| vdmx.extents(ppem)) {
+            Some((y_max, y_min)) => (y_max as i32, y_min as i32),
+            None => {
+                let scale = self.scale_for_mapping_em_to_pixels(ppem as f32);
+                (libm::roundf(self.hhea.ascent() as f32 * scale) as i32,
+                 libm::roundf(self.hhea.descent() as f32 * scale) as i32)
+            }
+        }
+    }
+
+    /// Returns the ascent, descent and line gap of the font from `source`,
+    /// to lay out lines like a platform does.
+    ///
+    /// Fonts without the metrics of the `OS/2` table have those of `hhea`.
+    /// With `MetricsSource::Win` the line gap is the part of the one of
+    /// `hhea` that doesn't fit between the Windows ascent and descent, like
+    /// the external leading of GDI.
+    pub fn line_metrics(&self, source: MetricsSource) -> LineMetrics {
+        let hhea = LineMetrics {
+            ascent: self.hhea.ascent(),
+            descent: self.hhea.descent(),
+            line_gap: self.hhea.line_gap(),
+        };
+        let os2 = match self.os2() {
+            Some(os2) => os2,
+            None => return hhea,
+        };
+        match source {
+            MetricsSource::Hhea => hhea,
+            MetricsSource::Typo => match os2.typo_metrics() {
+                Some((ascender, descender, line_gap)) => LineMetrics {
+                    ascent: ascender as i32,
+                    descent: descender as i32,
+                    line_gap: line_gap as i32,
+                },
+                None => hhea,
+            },
+            MetricsSource::Win => match os2.win_metrics() {
+                Some((ascent, descent)) => {
+                    let extra = (ascent as i32 + descent as i32) - (hhea.ascent - hhea.descent);
+                    LineMetrics {
+                        ascent: ascent as i32,
+                        descent: -(descent as i32),
+                        line_gap: (hhea.line_gap - extra).max(0),
+                    }
+                }
+                None => hhea,
+            },
+        }
+    }
+
+    /// Returns where the font designer wants underlines and strikeouts, from
+    /// the `post` and `OS/2` tables.
+    ///
+    /// Fonts without the tables have lines a fourteenth of the em thick, the
+    /// underline halfway down the descender and the strikeout at a third of
+    /// the ascender.
+    pub fn decoration_metrics(&self) -> DecorationMetrics {
+        let thickness = libm::roundf(self.head.units_per_em() / 14.0) as i32;
+        let (underline_position, underline_thickness) = match self.post() {
+            Some(post) if post.underline_thickness() > 0 => {
+                (post.underline_position() as i32, post.underline_thickness() as i32)
+            }
+            _ => (self.hhea.descent() / 2, thickness),
+        };
+        let (strikeout_position, strikeout_thickness) =
+            match self.os2().and_then(|os2| os2.strikeout()) {
+                Some((position, size)) if size > 0 => (position as i32, size as i32),
+                _ => (self.hhea.ascent() / 3, underline_thickness),
+            };
+        DecorationMetrics {
+            underline_position: underline_position,
+            underline_thickness: underline_thickness,
+            strikeout_position: strikeout_position,
+            strikeout_thickness: strikeout_thickness,
+        }
+    }
+
+    /// Returns the height of lowercase letters in pixels at `scale`, e.g. to
+    /// align text to a baseline grid by its x-height.
+    ///
+    /// Comes from the `OS/2` table, or else the top of the glyph of 'x', or
+    /// else half the em.
+    pub fn x_height(&self, scale: f32) -> f32 {
+        let height = self.os2().and_then(|os2| os2.x_height()).map(|h| h as f32)
+            .or_else(|| self.letter_height('x'))
+            .unwrap_or(self.head.units_per_em() * 0.5);
+        height * scale
+    }
+
+    /// Returns the height of capital letters in pixels at `scale`.
+    ///
+    /// Comes from the `OS/2` table, or else the top of the glyph of 'H', or
+    /// else 70% of the em.
+    pub fn cap_height(&self, scale: f32) -> f32 {
+        let height = self.os2().and_then(|os2| os2.cap_height()).map(|h| h as f32)
+            .or_else(|| self.letter_height('H'))
+            .unwrap_or(self.head.units_per_em() * 0.7);
+        height * scale
+    }
+
+    // The top of the glyph of 'letter' in font units, if the font has it.
+    fn letter_height(&self, letter: char) -> Option<f32> {
+        match self.glyph_index_for_code(letter) {
+            GlyphId(0) => None,
+            glyph => self.glyph_data_for_glyph_at_index(glyph).bounding_box()
+                .map(|bbox| bbox.y1 as f32),
+        }
+    }
+
     /// Returns the offset to the location of the glyph in the font.
     ///
-    /// Returns `None` if `i` is out of bounds or if the font does not contain
-    /// an outline for the glyph at index `i`.
-    pub fn offset_for_glyph_at_index(&self, i: usize) -> Option<usize> {
-        self.loca.offset_for_glyph_at_index(i).map(|c| c + self._glyf)
+    /// Returns `None` if `glyph` is out of bounds or if the font does not
+    /// contain an outline for it.
+    pub fn offset_for_glyph_at_index(&self, glyph: GlyphId) -> Option<usize> {
+        self.loca().offset_for_glyph_at_index(glyph.index()).map(|c| c + self.glyf)
+    }
+
+    /// Returns the glyph of character `c`.
+    ///
+    /// Returns glyph 0 (special glyph representing a missing character) if
+    /// the font doesn't have one.
+    pub fn glyph_index_for_code(&self, c: char) -> GlyphId {
+        self.glyph_for_code(c as usize)
+    }
+
+    // Same as `glyph_index_for_code` for any code, which ranges of
+    // characters can reach. Glyphs past 65535 in a malformed map are missing.
+    fn glyph_for_code(&self, code: usize) -> GlyphId {
+        match self.cmap().index_for_code_in(&self.data, code) {
+            Some(index) if index <= 0xffff => GlyphId(index as u16),
+            _ => GlyphId(0),
+        }
+    }
+
+    /// Returns the characters the font has a glyph for, in ascending order.
+    ///
+    /// Walks the ranges of the `cmap` table, so it is much faster than
+    /// looking up every character.
+    pub fn codepoints<'b>(&'b self) -> impl Iterator<Item = char> + 'b {
+        self.cmap().codes_in(&self.data)
+    }
+
+    /// Returns `true` if the font has a glyph for character `c`.
+    pub fn supports(&self, c: char) -> bool {
+        self.cmap().index_for_code_in(&self.data, c as usize).unwrap_or(0) != 0
+    }
+
+    /// Returns the characters that map to `glyph`, in ascending order.
+    ///
+    /// Looks the glyph up in `codepoints_by_glyph`, so the first call walks
+    /// all characters of the font and later ones are fast.
+    pub fn codepoints_for_glyph(&self, glyph: GlyphId) -> Vec<char> {
+        self.codepoints_by_glyph().get(&glyph).cloned().unwrap_or_default()
+    }
+
+    /// Returns the characters that map to each glyph, the reverse of the
+    /// `cmap` table, e.g. for the `ToUnicode` maps of PDF files.
+    ///
+    /// The map is built the first time it's asked for and kept by the
+    /// font. Glyphs no character maps to are missing.
+    pub fn codepoints_by_glyph(&self) -> &BTreeMap<GlyphId, Vec<char>> {
+        self.codepoints_by_glyph.get(|_| {
+            let mut map = BTreeMap::new();
+            for c in self.codepoints() {
+                map.entry(self.glyph_index_for_code(c)).or_insert_with(Vec::new).push(c);
+            }
+            Ok(map)
+        })
+    }
+
+    /// Returns the horizontal metrics of `glyph`.
+    ///
+    /// A compound glyph has the metrics of its component with the
+    /// USE_MY_METRICS flag, if there is one.
+    pub fn hmetric_for_glyph_at_index(&self, glyph: GlyphId) -> LongHorizontalMetric {
+        const USE_MY_METRICS: u16 = 1 << 9;
+
+        let mut i = glyph.index();
+        // Compound glyphs of a corrupt font may contain themselves.
+        for _ in 0..self.max_component_depth {
+            if self.loca().offset_for_glyph_at_index(i).is_none() {
+                break;
+            }
+            let components = self.glyph_data(i).components().unwrap_or(vec![]);
+            match components.iter().find(|&&(flags, _)| flags & USE_MY_METRICS != 0) {
+                Some(&(_, component)) => i = component,
+                None => break,
+            }
+        }
+        self.hmtx.hmetric_for_glyph_at_index(i)
+    }
+
+    /// Returns the advance width of `glyph` in font units, straight from the
+    /// `hmtx` table for measuring text quickly.
+    ///
+    /// Unlike `hmetric_for_glyph_at_index`, it doesn't follow the
+    /// USE_MY_METRICS flag of compound glyphs, which only matters for fonts
+    /// whose `hmtx` table disagrees with the components.
+    pub fn advance_width(&self, glyph: GlyphId) -> u16 {
+        self.hmtx.advance_width_for_glyph_at_index(glyph.index())
+    }
+
+    /// Returns the outline of `glyph` scaled to pixels, with the y axis
+    /// pointing down like in bitmaps.
+    pub fn glyph_path(&self, glyph: GlyphId, scale: f32) -> Result<Vec<PathSegment>> {
+        self.glyph_shape_scaled(glyph, scale, scale, 0.0, 0.0)
+    }
+
+    /// Returns the outline of `glyph` scaled to pixels and moved by
+    /// `(shift_x, shift_y)` pixels, with the y axis pointing down like in the
+    /// bitmaps of `get_glyph_bitmap_subpixel`.
+    pub fn glyph_shape_scaled(&self, glyph: GlyphId, scale_x: f32, scale_y: f32,
+        shift_x: f32, shift_y: f32) -> Result<Vec<PathSegment>>
+    {
+        let (vertices, _) = try!(glyph_vertices(self, glyph.index(), &mut vec![]));
+        let point = |x: VertexType, y: VertexType| {
+            (x as f32 * scale_x + shift_x, -(y as f32) * scale_y + shift_y)
+        };
+        let mut path = Vec::with_capacity(vertices.len());
+        for v in &vertices {
+            let (x, y) = point(v.x, v.y);
+            path.push(match v.type_ {
+                Cmd::Move => PathSegment::MoveTo(x, y),
+                Cmd::Line => PathSegment::LineTo(x, y),
+                Cmd::Curve => {
+                    let (cx, cy) = point(v.cx, v.cy);
+                    PathSegment::QuadTo(cx, cy, x, y)
+                }
+            });
+        }
+        Ok(path)
     }
 
-    /// Returns an index for character `code` in a `loca` font table.
+    /// Rasterizes the outline of `glyph` with a line `stroke_width` pixels
+    /// wide, for outlined text.
     ///
-    /// Returns 0 (special glyph representing a missing character) in other
-    /// cases.
-    pub fn glyph_index_for_code(&self, code: usize) -> usize {
-        self.cmap.index_for_code(code).unwrap_or(0)
+    /// The bitmap is positioned relative to the glyph origin like the one of
+    /// `get_glyph_bitmap`.
+    pub fn rasterize_stroked(&self, glyph: GlyphId, scale: f32, stroke_width: f32) -> GrayBitmap {
+        rasterize_stroked_path(&self.glyph_path(glyph, scale).unwrap_or(vec![]), stroke_width)
     }
 
-    pub fn glyph_data_for_glyph_at_index(&self, i: usize) -> GlyphData {
-        let offset = self.loca.offset_for_glyph_at_index(i).unwrap_or(0);
-        self.glyf.glyph_data(offset)
+    /// Returns `true` if the point `(x, y)` in pixels from the origin of
+    /// `glyph` rendered at `scale` is inside it, with the y axis pointing
+    /// down like in bitmaps.
+    pub fn glyph_contains(&self, glyph: GlyphId, x: f32, y: f32, scale: f32) -> bool {
+        match self.glyph_path(glyph, scale) {
+            Ok(path) => path_contains(&path, x, y, FillRule::NonZero),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the number of contours and points, the area and the
+    /// orientation of the outline of `glyph`.
+    pub fn glyph_stats(&self, glyph: GlyphId) -> Result<GlyphStats> {
+        let (vertices, points) = try!(glyph_vertices(self, glyph.index(), &mut vec![]));
+
+        // Twice the area by the shoelace formula, where a curve adds two
+        // thirds of the triangle with its control point. Products of
+        // coordinates don't fit into the precision of `f32`.
+        let mut area = 0.0;
+        let mut contours = 0;
+        let (mut start, mut last) = ((0.0, 0.0), (0.0, 0.0));
+        for v in &vertices {
+            let p = (v.x as f64, v.y as f64);
+            if v.type_ == Cmd::Move {
+                area += last.0 * start.1 - start.0 * last.1;
+                contours += 1;
+                start = p;
+            } else {
+                area += last.0 * p.1 - p.0 * last.1;
+                if v.type_ == Cmd::Curve {
+                    let c = (v.cx as f64, v.cy as f64);
+                    area += 2.0 / 3.0 * ((c.0 - last.0) * (p.1 - last.1)
+                        - (p.0 - last.0) * (c.1 - last.1));
+                }
+            }
+            last = p;
+        }
+        area += last.0 * start.1 - start.0 * last.1;
+
+        let area = (area / 2.0) as f32;
+        Ok(GlyphStats {
+            contours: contours,
+            points: points.len(),
+            area: area,
+            orientation: if area < 0.0 {
+                Some(Orientation::Clockwise)
+            } else if area > 0.0 {
+                Some(Orientation::CounterClockwise)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Renders `glyph` at `scale` with its origin at pixel `(x, y)` of
+    /// `target`, a bitmap of `width` by `height` pixels whose rows are
+    /// `stride` bytes apart.
+    ///
+    /// The pixels of the glyph bitmap inside the target are overwritten, the
+    /// rest is clipped.
+    ///
+    /// # Panics
+    ///
+    /// If `stride` is less than `width` or `target` is too small for the
+    /// bitmap.
+    pub fn render_into(&self, glyph: GlyphId, scale: f32, target: &mut [u8],
+        width: usize, height: usize, stride: usize, x: i32, y: i32)
+    {
+        assert!(width <= stride);
+        assert!(height == 0 || target.len() >= (height - 1) * stride + width);
+        let bbox = match self.glyph_data_for_glyph_at_index(glyph).bitmap_box(scale, scale) {
+            Some(bbox) => bbox,
+            None => return,
+        };
+        let (x, y) = (x as i64, y as i64);
+        let x0 = (x + bbox.x0 as i64).max(0);
+        let y0 = (y + bbox.y0 as i64).max(0);
+        let x1 = (x + bbox.x1 as i64).min(width as i64);
+        let y1 = (y + bbox.y1 as i64).min(height as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        let mut bitmap = Bitmap {
+            w: (x1 - x0) as isize,
+            h: (y1 - y0) as isize,
+            stride: stride as isize,
+            pixels: target[y0 as usize * stride + x0 as usize..].as_mut_ptr(),
+        };
+        unsafe {
+            let mut vertices = null_mut();
+            let n = get_glyph_shape(self, glyph, &mut vertices);
+            rasterize_bitmap(&mut bitmap, 0.35, vertices, n,
+                &scale_transform(scale, scale, 0.0, 0.0, 1), (x0 - x) as isize, (y0 - y) as isize, &RasterOptions::default());
+            free_shape(self, vertices);
+        }
+    }
+
+    pub fn glyph_data_for_glyph_at_index(&self, glyph: GlyphId) -> GlyphData {
+        self.glyph_data(glyph.index())
+    }
+
+    fn glyph_data(&self, i: usize) -> GlyphData {
+        let offset = self.loca().offset_for_glyph_at_index(i).unwrap_or(0);
+        GlyphData::new(self.glyf_data(), offset)
+    }
+
+    /// Returns the outline of `glyph` as stored in the font, see
+    /// `GlyphData::outline`. Empty glyphs have no contours.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if the glyph is truncated.
+    pub fn glyph_outline(&self, glyph: GlyphId) -> Result<Outline> {
+        self.outline(glyph.index())
+    }
+
+    // Same as `glyph_outline` for the components of composite glyphs, which
+    // can have any index.
+    fn outline(&self, i: usize) -> Result<Outline> {
+        match self.glyph_bytes(i) {
+            Some(bytes) => GlyphData::new(bytes, 0).outline(),
+            None => Ok(Outline::Simple(vec![])),
+        }
+    }
+
+    /// Returns the glyphs that have outlines with their data, in the order
+    /// of their indices, e.g. to show all glyphs of the font or to subset
+    /// it.
+    ///
+    /// Glyphs with no bytes in the `glyf` table, like spaces, are skipped.
+    pub fn glyphs<'b>(&'b self) -> impl Iterator<Item = (GlyphId, GlyphData<'b>)> + 'b {
+        (0..self.num_glyphs as usize).filter_map(move |i| {
+            self.glyph_bytes(i).map(|bytes| (GlyphId(i as u16), GlyphData::new(bytes, 0)))
+        })
+    }
+
+    fn loca(&self) -> &LOCA {
+        self.loca.get(|offset| {
+            let loca = try!(LOCA::from_data(&self.data, offset, self.num_glyphs, self.head.location_format()));
+            // Only checks the bounds, the table is created on demand.
+            try!(GLYF::from_data(&self.data, self.glyf, loca.size_of_glyf_table()));
+            Ok(loca)
+        })
+    }
+
+    fn cmap(&self) -> &CMAP<'static> {
+        // The map can't borrow the data it's read from once the font is
+        // created, so it's kept without it and looked up in `self.data`.
+        self.cmap.get(|offset| CMAP::from_data(&self.data, offset).map(CMAP::without_data))
+    }
+
+    fn gasp(&self) -> Option<&GASP> {
+        self.gasp.get(|offset| GASP::from_data(&self.data, offset).map(Some)).as_ref()
+    }
+
+    fn hdmx(&self) -> Option<&HDMX> {
+        self.hdmx.get(|offset| HDMX::from_data(&self.data, offset, self.num_glyphs).map(Some)).as_ref()
+    }
+
+    fn vdmx(&self) -> Option<&VDMX> {
+        self.vdmx.get(|offset| VDMX::from_data(&self.data, offset).map(Some)).as_ref()
+    }
+
+    // Returns the bytes of the glyph at index `i`, `None` for empty glyphs.
+    fn glyph_bytes(&self, i: usize) -> Option<&[u8]> {
+        self.loca().offset(i).and_then(|range| self.glyf_data().get(range))
+    }
+
+    fn glyf_data(&self) -> &[u8] {
+        // Bounds are checked when the `loca` table is read, if that fails
+        // the table is empty.
+        let end = self.glyf.saturating_add(self.loca().size_of_glyf_table());
+        self.data.get(self.glyf..end).unwrap_or(&[])
+    }
+
+    /// Returns the `kern` table of the font, which is empty if the font
+    /// has no kerning or the table is malformed.
+    pub fn kern(&self) -> &KERN {
+        self.kern.get(|offset| KERN::from_data(&self.data, offset))
+    }
+
+    /// Returns the extended kerning table of Apple fonts, empty if the
+    /// font has none.
+    pub fn kerx(&self) -> &KERX {
+        self.kerx.get(|offset| KERX::from_data(&self.data, offset, self.num_glyphs))
+    }
+
+    /// Returns the kerning between the glyphs `left` and `right` in unscaled
+    /// coordinates, by the `kerx` table if the font has one it can read,
+    /// else by the `kern` table.
+    pub fn kerning(&self, left: GlyphId, right: GlyphId) -> i16 {
+        if self.kerx().is_empty() {
+            self.kern().kerning(left.index(), right.index())
+        } else {
+            self.kerx().kerning(left.index(), right.index())
+        }
+    }
+
+    /// Returns the kerning of all pairs of glyphs, in unscaled coordinates,
+    /// from the same table as `kerning`.
+    ///
+    /// Looking pairs up in the map takes constant time instead of a binary
+    /// search of the table, which pays off for fonts with many pairs, see the
+    /// `kerning` benchmarks. The map can also be extended with pairs from
+    /// other sources.
+    #[cfg(feature = "std")]
+    pub fn build_kern_cache(&self) -> HashMap<(GlyphId, GlyphId), i16> {
+        let pair = |(left, right, value)| ((GlyphId(left), GlyphId(right)), value);
+        if self.kerx().is_empty() {
+            self.kern().pairs().map(pair).collect()
+        } else {
+            self.kerx().pairs().into_iter().map(pair).collect()
+        }
     }
 }
 
@@ -531,26 +1766,56 @@ impl<'a> FontInfo<'a> {
 // the bitmaps for C declaration-order reasons)
 //
 
-#[derive(Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum Cmd {
   Move=1,
   Line=2,
   Curve=3
 }
 
-type VertexType = i16;
-#[derive(Copy, Clone)]
+// Wider than the coordinates in the font, which compound glyphs may move
+// and scale beyond the range of `i16`.
+type VertexType = i32;
+/// A point of a glyph shape from `get_glyph_shape`, ending a segment of
+/// the kind `cmd` from the previous point.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub struct Vertex {
-   x: i16,
-   y: i16,
-   cx: i16,
-   cy: i16,
+   x: VertexType,
+   y: VertexType,
+   cx: VertexType,
+   cy: VertexType,
    type_: Cmd,
    flags: u8,
 }
 
-// @TODO: don't expose this structure
-pub struct Bitmap
+impl Vertex {
+    /// Returns the kind of the segment ending at the point.
+    pub fn cmd(&self) -> Cmd {
+        self.type_
+    }
+
+    /// Returns the x coordinate of the point.
+    pub fn x(&self) -> VertexType {
+        self.x
+    }
+
+    /// Returns the y coordinate of the point.
+    pub fn y(&self) -> VertexType {
+        self.y
+    }
+
+    /// Returns the x coordinate of the control point of a curve.
+    pub fn cx(&self) -> VertexType {
+        self.cx
+    }
+
+    /// Returns the y coordinate of the control point of a curve.
+    pub fn cy(&self) -> VertexType {
+        self.cy
+    }
+}
+
+struct Bitmap
 {
     w: isize,
     h: isize,
@@ -558,6 +1823,17 @@ pub struct Bitmap
     pixels: *mut u8,
 }
 
+/// Returns the raw bitmap the rasterizer writes the pixels of `bitmap` through.
+fn raw_bitmap(bitmap: &mut GrayBitmap) -> Bitmap {
+    // A `GrayBitmap` always has `width * height` pixels.
+    Bitmap {
+        w: bitmap.width() as isize,
+        h: bitmap.height() as isize,
+        stride: bitmap.width() as isize,
+        pixels: bitmap.as_mut_slice().as_mut_ptr(),
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////
 //
 // Finding the right font...
@@ -629,13 +1905,6 @@ const STBTT_MAX_OVERSAMPLE: usize = 8;
 // on platforms that don't allow misaligned reads, if we want to allow
 // truetype fonts that aren't padded to alignment, define ALLOW_UNALIGNED_TRUETYPE
 
-macro_rules! ttCHAR {
-    ($p:expr) => {
-        *($p as *const i8)
-    }
-}
-
-// #define ttCHAR(p)     (* (stbtt_int8 *) (p))
 // TODO: Macro.
 // #define ttFixed(p)    ttLONG(p)
 
@@ -647,12 +1916,6 @@ macro_rules! ttUSHORT {
     }
 }
 
-macro_rules! ttSHORT {
-    ($p:expr) => {
-        BigEndian::read_i16(slice::from_raw_parts($p, 2))
-    }
-}
-
 macro_rules! ttULONG {
     ($p:expr) => {
         BigEndian::read_u32(slice::from_raw_parts($p, 4))
@@ -665,31 +1928,6 @@ macro_rules! ttLONG {
     }
 }
 
-macro_rules! stbtt_tag4 {
-    ($p:expr, $c0:expr, $c1:expr, $c2:expr, $c3:expr) => {
-        *$p.offset(0) == ($c0) && *$p.offset(1) == ($c1) && *$p.offset(2) == ($c2) && *$p.offset(3) == ($c3)
-    }
-}
-
-// #define stbtt_tag4(p,c0,c1,c2,c3) ((p)[0] == (c0) && (p)[1] == (c1) && (p)[2] == (c2) && (p)[3] == (c3))
-
-macro_rules! stbtt_tag {
-    ($p:expr, $s:expr) => {
-        stbtt_tag4!($p,*$s.offset(0),*$s.offset(1),*$s.offset(2),*$s.offset(3))
-    }
-}
-
-// #define stbtt_tag(p,str)           stbtt_tag4(p,str[0],str[1],str[2],str[3])
-
-pub unsafe fn isfont(font: *const u8) -> isize {
-   // check the version number
-   if stbtt_tag4!(font, '1' as u8,0,0,0) { return 1; } // TrueType 1
-   if stbtt_tag!(font, "typ1".as_ptr())  { return 1; } // TrueType with type 1 font -- we don't support this!
-   if stbtt_tag!(font, "OTTO".as_ptr())  { return 1; } // OpenType with CFF
-   if stbtt_tag4!(font, 0,1,0,0) { return 1; } // OpenType 1.0
-   return 0;
-}
-
 // Each .ttf/.ttc file may have more than one font. Each font has a sequential
 // index number starting from 0. Call this function to get the font offset for
 // a given index; it returns -1 if the index is out of range. A regular .ttf
@@ -700,13 +1938,14 @@ pub unsafe fn get_font_offset_for_index(
     font_collection: *const u8,
     index: isize
 ) -> i32 {
+   let kind = FontKind::detect(slice::from_raw_parts(font_collection, 4));
    // if it's just a font, there's only one valid index
-   if isfont(font_collection) != 0 {
+   if kind.map_or(false, |kind| kind.is_font()) {
       return if index == 0 { 0 } else { -1 };
    }
 
    // check if it's a TTC
-   if stbtt_tag!(font_collection, "ttcf".as_ptr()) {
+   if kind == Some(FontKind::TrueTypeCollection) {
       // version 1?
       if ttULONG!(font_collection.offset(4)) == 0x00010000
        || ttULONG!(font_collection.offset(4)) == 0x00020000 {
@@ -722,11 +1961,10 @@ pub unsafe fn get_font_offset_for_index(
 
 pub unsafe fn get_codepoint_shape(
     info: *const FontInfo,
-    unicode_codepoint: isize,
+    c: char,
     vertices: *mut *mut Vertex
 ) -> isize {
-    assert!(unicode_codepoint >= 0);
-    get_glyph_shape(info, (*info).glyph_index_for_code(unicode_codepoint as usize) as isize, vertices)
+    get_glyph_shape(info, (*info).glyph_index_for_code(c), vertices)
 }
 
 pub unsafe fn stbtt_setvertex(
@@ -738,10 +1976,10 @@ pub unsafe fn stbtt_setvertex(
     cy: i32
 ) {
    (*v).type_ = type_;
-   (*v).x = x as i16;
-   (*v).y = y as i16;
-   (*v).cx = cx as i16;
-   (*v).cy = cy as i16;
+   (*v).x = x as VertexType;
+   (*v).y = y as VertexType;
+   (*v).cx = cx as VertexType;
+   (*v).cy = cy as VertexType;
 }
 
 pub unsafe fn close_shape(
@@ -787,334 +2025,187 @@ pub unsafe fn close_shape(
 // its x,y, using cx,cy as the bezier control point.
 pub unsafe fn get_glyph_shape(
     info: *const FontInfo,
-    glyph_index: isize,
+    glyph: GlyphId,
     pvertices: *mut *mut Vertex
 ) -> isize {
-   let number_of_contours: i16;
-   let end_pts_of_contours: *const u8;
-   let data: *const u8 = (*info).data.as_ptr();
-   let mut vertices: *mut Vertex=null_mut();
-   let mut num_vertices: isize =0;
-   let g = (*info).offset_for_glyph_at_index(glyph_index as usize).map(|c| c as isize).unwrap_or(-1);
+   try_get_glyph_shape(info, glyph, pvertices).unwrap_or(0)
+}
 
+/// Same as `get_glyph_shape`, but fails for corrupt compound glyphs.
+///
+/// # Errors
+/// Returns `Error::Malformed` if compound glyphs are nested deeper than
+/// the maximum component depth of the font or a glyph contains itself.
+pub unsafe fn try_get_glyph_shape(
+    info: *const FontInfo,
+    glyph: GlyphId,
+    pvertices: *mut *mut Vertex
+) -> Result<isize> {
    *pvertices = null_mut();
+   let (vertices, _) = try!(glyph_vertices(&*info, glyph.index(), &mut vec![]));
+   if vertices.is_empty() {
+      return Ok(0);
+   }
+   let p = STBTT_malloc!(vertices.len() * size_of::<Vertex>()) as *mut Vertex;
+   if p == null_mut() {
+      return Ok(0);
+   }
+   STBTT_memcpy(vertices.as_ptr(), p, vertices.len());
+   *pvertices = p;
+   Ok(vertices.len() as isize)
+}
 
-   if g < 0 { return 0; }
-
-   number_of_contours = ttSHORT!(data.offset(g));
-
-   if number_of_contours > 0 {
-      let mut flags: u8 =0;
-      let mut flagcount: u8;
-      let ins: i32;
-      let mut j: i32 =0;
-      let m: i32;
-      let n: i32;
-      let mut next_move: i32;
-      let mut was_off: i32 =0;
-      let off: i32;
-      let mut start_off: i32 =0;
-      let mut x: i32;
-      let mut y: i32;
-      let mut cx: i32;
-      let mut cy: i32;
-      let mut sx: i32;
-      let mut sy: i32;
-      let mut scx: i32;
-      let mut scy: i32;
-      let mut points: *const u8;
-      end_pts_of_contours = data.offset(g + 10);
-      ins = ttUSHORT!(data.offset(g + 10 + number_of_contours as isize * 2)) as i32;
-      points = data.offset(g + 10 + number_of_contours as isize * 2 + 2 + ins as isize);
-
-      n = 1+ttUSHORT!(end_pts_of_contours.offset(number_of_contours as isize *2-2)) as i32;
-
-      m = n + 2*number_of_contours as i32;  // a loose bound on how many vertices we might need
-      vertices = STBTT_malloc!(m as usize * size_of::<Vertex>()) as *mut Vertex;
-      if vertices == null_mut() {
-         return 0;
-      }
-
-      next_move = 0;
-      flagcount=0;
-
-      // in first pass, we load uninterpreted data into the allocated array
-      // above, shifted to the end of the array so we won't overwrite it when
-      // we create our final data starting from the front
-
-      off = m - n; // starting offset for uninterpreted data, regardless of how m ends up being calculated
-
-      // first load flags
-
-      for i in 0..n {
-         if flagcount == 0 {
-            flags = *points;
-            points = points.offset(1);
-            if (flags & 8) != 0 {
-               flagcount = *points;
-               points = points.offset(1);
-            }
-         } else {
-            flagcount -= 1;
+// Gets the shape of a component of the compound glyphs in `ancestors`, and
+// its points in the order instructions and matching components number them,
+// the components of a compound glyph transformed.
+//
+// Unlike the vertices, the points have no implied on-curve points.
+fn glyph_vertices(info: &FontInfo, glyph_index: usize, ancestors: &mut Vec<usize>)
+    -> Result<(Vec<Vertex>, Vec<(f32, f32)>)>
+{
+   let (mut vertices, mut points) = (vec![], vec![]);
+   match try!(info.outline(glyph_index)) {
+      Outline::Simple(contours) => {
+         for contour in &contours {
+            contour_vertices(contour, &mut vertices);
+            points.extend(contour.iter().map(|p| (p.x as f32, p.y as f32)));
          }
-         (*vertices.offset(off as isize +i as isize)).flags = flags;
       }
-      // now load x coordinates
-      x=0;
-      for i in 0..n {
-         flags = (*vertices.offset(off as isize + i as isize)).flags;
-         if (flags & 2) != 0 {
-            let dx: i16 = *points as i16;
-            points = points.offset(1);
-            x += if (flags & 16) != 0 { dx as i32 } else { -dx as i32 }; // ???
-         } else {
-            if (flags & 16) == 0 {
-               x = x + BigEndian::read_i16(slice::from_raw_parts(points, 2)) as i32;
-               points = points.offset(2);
+      Outline::Composite(components) => {
+         try!(enter_compound_glyph(info, glyph_index, ancestors));
+         for component in &components {
+            let (child_vertices, child_points) = try!(glyph_vertices(info, component.glyph_index, ancestors));
+            let mut mtx = component.transform;
+            if let Some((parent, child)) = component.matched_points {
+               // The component is aligned by matching a point of the glyph
+               // so far to one of its points.
+               match_component_points(&mut mtx, points.get(parent).cloned(),
+                  child_points.get(child).cloned());
             }
-         }
-         (*vertices.offset(off as isize +i as isize)).x = x as i16;
-      }
-
-      // now load y coordinates
-      y=0;
-      for i in 0..n {
-         flags = (*vertices.offset(off as isize + i as isize)).flags;
-         if (flags & 4) != 0 {
-            let dy: i16 = *points as i16;
-            points = points.offset(1);
-            y += if (flags & 32) != 0 { dy as i32 } else { -dy as i32 }; // ???
-         } else {
-            if (flags & 32) == 0 {
-               y = y + BigEndian::read_i16(slice::from_raw_parts(points, 2)) as i32;
-               points = points.offset(2);
+            for mut v in child_vertices {
+               let (x, y) = transform_component_point(&mtx, v.x as f32, v.y as f32);
+               v.x = x as VertexType;
+               v.y = y as VertexType;
+               let (x, y) = transform_component_point(&mtx, v.cx as f32, v.cy as f32);
+               v.cx = x as VertexType;
+               v.cy = y as VertexType;
+               vertices.push(v);
             }
+            points.extend(child_points.iter().map(|&(x, y)| transform_component_point(&mtx, x, y)));
          }
-         (*vertices.offset(off as isize +i as isize)).y = y as i16;
+         ancestors.pop();
       }
+   }
+   Ok((vertices, points))
+}
 
-      // now convert them to our format
-      num_vertices=0;
-      sx = 0; sy = 0;
-      cx = 0; cy = 0;
-      scx = 0; scy = 0;
-      let mut i_iter = (0..n).into_iter();
-      let mut i = 0;
-      while { if let Some(v) = i_iter.next() { i = v; true } else { false } } {
-         flags = (*vertices.offset(off as isize +i as isize)).flags;
-         x     = (*vertices.offset(off as isize +i as isize)).x as i32;
-         y     = (*vertices.offset(off as isize +i as isize)).y as i32;
-         if next_move == i {
-            if i != 0 {
-               num_vertices = close_shape(vertices,
-                   num_vertices, was_off as isize, start_off as isize, sx,sy,scx,scy,cx,cy);
-            }
-
-            // now start the new one
-            start_off = (1 - (flags & 1)) as i32;
-            if start_off != 0 {
-               // if we start off with an off-curve point, then when we need to find a point on the curve
-               // where we can start, and we need to save some state for when we wraparound.
-               scx = x;
-               scy = y;
-               if (*vertices.offset(off as isize +i as isize +1)).type_ == Cmd::Line {
-                  // next point is also a curve point, so interpolate an on-point curve
-                  sx = (x + (*vertices.offset(off as isize +i as isize +1)).x as i32) >> 1;
-                  sy = (y + (*vertices.offset(off as isize +i as isize +1)).y as i32) >> 1;
-               } else {
-                  // otherwise just use the next point as our start point
-                  sx = (*vertices.offset(off as isize +i as isize +1)).x as i32;
-                  sy = (*vertices.offset(off as isize +i as isize +1)).y as i32;
-                  i_iter.next(); // we're using point i+1 as the starting point, so skip it
-               }
-            } else {
-               sx = x;
-               sy = y;
-            }
-            stbtt_setvertex(vertices.offset(num_vertices), Cmd::Move,sx,sy,0,0);
-            num_vertices += 1;
-            was_off = 0;
-            next_move = 1 + ttUSHORT!(end_pts_of_contours.offset(j as isize *2)) as i32;
-            j += 1;
-         } else {
-            if (flags & 1) == 0 { // if it's a curve
-               if was_off != 0 { // two off-curve control points in a row means interpolate an on-curve midpoint
-                  stbtt_setvertex(vertices.offset(num_vertices),
-                      Cmd::Curve, (cx+x)>>1, (cy+y)>>1, cx, cy);
-                  num_vertices += 1;
-               }
-               cx = x;
-               cy = y;
-               was_off = 1;
-            } else {
-               if was_off != 0 {
-                  stbtt_setvertex(vertices.offset(num_vertices), Cmd::Curve, x,y, cx, cy);
-                  num_vertices += 1;
-               } else {
-                  stbtt_setvertex(vertices.offset(num_vertices), Cmd::Line, x,y,0,0);
-                  num_vertices += 1;
-               }
-               was_off = 0;
-            }
+// Appends the vertices of a contour of a simple glyph, closing it. Two
+// off-curve points in a row have an implied on-curve point in the middle.
+fn contour_vertices(contour: &[OutlinePoint], vertices: &mut Vec<Vertex>) {
+   let vertex = |type_, (x, y): (i32, i32), (cx, cy): (i32, i32)| Vertex {
+      x: x as VertexType, y: y as VertexType, cx: cx as VertexType, cy: cy as VertexType,
+      type_: type_, flags: 0,
+   };
+   let point = |p: &OutlinePoint| (p.x as i32, p.y as i32);
+   let first = point(&contour[0]);
+   let start_off = !contour[0].on_curve;
+   let mut skip = 1;
+   let start = if !start_off || contour.len() == 1 {
+      first
+   } else if !contour[1].on_curve {
+      // if we start off with an off-curve point, then when we need to find a point on the curve
+      // where we can start, and we need to save some state for when we wraparound.
+      let next = point(&contour[1]);
+      ((first.0 + next.0) >> 1, (first.1 + next.1) >> 1)
+   } else {
+      // otherwise just use the next point as our start point
+      skip = 2;
+      point(&contour[1])
+   };
+   vertices.push(vertex(Cmd::Move, start, (0, 0)));
+
+   // the last off-curve point
+   let mut control: Option<(i32, i32)> = None;
+   for p in &contour[skip..] {
+      let xy = point(p);
+      if !p.on_curve {
+         if let Some(c) = control {
+            // two off-curve control points in a row means interpolate an on-curve midpoint
+            vertices.push(vertex(Cmd::Curve, ((c.0 + xy.0) >> 1, (c.1 + xy.1) >> 1), c));
          }
+         control = Some(xy);
+      } else {
+         vertices.push(match control {
+            Some(c) => vertex(Cmd::Curve, xy, c),
+            None => vertex(Cmd::Line, xy, (0, 0)),
+         });
+         control = None;
       }
-      num_vertices = close_shape(vertices, num_vertices, was_off as isize, start_off as isize, sx,sy,scx,scy,cx,cy);
-   } else if number_of_contours == -1 {
-      // Compound shapes.
-      let mut more: isize = 1;
-      let mut comp: *const u8 = data.offset(g + 10);
-      num_vertices = 0;
-      vertices = null_mut();
-      while more != 0 {
-         let flags: u16;
-         let gidx: u16;
-         let comp_num_verts: isize;
-         let mut comp_verts: *mut Vertex = null_mut();
-         let tmp: *mut Vertex;
-         let mut mtx: [f32; 6] = [1.0,0.0,0.0,1.0,0.0,0.0];
-         let m: f32;
-         let n: f32;
-
-         flags = ttSHORT!(comp) as u16; comp=comp.offset(2);
-         gidx = ttSHORT!(comp) as u16; comp=comp.offset(2);
-
-         if (flags & 2) != 0 { // XY values
-            if (flags & 1) != 0 { // shorts
-               mtx[4] = ttSHORT!(comp) as f32; comp=comp.offset(2);
-               mtx[5] = ttSHORT!(comp) as f32; comp=comp.offset(2);
-            } else {
-               mtx[4] = ttCHAR!(comp) as f32; comp=comp.offset(1);
-               mtx[5] = ttCHAR!(comp) as f32; comp=comp.offset(1);
-            }
-         }
-         else {
-            // @TODO handle matching point
-            unimplemented!();
-         }
-         if (flags & (1<<3)) != 0 { // WE_HAVE_A_SCALE
-             let v = ttSHORT!(comp) as f32 /16384.0; comp=comp.offset(2);
-            mtx[0] = v;
-            mtx[3] = v;
-            mtx[1] = 0.0;
-            mtx[2] = 0.0;
-         } else if (flags & (1<<6)) != 0 { // WE_HAVE_AN_X_AND_YSCALE
-            mtx[0] = ttSHORT!(comp) as f32 /16384.0; comp=comp.offset(2);
-            mtx[1] = 0.0;
-            mtx[2] = 0.0;
-            mtx[3] = ttSHORT!(comp) as f32 /16384.0; comp=comp.offset(2);
-         } else if (flags & (1<<7)) != 0 { // WE_HAVE_A_TWO_BY_TWO
-            mtx[0] = ttSHORT!(comp) as f32 /16384.0; comp=comp.offset(2);
-            mtx[1] = ttSHORT!(comp) as f32 /16384.0; comp=comp.offset(2);
-            mtx[2] = ttSHORT!(comp) as f32 /16384.0; comp=comp.offset(2);
-            mtx[3] = ttSHORT!(comp) as f32 /16384.0; comp=comp.offset(2);
-         }
+   }
 
-         // Find transformation scales.
-         m = (mtx[0]*mtx[0] + mtx[1]*mtx[1]).sqrt();
-         n = (mtx[2]*mtx[2] + mtx[3]*mtx[3]).sqrt();
-
-         // Get indexed glyph.
-         comp_num_verts = get_glyph_shape(info, gidx as isize, &mut comp_verts);
-         if comp_num_verts > 0 {
-            // Transform vertices.
-            for i in 0..comp_num_verts {
-               let v: *mut Vertex = comp_verts.offset(i);
-               let mut x: VertexType;
-               let mut y: VertexType;
-               x=(*v).x; y=(*v).y;
-               (*v).x = (m as f32 * (mtx[0]*x as f32 + mtx[2]*y as f32 + mtx[4])) as VertexType;
-               (*v).y = (n as f32 * (mtx[1]*x as f32 + mtx[3]*y as f32 + mtx[5])) as VertexType;
-               x=(*v).cx; y=(*v).cy;
-               (*v).cx = (m as f32 * (mtx[0]*x as f32 + mtx[2]*y as f32 + mtx[4])) as VertexType;
-               (*v).cy = (n as f32 * (mtx[1]*x as f32 + mtx[3]*y as f32 + mtx[5])) as VertexType;
-            }
-            // Append vertices.
-            tmp = STBTT_malloc!((num_vertices+comp_num_verts) as usize *size_of::<Vertex>())
-                as *mut Vertex;
-            if tmp == null_mut() {
-               if vertices != null_mut() { STBTT_free!(vertices as *mut c_void); }
-               if comp_verts != null_mut() { STBTT_free!(comp_verts as *mut c_void); }
-               return 0;
-            }
-            if num_vertices > 0 {
-                STBTT_memcpy(tmp, vertices,
-                    num_vertices as usize *size_of::<Vertex>());
-            }
-            STBTT_memcpy(tmp.offset(num_vertices), comp_verts,
-                comp_num_verts as usize *size_of::<Vertex>());
-            if vertices != null_mut() { STBTT_free!(vertices as *mut c_void); }
-            vertices = tmp;
-            STBTT_free!(comp_verts as *mut c_void);
-            num_vertices += comp_num_verts;
-         }
-         // More components ?
-         more = (flags & (1<<5)) as isize;
+   if start_off {
+      if let Some(c) = control {
+         vertices.push(vertex(Cmd::Curve, ((c.0 + first.0) >> 1, (c.1 + first.1) >> 1), c));
       }
-   } else if number_of_contours < 0 {
-        // @TODO other compound variations?
-        unimplemented!();
+      vertices.push(vertex(Cmd::Curve, start, first));
    } else {
-      // numberOfCounters == 0, do nothing
+      vertices.push(match control {
+         Some(c) => vertex(Cmd::Curve, start, c),
+         None => vertex(Cmd::Line, start, (0, 0)),
+      });
    }
+}
 
-   *pvertices = vertices;
-   return num_vertices;
+// Applies the transformation of a component of a compound glyph to a point.
+fn transform_component_point(mtx: &[f32; 6], x: f32, y: f32) -> (f32, f32) {
+   (mtx[0]*x + mtx[2]*y + mtx[4], mtx[1]*x + mtx[3]*y + mtx[5])
 }
 
-pub unsafe fn get_glyph_kern_advance(
-    info: *mut FontInfo,
-    glyph1: isize,
-    glyph2: isize
-) -> isize {
-   let data: *const u8 = (*info).data.as_ptr().offset((*info).kern as isize);
-   let needle: u32;
-   let mut straw: u32;
-   let mut l: isize;
-   let mut r: isize;
-   let mut m: isize;
-
-   // we only look at the first table. it must be 'horizontal' and format 0.
-   if (*info).kern == 0 {
-      return 0;
-   }
-   if ttUSHORT!(data.offset(2)) < 1 { // number of tables, need at least 1
-      return 0;
-   }
-   if ttUSHORT!(data.offset(8)) != 1 { // horizontal flag must be set in format
-      return 0;
-   }
+// Replaces the point numbers of a component transformation with the offsets
+// that move the `child` point of the component onto the `parent` point of
+// the compound glyph. Points that don't exist are taken to be the origin.
+fn match_component_points(mtx: &mut [f32; 6], parent: Option<(f32, f32)>,
+    child: Option<(f32, f32)>)
+{
+   mtx[4] = 0.0;
+   mtx[5] = 0.0;
+   let (px, py) = parent.unwrap_or((0.0, 0.0));
+   let (cx, cy) = child.map_or((0.0, 0.0), |(x, y)| transform_component_point(mtx, x, y));
+   mtx[4] = px - cx;
+   mtx[5] = py - cy;
+}
 
-   l = 0;
-   r = ttUSHORT!(data.offset(10)) as isize - 1;
-   needle = (glyph1 << 16 | glyph2) as u32;
-   while l <= r {
-      m = (l + r) >> 1;
-      straw = ttULONG!(data.offset(18+(m*6))); // note: unaligned read
-      if needle < straw {
-         r = m - 1;
-      }
-      else if needle > straw {
-         l = m + 1;
-      } else {
-         return ttSHORT!(data.offset(22+(m*6))) as isize;
-      }
+// Adds a compound glyph to the `ancestors` of the components about to be
+// read, checking that it isn't one of them and the depth limit of the font.
+fn enter_compound_glyph(info: &FontInfo, glyph_index: usize,
+    ancestors: &mut Vec<usize>) -> Result<()>
+{
+   if ancestors.len() >= info.max_component_depth || ancestors.contains(&glyph_index) {
+      return Err(Error::Malformed);
    }
-   return 0;
+   ancestors.push(glyph_index);
+   Ok(())
+}
+
+pub unsafe fn get_glyph_kern_advance(
+    info: *const FontInfo,
+    glyph1: GlyphId,
+    glyph2: GlyphId
+) -> isize {
+   (*info).kerning(glyph1, glyph2) as isize
 }
 
 // an additional amount to add to the 'advance' value between ch1 and ch2
 pub unsafe fn get_codepoint_kern_advance(
-    info: *mut FontInfo,
-    ch1: isize,
-    ch2: isize
+    info: *const FontInfo,
+    ch1: char,
+    ch2: char
 ) -> isize {
-    if (*info).kern == 0 { // if no kerning table, don't waste time looking up both codepoint->glyphs
+    // if no kerning table, don't waste time looking up both codepoint->glyphs
+    if (*info).kern().is_empty() && (*info).kerx().is_empty() {
       return 0;
     }
-    assert!(ch1 >= 0 && ch2 >= 0);
-    let i1 = (*info).glyph_index_for_code(ch1 as usize) as isize;
-    let i2 = (*info).glyph_index_for_code(ch2 as usize) as isize;
-    get_glyph_kern_advance(info, i1, i2)
+    get_glyph_kern_advance(info, (*info).glyph_index_for_code(ch1), (*info).glyph_index_for_code(ch2))
 }
 
 // frees the data allocated above
@@ -1149,7 +2240,7 @@ pub struct Hheap
 
 pub unsafe fn hheap_alloc(
     hh: *mut Hheap,
-    size: size_t
+    size: usize
 ) -> *const () {
    if (*hh).first_free != null_mut() {
       let p: *mut () = (*hh).first_free;
@@ -1569,6 +2660,10 @@ pub unsafe fn fill_active_edges_new(
                x2 = x_bottom as isize;
                // compute intersection with y axis at x1+1
                y_crossing = (x1 as f32 +1.0 - x0) * dy + y_top;
+               // if x2 is right at the right edge of x1, y_crossing can blow up
+               if y_crossing > y_bottom {
+                  y_crossing = y_bottom;
+               }
 
                sign = (*e).direction;
                // area of the rectangle covered from y0..y_crossing
@@ -1576,6 +2671,11 @@ pub unsafe fn fill_active_edges_new(
                // area of the triangle (x_top,y0), (x+1,y0), (x+1,y_crossing)
                (*scanline.offset(x1)) += area * (1.0-((x_top - x1 as f32)+(x1+1-x1) as f32)/2.0);
 
+               // same for the intersection at x2, keep it within the scanline
+               if x2 > x1 + 1 && (x2 as f32 - x0) * dy + y_top > y_bottom {
+                  dy = (y_bottom - y_crossing) / (x2 - (x1+1)) as f32;
+               }
+
                step = sign * dy;
                for x in x1 + 1..x2 {
                   (*scanline.offset(x)) += area + step/2.0;
@@ -1583,7 +2683,7 @@ pub unsafe fn fill_active_edges_new(
                }
                y_crossing += dy * (x2 - (x1+1)) as f32;
 
-               STBTT_assert!(area.abs() <= 1.01);
+               STBTT_assert!(libm::fabsf(area) <= 1.01);
 
                (*scanline.offset(x2)) += area + sign * (1.0-((x2-x2) as f32
                     +(x_bottom-x2 as f32))/2.0) * (sy1-y_crossing);
@@ -1655,13 +2755,50 @@ pub unsafe fn fill_active_edges_new(
 }
 
 // directly AA rasterize edges w/o supersampling
-pub unsafe fn rasterize_sorted_edges(
+unsafe fn rasterize_sorted_edges(
     result: *mut Bitmap,
+    e: *mut Edge,
+    n: isize,
+    vsubsample: isize,
+    off_x: isize,
+    off_y: isize,
+    deterministic: bool
+) {
+   rasterize_sorted_edges_into((*result).w, (*result).h, e, n, vsubsample, off_x, off_y,
+       deterministic, (*result).pixels, (*result).stride, |_, _| {});
+}
+
+// same as rasterize_sorted_edges, but passes every row of w pixels with its
+// index to 'emit' instead of keeping a bitmap of all of them
+unsafe fn rasterize_sorted_edges_rows<F: FnMut(isize, &[u8])>(
+    w: isize,
+    h: isize,
+    e: *mut Edge,
+    n: isize,
+    vsubsample: isize,
+    off_x: isize,
+    off_y: isize,
+    deterministic: bool,
+    emit: F
+) {
+   rasterize_sorted_edges_into(w, h, e, n, vsubsample, off_x, off_y, deterministic,
+       null_mut(), 0, emit);
+}
+
+// writes the rows straight into 'pixels' with 'stride' if it isn't null,
+// otherwise into one row passed to 'emit' after each
+unsafe fn rasterize_sorted_edges_into<F: FnMut(isize, &[u8])>(
+    w: isize,
+    h: isize,
     mut e: *mut Edge,
     n: isize,
     _vsubsample: isize,
     off_x: isize,
-    off_y: isize
+    off_y: isize,
+    deterministic: bool,
+    pixels: *mut u8,
+    stride: isize,
+    mut emit: F
 ) {
    let mut hh: Hheap = Hheap {
       head: null_mut(),
@@ -1674,27 +2811,28 @@ pub unsafe fn rasterize_sorted_edges(
    let mut scanline_data: [f32; 129] = [0.0; 129];
    let scanline: *mut f32;
    let scanline2: *mut f32;
+   let mut row: Vec<u8> = if pixels.is_null() { vec![0; w as usize] } else { Vec::new() };
 
-   if (*result).w > 64 {
-      scanline = STBTT_malloc!(((*result).w*2+1) as usize * size_of::<f32>()) as *mut f32;
+   if w > 64 {
+      scanline = STBTT_malloc!((w*2+1) as usize * size_of::<f32>()) as *mut f32;
    } else {
       scanline = scanline_data.as_mut_ptr();
    }
 
-   scanline2 = scanline.offset((*result).w);
+   scanline2 = scanline.offset(w);
 
    y = off_y;
-   (*e.offset(n)).y0 = (off_y + (*result).h) as f32 + 1.0;
+   (*e.offset(n)).y0 = (off_y + h) as f32 + 1.0;
 
-   while j < (*result).h {
+   while j < h {
       // find center of pixel for this scanline
       let scan_y_top: f32 = y as f32 + 0.0;
       let scan_y_bottom: f32 = y as f32 + 1.0;
       let mut step: *mut *mut ActiveEdge = &mut active;
 
-      memset(scanline as *mut c_void, 0, (*result).w as usize * size_of::<f32>());
+      memset(scanline as *mut c_void, 0, w as usize * size_of::<f32>());
       memset(scanline2 as *mut c_void, 0,
-          ((*result).w+1) as usize * size_of::<f32>());
+          (w+1) as usize * size_of::<f32>());
 
       // update all active edges;
       // remove all active edges that terminate before the top of this scanline
@@ -1713,7 +2851,8 @@ pub unsafe fn rasterize_sorted_edges(
 
       // insert all edges that start before the bottom of this scanline
       while (*e).y0 <= scan_y_bottom {
-         if (*e).y0 != (*e).y1 {
+         // Edges of a corrupt outline may end above the bitmap.
+         if (*e).y0 != (*e).y1 && (*e).y1 > scan_y_top {
             let z: *mut ActiveEdge = new_active(
                 &mut hh, e, off_x, scan_y_top);
             STBTT_assert!((*z).ey >= scan_y_top);
@@ -1726,22 +2865,18 @@ pub unsafe fn rasterize_sorted_edges(
 
       // now process all active edges
       if active != null_mut() {
-         fill_active_edges_new(scanline, scanline2.offset(1), (*result).w,
+         fill_active_edges_new(scanline, scanline2.offset(1), w,
             active, scan_y_top);
       }
 
-      {
-         let mut sum: f32 = 0.0;
-         for i in 0..(*result).w {
-            let mut k: f32;
-            let mut m: isize;
-            sum += *scanline2.offset(i);
-            k = *scanline.offset(i) + sum;
-            k = k.abs() as f32 * 255.0 as f32 + 0.5;
-            m = k as isize;
-            if m > 255 { m = 255; }
-            *(*result).pixels.offset(j*(*result).stride + i) = m as u8;
-         }
+      let (coverage, sums) = (slice::from_raw_parts(scanline, w as usize),
+          slice::from_raw_parts(scanline2, w as usize));
+      if pixels.is_null() {
+         simd::accumulate(coverage, sums, &mut row, deterministic);
+         emit(j, &row);
+      } else {
+         simd::accumulate(coverage, sums,
+             slice::from_raw_parts_mut(pixels.offset(j*stride), w as usize), deterministic);
       }
       // advance all the edges
       step = &mut active;
@@ -1869,30 +3004,37 @@ pub unsafe fn sort_edges(p: *mut Edge, n: isize) {
    sort_edges_ins_sort(p, n);
 }
 
+#[derive(Clone, Copy)]
 pub struct Point
 {
    x: f32,
    y: f32,
 }
 
+/// Maps `p` with the affine `transform`, laid out like the matrix of
+/// a compound glyph component.
+fn transform_point(transform: &[f32; 6], p: &Point) -> Point {
+   Point {
+      x: transform[0] * p.x + transform[2] * p.y + transform[4],
+      y: transform[1] * p.x + transform[3] * p.y + transform[5],
+   }
+}
+
+/// Returns the transform from font units to pixels used by the functions
+/// taking a scale and shift, flipping the y axis if `invert` is non-zero.
+fn scale_transform(scale_x: f32, scale_y: f32, shift_x: f32, shift_y: f32, invert: isize)
+    -> [f32; 6]
+{
+   [scale_x, 0.0, 0.0, if invert != 0 { -scale_y } else { scale_y }, shift_x, shift_y]
+}
+
 unsafe fn rasterize_(
     result: *mut Bitmap,
-    pts: *mut Point,
-    wcount: *mut isize,
-    windings: isize,
-    scale_x: f32,
-    scale_y: f32,
-    shift_x: f32,
-    shift_y: f32,
+    mut e: Vec<Edge>,
     off_x: isize,
     off_y: isize,
-    invert: isize
+    options: &RasterOptions
 ) {
-   let y_scale_inv: f32 = if invert != 0 { -scale_y } else { scale_y };
-   let e: *mut Edge;
-   let mut n: isize;
-   let mut j: isize;
-   let mut m: isize;
 // TODO: Conditional compilation.
 // #if STBTT_RASTERIZER_VERSION == 1
 //    int vsubsample = result->h < 8 ? 15 : 5;
@@ -1902,55 +3044,101 @@ unsafe fn rasterize_(
 //   #error "Unrecognized value of STBTT_RASTERIZER_VERSION"
 // #endif
    // vsubsample should divide 255 evenly; otherwise we won't reach full opacity
+   // (the edges are built with y unscaled, which is right for 1)
+   let n = e.len() as isize;
 
-   // now we have to blow out the windings into explicit edge lists
-   n = 0;
-   for i in 0..windings {
-      n = n + *wcount.offset(i);
-   }
-
-   e = STBTT_malloc!(size_of::<Edge>() * (n+1) as usize)
-        as *mut Edge; // add an extra one as a sentinel
-   if e == null_mut() { return };
-   n = 0;
+   // now sort the edges by their highest point (should snap to integer, and then by x)
+   //STBTT_sort(e, n, sizeof(e[0]), stbtt__edge_compare);
+   sort_edges(e.as_mut_ptr(), n);
 
-   m=0;
-   for i in 0..windings {
-      let p: *const Point = pts.offset(m);
-      m += *wcount.offset(i);
-      j = *wcount.offset(i)-1;
-      for k in 0..(*wcount.offset(i)) {
-         let mut a: isize=k;
-         let mut b: isize =j;
-         // skip the edge if horizontal
-         if (*p.offset(j)).y != (*p.offset(k)).y {
-            // add edge from j to k to the list
-            (*e.offset(n)).invert = 0;
-            if if invert != 0 { (*p.offset(j)).y > (*p.offset(k)).y }
-               else { (*p.offset(j)).y < (*p.offset(k)).y } {
-               (*e.offset(n)).invert = 1;
-               a=j;
-               b=k;
-            }
-            (*e.offset(n)).x0 = (*p.offset(a)).x * scale_x + shift_x;
-            (*e.offset(n)).y0 = ((*p.offset(a)).y * y_scale_inv + shift_y) * vsubsample as f32;
-            (*e.offset(n)).x1 = (*p.offset(b)).x * scale_x + shift_x;
-            (*e.offset(n)).y1 = ((*p.offset(b)).y * y_scale_inv + shift_y) * vsubsample as f32;
+   // now, traverse the scanlines and find the intersections on each scanline, use xor winding rule
+   if options.is_sampled() {
+      raster::rasterize_sorted_edges_sampled(result, &e, off_x, off_y, options);
+   } else {
+      e.push(Edge { x0: 0.0, y0: 0.0, x1: 0.0, y1: 0.0, invert: 0 }); // add an extra one as a sentinel
+      rasterize_sorted_edges(result, e.as_mut_ptr(), n, vsubsample, off_x, off_y,
+          options.deterministic);
+   }
+}
 
-            n += 1;
+// Builds the edges of the contours of 'vertices' mapped with 'transform',
+// tesselating the curves straight into the edge list instead of flattening
+// them to a list of points first (the stb TODO "build edge-list directly
+// from curves"). Most glyphs need less than two edges per vertex.
+fn vertex_edges(vertices: &[Vertex], transform: &[f32; 6], objspace_flatness: f32) -> Vec<Edge> {
+   let objspace_flatness_squared = objspace_flatness * objspace_flatness;
+   let mut edges = Vec::with_capacity(vertices.len() * 2);
+   // the first point of the contour, the last one added and the end of the
+   // last segment, which only differ from it if a curve is too deep
+   let mut start = Point { x: 0.0, y: 0.0 };
+   let mut last = start;
+   let mut pen = start;
+   for v in vertices {
+      let p = Point { x: v.x as f32, y: v.y as f32 };
+      match v.type_ {
+         Cmd::Move => {
+            // close the previous contour and start the next one
+            add_edge(&mut edges, transform, &last, &start);
+            start = p;
+            last = p;
+         }
+         Cmd::Line => {
+            add_edge(&mut edges, transform, &last, &p);
+            last = p;
+         }
+         Cmd::Curve => {
+            let control = Point { x: v.cx as f32, y: v.cy as f32 };
+            tesselate_curve_edges(&mut edges, transform, &mut last, &pen, &control, &p,
+                objspace_flatness_squared, 0);
          }
-         j = k;
       }
+      pen = p;
    }
+   add_edge(&mut edges, transform, &last, &start);
+   edges
+}
 
-   // now sort the edges by their highest point (should snap to integer, and then by x)
-   //STBTT_sort(e, n, sizeof(e[0]), stbtt__edge_compare);
-   sort_edges(e, n);
-
-   // now, traverse the scanlines and find the intersections on each scanline, use xor winding rule
-   rasterize_sorted_edges(result, e, n, vsubsample, off_x, off_y);
+// adds the edge from 'a' to 'b' mapped with 'transform' going from top to
+// bottom, unless it's horizontal
+fn add_edge(edges: &mut Vec<Edge>, transform: &[f32; 6], a: &Point, b: &Point) {
+   let a = transform_point(transform, a);
+   let b = transform_point(transform, b);
+   if a.y < b.y {
+      edges.push(Edge { x0: a.x, y0: a.y, x1: b.x, y1: b.y, invert: 1 });
+   } else if a.y > b.y {
+      edges.push(Edge { x0: b.x, y0: b.y, x1: a.x, y1: a.y, invert: 0 });
+   }
+}
 
-   STBTT_free!(e as *mut c_void);
+// same as tesselate_curve, but adds edges from 'last', the last point
+// reached, instead of points
+fn tesselate_curve_edges(
+    edges: &mut Vec<Edge>,
+    transform: &[f32; 6],
+    last: &mut Point,
+    p0: &Point,
+    p1: &Point,
+    p2: &Point,
+    objspace_flatness_squared: f32,
+    n: isize
+) {
+   // midpoint
+   let m = Point { x: (p0.x + 2.0*p1.x + p2.x)/4.0, y: (p0.y + 2.0*p1.y + p2.y)/4.0 };
+   // versus directly drawn line
+   let dx: f32 = (p0.x+p2.x)/2.0 - m.x;
+   let dy: f32 = (p0.y+p2.y)/2.0 - m.y;
+   if n > 16 { // 65536 segments on one curve better be enough!
+      return;
+   }
+   if dx*dx+dy*dy > objspace_flatness_squared { // half-pixel error allowed... need to be smaller if AA
+      let a = Point { x: (p0.x+p1.x)/2.0, y: (p0.y+p1.y)/2.0 };
+      let b = Point { x: (p1.x+p2.x)/2.0, y: (p1.y+p2.y)/2.0 };
+      tesselate_curve_edges(edges, transform, last, p0, &a, &m, objspace_flatness_squared, n+1);
+      tesselate_curve_edges(edges, transform, last, &m, &b, p2, objspace_flatness_squared, n+1);
+   } else {
+      add_edge(edges, transform, last, p2);
+      *last = *p2;
+   }
 }
 
 pub unsafe fn add_point(
@@ -2087,8 +3275,8 @@ pub unsafe fn flatten_curves(
 
 // rasterize a shape with quadratic beziers into a bitmap
 pub unsafe fn rasterize(
-    // 1-channel bitmap to draw into
-    result: *mut Bitmap,
+    // 1-channel bitmap to draw into, its position is subtracted from the shape
+    result: &mut GrayBitmap,
     // allowable error of curve in pixels
     flatness_in_pixels: f32,
     // array of vertices defining shape
@@ -2101,23 +3289,66 @@ pub unsafe fn rasterize(
     // translation applied to input vertices
     shift_x: f32,
     shift_y: f32,
-    // another translation applied to input
-    x_off: isize,
-    y_off: isize,
     // if non-zero, vertically flip shape
     invert: isize
 ) {
+   rasterize_with_options(result, flatness_in_pixels, vertices, num_verts,
+       scale_x, scale_y, shift_x, shift_y, invert, &RasterOptions::default());
+}
+
+/// Same as `rasterize`, but with `options` for the rasterizer.
+pub unsafe fn rasterize_with_options(
+    result: &mut GrayBitmap,
+    flatness_in_pixels: f32,
+    vertices: *mut Vertex,
+    num_verts: isize,
+    scale_x: f32,
+    scale_y: f32,
+    shift_x: f32,
+    shift_y: f32,
+    invert: isize,
+    options: &RasterOptions
+) {
+   rasterize_transformed(result, flatness_in_pixels, vertices, num_verts,
+       &scale_transform(scale_x, scale_y, shift_x, shift_y, invert), options);
+}
+
+/// Same as `rasterize_with_options`, but maps the vertices with an affine
+/// `transform` `[a, b, c, d, e, f]` taking `(x, y)` to
+/// `(a * x + c * y + e, b * x + d * y + f)`, which allows rotating and
+/// skewing the shape.
+pub unsafe fn rasterize_transformed(
+    result: &mut GrayBitmap,
+    flatness_in_pixels: f32,
+    vertices: *mut Vertex,
+    num_verts: isize,
+    transform: &[f32; 6],
+    options: &RasterOptions
+) {
+   let (x_off, y_off) = (result.x as isize, result.y as isize);
+   rasterize_bitmap(&mut raw_bitmap(result), flatness_in_pixels, vertices, num_verts,
+       transform, x_off, y_off, options);
+}
+
+unsafe fn rasterize_bitmap(
+    result: *mut Bitmap,
+    flatness_in_pixels: f32,
+    vertices: *mut Vertex,
+    num_verts: isize,
+    transform: &[f32; 6],
+    x_off: isize,
+    y_off: isize,
+    options: &RasterOptions
+) {
+   let scale_x = libm::sqrtf(transform[0] * transform[0] + transform[1] * transform[1]);
+   let scale_y = libm::sqrtf(transform[2] * transform[2] + transform[3] * transform[3]);
    let scale: f32 = if scale_x > scale_y { scale_y } else { scale_x };
-   let mut winding_count: isize = 0;
-   let mut winding_lengths: *mut isize = null_mut();
-   let windings: *mut Point = flatten_curves(vertices, num_verts,
-       flatness_in_pixels / scale, &mut winding_lengths, &mut winding_count);
-   if windings != null_mut() {
-      rasterize_(result, windings, winding_lengths, winding_count,
-          scale_x, scale_y, shift_x, shift_y, x_off, y_off, invert);
-      STBTT_free!(winding_lengths as *mut c_void);
-      STBTT_free!(windings as *mut c_void);
+   if vertices == null_mut() || num_verts <= 0 {
+      return;
    }
+   let edges = vertex_edges(slice::from_raw_parts(vertices, num_verts as usize), transform,
+       flatness_in_pixels / scale);
+   rasterize_(result, edges, x_off, y_off, options);
 }
 
 // frees the bitmap allocated below
@@ -2127,28 +3358,64 @@ pub unsafe fn free_bitmap(bitmap: *mut u8)
 }
 
 pub unsafe fn get_glyph_bitmap_subpixel(
+    info: *const FontInfo,
+    scale_x: f32,
+    scale_y: f32,
+    shift_x: f32,
+    shift_y: f32,
+    glyph: GlyphId,
+    width: *mut isize,
+    height: *mut isize,
+    xoff: *mut isize,
+    yoff: *mut isize
+) -> *mut u8 {
+   get_glyph_bitmap_with_options(info, scale_x, scale_y, shift_x, shift_y, glyph,
+       &RasterOptions::default(), width, height, xoff, yoff)
+}
+
+/// Same as `get_glyph_bitmap_subpixel`, but with `options` for the rasterizer.
+pub unsafe fn get_glyph_bitmap_with_options(
     info: *const FontInfo,
     mut scale_x: f32,
     mut scale_y: f32,
     shift_x: f32,
     shift_y: f32,
-    glyph: isize,
+    glyph: GlyphId,
+    options: &RasterOptions,
     width: *mut isize,
     height: *mut isize,
     xoff: *mut isize,
     yoff: *mut isize
 ) -> *mut u8 {
-   let mut vertices: *mut Vertex = null_mut();
-   let num_verts: isize = get_glyph_shape(info, glyph, &mut vertices);
-
    if scale_x == 0.0 { scale_x = scale_y; }
    if scale_y == 0.0 {
       if scale_x == 0.0 { return null_mut(); }
       scale_y = scale_x;
    }
 
-   let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph as usize);
-   let bbox = glyph_data.bitmap_box_subpixel(scale_x, scale_y, shift_x, shift_y).unwrap_or_default();
+   get_glyph_bitmap_transformed(info, &scale_transform(scale_x, scale_y, shift_x, shift_y, 1),
+       glyph, options, width, height, xoff, yoff)
+}
+
+/// Same as `get_glyph_bitmap_with_options`, but maps the glyph with an affine
+/// `transform` from font units to pixels, see `rasterize_transformed`. The
+/// y axis of the glyph points up and the one of the bitmap down, so
+/// `get_glyph_bitmap_subpixel` uses `[scale_x, 0.0, 0.0, -scale_y, shift_x, shift_y]`.
+pub unsafe fn get_glyph_bitmap_transformed(
+    info: *const FontInfo,
+    transform: &[f32; 6],
+    glyph: GlyphId,
+    options: &RasterOptions,
+    width: *mut isize,
+    height: *mut isize,
+    xoff: *mut isize,
+    yoff: *mut isize
+) -> *mut u8 {
+   let mut vertices: *mut Vertex = null_mut();
+   let num_verts: isize = get_glyph_shape(info, glyph, &mut vertices);
+
+   let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph);
+   let bbox = glyph_data.bitmap_box_transformed(transform).unwrap_or_default();
 
    // now we get the size
    let mut gbm = Bitmap
@@ -2164,14 +3431,13 @@ pub unsafe fn get_glyph_bitmap_subpixel(
    if xoff != null_mut() { *xoff   = bbox.x0 as isize; }
    if yoff != null_mut() { *yoff   = bbox.y0 as isize; }
 
-   if gbm.w != 0 && gbm.h != 0 {
+   if gbm.w > 0 && gbm.h > 0 {
       gbm.pixels = STBTT_malloc!((gbm.w * gbm.h) as usize) as *mut u8;
       if gbm.pixels != null_mut() {
          gbm.stride = gbm.w;
 
-         rasterize(&mut gbm, 0.35,
-             vertices, num_verts, scale_x, scale_y, shift_x, shift_y, bbox.x0 as isize, bbox.y0 as isize,
-              1);
+         rasterize_bitmap(&mut gbm, 0.35,
+             vertices, num_verts, transform, bbox.x0 as isize, bbox.y0 as isize, options);
       }
    }
    STBTT_free!(vertices as *mut c_void);
@@ -2185,7 +3451,7 @@ pub unsafe fn get_glyph_bitmap(
     info: *const FontInfo,
     scale_x: f32,
     scale_y: f32,
-    glyph: isize,
+    glyph: GlyphId,
     width: *mut isize,
     height: *mut isize,
     xoff: *mut isize,
@@ -2205,13 +3471,28 @@ pub unsafe fn make_glyph_bitmap_subpixel(
     scale_y: f32,
     shift_x: f32,
     shift_y: f32,
-    glyph: isize
+    glyph: GlyphId
+) {
+   make_glyph_bitmap_transformed(info, output, out_w, out_h, out_stride,
+       &scale_transform(scale_x, scale_y, shift_x, shift_y, 1), glyph);
+}
+
+/// Same as `make_glyph_bitmap_subpixel`, but maps the glyph with an affine
+/// `transform`, see `get_glyph_bitmap_transformed`.
+pub unsafe fn make_glyph_bitmap_transformed(
+    info: *const FontInfo,
+    output: *mut u8,
+    out_w: isize,
+    out_h: isize,
+    out_stride: isize,
+    transform: &[f32; 6],
+    glyph: GlyphId
 ) {
    let mut vertices: *mut Vertex = null_mut();
    let num_verts: isize = get_glyph_shape(info, glyph, &mut vertices);
 
-   let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph as usize);
-   let bbox = glyph_data.bitmap_box_subpixel(scale_x, scale_y, shift_x, shift_y).unwrap_or_default();
+   let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph);
+   let bbox = glyph_data.bitmap_box_transformed(transform).unwrap_or_default();
 
    let mut gbm: Bitmap = Bitmap
    {
@@ -2222,8 +3503,8 @@ pub unsafe fn make_glyph_bitmap_subpixel(
    };
 
    if gbm.w != 0 && gbm.h != 0 {
-      rasterize(&mut gbm, 0.35, vertices, num_verts,
-          scale_x, scale_y, shift_x, shift_y, bbox.x0 as isize,bbox.y0 as isize, 1);
+      rasterize_bitmap(&mut gbm, 0.35, vertices, num_verts,
+          transform, bbox.x0 as isize, bbox.y0 as isize, &RasterOptions::default());
    }
 
    STBTT_free!(vertices as *mut c_void);
@@ -2237,7 +3518,7 @@ pub unsafe fn make_glyph_bitmap(
     out_stride: isize,
     scale_x: f32,
     scale_y: f32,
-    glyph: isize
+    glyph: GlyphId
 ) {
    make_glyph_bitmap_subpixel(info, output, out_w, out_h,
        out_stride, scale_x, scale_y, 0.0,0.0, glyph);
@@ -2251,14 +3532,13 @@ pub unsafe fn get_codepoint_bitmap_subpixel(
     scale_y: f32,
     shift_x: f32,
     shift_y: f32,
-    codepoint: isize,
+    codepoint: char,
     width: *mut isize,
     height: *mut isize,
     xoff: *mut isize,
     yoff: *mut isize
 ) -> *mut u8 {
-    assert!(codepoint >= 0);
-    let i = (*info).glyph_index_for_code(codepoint as usize) as isize;
+    let i = (*info).glyph_index_for_code(codepoint);
     get_glyph_bitmap_subpixel(info, scale_x, scale_y,shift_x,shift_y, i, width,height,xoff,yoff)
 }
 
@@ -2274,10 +3554,9 @@ pub unsafe fn make_codepoint_bitmap_subpixel(
     scale_y: f32,
     shift_x: f32,
     shift_y: f32,
-    codepoint: isize
+    codepoint: char
 ) {
-    assert!(codepoint >= 0);
-    let i = (*info).glyph_index_for_code(codepoint as usize) as isize;
+    let i = (*info).glyph_index_for_code(codepoint);
     make_glyph_bitmap_subpixel(info, output, out_w, out_h,
         out_stride, scale_x, scale_y, shift_x, shift_y, i);
 }
@@ -2293,7 +3572,7 @@ pub unsafe fn get_codepoint_bitmap(
     info: *const FontInfo,
     scale_x: f32,
     scale_y: f32,
-    codepoint: isize,
+    codepoint: char,
     width: *mut isize,
     height: *mut isize,
     xoff: *mut isize,
@@ -2315,7 +3594,7 @@ pub unsafe fn make_codepoint_bitmap(
     out_stride: isize,
     scale_x: f32,
     scale_y: f32,
-    codepoint: isize
+    codepoint: char
 ) {
    make_codepoint_bitmap_subpixel(info, output, out_w, out_h,
        out_stride, scale_x, scale_y, 0.0,0.0, codepoint);
@@ -2334,7 +3613,7 @@ pub unsafe fn bake_font_bitmap(
     data: &[u8], offset: usize,  // font location (use offset=0 for plain .ttf)
     pixel_height: f32,                     // height of font in pixels
     pixels: *mut u8, pw: isize, ph: isize,  // bitmap to be filled in
-    first_char: isize, num_chars: isize,          // characters to bake
+    first_char: char, num_chars: isize,           // characters to bake
     chardata: *mut BakedChar
 ) -> Result<isize> {
     let scale: f32;
@@ -2350,10 +3629,10 @@ pub unsafe fn bake_font_bitmap(
     scale = f.scale_for_pixel_height(pixel_height);
 
    for i in 0..num_chars {
-      let g = f.glyph_index_for_code((first_char + i) as usize) as isize;
-      let glyph_data = f.glyph_data_for_glyph_at_index(g as usize);
+      let g = f.glyph_for_code(first_char as usize + i as usize);
+      let glyph_data = f.glyph_data_for_glyph_at_index(g);
       let bbox = glyph_data.bitmap_box(scale, scale).unwrap_or_default();
-      let metric = f.hmtx.hmetric_for_glyph_at_index(g as usize);
+      let metric = f.hmetric_for_glyph_at_index(g);
 
       let gw = (bbox.x1 - bbox.x0) as isize;
       let gh = (bbox.y1 - bbox.y0) as isize;
@@ -2382,6 +3661,77 @@ pub unsafe fn bake_font_bitmap(
    return Ok(bottom_y);
 }
 
+// Same as bake_font_bitmap, but renders the characters 'h_oversample' by
+// 'v_oversample' times larger and filters them like the pack API does (see
+// PackSettings), which makes small text sharper and allows subpixel
+// positioning. The characters are PackedChars, draw them with
+// get_packed_quad. Oversampling of 1 bakes like bake_font_bitmap.
+pub unsafe fn bake_font_bitmap_oversampled(
+    data: &[u8], offset: usize,  // font location (use offset=0 for plain .ttf)
+    pixel_height: f32,                     // height of font in pixels
+    pixels: *mut u8, pw: isize, ph: isize,  // bitmap to be filled in
+    first_char: char, num_chars: isize,           // characters to bake
+    h_oversample: usize, v_oversample: usize,     // 1 to 8
+    chardata: *mut PackedChar
+) -> Result<isize> {
+   let f: FontInfo = try!(FontInfo::new_with_offset(data, offset));
+   STBTT_assert!(h_oversample <= STBTT_MAX_OVERSAMPLE);
+   STBTT_assert!(v_oversample <= STBTT_MAX_OVERSAMPLE);
+   let h_over = h_oversample.max(1).min(STBTT_MAX_OVERSAMPLE) as isize;
+   let v_over = v_oversample.max(1).min(STBTT_MAX_OVERSAMPLE) as isize;
+   memset(pixels as *mut _ as *mut c_void, 0, (pw*ph) as usize); // background of 0 around pixels
+   let mut x: isize = 1;
+   let mut y: isize = 1;
+   let mut bottom_y: isize = 1;
+
+   let scale = f.scale_for_pixel_height(pixel_height);
+   let (recip_h, recip_v) = (1.0 / h_over as f32, 1.0 / v_over as f32);
+   let (sub_x, sub_y) = (oversample_shift(h_over), oversample_shift(v_over));
+
+   for i in 0..num_chars {
+      let g = f.glyph_for_code(first_char as usize + i as usize);
+      let glyph_data = f.glyph_data_for_glyph_at_index(g);
+      let bbox = glyph_data.bitmap_box(scale * h_over as f32, scale * v_over as f32)
+          .unwrap_or_default();
+      let metric = f.hmetric_for_glyph_at_index(g);
+
+      // the prefilter needs oversample-1 empty pixels right of and below the glyph
+      let gw = (bbox.x1 - bbox.x0) as isize + h_over - 1;
+      let gh = (bbox.y1 - bbox.y0) as isize + v_over - 1;
+      if x + gw + 1 >= pw {
+         y = bottom_y;
+         x = 1; // advance to next row
+      }
+      if y + gh + 1 >= ph { // check if it fits vertically AFTER potentially moving to next row
+         return Ok(-i);
+      }
+      let glyph_pixels = pixels.offset(x+y*pw);
+      make_glyph_bitmap_subpixel(&f, glyph_pixels, gw - h_over + 1, gh - v_over + 1, pw,
+          scale * h_over as f32, scale * v_over as f32, 0.0, 0.0, g);
+      if h_over > 1 {
+         h_prefilter(glyph_pixels, gw, gh, pw, h_over as usize);
+      }
+      if v_over > 1 {
+         v_prefilter(glyph_pixels, gw, gh, pw, v_over as usize);
+      }
+      let bc = &mut *chardata.offset(i);
+      bc.x0 = x as u16;
+      bc.y0 = y as u16;
+      bc.x1 = (x + gw) as u16;
+      bc.y1 = (y + gh) as u16;
+      bc.xadvance = scale * metric.advance_width as f32;
+      bc.xoff = bbox.x0 as f32 * recip_h + sub_x;
+      bc.yoff = bbox.y0 as f32 * recip_v + sub_y;
+      bc.xoff2 = (bbox.x0 as isize + gw) as f32 * recip_h + sub_x;
+      bc.yoff2 = (bbox.y0 as isize + gh) as f32 * recip_v + sub_y;
+      x = x + gw + 1;
+      if y+gh+1 > bottom_y {
+         bottom_y = y+gh+1;
+      }
+   }
+   return Ok(bottom_y);
+}
+
 // Call GetBakedQuad with char_index = 'character - first_char', and it
 // creates the quad you need to draw and advances the current position.
 //
@@ -2404,24 +3754,10 @@ pub unsafe fn get_baked_quad(
     opengl_fillrule: isize
 )
 {
-   let d3d_bias: f32 = if opengl_fillrule != 0 { 0.0 } else { -0.5 };
-   let ipw: f32 = 1.0 / pw as f32;
-   let iph = 1.0 / ph as f32;
-   let b: *mut BakedChar = chardata.offset(char_index);
-   let round_x = ifloor((*xpos + (*b).xoff) + 0.5);
-   let round_y = ifloor((*ypos + (*b).yoff) + 0.5);
-
-   (*q).x0 = round_x as f32 + d3d_bias;
-   (*q).y0 = round_y as f32 + d3d_bias;
-   (*q).x1 = round_x as f32 + (*b).x1 as f32 - (*b).x0 as f32 + d3d_bias;
-   (*q).y1 = round_y as f32 + (*b).y1 as f32 - (*b).y0 as f32 + d3d_bias;
-
-   (*q).s0 = (*b).x0 as f32 * ipw;
-   (*q).t0 = (*b).y0 as f32 * iph;
-   (*q).s1 = (*b).x1 as f32 * ipw;
-   (*q).t1 = (*b).y1 as f32 * iph;
-
-   *xpos += (*b).xadvance;
+   let (quad, x) = (*chardata.offset(char_index)).quad(pw as usize, ph as usize,
+       *xpos, *ypos, opengl_fillrule != 0);
+   *q = quad;
+   *xpos = x;
 }
 
 //////////////////////////////////////////////////////////////////////////////
@@ -2536,7 +3872,8 @@ pub unsafe fn stbrp_pack_rects(
 // the distance from one row to the next (or 0 to mean they are packed tightly
 // together). "padding" is the amount of padding to leave between each
 // character (normally you want '1' for bitmaps you'll use as textures with
-// bilinear filtering).
+// bilinear filtering). Use aligned_stride for rows that can be uploaded to
+// OpenGL or Vulkan as they are.
 //
 // Returns 0 on failure, 1 on success.
 pub unsafe fn pack_begin(
@@ -2569,13 +3906,11 @@ pub unsafe fn pack_begin(
    (*spc).nodes = nodes as *mut c_void;
    (*spc).padding = padding;
    (*spc).stride_in_bytes = if stride_in_bytes != 0 { stride_in_bytes } else { pw };
-   (*spc).h_oversample = 1;
-   (*spc).v_oversample = 1;
 
    stbrp_init_target(context, pw-padding, ph-padding, nodes, num_nodes);
 
    if pixels != null_mut() {
-      memset(pixels as *mut c_void, 0, (pw*ph) as usize); // background of 0 around pixels
+      memset(pixels as *mut c_void, 0, ((*spc).stride_in_bytes*ph) as usize); // background of 0 around pixels
    }
 
    return 1;
@@ -2588,35 +3923,6 @@ pub unsafe fn pack_end(spc: *mut PackContext)
    STBTT_free!((*spc).pack_info);
 }
 
-// Oversampling a font increases the quality by allowing higher-quality subpixel
-// positioning, and is especially valuable at smaller text sizes.
-//
-// This function sets the amount of oversampling for all following calls to
-// stbtt_PackFontRange(s) or stbtt_PackFontRangesGatherRects for a given
-// pack context. The default (no oversampling) is achieved by h_oversample=1
-// and v_oversample=1. The total number of pixels required is
-// h_oversample*v_oversample larger than the default; for example, 2x2
-// oversampling requires 4x the storage of 1x1. For best results, render
-// oversampled textures with bilinear filtering. Look at the readme in
-// stb/tests/oversample for information about oversampled fonts
-//
-// To use with PackFontRangesGather etc., you must set it before calls
-// call to PackFontRangesGatherRects.
-pub unsafe fn pack_set_oversampling(
-    spc: *mut PackContext,
-    h_oversample: usize,
-    v_oversample: usize)
-{
-   STBTT_assert!(h_oversample <= STBTT_MAX_OVERSAMPLE);
-   STBTT_assert!(v_oversample <= STBTT_MAX_OVERSAMPLE);
-   if h_oversample <= STBTT_MAX_OVERSAMPLE {
-      (*spc).h_oversample = h_oversample;
-   }
-   if v_oversample <= STBTT_MAX_OVERSAMPLE {
-      (*spc).v_oversample = v_oversample;
-   }
-}
-
 const STBTT__OVER_MASK: usize = (STBTT_MAX_OVERSAMPLE-1);
 
 pub unsafe fn h_prefilter(
@@ -2637,43 +3943,43 @@ pub unsafe fn h_prefilter(
       // make kernel_width a constant in common cases so compiler can optimize out the divide
       match kernel_width {
         2 => {
-            for i in 0..safe_w {
+            for i in 0..safe_w + 1 {
                total = total + *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i);
                *pixels.offset(i) = (total / 2) as u8;
             }
         }
         3 => {
-            for i in 0..safe_w {
-               total += *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_w + 1 {
+               total = total + *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i);
                *pixels.offset(i) = (total / 3) as u8;
             }
         }
         4 => {
-            for i in 0..safe_w {
-               total += *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_w + 1 {
+               total = total + *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i);
                *pixels.offset(i) = (total / 4) as u8;
             }
         }
         5 => {
-            for i in 0..safe_w {
-               total += *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_w + 1 {
+               total = total + *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i);
                *pixels.offset(i) = (total / 5) as u8;
             }
         }
         _ => {
-            for i in 0..safe_w {
-               total += *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_w + 1 {
+               total = total + *pixels.offset(i) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i);
                *pixels.offset(i) = (total / kernel_width) as u8;
             }
         }
       }
 
-      for i in safe_w..w {
+      for i in safe_w + 1..w {
          STBTT_assert!(*pixels.offset(i) == 0);
          total -= buffer[i as usize & STBTT__OVER_MASK] as usize;
          *pixels.offset(i) = (total / kernel_width) as u8;
@@ -2692,7 +3998,9 @@ pub unsafe fn v_prefilter(
 ) {
    let mut buffer: [u8; STBTT_MAX_OVERSAMPLE] = [0; STBTT_MAX_OVERSAMPLE];
    let safe_h: isize = h - kernel_width as isize;
-   for _ in 0..w {
+   let columns = simd::v_prefilter(pixels, w, h, stride_in_bytes, kernel_width);
+   pixels = pixels.offset(columns);
+   for _ in columns..w {
       let mut total: usize;
       memset(&mut buffer[0] as *mut _ as *mut c_void, 0, kernel_width);
 
@@ -2701,43 +4009,43 @@ pub unsafe fn v_prefilter(
       // make kernel_width a constant in common cases so compiler can optimize out the divide
       match kernel_width {
          2 => {
-            for i in 0..safe_h {
-               total += *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_h + 1 {
+               total = total + *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i*stride_in_bytes);
                *pixels.offset(i*stride_in_bytes) = (total / 2) as u8;
             }
         }
         3 => {
-            for i in 0..safe_h {
-               total += *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_h + 1 {
+               total = total + *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i*stride_in_bytes);
                *pixels.offset(i*stride_in_bytes) = (total / 3) as u8;
             }
         }
         4 => {
-            for i in 0..safe_h {
-               total += *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_h + 1 {
+               total = total + *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i*stride_in_bytes);
                *pixels.offset(i*stride_in_bytes) = (total / 4) as u8;
             }
         }
         5 => {
-            for i in 0..safe_h {
-               total += *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_h + 1 {
+               total = total + *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i as usize +kernel_width) & STBTT__OVER_MASK] = *pixels.offset(i*stride_in_bytes);
                *pixels.offset(i*stride_in_bytes) = (total / 5) as u8;
             }
         }
         _ => {
-            for i in 0..safe_h {
-               total += *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
+            for i in 0..safe_h + 1 {
+               total = total + *pixels.offset(i*stride_in_bytes) as usize - buffer[i as usize & STBTT__OVER_MASK] as usize;
                buffer[(i+kernel_width as isize) as usize & STBTT__OVER_MASK] = *pixels.offset(i*stride_in_bytes);
                *pixels.offset(i*stride_in_bytes) = (total / kernel_width) as u8;
             }
         }
       }
 
-      for i in safe_h..h {
+      for i in safe_h + 1..h {
          STBTT_assert!(*pixels.offset(i*stride_in_bytes) == 0);
          total -= buffer[i as usize & STBTT__OVER_MASK] as usize;
          *pixels.offset(i*stride_in_bytes) = (total / kernel_width) as u8;
@@ -2747,6 +4055,95 @@ pub unsafe fn v_prefilter(
    }
 }
 
+/// Same as `h_prefilter`, but with the tent filter of `Prefilter::Tent`.
+pub unsafe fn h_prefilter_tent(
+    pixels: *mut u8,
+    w: isize,
+    h: isize,
+    stride_in_bytes: isize,
+    kernel_width: usize
+) {
+   for j in 0..h {
+      tent_prefilter(pixels.offset(j*stride_in_bytes), w, 1, kernel_width);
+   }
+}
+
+/// Same as `v_prefilter`, but with the tent filter of `Prefilter::Tent`.
+pub unsafe fn v_prefilter_tent(
+    pixels: *mut u8,
+    w: isize,
+    h: isize,
+    stride_in_bytes: isize,
+    kernel_width: usize
+) {
+   for i in 0..w {
+      tent_prefilter(pixels.offset(i), h, stride_in_bytes, kernel_width);
+   }
+}
+
+// Filters the 'n' pixels 'step' bytes apart from 'pixels' with a tent of
+// 'kernel_width' weights, each pixel becoming the weighted average of
+// itself and the kernel_width-1 before it like with the box filter.
+unsafe fn tent_prefilter(pixels: *mut u8, n: isize, step: isize, kernel_width: usize) {
+   let mut weights = [0u32; STBTT_MAX_OVERSAMPLE];
+   for k in 0..kernel_width {
+      weights[k] = ::core::cmp::min(k + 1, kernel_width - k) as u32;
+   }
+   let total: u32 = weights[..kernel_width].iter().sum();
+   // the last kernel_width-1 inputs, the pixels are overwritten
+   let mut previous = [0u8; STBTT_MAX_OVERSAMPLE];
+   for i in 0..n {
+      let p = pixels.offset(i*step);
+      let mut sum = *p as u32 * weights[0];
+      for k in 1..kernel_width {
+         sum += previous[(i as usize + STBTT_MAX_OVERSAMPLE - k) & STBTT__OVER_MASK] as u32 * weights[k];
+      }
+      previous[i as usize & STBTT__OVER_MASK] = *p;
+      *p = ((sum + total / 2) / total) as u8;
+   }
+}
+
+/// Returns the distance in bytes between rows of `width` pixels that start
+/// at multiples of `alignment` bytes, e.g. 4 for the default
+/// `GL_UNPACK_ALIGNMENT` of OpenGL.
+///
+/// Pass it to `pack_begin`, or use `PagedAtlas::with_stride_alignment`, to
+/// upload bitmaps without copying the rows.
+pub fn aligned_stride(width: usize, alignment: usize) -> usize {
+    if alignment <= 1 {
+        width
+    } else {
+        (width + alignment - 1) / alignment * alignment
+    }
+}
+
+// Copies the pixels at the edges of the w x h rectangle at (x,y) of a
+// bitmap into 'gutter' pixels around it, clipped to the bitmap.
+unsafe fn bleed_edges(
+    pixels: *mut u8,
+    stride_in_bytes: isize,
+    bw: isize,
+    bh: isize,
+    x: isize,
+    y: isize,
+    w: isize,
+    h: isize,
+    gutter: isize
+) {
+   if w <= 0 || h <= 0 {
+      return;
+   }
+   for j in ::core::cmp::max(y - gutter, 0)..::core::cmp::min(y + h + gutter, bh) {
+      let sy = ::core::cmp::min(::core::cmp::max(j, y), y + h - 1);
+      for i in ::core::cmp::max(x - gutter, 0)..::core::cmp::min(x + w + gutter, bw) {
+         let sx = ::core::cmp::min(::core::cmp::max(i, x), x + w - 1);
+         if sx != i || sy != j {
+            *pixels.offset(i + j*stride_in_bytes) = *pixels.offset(sx + sy*stride_in_bytes);
+         }
+      }
+   }
+}
+
 pub fn oversample_shift(oversample: isize) -> f32
 {
    if oversample == 0 {
@@ -2777,7 +4174,7 @@ pub fn oversample_shift(oversample: isize) -> f32
 // rects array must be big enough to accommodate all characters in the given ranges
 pub unsafe fn pack_font_ranges_gather_rects(
     spc: *mut PackContext,
-    info: *mut FontInfo,
+    info: *const FontInfo,
     ranges: *mut PackRange,
     num_ranges: isize,
     rects: *mut Rect
@@ -2792,24 +4189,26 @@ pub unsafe fn pack_font_ranges_gather_rects(
         } else {
             (*info).scale_for_mapping_em_to_pixels(-fh)
         };
-      (*ranges.offset(i)).h_oversample = (*spc).h_oversample as u8;
-      (*ranges.offset(i)).v_oversample = (*spc).v_oversample as u8;
+      let (h_oversample, v_oversample) = (*ranges.offset(i)).settings.oversample();
+      let mut padding = (*ranges.offset(i)).settings.padding(&*spc);
+      if (*ranges.offset(i)).settings.bleed {
+         padding *= 2; // the edges bleed right and down as well
+      }
+      let phases = (*ranges.offset(i)).settings.phases();
       for j in 0..(*ranges.offset(i)).num_chars {
-         let codepoint: isize = if (*ranges.offset(i)).array_of_unicode_codepoints == null() {
-                (*ranges.offset(i)).first_unicode_codepoint_in_range + j
-             } else {
-                *(*ranges.offset(i)).array_of_unicode_codepoints.offset(j)
-             };
-          assert!(codepoint >= 0);
-         let glyph = (*info).glyph_index_for_code(codepoint as usize) as isize;
-         let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph as usize);
-         let bbox = glyph_data.bitmap_box(
-            scale * (*spc).h_oversample as f32,
-            scale * (*spc).v_oversample as f32).unwrap_or_default();
-
-         (*rects.offset(k)).w = ((bbox.x1-bbox.x0) as isize + (*spc).padding as isize + (*spc).h_oversample as isize -1) as Coord;
-         (*rects.offset(k)).h = ((bbox.y1-bbox.y0) as isize + (*spc).padding as isize + (*spc).v_oversample as isize -1) as Coord;
-         k += 1;
+         let codepoint = (*ranges.offset(i)).codepoint(j);
+         let glyph = (*info).glyph_for_code(codepoint);
+         let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph);
+         for p in 0..phases {
+            let shift = (p * h_oversample) as f32 / phases as f32;
+            let bbox = glyph_data.bitmap_box_subpixel(
+               scale * h_oversample as f32,
+               scale * v_oversample as f32, shift, 0.0).unwrap_or_default();
+
+            (*rects.offset(k)).w = ((bbox.x1-bbox.x0) as isize + padding + h_oversample as isize -1) as Coord;
+            (*rects.offset(k)).h = ((bbox.y1-bbox.y0) as isize + padding + v_oversample as isize -1) as Coord;
+            k += 1;
+         }
       }
    }
 
@@ -2819,7 +4218,7 @@ pub unsafe fn pack_font_ranges_gather_rects(
 // rects array must be big enough to accommodate all characters in the given ranges
 pub unsafe fn pack_font_ranges_render_into_rects(
     spc: *mut PackContext,
-    info: *mut FontInfo,
+    info: *const FontInfo,
     ranges: *mut PackRange,
     num_ranges: isize,
     rects: *mut Rect
@@ -2827,10 +4226,6 @@ pub unsafe fn pack_font_ranges_render_into_rects(
    let mut k: isize;
    let mut return_value: isize = 1;
 
-   // save current values
-   let old_h_over: isize = (*spc).h_oversample as isize;
-   let old_v_over: isize = (*spc).v_oversample as isize;
-
    k = 0;
    for i in 0..num_ranges {
       let fh: f32 = (*ranges.offset(i)).font_size;
@@ -2843,84 +4238,94 @@ pub unsafe fn pack_font_ranges_render_into_rects(
       let recip_v: f32;
       let sub_x: f32;
       let sub_y: f32;
-      (*spc).h_oversample = (*ranges.offset(i)).h_oversample as usize;
-      (*spc).v_oversample = (*ranges.offset(i)).v_oversample as usize;
-      recip_h = 1.0 / (*spc).h_oversample as f32;
-      recip_v = 1.0 / (*spc).v_oversample as f32;
-      sub_x = oversample_shift((*spc).h_oversample as isize);
-      sub_y = oversample_shift((*spc).v_oversample as isize);
+      let (h_oversample, v_oversample) = (*ranges.offset(i)).settings.oversample();
+      recip_h = 1.0 / h_oversample as f32;
+      recip_v = 1.0 / v_oversample as f32;
+      sub_x = oversample_shift(h_oversample as isize);
+      sub_y = oversample_shift(v_oversample as isize);
+      let phases = (*ranges.offset(i)).settings.phases();
       for j in 0..(*ranges.offset(i)).num_chars {
-         let r: *mut Rect = rects.offset(k);
-         if (*r).was_packed != 0 {
-            let bc: *mut PackedChar = (*ranges.offset(i)).chardata_for_range.offset(j);
-
-            let codepoint: isize =
-                if (*ranges.offset(i)).array_of_unicode_codepoints == null() {
-                    (*ranges.offset(i)).first_unicode_codepoint_in_range + j
-                } else {
-                    (*(*ranges.offset(i)).array_of_unicode_codepoints.offset(j))
-                };
-            assert!(codepoint >= 0);
-            let glyph = (*info).glyph_index_for_code(codepoint as usize) as isize;
-            let pad: Coord = (*spc).padding as Coord;
-
-            // pad on left and top
-            (*r).x += pad;
-            (*r).y += pad;
-            (*r).w -= pad;
-            (*r).h -= pad;
-
-            let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph as usize);
-            let bbox = glyph_data.bitmap_box(
-                scale * (*spc).h_oversample as f32,
-                scale * (*spc).v_oversample as f32).unwrap_or_default();
-
-            make_glyph_bitmap_subpixel(info,
-                                          (*spc).pixels.offset((*r).x + (*r).y*(*spc).stride_in_bytes),
-                                          (*r).w - (*spc).h_oversample as isize +1,
-                                          (*r).h - (*spc).v_oversample as isize +1,
-                                          (*spc).stride_in_bytes,
-                                          scale * (*spc).h_oversample as f32,
-                                          scale * (*spc).v_oversample as f32,
-                                          0.0,0.0,
-                                          glyph);
-
-            if (*spc).h_oversample > 1 {
-               h_prefilter((*spc).pixels.offset((*r).x + (*r).y*(*spc).stride_in_bytes),
-                                  (*r).w, (*r).h, (*spc).stride_in_bytes,
-                                  (*spc).h_oversample);
-            }
+         let codepoint = (*ranges.offset(i)).codepoint(j);
+         let glyph = (*info).glyph_for_code(codepoint);
+         for p in 0..phases {
+            let r: *mut Rect = rects.offset(k);
+            if (*r).was_packed != 0 {
+               let bc: *mut PackedChar = (*ranges.offset(i)).chardata_for_range
+                   .offset(j * phases as isize + p as isize);
+               let pad: Coord = (*ranges.offset(i)).settings.padding(&*spc) as Coord;
+               let bleed = (*ranges.offset(i)).settings.bleed;
+
+               // pad on left and top, and right and bottom if the edges bleed
+               (*r).x += pad;
+               (*r).y += pad;
+               (*r).w -= if bleed { 2 * pad } else { pad };
+               (*r).h -= if bleed { 2 * pad } else { pad };
+
+               // the phase is rendered p/phases pixels to the right
+               let phase = p as f32 / phases as f32;
+               let shift = phase * h_oversample as f32;
+               let glyph_data = (*info).glyph_data_for_glyph_at_index(glyph);
+               let bbox = glyph_data.bitmap_box_subpixel(
+                   scale * h_oversample as f32,
+                   scale * v_oversample as f32, shift, 0.0).unwrap_or_default();
+
+               make_glyph_bitmap_subpixel(info,
+                                             (*spc).pixels.offset((*r).x + (*r).y*(*spc).stride_in_bytes),
+                                             (*r).w - h_oversample as isize +1,
+                                             (*r).h - v_oversample as isize +1,
+                                             (*spc).stride_in_bytes,
+                                             scale * h_oversample as f32,
+                                             scale * v_oversample as f32,
+                                             shift,0.0,
+                                             glyph);
+
+               let glyph_pixels = (*spc).pixels.offset((*r).x + (*r).y*(*spc).stride_in_bytes);
+               let filter = (*ranges.offset(i)).settings.filter;
+               if h_oversample > 1 {
+                  match filter {
+                     Prefilter::Box => h_prefilter(glyph_pixels, (*r).w, (*r).h,
+                        (*spc).stride_in_bytes, h_oversample),
+                     Prefilter::Tent => h_prefilter_tent(glyph_pixels, (*r).w, (*r).h,
+                        (*spc).stride_in_bytes, h_oversample),
+                  }
+               }
+
+               if v_oversample > 1 {
+                  match filter {
+                     Prefilter::Box => v_prefilter(glyph_pixels, (*r).w, (*r).h,
+                        (*spc).stride_in_bytes, v_oversample),
+                     Prefilter::Tent => v_prefilter_tent(glyph_pixels, (*r).w, (*r).h,
+                        (*spc).stride_in_bytes, v_oversample),
+                  }
+               }
+
+               if bleed {
+                  bleed_edges((*spc).pixels, (*spc).stride_in_bytes, (*spc).width, (*spc).height,
+                     (*r).x, (*r).y, (*r).w, (*r).h, pad);
+               }
 
-            if (*spc).v_oversample > 1 {
-               v_prefilter((*spc).pixels.offset((*r).x + (*r).y*(*spc).stride_in_bytes),
-                                  (*r).w, (*r).h, (*spc).stride_in_bytes,
-                                  (*spc).v_oversample);
+               let metric = (*info).hmetric_for_glyph_at_index(glyph);
+
+               // the offsets are from the origin, so that the shift moves
+               // the outline within the quad
+               (*bc).x0 = (*r).x as u16;
+               (*bc).y0 = (*r).y as u16;
+               (*bc).x1 = ((*r).x + (*r).w) as u16;
+               (*bc).y1 = ((*r).y + (*r).h) as u16;
+               (*bc).xadvance = scale * metric.advance_width as f32;
+               (*bc).xoff = bbox.x0 as f32 * recip_h + sub_x - phase;
+               (*bc).yoff = bbox.y0 as f32 * recip_v + sub_y;
+               (*bc).xoff2 = (bbox.x0 as isize + (*r).w) as f32 * recip_h + sub_x - phase;
+               (*bc).yoff2 = (bbox.y0 as isize + (*r).h) as f32 * recip_v + sub_y;
+            } else {
+               return_value = 0; // if any fail, report failure
             }
 
-            assert!(glyph >= 0);
-            let metric = (*info).hmtx.hmetric_for_glyph_at_index(glyph as usize);
-
-            (*bc).x0 = (*r).x as u16;
-            (*bc).y0 = (*r).y as u16;
-            (*bc).x1 = ((*r).x + (*r).w) as u16;
-            (*bc).y1 = ((*r).y + (*r).h) as u16;
-            (*bc).xadvance = scale * metric.advance_width as f32;
-            (*bc).xoff = bbox.x0 as f32 * recip_h + sub_x;
-            (*bc).yoff = bbox.y0 as f32 * recip_v + sub_y;
-            (*bc).xoff2 = (bbox.x0 as isize + (*r).w) as f32 * recip_h + sub_x;
-            (*bc).yoff2 = (bbox.y0 as isize + (*r).h) as f32 * recip_v + sub_y;
-         } else {
-            return_value = 0; // if any fail, report failure
+            k += 1;
          }
-
-         k += 1;
       }
    }
 
-   // restore original values
-   (*spc).h_oversample = old_h_over as usize;
-   (*spc).v_oversample = old_v_over as usize;
-
    return return_value;
 }
 
@@ -2935,7 +4340,9 @@ pub unsafe fn pack_font_ranges_pack_rects(
 // Creates character bitmaps from multiple ranges of characters stored in
 // ranges. This will usually create a better-packed bitmap than multiple
 // calls to stbtt_PackFontRange. Note that you can call this multiple
-// times within a single PackBegin/PackEnd.
+// times within a single PackBegin/PackEnd. Returns Error::Malformed if
+// font_index is negative or out of bounds, or if a table of the font is
+// truncated; the checksums of the font aren't checked.
 pub unsafe fn pack_font_ranges(
     spc: *mut PackContext,
     fontdata: &[u8],
@@ -2948,9 +4355,17 @@ pub unsafe fn pack_font_ranges(
    //stbrp_context *context = (stbrp_context *) spc->pack_info;
    let rects: *mut Rect;
 
+   // The checksum isn't checked, only that the tables are within bounds.
+   let collection = try!(FontCollection::from_data(fontdata));
+   let offset = if font_index < 0 { None } else { collection.offset_for_font_at_index(font_index as usize) };
+   let offset = try!(offset.ok_or(Error::Malformed));
+   try!(utils::read_table_records(fontdata, offset));
+   let info = try!(FontInfo::new_with_offset(fontdata, offset));
+
    // flag all characters as NOT packed
    for i in 0..num_ranges {
-      for j in 0..(*ranges.offset(i)).num_chars {
+      let phases = (*ranges.offset(i)).settings.phases() as isize;
+      for j in 0..(*ranges.offset(i)).num_chars * phases {
          (*(*ranges.offset(i)).chardata_for_range.offset(j)).x0 = 0;
          (*(*ranges.offset(i)).chardata_for_range.offset(j)).y0 = 0;
          (*(*ranges.offset(i)).chardata_for_range.offset(j)).x1 = 0;
@@ -2960,7 +4375,7 @@ pub unsafe fn pack_font_ranges(
 
    n = 0;
    for i in 0..num_ranges {
-      n += (*ranges.offset(i)).num_chars;
+      n += (*ranges.offset(i)).num_chars * (*ranges.offset(i)).settings.phases() as isize;
    }
 
    rects = STBTT_malloc!(size_of::<Rect>() * n as usize)
@@ -2969,13 +4384,11 @@ pub unsafe fn pack_font_ranges(
       return Ok(0);
    }
 
-   let mut info = try!(FontInfo::new_with_offset(fontdata, get_font_offset_for_index(fontdata.as_ptr(),font_index) as usize));
-
-   n = pack_font_ranges_gather_rects(spc, &mut info, ranges, num_ranges, rects);
+   n = pack_font_ranges_gather_rects(spc, &info, ranges, num_ranges, rects);
 
    pack_font_ranges_pack_rects(spc, rects, n);
 
-   let return_value = pack_font_ranges_render_into_rects(spc, &mut info, ranges, num_ranges, rects);
+   let return_value = pack_font_ranges_render_into_rects(spc, &info, ranges, num_ranges, rects);
 
    STBTT_free!(rects as *mut c_void);
    return Ok(return_value);
@@ -2993,27 +4406,26 @@ pub unsafe fn pack_font_ranges(
 // and pass that result as 'font_size':
 //       ...,                  20 , ... // font max minus min y is 20 pixels tall
 //       ..., STBTT_POINT_SIZE(20), ... // 'M' is 20 pixels tall
+//
+// 'settings' sets the oversampling and padding of the characters, see
+// PackSettings.
 pub unsafe fn pack_font_range(
     spc: *mut PackContext,
     fontdata: &[u8],
     font_index: isize,
     font_size: f32,
-    first_unicode_codepoint_in_range: isize,
+    first_unicode_codepoint_in_range: char,
     num_chars_in_range: isize,
-    chardata_for_range: *mut PackedChar
+    chardata_for_range: *mut PackedChar,
+    settings: PackSettings
 ) -> Result<isize> {
-   let mut range: PackRange = PackRange {
-       first_unicode_codepoint_in_range: first_unicode_codepoint_in_range,
-       array_of_unicode_codepoints: null(),
-       num_chars: num_chars_in_range,
-       chardata_for_range: chardata_for_range,
-       font_size: font_size,
-       v_oversample: 0,
-       h_oversample: 0,
-   };
+   let mut range = PackRange::new(font_size, first_unicode_codepoint_in_range,
+       num_chars_in_range, chardata_for_range).with_settings(settings);
    pack_font_ranges(spc, fontdata, font_index, &mut range, 1)
 }
 
+// Returns the quad of a character packed by pack_font_ranges and advances
+// xpos.
 pub unsafe fn get_packed_quad(
     chardata: *mut PackedChar,
     pw: isize,
@@ -3027,30 +4439,32 @@ pub unsafe fn get_packed_quad(
     q: *mut AlignedQuad,
     align_to_integer: isize
 ) {
-   let ipw: f32 = 1.0 / pw as f32;
-   let iph: f32 = 1.0 / ph as f32;
-   let b: *const PackedChar = chardata.offset(char_index);
-
-   if align_to_integer != 0 {
-      let x = ((*xpos + (*b).xoff) + 0.5).floor();
-      let y = ((*ypos + (*b).yoff) + 0.5).floor();
-      (*q).x0 = x;
-      (*q).y0 = y;
-      (*q).x1 = x + (*b).xoff2 - (*b).xoff;
-      (*q).y1 = y + (*b).yoff2 - (*b).yoff;
-   } else {
-      (*q).x0 = *xpos + (*b).xoff;
-      (*q).y0 = *ypos + (*b).yoff;
-      (*q).x1 = *xpos + (*b).xoff2;
-      (*q).y1 = *ypos + (*b).yoff2;
-   }
-
-   (*q).s0 = (*b).x0 as f32 * ipw;
-   (*q).t0 = (*b).y0 as f32 * iph;
-   (*q).s1 = (*b).x1 as f32 * ipw;
-   (*q).t1 = (*b).y1 as f32 * iph;
+   get_packed_quad_phased(chardata, pw, ph, char_index, 1, xpos, ypos, q, align_to_integer);
+}
 
-   *xpos += (*b).xadvance;
+// Same as get_packed_quad for a range packed with 'subpixel_phases' phases
+// (see PackSettings), picks the phase nearest to the fraction of xpos.
+pub unsafe fn get_packed_quad_phased(
+    chardata: *mut PackedChar,
+    pw: isize,
+    ph: isize,
+    // character to display
+    char_index: isize,
+    subpixel_phases: isize,
+    // pointers to current position in screen pixel space
+    xpos: *mut f32,
+    ypos: *mut f32,
+    // output: quad to draw
+    q: *mut AlignedQuad,
+    align_to_integer: isize
+) {
+   let phases = subpixel_phases.max(1);
+   let fraction = *xpos - libm::floorf(*xpos);
+   let phase = (libm::floorf(fraction * phases as f32 + 0.5) as isize) % phases;
+   let (quad, x) = (*chardata.offset(char_index * phases + phase)).quad(pw as usize,
+       ph as usize, *xpos, *ypos, align_to_integer != 0);
+   *q = quad;
+   *xpos = x;
 }
 
 
@@ -3231,15 +4645,22 @@ pub unsafe fn matches(
     name: *mut u8,
     flags: i32
 ) -> isize {
-    let nlen: i32 = STBTT_strlen(name as *mut c_char) as i32;
+    let nlen: i32 = STBTT_strlen(name) as i32;
     let nm: u32;
     let hd: u32;
-   if isfont(fc.offset(offset as isize)) == 0 { return 0; }
+   if !utils::is_font(slice::from_raw_parts(fc.offset(offset as isize), 4)) { return 0; }
 
    // check italics/bold/underline flags in macStyle...
    if flags != 0 {
       hd = utils::find_table(fc, offset, b"head");
-      if (ttUSHORT!(fc.offset(hd as isize + 44)) & 7) != (flags as u16 & 7) { return 0; }
+      if hd == 0 { return 0; }
+      // the fields up to glyphDataFormat
+      let head = match HEAD::from_data(slice::from_raw_parts(fc.offset(hd as isize), 54), 0) {
+         Ok(head) => head,
+         Err(_) => return 0,
+      };
+      let checked = MacStyle::BOLD | MacStyle::ITALIC | MacStyle::UNDERLINE;
+      if head.mac_style() & checked != MacStyle::from_bits(flags as u16) & checked { return 0; }
    }
 
    nm = utils::find_table(fc, offset, b"name");
@@ -3263,6 +4684,7 @@ pub unsafe fn matches(
 //   if you use STBTT_MACSTYLE_DONTCARE, use a font name like "Arial Bold".
 //   if you use any other flag, use a font name like "Arial"; this checks
 //     the 'macStyle' header field; i don't know if fonts set this consistently
+// see FontCollection::find for a safe version
 pub unsafe fn find_matching_font(
     font_collection: *const u8,
     name_utf8: *const u8,
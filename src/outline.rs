@@ -0,0 +1,150 @@
+//! Transforms of paths, such as glyph outlines from `FontInfo::glyph_path`,
+//! to be applied before rasterization.
+
+use prelude::*;
+use raster::signed_area;
+use PathSegment;
+
+/// Makes the strokes of `path` `strength` units thicker by moving every
+/// contour outwards by half of it, to fake a missing bold face.
+///
+/// The advance width of an emboldened glyph usually grows by `strength` too.
+pub fn embolden(path: &[PathSegment], strength: f32) -> Vec<PathSegment> {
+    let contours = contour_points(path);
+    // The direction of the outer contours decides which side is outside.
+    let area: f32 = contours.iter().map(|c| signed_area(c)).sum();
+    let outside = if area < 0.0 { -strength / 2.0 } else { strength / 2.0 };
+
+    let mut points = vec![];
+    for contour in &contours {
+        let n = contour.len();
+        for (i, &p) in contour.iter().enumerate() {
+            // Repeated points would have no direction.
+            let prev = (1..n).map(|j| contour[(i + n - j) % n]).find(|&q| q != p);
+            let next = (1..n).map(|j| contour[(i + j) % n]).find(|&q| q != p);
+            let (prev, next) = match (prev, next) {
+                (Some(prev), Some(next)) => (prev, next),
+                _ => {
+                    points.push(p);
+                    continue;
+                }
+            };
+            let normal = |a: (f32, f32), b: (f32, f32)| {
+                let length = libm::sqrtf((b.0 - a.0) * (b.0 - a.0) + (b.1 - a.1) * (b.1 - a.1));
+                ((b.1 - a.1) / length, (a.0 - b.0) / length)
+            };
+            let (n0, n1) = (normal(prev, p), normal(p, next));
+            // Moves both lines by the same distance, limiting the miter of
+            // sharp corners.
+            let d = (1.0 + n0.0 * n1.0 + n0.1 * n1.1).max(1.0 / 16.0);
+            points.push((p.0 + (n0.0 + n1.0) * outside / d, p.1 + (n0.1 + n1.1) * outside / d));
+        }
+    }
+
+    let mut points = points.into_iter();
+    let mut result = Vec::with_capacity(path.len() + 1);
+    for (i, segment) in path.iter().enumerate() {
+        if i == 0 {
+            if let PathSegment::MoveTo(..) = *segment {
+            } else {
+                let p = points.next().unwrap();
+                result.push(PathSegment::MoveTo(p.0, p.1));
+            }
+        }
+        result.push(match *segment {
+            PathSegment::MoveTo(..) => {
+                let p = points.next().unwrap();
+                PathSegment::MoveTo(p.0, p.1)
+            }
+            PathSegment::LineTo(..) => {
+                let p = points.next().unwrap();
+                PathSegment::LineTo(p.0, p.1)
+            }
+            PathSegment::QuadTo(..) => {
+                let (c, p) = (points.next().unwrap(), points.next().unwrap());
+                PathSegment::QuadTo(c.0, c.1, p.0, p.1)
+            }
+        });
+    }
+    result
+}
+
+/// Slants `path` to the right by `shear` units per unit of height, to fake
+/// a missing italic face. Common values are around 0.2.
+pub fn oblique(path: &[PathSegment], shear: f32) -> Vec<PathSegment> {
+    // The y axis points down.
+    let f = |x: f32, y: f32| (x - shear * y, y);
+    path.iter().map(|segment| match *segment {
+        PathSegment::MoveTo(x, y) => {
+            let p = f(x, y);
+            PathSegment::MoveTo(p.0, p.1)
+        }
+        PathSegment::LineTo(x, y) => {
+            let p = f(x, y);
+            PathSegment::LineTo(p.0, p.1)
+        }
+        PathSegment::QuadTo(cx, cy, x, y) => {
+            let (c, p) = (f(cx, cy), f(x, y));
+            PathSegment::QuadTo(c.0, c.1, p.0, p.1)
+        }
+    }).collect()
+}
+
+/// Returns the points of every contour of `path` including the control
+/// points, in order. A contour that doesn't start with a move starts at the
+/// origin.
+fn contour_points(path: &[PathSegment]) -> Vec<Vec<(f32, f32)>> {
+    let mut contours: Vec<Vec<(f32, f32)>> = vec![];
+    for segment in path {
+        if let PathSegment::MoveTo(x, y) = *segment {
+            contours.push(vec![(x, y)]);
+            continue;
+        }
+        if contours.is_empty() {
+            contours.push(vec![(0.0, 0.0)]);
+        }
+        let contour = contours.last_mut().unwrap();
+        match *segment {
+            PathSegment::LineTo(x, y) => contour.push((x, y)),
+            PathSegment::QuadTo(cx, cy, x, y) => {
+                contour.push((cx, cy));
+                contour.push((x, y));
+            }
+            PathSegment::MoveTo(..) => unreachable!(),
+        }
+    }
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+
+    fn square(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<PathSegment> {
+        vec![PathSegment::MoveTo(x0, y0), PathSegment::LineTo(x1, y0),
+            PathSegment::LineTo(x1, y1), PathSegment::LineTo(x0, y1)]
+    }
+
+    #[test]
+    fn embolden_contours() {
+        expect!(embolden(&square(0.0, 0.0, 4.0, 4.0), 2.0))
+            .to(be_equal_to(square(-1.0, -1.0, 5.0, 5.0)));
+
+        // The hole gets smaller.
+        let mut path = square(0.0, 0.0, 10.0, 10.0);
+        let mut hole = square(2.0, 2.0, 8.0, 8.0);
+        hole[1..].reverse();
+        path.extend(hole);
+        let mut hole = square(3.0, 3.0, 7.0, 7.0);
+        hole[1..].reverse();
+        expect!(embolden(&path, 2.0)[4..].to_vec()).to(be_equal_to(hole));
+    }
+
+    #[test]
+    fn oblique_points() {
+        let path = [PathSegment::QuadTo(0.0, -10.0, 2.0, 0.0)];
+        expect!(oblique(&path, 0.5).to_vec())
+            .to(be_equal_to(vec![PathSegment::QuadTo(5.0, -10.0, 2.0, 0.0)]));
+    }
+}
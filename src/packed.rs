@@ -0,0 +1,179 @@
+//! Bitmaps with less than a byte per pixel, for displays with few gray
+//! levels and little memory.
+
+use prelude::*;
+use GrayBitmap;
+
+/// A bitmap with `bits` of coverage per pixel, packed into bytes with the
+/// leftmost pixel in the most significant bits.
+///
+/// Every row starts at a byte, the bits after the last pixel of a row are
+/// zero.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackedBitmap {
+    /// The position of the left column in the coordinates of the path.
+    pub x: i32,
+    /// The position of the top row in the coordinates of the path.
+    pub y: i32,
+    pub width: usize,
+    pub height: usize,
+    /// The bits per pixel, 1, 2, 4 or 8.
+    pub bits: u8,
+    /// The bytes per row.
+    pub stride: usize,
+    /// Rows of `stride` bytes from the top.
+    pub data: Vec<u8>,
+}
+
+impl PackedBitmap {
+    /// Returns a bitmap of `width` by `height` empty pixels of `bits` each
+    /// with the top left pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `bits` isn't 1, 2, 4 or 8.
+    pub fn new(x: i32, y: i32, width: usize, height: usize, bits: u8) -> PackedBitmap {
+        assert!(bits == 1 || bits == 2 || bits == 4 || bits == 8,
+            "pixels can only have 1, 2, 4 or 8 bits");
+        let stride = (width * bits as usize + 7) / 8;
+        PackedBitmap {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+            bits: bits,
+            stride: stride,
+            data: vec![0; stride * height],
+        }
+    }
+
+    /// Returns the largest value of a pixel, full coverage.
+    pub fn max_value(&self) -> u8 {
+        (0xffu32 >> (8 - self.bits)) as u8
+    }
+
+    /// Returns the value of the pixel at column `x` and row `y` from the top
+    /// left, from 0 to `max_value`.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel is outside of the bitmap.
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        assert!(x < self.width && y < self.height);
+        let (byte, shift) = self.position(x, y);
+        (self.data[byte] >> shift) & self.max_value()
+    }
+
+    /// Sets the pixel at column `x` and row `y` from the top left to
+    /// `value`, whose bits above `bits` are ignored.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel is outside of the bitmap.
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        assert!(x < self.width && y < self.height);
+        let (byte, shift) = self.position(x, y);
+        let mask = self.max_value() << shift;
+        self.data[byte] = self.data[byte] & !mask | (value << shift) & mask;
+    }
+
+    /// Returns the bytes of row `y` from the top.
+    ///
+    /// # Panics
+    ///
+    /// If `y` is not less than the height.
+    pub fn row(&self, y: usize) -> &[u8] {
+        assert!(y < self.height);
+        &self.data[y * self.stride..(y + 1) * self.stride]
+    }
+
+    /// Returns the bitmap with the values scaled back to bytes, e.g. to
+    /// preview it.
+    pub fn to_gray(&self) -> GrayBitmap {
+        let mut gray = GrayBitmap::new(self.x, self.y, self.width, self.height);
+        let max = self.max_value() as u32;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                gray.as_mut_slice()[y * self.width + x] = (self.get(x, y) as u32 * 255 / max) as u8;
+            }
+        }
+        gray
+    }
+
+    /// Returns the byte and the shift of the bits of a pixel.
+    fn position(&self, x: usize, y: usize) -> (usize, u8) {
+        let bit = x * self.bits as usize;
+        (y * self.stride + bit / 8, (8 - self.bits as usize - bit % 8) as u8)
+    }
+}
+
+impl GrayBitmap {
+    /// Returns the bitmap with the coverage rounded to the nearest of the
+    /// levels `bits` bits per pixel have, packed into bytes.
+    ///
+    /// Four levels take a quarter of the memory, sixteen levels a half,
+    /// and keep most of the anti-aliasing at small sizes.
+    ///
+    /// # Panics
+    ///
+    /// If `bits` isn't 1, 2, 4 or 8.
+    pub fn to_packed(&self, bits: u8) -> PackedBitmap {
+        let mut packed = PackedBitmap::new(self.x, self.y, self.width(), self.height(), bits);
+        let max = packed.max_value() as u32;
+        for y in 0..self.height() {
+            for (x, &coverage) in self.row(y).iter().enumerate() {
+                let coverage = coverage as u32;
+                packed.set(x, y, ((coverage * max + 127) / 255) as u8);
+            }
+        }
+        packed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn pack() {
+        let gray = GrayBitmap::from_vec(-1, 2, 5, 2, vec![0, 42, 43, 128, 255, 255, 0, 212, 213, 84])
+            .unwrap();
+        let packed = gray.to_packed(2);
+        expect!((packed.x, packed.y, packed.stride, packed.max_value()))
+            .to(be_equal_to((-1, 2, 2, 3)));
+        expect!(packed.data.clone()).to(be_equal_to(vec![0b00_00_01_10, 0b11_000000,
+            0b11_00_10_11, 0b01_000000]));
+        expect!(packed.get(3, 0)).to(be_equal_to(2));
+        expect!(packed.row(1)).to(be_equal_to(&[0b11_00_10_11u8, 0b01_000000][..]));
+        expect!(packed.to_gray().into_vec()).to(be_equal_to(vec![0u8, 0, 85, 170, 255,
+            255, 0, 170, 255, 85]));
+
+        let packed = gray.to_packed(4);
+        expect!(packed.stride).to(be_equal_to(3));
+        expect!(packed.row(0)).to(be_equal_to(&[0x02u8, 0x38, 0xf0][..]));
+        // Quantizing twice changes nothing.
+        expect!(packed.to_gray().to_packed(4)).to(be_equal_to(packed.clone()));
+
+        expect!(gray.to_packed(1).data).to(be_equal_to(vec![0b00011000, 0b10110000]));
+        expect!(gray.to_packed(8).to_gray()).to(be_equal_to(gray));
+    }
+
+    #[test]
+    fn set() {
+        let mut packed = PackedBitmap::new(0, 0, 3, 1, 4);
+        packed.set(1, 0, 0x1f);
+        packed.set(2, 0, 7);
+        expect!(packed.data.clone()).to(be_equal_to(vec![0x0f, 0x70]));
+        packed.set(1, 0, 2);
+        expect!(packed.get(1, 0)).to(be_equal_to(2));
+        expect!(packed.get(2, 0)).to(be_equal_to(7));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unsupported_bits() {
+        PackedBitmap::new(0, 0, 1, 1, 3);
+    }
+}
@@ -0,0 +1,53 @@
+//! Integration with Piston textures and `graphics::Image`, enabled by the
+//! `piston` feature.
+
+use texture::{CreateTexture, Format, TextureSettings};
+use {Atlas, GrayBitmap};
+
+/// Where to draw a glyph of an atlas texture, as `graphics::Image` takes it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GlyphImage {
+    /// The glyph in the texture, `[x, y, width, height]` in pixels, for
+    /// `Image::src_rect`.
+    pub src_rect: [f64; 4],
+    /// Where to draw the glyph, `[x, y, width, height]`, for `Image::rect`.
+    pub rect: [f64; 4],
+    /// The distance to the origin of the next glyph.
+    pub advance: f64,
+}
+
+impl GrayBitmap {
+    /// Creates an RGBA texture of the bitmap, white with the coverage as
+    /// alpha, so that `graphics::Image::color` colors it.
+    pub fn create_texture<F, T>(&self, factory: &mut F, settings: &TextureSettings)
+        -> Result<T, T::Error>
+        where T: CreateTexture<F>
+    {
+        T::create(factory, Format::Rgba8, &self.to_rgba([255; 4]),
+            [self.width() as u32, self.height() as u32], settings)
+    }
+}
+
+impl Atlas {
+    /// Creates an RGBA texture of the atlas, see `GrayBitmap::create_texture`.
+    pub fn create_texture<F, T>(&self, factory: &mut F, settings: &TextureSettings)
+        -> Result<T, T::Error>
+        where T: CreateTexture<F>
+    {
+        self.bitmap.create_texture(factory, settings)
+    }
+
+    /// Returns where to draw character `c` from the texture of the atlas with
+    /// its origin at `(x, y)` on the baseline, aligned to whole pixels.
+    pub fn glyph_image(&self, c: char, x: f64, y: f64) -> Option<GlyphImage> {
+        self.baked_char(c).map(|b| {
+            let (width, height) = ((b.x1 - b.x0) as f64, (b.y1 - b.y0) as f64);
+            GlyphImage {
+                src_rect: [b.x0 as f64, b.y0 as f64, width, height],
+                rect: [(x + b.xoff as f64 + 0.5).floor(), (y + b.yoff as f64 + 0.5).floor(),
+                    width, height],
+                advance: b.xadvance as f64,
+            }
+        })
+    }
+}
@@ -0,0 +1,744 @@
+//! Rasterizer options and the sampling rasterizer.
+//!
+//! The default rasterizer adds up the signed area every edge covers in
+//! a pixel, which is fast, but overestimates the coverage where contours
+//! overlap. The sampling rasterizer computes the winding number instead,
+//! which also allows choosing the fill rule, and renders without
+//! anti-aliasing with `RasterMode::Mono`.
+//!
+//! Besides glyphs, both can fill arbitrary paths, see `rasterize_path`, or
+//! pass the coverage on without a bitmap, see `rasterize_spans` and
+//! `rasterize_bands`.
+
+use prelude::*;
+use Bitmap;
+use Edge;
+use rasterize_sorted_edges_rows;
+
+/// Number of lines per pixel row sampled by the sampling rasterizer.
+const SAMPLES: usize = 16;
+
+/// Decides which parts of a shape are inside from the winding number,
+/// the number of times its contours go around a point.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FillRule {
+    /// Fills points with a non-zero winding number, as TrueType requires.
+    NonZero,
+    /// Fills points with an odd winding number, so overlapping contours
+    /// leave holes.
+    EvenOdd,
+}
+
+impl Default for FillRule {
+    fn default() -> FillRule {
+        FillRule::NonZero
+    }
+}
+
+impl FillRule {
+    /// Returns `true` if points with the `winding` number are filled.
+    pub fn is_inside(&self, winding: i32) -> bool {
+        match *self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// How the rasterizer computes the value of a pixel.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RasterMode {
+    /// The part of the pixel the shape covers, from 0 to 255.
+    AntiAliased,
+    /// 255 if the center of the pixel is inside the shape, otherwise 0, for
+    /// crisp text on displays without gray levels, e.g. packed with
+    /// `GrayBitmap::to_packed(1)`.
+    ///
+    /// Parts of the shape thinner than a pixel that miss the centers of
+    /// pixels disappear.
+    Mono,
+}
+
+impl Default for RasterMode {
+    fn default() -> RasterMode {
+        RasterMode::AntiAliased
+    }
+}
+
+/// Options of the rasterizer.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct RasterOptions {
+    /// Computes the coverage of overlapping contours, e.g. of compound
+    /// glyphs, exactly instead of adding up the coverage of each contour.
+    ///
+    /// Samples 16 lines per row of pixels, so it's slower.
+    pub overlap_correct: bool,
+    /// The fill rule, `FillRule::EvenOdd` always uses the same rasterizer
+    /// as `overlap_correct`.
+    pub fill_rule: FillRule,
+    /// Adds up the coverage with the scalar loops even with the `simd`
+    /// feature, so bitmaps are the same whatever vectors the platform has,
+    /// e.g. to compare them with reference images.
+    pub deterministic: bool,
+    /// Whether to anti-alias, `RasterMode::Mono` always uses the same
+    /// rasterizer as `overlap_correct`.
+    pub mode: RasterMode,
+}
+
+impl RasterOptions {
+    /// Returns `true` if the options need the sampling rasterizer.
+    pub fn is_sampled(&self) -> bool {
+        self.overlap_correct || self.fill_rule != FillRule::NonZero
+            || self.mode == RasterMode::Mono
+    }
+}
+
+/// A segment of a path, in pixels with the y axis pointing down.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PathSegment {
+    /// Starts a new contour at the point.
+    MoveTo(f32, f32),
+    /// A line to the point.
+    LineTo(f32, f32),
+    /// A quadratic Bézier curve through the control point given first
+    /// to the point given second.
+    QuadTo(f32, f32, f32, f32),
+}
+
+/// A bitmap with one byte of coverage per pixel.
+///
+/// Always has `width * height` pixels, so its size can't be changed, but
+/// the pixels can.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "GrayBitmapFields"))]
+pub struct GrayBitmap {
+    /// The position of the left column in the coordinates of the path.
+    pub x: i32,
+    /// The position of the top row in the coordinates of the path.
+    pub y: i32,
+    width: usize,
+    height: usize,
+    // Rows of `width` pixels from the top.
+    pixels: Vec<u8>,
+}
+
+// The fields of a deserialized `GrayBitmap`, before checking its size.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct GrayBitmapFields {
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl ::core::convert::TryFrom<GrayBitmapFields> for GrayBitmap {
+    type Error = &'static str;
+
+    fn try_from(f: GrayBitmapFields) -> ::core::result::Result<GrayBitmap, &'static str> {
+        GrayBitmap::from_vec(f.x, f.y, f.width, f.height, f.pixels)
+            .ok_or("the number of pixels isn't width * height")
+    }
+}
+
+impl GrayBitmap {
+    /// Returns a bitmap of `width` by `height` empty pixels with the top
+    /// left pixel at `(x, y)`.
+    pub fn new(x: i32, y: i32, width: usize, height: usize) -> GrayBitmap {
+        GrayBitmap { x: x, y: y, width: width, height: height, pixels: vec![0; width * height] }
+    }
+
+    /// Same as `new`, but with the given pixels, row by row from the top.
+    ///
+    /// Returns `None` if there aren't `width * height` pixels.
+    pub fn from_vec(x: i32, y: i32, width: usize, height: usize, pixels: Vec<u8>) -> Option<GrayBitmap> {
+        if width.checked_mul(height) != Some(pixels.len()) {
+            return None;
+        }
+        Some(GrayBitmap { x: x, y: y, width: width, height: height, pixels: pixels })
+    }
+
+    /// Returns the number of pixels in a row.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns all pixels, row by row from the top.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Same as `as_slice`, but the pixels can be changed.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Returns the pixels of row `y` from the top.
+    ///
+    /// # Panics
+    ///
+    /// If `y` is not less than the height.
+    pub fn row(&self, y: usize) -> &[u8] {
+        assert!(y < self.height);
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+
+    /// Returns the pixels, row by row from the top.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Returns the pixels as RGBA with straight alpha, filled with `color`
+    /// whose alpha is scaled by the coverage.
+    pub fn to_rgba(&self, color: [u8; 4]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for &coverage in &self.pixels {
+            rgba.extend_from_slice(&[color[0], color[1], color[2], mul(color[3], coverage)]);
+        }
+        rgba
+    }
+
+    /// Returns the pixels as RGBA with premultiplied alpha, filled with
+    /// `color` given with straight alpha.
+    pub fn to_rgba_premultiplied(&self, color: [u8; 4]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for &coverage in &self.pixels {
+            let alpha = mul(color[3], coverage);
+            rgba.extend_from_slice(&[mul(color[0], alpha), mul(color[1], alpha),
+                mul(color[2], alpha), alpha]);
+        }
+        rgba
+    }
+}
+
+/// Multiplies two values in 0..255 as if they were in 0..1, rounding.
+fn mul(a: u8, b: u8) -> u8 {
+    let x = a as u32 * b as u32 + 128;
+    ((x + (x >> 8)) >> 8) as u8
+}
+
+/// Rasterizes `path` with the same anti-aliasing as glyphs into a bitmap
+/// just large enough for it.
+///
+/// All contours are closed, a contour that doesn't start with
+/// `PathSegment::MoveTo` starts at the origin.
+pub fn rasterize_path(path: &[PathSegment], options: &RasterOptions) -> GrayBitmap {
+    rasterize_contours(&flatten_path(path, 0.35), options)
+}
+
+/// Rasterizes `path` like `rasterize_path`, but instead of a bitmap calls
+/// `span(y, x0, x1, coverage)` for every run of pixels of the row `y` from
+/// `x0` to `x1` (exclusive) with the same coverage, from the top row and the
+/// left.
+///
+/// Only one row of coverage is kept at a time, so large shapes can be
+/// blended right into a framebuffer of any format. Empty pixels are skipped.
+pub fn rasterize_spans<F>(path: &[PathSegment], options: &RasterOptions, mut span: F)
+    where F: FnMut(i32, i32, i32, u8)
+{
+    let contours = flatten_path(path, 0.35);
+    if let Some((x0, y0, width, height)) = contour_bounds(&contours) {
+        rasterize_rows(&contours, x0, y0, width, height, options, |y, row| {
+            let mut start = 0;
+            for x in 1..row.len() + 1 {
+                if x == row.len() || row[x] != row[start] {
+                    if row[start] != 0 {
+                        span(y, x0 + start as i32, x0 + x as i32, row[start]);
+                    }
+                    start = x;
+                }
+            }
+        });
+    }
+}
+
+/// Rasterizes `path` like `rasterize_path`, but in bands of `band_height`
+/// rows from the top, calling `band` with each of them.
+///
+/// The bands are as wide as the whole bitmap and placed at their position
+/// in it, the last one may be lower. Only one band is kept at a time, which
+/// bounds the memory needed to render huge glyphs, e.g. their
+/// `FontInfo::glyph_path`.
+pub fn rasterize_bands<F>(path: &[PathSegment], options: &RasterOptions, band_height: usize,
+    mut band: F)
+    where F: FnMut(&GrayBitmap)
+{
+    let contours = flatten_path(path, 0.35);
+    let (x0, y0, width, height) = match contour_bounds(&contours) {
+        Some(bounds) => bounds,
+        None => return,
+    };
+    let mut bitmap = GrayBitmap::new(x0, y0, width, band_height.min(height).max(1));
+    let mut rows = 0;
+    rasterize_rows(&contours, x0, y0, width, height, options, |y, row| {
+        bitmap.pixels[rows * width..(rows + 1) * width].copy_from_slice(row);
+        rows += 1;
+        if rows == bitmap.height || y + 1 == y0 + height as i32 {
+            bitmap.height = rows;
+            bitmap.pixels.truncate(rows * width);
+            band(&bitmap);
+            bitmap.y = y + 1;
+            rows = 0;
+        }
+    });
+}
+
+/// Returns `true` if the point `(x, y)` is inside `path` according to
+/// `fill_rule`, with curves as precise as in the bitmaps of
+/// `rasterize_path`.
+pub fn path_contains(path: &[PathSegment], x: f32, y: f32, fill_rule: FillRule) -> bool {
+    let mut winding = 0;
+    for contour in flatten_path(path, 0.35) {
+        for (i, &b) in contour.iter().enumerate() {
+            let a = contour[if i == 0 { contour.len() - 1 } else { i - 1 }];
+            // Which side of the line from a to b the point is on.
+            let side = (b.0 - a.0) * (y - a.1) - (x - a.0) * (b.1 - a.1);
+            if a.1 <= y && y < b.1 && side < 0.0 {
+                winding += 1;
+            } else if b.1 <= y && y < a.1 && side > 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+    fill_rule.is_inside(winding)
+}
+
+/// Rasterizes the outline of `path`, a line `stroke_width` pixels wide
+/// centered on it with round joins.
+pub fn rasterize_stroked_path(path: &[PathSegment], stroke_width: f32) -> GrayBitmap {
+    let radius = stroke_width / 2.0;
+    if !(radius > 0.0) {
+        return GrayBitmap::default();
+    }
+    // The union of a rectangle around every line and a disk at every point,
+    // all wound the same way so that the overlaps don't cancel out.
+    let sides = (libm::ceilf(radius * 4.0) as usize).max(8).min(64);
+    let mut pieces = vec![];
+    for contour in flatten_path(path, 0.35) {
+        for (i, &b) in contour.iter().enumerate() {
+            pieces.push((0..sides).map(|j| {
+                let angle = j as f32 * 2.0 * ::core::f32::consts::PI / sides as f32;
+                (b.0 + radius * libm::cosf(angle), b.1 + radius * libm::sinf(angle))
+            }).collect());
+            let a = contour[if i == 0 { contour.len() - 1 } else { i - 1 }];
+            let length = libm::sqrtf((b.0 - a.0) * (b.0 - a.0) + (b.1 - a.1) * (b.1 - a.1));
+            if length > 0.0 {
+                let n = ((a.1 - b.1) / length * radius, (b.0 - a.0) / length * radius);
+                pieces.push(vec![(a.0 + n.0, a.1 + n.1), (b.0 + n.0, b.1 + n.1),
+                    (b.0 - n.0, b.1 - n.1), (a.0 - n.0, a.1 - n.1)]);
+            }
+        }
+    }
+    for piece in &mut pieces {
+        if signed_area(piece) < 0.0 {
+            piece.reverse();
+        }
+    }
+    rasterize_contours(&pieces, &RasterOptions { overlap_correct: true, ..RasterOptions::default() })
+}
+
+/// Returns twice the area of `contour`, positive if it goes clockwise with
+/// the y axis pointing down.
+pub fn signed_area(contour: &[(f32, f32)]) -> f32 {
+    let mut area = 0.0;
+    for (i, &b) in contour.iter().enumerate() {
+        let a = contour[if i == 0 { contour.len() - 1 } else { i - 1 }];
+        area += a.0 * b.1 - b.0 * a.1;
+    }
+    area
+}
+
+/// Rasterizes closed `contours` into a bitmap just large enough for them.
+fn rasterize_contours(contours: &[Vec<(f32, f32)>], options: &RasterOptions) -> GrayBitmap {
+    let (x0, y0, width, height) = match contour_bounds(contours) {
+        Some(bounds) => bounds,
+        None => return GrayBitmap::default(),
+    };
+    let mut result = GrayBitmap::new(x0, y0, width, height);
+    {
+        let pixels = &mut result.pixels;
+        rasterize_rows(contours, x0, y0, width, height, options, |y, row| {
+            let start = (y - y0) as usize * width;
+            pixels[start..start + width].copy_from_slice(row);
+        });
+    }
+    result
+}
+
+/// Returns the left and top pixel and the width and height of the pixels
+/// touched by `contours`, `None` if there are no points.
+fn contour_bounds(contours: &[Vec<(f32, f32)>]) -> Option<(i32, i32, usize, usize)> {
+    if contours.iter().all(|c| c.is_empty()) {
+        return None;
+    }
+    let points = || contours.iter().flat_map(|c| c.iter());
+    let x0 = libm::floorf(points().fold(::core::f32::MAX, |m, p| m.min(p.0))) as i32;
+    let y0 = libm::floorf(points().fold(::core::f32::MAX, |m, p| m.min(p.1))) as i32;
+    let x1 = libm::ceilf(points().fold(::core::f32::MIN, |m, p| m.max(p.0))) as i32;
+    let y1 = libm::ceilf(points().fold(::core::f32::MIN, |m, p| m.max(p.1))) as i32;
+    Some((x0, y0, (x1 - x0).max(0) as usize, (y1 - y0).max(0) as usize))
+}
+
+/// Rasterizes closed `contours` into `width` by `height` pixels with the top
+/// left one at `(x0, y0)`, calling `emit` with the y and the coverage of
+/// every row from the top.
+fn rasterize_rows<F>(contours: &[Vec<(f32, f32)>], x0: i32, y0: i32, width: usize,
+    height: usize, options: &RasterOptions, mut emit: F)
+    where F: FnMut(i32, &[u8])
+{
+    if width == 0 || height == 0 {
+        return;
+    }
+    let mut edges = contour_edges(contours);
+    unsafe {
+        if options.is_sampled() {
+            sample_sorted_edges(width, height, &edges, x0 as isize, y0 as isize, options,
+                |j, row| emit(y0 + j as i32, row));
+        } else {
+            // The rasterizer needs room for a sentinel.
+            let n = edges.len() as isize;
+            edges.push(Edge { x0: 0.0, y0: 0.0, x1: 0.0, y1: 0.0, invert: 0 });
+            rasterize_sorted_edges_rows(width as isize, height as isize, edges.as_mut_ptr(), n,
+                1, x0 as isize, y0 as isize, options.deterministic,
+                |j, row| emit(y0 + j as i32, row));
+        }
+    }
+}
+
+/// Splits `path` into contours of points, approximating curves with lines
+/// that are at most `flatness` away from them.
+pub fn flatten_path(path: &[PathSegment], flatness: f32) -> Vec<Vec<(f32, f32)>> {
+    fn quad(points: &mut Vec<(f32, f32)>, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32),
+        flatness_squared: f32, depth: usize)
+    {
+        // Distance of the midpoint of the curve from the midpoint of the line.
+        let m = ((p0.0 + 2.0 * p1.0 + p2.0) / 4.0, (p0.1 + 2.0 * p1.1 + p2.1) / 4.0);
+        let (dx, dy) = ((p0.0 + p2.0) / 2.0 - m.0, (p0.1 + p2.1) / 2.0 - m.1);
+        if depth < 16 && dx * dx + dy * dy > flatness_squared {
+            let a = ((p0.0 + p1.0) / 2.0, (p0.1 + p1.1) / 2.0);
+            let b = ((p1.0 + p2.0) / 2.0, (p1.1 + p2.1) / 2.0);
+            quad(points, p0, a, m, flatness_squared, depth + 1);
+            quad(points, m, b, p2, flatness_squared, depth + 1);
+        } else {
+            points.push(p2);
+        }
+    }
+
+    let mut contours: Vec<Vec<(f32, f32)>> = vec![];
+    let mut current = (0.0, 0.0);
+    for segment in path {
+        if let PathSegment::MoveTo(..) = *segment {
+        } else if contours.is_empty() {
+            contours.push(vec![current]);
+        }
+        match *segment {
+            PathSegment::MoveTo(x, y) => contours.push(vec![(x, y)]),
+            PathSegment::LineTo(x, y) => contours.last_mut().unwrap().push((x, y)),
+            PathSegment::QuadTo(cx, cy, x, y) => {
+                quad(contours.last_mut().unwrap(), current, (cx, cy), (x, y),
+                    flatness * flatness, 0);
+            }
+        }
+        current = *contours.last().unwrap().last().unwrap();
+    }
+    contours
+}
+
+/// Returns the edges of closed `contours` going from top to bottom sorted
+/// by their top, the way the rasterizers take them.
+fn contour_edges(contours: &[Vec<(f32, f32)>]) -> Vec<Edge> {
+    let mut edges = vec![];
+    for contour in contours {
+        for (i, &b) in contour.iter().enumerate() {
+            let a = contour[if i == 0 { contour.len() - 1 } else { i - 1 }];
+            // Horizontal edges don't cover anything.
+            if a.1 < b.1 {
+                edges.push(Edge { x0: a.0, y0: a.1, x1: b.0, y1: b.1, invert: 1 });
+            } else if a.1 > b.1 {
+                edges.push(Edge { x0: b.0, y0: b.1, x1: a.0, y1: a.1, invert: 0 });
+            }
+        }
+    }
+    edges.sort_by(|a, b| a.y0.partial_cmp(&b.y0).unwrap_or(::core::cmp::Ordering::Equal));
+    edges
+}
+
+/// Rasterizes `edges` into `result` by sampling which parts of `SAMPLES`
+/// horizontal lines per row of pixels are inside the shape according to
+/// the fill rule of `options`.
+///
+/// Edges go from top to bottom and are sorted by `y0`, `invert` is set for
+/// edges going up in the original shape. The coverage along each line is
+/// exact, so there is no horizontal aliasing. With `RasterMode::Mono` only
+/// the centers of the pixels are sampled.
+pub unsafe fn rasterize_sorted_edges_sampled(
+    result: *mut Bitmap,
+    edges: &[Edge],
+    off_x: isize,
+    off_y: isize,
+    options: &RasterOptions,
+) {
+    let (pixels, stride) = ((*result).pixels, (*result).stride);
+    sample_sorted_edges((*result).w as usize, (*result).h as usize, edges, off_x, off_y,
+        options, |j, row| {
+            for (i, &c) in row.iter().enumerate() {
+                *pixels.offset(j as isize * stride + i as isize) = c;
+            }
+        });
+}
+
+/// Same as `rasterize_sorted_edges_sampled`, but calls `emit` with the index
+/// and the coverage of every row of `w` pixels instead of keeping a bitmap.
+fn sample_sorted_edges<F>(w: usize, h: usize, edges: &[Edge], off_x: isize, off_y: isize,
+    options: &RasterOptions, mut emit: F)
+    where F: FnMut(usize, &[u8])
+{
+    let mono = options.mode == RasterMode::Mono;
+    // One line through the centers of the pixels without anti-aliasing.
+    let samples = if mono { 1 } else { SAMPLES };
+    let mut coverage = vec![0.0f32; w];
+    let mut row = vec![0u8; w];
+    let mut active: Vec<&Edge> = vec![];
+    let mut crossings: Vec<(f32, i32)> = vec![];
+    let mut next = 0;
+
+    for j in 0..h {
+        for c in coverage.iter_mut() {
+            *c = 0.0;
+        }
+
+        for s in 0..samples {
+            let y = (off_y + j as isize) as f32 + (s as f32 + 0.5) / samples as f32;
+            while next < edges.len() && edges[next].y0 <= y {
+                active.push(&edges[next]);
+                next += 1;
+            }
+            active.retain(|e| e.y1 > y);
+
+            crossings.clear();
+            for e in &active {
+                let x = e.x0 + (y - e.y0) * (e.x1 - e.x0) / (e.y1 - e.y0);
+                crossings.push((x - off_x as f32, if e.invert != 0 { 1 } else { -1 }));
+            }
+            crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(::core::cmp::Ordering::Equal));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if !options.fill_rule.is_inside(winding) {
+                    continue;
+                }
+                if mono {
+                    // The pixels whose center is in the span.
+                    let x0 = libm::ceilf(pair[0].0 - 0.5).max(0.0);
+                    let x1 = libm::ceilf(pair[1].0 - 0.5).min(w as f32);
+                    add_span(&mut coverage, x0, x1, 1.0);
+                } else {
+                    add_span(&mut coverage, pair[0].0, pair[1].0, 1.0 / samples as f32);
+                }
+            }
+        }
+
+        for (p, &c) in row.iter_mut().zip(&coverage) {
+            let m = (c * 255.0 + 0.5) as isize;
+            *p = if m > 255 { 255 } else { m as u8 };
+        }
+        emit(j, &row);
+    }
+}
+
+/// Adds `weight` times the part of each pixel covered by the span from `x0`
+/// to `x1` to its coverage.
+fn add_span(coverage: &mut [f32], x0: f32, x1: f32, weight: f32) {
+    let x0 = x0.max(0.0);
+    let x1 = x1.min(coverage.len() as f32);
+    if !(x0 < x1) {
+        return;
+    }
+    let (i0, i1) = (x0 as usize, x1 as usize);
+    if i0 == i1 {
+        coverage[i0] += (x1 - x0) * weight;
+        return;
+    }
+    coverage[i0] += (i0 as f32 + 1.0 - x0) * weight;
+    for c in &mut coverage[i0 + 1..i1] {
+        *c += weight;
+    }
+    if i1 < coverage.len() {
+        coverage[i1] += (x1 - i1 as f32) * weight;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn spans() {
+        let mut coverage = [0.0; 4];
+        add_span(&mut coverage, 0.5, 2.25, 1.0);
+        expect!(coverage).to(be_equal_to([0.5, 1.0, 0.25, 0.0]));
+
+        add_span(&mut coverage, 3.25, 3.75, 0.5);
+        add_span(&mut coverage, -1.0, 0.5, 1.0);
+        add_span(&mut coverage, 2.0, 1.0, 1.0);
+        expect!(coverage).to(be_equal_to([1.0, 1.0, 0.25, 0.25]));
+    }
+
+    #[test]
+    fn flatten() {
+        let path = [PathSegment::LineTo(1.0, 0.0), PathSegment::MoveTo(2.0, 2.0),
+            PathSegment::QuadTo(4.0, 2.0, 4.0, 4.0)];
+        let contours = flatten_path(&path, 0.35);
+        expect!(contours.len()).to(be_equal_to(2));
+        expect!(contours[0].clone()).to(be_equal_to(vec![(0.0, 0.0), (1.0, 0.0)]));
+        expect!(contours[1].len()).to(be_greater_than(2));
+        expect!(*contours[1].last().unwrap()).to(be_equal_to((4.0, 4.0)));
+
+        // Only the two vertical edges of a square are left.
+        let square = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let edges = contour_edges(&[square]);
+        expect!(edges.len()).to(be_equal_to(2));
+        expect!(edges.iter().map(|e| e.invert).collect::<Vec<_>>()).to(be_equal_to(vec![0, 1]));
+    }
+
+    #[test]
+    fn gray_bitmap() {
+        let mut bitmap = GrayBitmap::new(-1, 2, 3, 2);
+        bitmap.as_mut_slice()[4] = 255;
+        expect!((bitmap.width(), bitmap.height())).to(be_equal_to((3, 2)));
+        expect!(bitmap.row(1)).to(be_equal_to(&[0u8, 255, 0][..]));
+        expect!(bitmap.as_slice()).to(be_equal_to(&[0u8, 0, 0, 0, 255, 0][..]));
+        expect!(GrayBitmap::from_vec(-1, 2, 3, 2, bitmap.as_slice().to_vec())).to(be_some().value(bitmap.clone()));
+        expect!(bitmap.into_vec()).to(be_equal_to(vec![0u8, 0, 0, 0, 255, 0]));
+
+        // The size has to match the pixels.
+        expect!(GrayBitmap::from_vec(0, 0, 3, 2, vec![0; 5])).to(be_none());
+        expect!(GrayBitmap::from_vec(0, 0, usize::max_value(), 2, vec![])).to(be_none());
+    }
+
+    #[test]
+    fn rgba() {
+        expect!(mul(255, 255)).to(be_equal_to(255));
+        expect!(mul(255, 0)).to(be_equal_to(0));
+        expect!(mul(128, 128)).to(be_equal_to(64));
+        expect!((0..256).all(|a| mul(a as u8, 255) == a as u8)).to(be_true());
+
+        let bitmap = GrayBitmap { x: 0, y: 0, width: 2, height: 1, pixels: vec![255, 51] };
+        expect!(bitmap.to_rgba([200, 100, 50, 255]))
+            .to(be_equal_to(vec![200u8, 100, 50, 255, 200, 100, 50, 51]));
+        expect!(bitmap.to_rgba_premultiplied([200, 100, 50, 128]))
+            .to(be_equal_to(vec![100u8, 50, 25, 128, 20, 10, 5, 26]));
+    }
+
+    #[test]
+    fn contains() {
+        let square = [PathSegment::MoveTo(0.0, 0.0), PathSegment::LineTo(4.0, 0.0),
+            PathSegment::LineTo(4.0, 4.0), PathSegment::LineTo(0.0, 4.0),
+            PathSegment::MoveTo(1.0, 1.0), PathSegment::LineTo(3.0, 1.0),
+            PathSegment::LineTo(3.0, 3.0), PathSegment::LineTo(1.0, 3.0)];
+        expect!(path_contains(&square, 0.5, 2.0, FillRule::NonZero)).to(be_true());
+        expect!(path_contains(&square, 2.0, 2.0, FillRule::NonZero)).to(be_true());
+        expect!(path_contains(&square, 2.0, 2.0, FillRule::EvenOdd)).to(be_false());
+        expect!(path_contains(&square, 4.5, 2.0, FillRule::NonZero)).to(be_false());
+        expect!(path_contains(&[], 0.0, 0.0, FillRule::NonZero)).to(be_false());
+    }
+
+    #[test]
+    fn spans_match_bitmap() {
+        let path = [PathSegment::MoveTo(0.5, 1.0), PathSegment::LineTo(9.0, 1.0),
+            PathSegment::QuadTo(12.0, 6.0, 4.0, 8.5), PathSegment::MoveTo(2.0, 2.0),
+            PathSegment::LineTo(6.0, 2.0), PathSegment::LineTo(6.0, 6.0)];
+        for options in &[RasterOptions::default(),
+            RasterOptions { fill_rule: FillRule::EvenOdd, ..RasterOptions::default() }] {
+            let bitmap = rasterize_path(&path, options);
+            let mut pixels = vec![0; bitmap.pixels.len()];
+            let mut last = (bitmap.y, bitmap.x);
+            rasterize_spans(&path, options, |y, x0, x1, coverage| {
+                expect!(coverage).to(be_greater_than(0));
+                expect!((y, x0) >= last).to(be_true());
+                last = (y, x1);
+                for x in x0..x1 {
+                    pixels[((y - bitmap.y) * bitmap.width as i32 + x - bitmap.x) as usize] = coverage;
+                }
+            });
+            expect!(pixels).to(be_equal_to(bitmap.pixels));
+        }
+        rasterize_spans(&[], &RasterOptions::default(), |_, _, _, _| panic!());
+    }
+
+    #[test]
+    fn bands() {
+        let path = [PathSegment::MoveTo(0.5, 1.0), PathSegment::LineTo(9.0, 1.0),
+            PathSegment::QuadTo(12.0, 6.0, 4.0, 8.5)];
+        let bitmap = rasterize_path(&path, &RasterOptions::default());
+        expect!(bitmap.height).to(be_equal_to(8));
+        for &(band_height, heights) in &[(3, &[3, 3, 2][..]), (8, &[8][..]), (20, &[8][..]),
+            (0, &[1; 8][..])] {
+            let mut pixels = vec![];
+            let mut bands = vec![];
+            rasterize_bands(&path, &RasterOptions::default(), band_height, |band| {
+                let y = bitmap.y + (pixels.len() / bitmap.width) as i32;
+                expect!((band.x, band.y, band.width)).to(be_equal_to((bitmap.x, y, bitmap.width)));
+                pixels.extend_from_slice(&band.pixels);
+                bands.push(band.height);
+            });
+            expect!(&bands[..]).to(be_equal_to(heights));
+            expect!(pixels).to(be_equal_to(bitmap.pixels.clone()));
+        }
+    }
+
+    #[test]
+    fn stroke() {
+        expect!(signed_area(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0)])).to(be_equal_to(2.0));
+
+        let line = [PathSegment::MoveTo(1.0, 4.0), PathSegment::LineTo(9.0, 4.0)];
+        let bitmap = rasterize_stroked_path(&line, 2.0);
+        expect!((bitmap.x, bitmap.y, bitmap.width, bitmap.height)).to(be_equal_to((0, 3, 10, 2)));
+        expect!(bitmap.pixels[5]).to(be_equal_to(255));
+        expect!(bitmap.pixels[10 + 5]).to(be_equal_to(255));
+        expect!(rasterize_stroked_path(&line, 0.0)).to(be_equal_to(GrayBitmap::default()));
+    }
+
+    #[test]
+    fn fill_rules() {
+        expect!(FillRule::default()).to(be_equal_to(FillRule::NonZero));
+        for &(winding, non_zero, even_odd) in &[(0, false, false), (1, true, true),
+            (-2, true, false), (-3, true, true)] {
+            expect!(FillRule::NonZero.is_inside(winding)).to(be_equal_to(non_zero));
+            expect!(FillRule::EvenOdd.is_inside(winding)).to(be_equal_to(even_odd));
+        }
+        expect!(RasterOptions::default().is_sampled()).to(be_false());
+        expect!(RasterOptions { fill_rule: FillRule::EvenOdd, ..RasterOptions::default() }.is_sampled())
+            .to(be_true());
+        expect!(RasterOptions { mode: RasterMode::Mono, ..RasterOptions::default() }.is_sampled())
+            .to(be_true());
+    }
+
+    #[test]
+    fn mono() {
+        let mono = RasterOptions { mode: RasterMode::Mono, ..RasterOptions::default() };
+        let square = |a: f32, b: f32| [PathSegment::MoveTo(a, a), PathSegment::LineTo(b, a),
+            PathSegment::LineTo(b, b), PathSegment::LineTo(a, b)];
+        // Covers the centers of all pixels it touches.
+        let bitmap = rasterize_path(&square(0.4, 2.6), &mono);
+        expect!((bitmap.width, bitmap.height)).to(be_equal_to((3, 3)));
+        expect!(bitmap.pixels).to(be_equal_to(vec![255; 9]));
+        // Only covers the center of the middle pixel.
+        let bitmap = rasterize_path(&square(0.6, 2.4), &mono);
+        expect!(bitmap.pixels).to(be_equal_to(vec![0, 0, 0, 0, 255, 0, 0, 0, 0]));
+        expect!(rasterize_path(&square(0.6, 2.4), &RasterOptions::default()).pixels[0])
+            .to(be_greater_than(0));
+        // Too thin to cover any center.
+        expect!(rasterize_path(&square(0.6, 1.4), &mono).pixels).to(be_equal_to(vec![0; 4]));
+    }
+}
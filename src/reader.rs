@@ -0,0 +1,113 @@
+//! Loading fonts piece by piece from files and other readers, with the
+//! `std` feature.
+
+use std::io::{self, Read, Seek, SeekFrom};
+use byteorder::{BigEndian, ByteOrder};
+use types::FontKind;
+use utils::assemble_font;
+use {Error, FontInfo, Result};
+
+/// The tables `FontInfo::from_reader` reads, all those `FontInfo` uses
+/// except the outlines.
+const TABLES: &[&[u8; 4]] = &[b"BASE", b"GDEF", b"GPOS", b"GSUB", b"OS/2", b"VDMX", b"cmap",
+    b"gasp", b"hdmx", b"head", b"hhea", b"hmtx", b"kern", b"kerx", b"maxp", b"meta", b"morx",
+    b"name", b"post", b"trak"];
+
+impl FontInfo<'static> {
+    /// Returns the font `reader` reads, the first one of a collection,
+    /// reading only the tables of the metrics, the character map, the
+    /// names and the layout.
+    ///
+    /// The outlines, which make up most of large fonts like those of CJK
+    /// scripts, are left out, as are tables `FontInfo` doesn't use. All
+    /// glyphs are empty, but have their metrics, so text can be measured
+    /// and laid out without holding the whole file in memory. Use `from_vec`
+    /// to render the font.
+    ///
+    /// The font is assembled from the tables that were read, so its table
+    /// directory and its checksums differ from those of the file.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if reading fails, `Error::Malformed` if a table
+    /// ends past the end of the file or the data isn't a font, or the errors
+    /// of `new_with_offset`.
+    pub fn from_reader<R: Read + Seek>(mut reader: R) -> Result<FontInfo<'static>> {
+        let header = try!(read_at(&mut reader, 0, 16));
+        let fontstart = match FontKind::detect(&header) {
+            Some(FontKind::TrueTypeCollection) => BigEndian::read_u32(&header[12..]) as u64,
+            Some(FontKind::Type1) => return Err(Error::Type1NotSupported),
+            Some(kind) if kind.is_font() => 0,
+            _ => return Err(Error::Malformed),
+        };
+        let offset_table = try!(read_at(&mut reader, fontstart, 12));
+        let num_tables = BigEndian::read_u16(&offset_table[4..]) as usize;
+        let directory = try!(read_at(&mut reader, fontstart + 12, 16 * num_tables));
+
+        let mut tables = vec![];
+        let mut outlines = false;
+        for record in directory.chunks(16) {
+            let tag = [record[0], record[1], record[2], record[3]];
+            outlines |= &tag == b"glyf";
+            if TABLES.contains(&&tag) {
+                let offset = BigEndian::read_u32(&record[8..]) as u64;
+                let length = BigEndian::read_u32(&record[12..]) as usize;
+                tables.push((tag, try!(read_at(&mut reader, offset, length))));
+            }
+        }
+
+        // Every glyph starts at 0 of an empty `glyf` table.
+        if outlines {
+            let table = |tag: &[u8; 4]| tables.iter().find(|t| &t.0 == tag).map(|t| &t.1[..]);
+            let num_glyphs = try!(table(b"maxp").and_then(|maxp| maxp.get(4..6)).ok_or(Error::Malformed));
+            let loca_format = try!(table(b"head").and_then(|head| head.get(50..52)).ok_or(Error::Malformed));
+            let entry_size = if BigEndian::read_u16(loca_format) == 0 { 2 } else { 4 };
+            let loca = vec![0; (BigEndian::read_u16(num_glyphs) as usize + 1) * entry_size];
+            tables.push((*b"loca", loca));
+            tables.push((*b"glyf", vec![]));
+        }
+        FontInfo::from_vec(assemble_font(tables), 0)
+    }
+}
+
+/// Returns the `length` bytes at `offset` of `reader`.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, length: usize) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(::core::cmp::min(length, 1 << 16));
+    try!(reader.seek(SeekFrom::Start(offset)).map_err(io_error));
+    try!(reader.take(length as u64).read_to_end(&mut bytes).map_err(io_error));
+    if bytes.len() < length {
+        return Err(Error::Malformed);
+    }
+    Ok(bytes)
+}
+
+fn io_error(_: io::Error) -> Error {
+    Error::Io
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use std::io::Cursor;
+    use expectest::prelude::*;
+    use utils::read_file;
+
+    #[test]
+    fn metrics_without_outlines() {
+        let data = read_file("assets/Tuffy_Bold.ttf");
+        let full = FontInfo::new_with_offset(&data, 0).unwrap();
+        let font = FontInfo::from_reader(Cursor::new(&data)).unwrap();
+        let glyph = full.glyph_index_for_code('W');
+        expect!(font.glyph_index_for_code('W')).to(be_equal_to(glyph));
+        expect!(font.advance_width(glyph)).to(be_equal_to(full.advance_width(glyph)));
+        expect!(font.layout("AV", 1.0, &Default::default())).to(be_equal_to(
+            full.layout("AV", 1.0, &Default::default())));
+        expect!(font.glyph_path(glyph, 1.0).unwrap().is_empty()).to(be_true());
+        expect!(font.load_tables()).to(be_ok());
+        expect!(font.data().len() < data.len() / 2).to(be_true());
+
+        expect!(FontInfo::from_reader(Cursor::new(&data[..1000])).err()).to(be_some().value(Malformed));
+        expect!(FontInfo::from_reader(Cursor::new(b"typ1\0\0\0\0\0\0\0\0\0\0\0\0")).err())
+            .to(be_some().value(Type1NotSupported));
+    }
+}
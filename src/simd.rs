@@ -0,0 +1,251 @@
+//! Vectorized inner loops of the rasterizer and the vertical prefilter.
+//!
+//! With the `simd` feature they use SSE2 on x86 and x86_64 and NEON on
+//! aarch64, elsewhere and for the pixels left over at the end of a row
+//! the scalar loops of stb_truetype run. The horizontal prefilter runs
+//! along the rows, so it stays scalar.
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"))]
+use self::sse2 as vector;
+#[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+use self::neon as vector;
+#[cfg(not(any(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"), all(feature = "simd", target_arch = "aarch64",
+    target_feature = "neon"))))]
+use self::scalar as vector;
+
+/// Adds up the area the edges cover in `scanline` and the running sum in
+/// `scanline2` into the coverage of a row of pixels from 0 to 255.
+///
+/// Vectors add up four lanes at a time, so the coverage may differ from the
+/// scalar loop by rounding, with `scalar` only the scalar loop runs.
+pub fn accumulate(scanline: &[f32], scanline2: &[f32], row: &mut [u8], scalar: bool) {
+    assert!(scanline.len() >= row.len() && scanline2.len() >= row.len());
+    let mut sum = 0.0;
+    let done = if scalar { 0 } else {
+        unsafe { vector::accumulate(scanline, scanline2, row, &mut sum) }
+    };
+    for i in done..row.len() {
+        sum += scanline2[i];
+        row[i] = quantize(scanline[i] + sum);
+    }
+}
+
+/// Returns the coverage `k` as 0 to 255.
+fn quantize(k: f32) -> u8 {
+    let m = (libm::fabsf(k) * 255.0 + 0.5) as isize;
+    if m > 255 { 255 } else { m as u8 }
+}
+
+/// Filters the columns of `w` by `h` pixels with a box filter
+/// `kernel_width` pixels high, like `v_prefilter`, as many at a time as
+/// vectors hold.
+///
+/// Returns the number of columns filtered from the left, the rest is left
+/// to the scalar loop.
+pub unsafe fn v_prefilter(pixels: *mut u8, w: isize, h: isize, stride_in_bytes: isize,
+    kernel_width: usize) -> isize
+{
+    if !(2..=8).contains(&kernel_width) || h < kernel_width as isize {
+        return 0;
+    }
+    // Totals of up to 8 pixels multiplied with it and shifted right by 16
+    // bits are divided by the kernel width exactly.
+    let divisor = (65535 / kernel_width + 1) as u16;
+    vector::v_prefilter(pixels, w, h, stride_in_bytes, kernel_width, divisor)
+}
+
+#[cfg(not(any(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"), all(feature = "simd", target_arch = "aarch64",
+    target_feature = "neon"))))]
+mod scalar {
+    pub unsafe fn accumulate(_: &[f32], _: &[f32], _: &mut [u8], _: &mut f32) -> usize {
+        0
+    }
+
+    pub unsafe fn v_prefilter(_: *mut u8, _: isize, _: isize, _: isize, _: usize, _: u16)
+        -> isize
+    {
+        0
+    }
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    pub unsafe fn accumulate(scanline: &[f32], scanline2: &[f32], row: &mut [u8],
+        sum: &mut f32) -> usize
+    {
+        let n = row.len() / 4 * 4;
+        let sign = _mm_set1_ps(-0.0);
+        let (scale, half) = (_mm_set1_ps(255.0), _mm_set1_ps(0.5));
+        let mut carry = _mm_set1_ps(*sum);
+        for i in (0..n).step_by(4) {
+            // The running sum of the lanes, then of the rows before.
+            let mut x = _mm_loadu_ps(scanline2.as_ptr().add(i));
+            x = _mm_add_ps(x, _mm_castsi128_ps(_mm_slli_si128::<4>(_mm_castps_si128(x))));
+            x = _mm_add_ps(x, _mm_castsi128_ps(_mm_slli_si128::<8>(_mm_castps_si128(x))));
+            x = _mm_add_ps(x, carry);
+            carry = _mm_shuffle_ps::<0xff>(x, x);
+
+            let k = _mm_add_ps(_mm_loadu_ps(scanline.as_ptr().add(i)), x);
+            let k = _mm_add_ps(_mm_mul_ps(_mm_andnot_ps(sign, k), scale), half);
+            // The minimum keeps NaN, which converts to 0 like in the scalar
+            // loop.
+            let m = _mm_cvttps_epi32(_mm_min_ps(scale, k));
+            let m = _mm_packs_epi32(m, m);
+            let m = _mm_cvtsi128_si32(_mm_packus_epi16(m, m)) as u32;
+            row[i..i + 4].copy_from_slice(&m.to_le_bytes());
+        }
+        *sum = _mm_cvtss_f32(carry);
+        n
+    }
+
+    pub unsafe fn v_prefilter(pixels: *mut u8, w: isize, h: isize, stride_in_bytes: isize,
+        kernel_width: usize, divisor: u16) -> isize
+    {
+        let zero = _mm_setzero_si128();
+        let divisor = _mm_set1_epi16(divisor as i16);
+        let columns = w / 8 * 8;
+        for x in (0..columns).step_by(8) {
+            // The totals of 8 columns, with the pixels that leave them.
+            let mut buffer = [zero; 8];
+            let mut total = zero;
+            for i in 0..h {
+                let p = pixels.offset(x + i * stride_in_bytes);
+                let slot = i as usize & 7;
+                if i <= h - kernel_width as isize {
+                    let v = _mm_unpacklo_epi8(_mm_loadl_epi64(p as *const __m128i), zero);
+                    total = _mm_sub_epi16(_mm_add_epi16(total, v), buffer[slot]);
+                    buffer[(slot + kernel_width) & 7] = v;
+                } else {
+                    total = _mm_sub_epi16(total, buffer[slot]);
+                }
+                let q = _mm_mulhi_epu16(total, divisor);
+                _mm_storel_epi64(p as *mut __m128i, _mm_packus_epi16(q, q));
+            }
+        }
+        columns
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use core::arch::aarch64::*;
+
+    pub unsafe fn accumulate(scanline: &[f32], scanline2: &[f32], row: &mut [u8],
+        sum: &mut f32) -> usize
+    {
+        let n = row.len() / 4 * 4;
+        let zero = vdupq_n_f32(0.0);
+        let (scale, half) = (vdupq_n_f32(255.0), vdupq_n_f32(0.5));
+        let mut carry = vdupq_n_f32(*sum);
+        let mut bytes = [0u8; 8];
+        for i in (0..n).step_by(4) {
+            // The running sum of the lanes, then of the rows before.
+            let mut x = vld1q_f32(scanline2.as_ptr().add(i));
+            x = vaddq_f32(x, vextq_f32::<3>(zero, x));
+            x = vaddq_f32(x, vextq_f32::<2>(zero, x));
+            x = vaddq_f32(x, carry);
+            carry = vdupq_n_f32(vgetq_lane_f32::<3>(x));
+
+            let k = vaddq_f32(vld1q_f32(scanline.as_ptr().add(i)), x);
+            let k = vaddq_f32(vmulq_f32(vabsq_f32(k), scale), half);
+            // The minimum keeps NaN, which converts to 0 like in the scalar
+            // loop.
+            let m = vmovn_u32(vcvtq_u32_f32(vminq_f32(k, scale)));
+            vst1_u8(bytes.as_mut_ptr(), vmovn_u16(vcombine_u16(m, m)));
+            row[i..i + 4].copy_from_slice(&bytes[..4]);
+        }
+        *sum = vgetq_lane_f32::<0>(carry);
+        n
+    }
+
+    pub unsafe fn v_prefilter(pixels: *mut u8, w: isize, h: isize, stride_in_bytes: isize,
+        kernel_width: usize, divisor: u16) -> isize
+    {
+        let zero = vdupq_n_u16(0);
+        let columns = w / 8 * 8;
+        for x in (0..columns).step_by(8) {
+            // The totals of 8 columns, with the pixels that leave them.
+            let mut buffer = [zero; 8];
+            let mut total = zero;
+            for i in 0..h {
+                let p = pixels.offset(x + i * stride_in_bytes);
+                let slot = i as usize & 7;
+                if i <= h - kernel_width as isize {
+                    let v = vmovl_u8(vld1_u8(p));
+                    total = vsubq_u16(vaddq_u16(total, v), buffer[slot]);
+                    buffer[(slot + kernel_width) & 7] = v;
+                } else {
+                    total = vsubq_u16(total, buffer[slot]);
+                }
+                let low = vshrn_n_u32::<16>(vmull_n_u16(vget_low_u16(total), divisor));
+                let high = vshrn_n_u32::<16>(vmull_n_u16(vget_high_u16(total), divisor));
+                vst1_u8(p, vmovn_u16(vcombine_u16(low, high)));
+            }
+        }
+        columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn accumulated_coverage() {
+        // Eighths add up exactly in any order.
+        let scanline: Vec<f32> = (0..19).map(|i| (i % 3) as f32 / 8.0 - 0.125).collect();
+        let scanline2: Vec<f32> = (0..19).map(|i| ((i * 5) % 7) as f32 / 8.0 - 0.375).collect();
+        let mut row = [0; 19];
+        accumulate(&scanline, &scanline2, &mut row, false);
+
+        let mut sum = 0.0;
+        for i in 0..19 {
+            sum += scanline2[i];
+            expect!(row[i]).to(be_equal_to(quantize(scanline[i] + sum)));
+        }
+        expect!(quantize(-0.5)).to(be_equal_to(128));
+        expect!(quantize(3.0)).to(be_equal_to(255));
+        expect!(quantize(::core::f32::NAN)).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn vertical_prefilter() {
+        // 11 columns of 7 pixels with the last 2 rows empty, like the
+        // prefilter expects for a kernel of 3.
+        let (w, h, stride) = (11usize, 7usize, 12usize);
+        let mut pixels: Vec<u8> = (0..stride * h).map(|i| {
+            if i / stride < h - 2 && i % stride < w { (i * 37 % 256) as u8 } else { 0 }
+        }).collect();
+        let expected: Vec<u8> = (0..stride * h).map(|i| {
+            let (x, y) = (i % stride, i / stride);
+            if x >= w {
+                return 0;
+            }
+            let total: usize = (y.saturating_sub(2)..y + 1).map(|y| pixels[y * stride + x] as usize).sum();
+            (total / 3) as u8
+        }).collect();
+
+        let columns = unsafe {
+            v_prefilter(pixels.as_mut_ptr(), w as isize, h as isize, stride as isize, 3)
+        };
+        expect!(columns == 0 || columns == 8).to(be_true());
+        for x in 0..columns as usize {
+            for y in 0..h {
+                expect!(pixels[y * stride + x]).to(be_equal_to(expected[y * stride + x]));
+            }
+        }
+        expect!(unsafe { v_prefilter(pixels.as_mut_ptr(), w as isize, 2, stride as isize, 3) })
+            .to(be_equal_to(0));
+    }
+}
@@ -0,0 +1,116 @@
+//! Sheets showing all glyphs of a font, to look for parsing and rendering
+//! regressions.
+
+use prelude::*;
+use {FontInfo, GrayBitmap};
+
+/// The space around glyphs and labels in pixels.
+const PADDING: usize = 2;
+/// The digits of the labels, 3 by 5 pixels, a row of 3 bits from the top
+/// for each row, the left pixel in the high bit.
+const DIGITS: [[u8; 5]; 10] = [[7, 5, 5, 5, 7], [2, 6, 2, 2, 7], [7, 1, 7, 4, 7], [7, 1, 7, 1, 7],
+    [5, 5, 7, 1, 1], [7, 4, 7, 1, 7], [7, 4, 7, 5, 7], [7, 1, 1, 1, 1], [7, 5, 7, 5, 7], [7, 5, 7, 1, 7]];
+/// The width of the label of glyph 65535.
+const LABEL_WIDTH: usize = 5 * 4 - 1;
+/// The value of the lines between cells.
+const GRID: u8 = 0x40;
+
+impl<'a> FontInfo<'a> {
+    /// Returns a sheet of the glyphs that have outlines, see `glyphs`,
+    /// rendered at a size of `px` pixels in `columns` columns, left to
+    /// right and top to bottom.
+    ///
+    /// Every glyph is in a cell as large as the bounding box of the font,
+    /// with its index below it in digits of 3 by 5 pixels, which don't
+    /// depend on the font. Cells are separated by gray lines on their right
+    /// and bottom.
+    pub fn render_specimen(&self, px: f32, columns: usize) -> GrayBitmap {
+        let scale = self.scale_for_px(px);
+        let bbox = self.head().bounding_box();
+        let x0 = libm::floorf(bbox.x0 as f32 * scale) as i32;
+        let y1 = libm::ceilf(bbox.y1 as f32 * scale) as i32;
+        let glyph_width = (libm::ceilf(bbox.x1 as f32 * scale) as i32 - x0).max(0) as usize;
+        let glyph_height = (y1 - libm::floorf(bbox.y0 as f32 * scale) as i32).max(0) as usize;
+        let cell_width = glyph_width.max(LABEL_WIDTH) + 2 * PADDING + 1;
+        let cell_height = glyph_height + 5 + 3 * PADDING + 1;
+
+        let glyphs: Vec<_> = self.glyphs().map(|(id, _)| id).collect();
+        let columns = columns.max(1).min(glyphs.len());
+        let rows = if columns == 0 { 0 } else { (glyphs.len() + columns - 1) / columns };
+        let mut sheet = GrayBitmap::new(0, 0, columns * cell_width, rows * cell_height);
+        let (width, height) = (sheet.width(), sheet.height());
+        for (n, glyph) in glyphs.into_iter().enumerate() {
+            let (x, y) = (n % columns * cell_width, n / columns * cell_height);
+            self.render_into(glyph, scale, sheet.as_mut_slice(), width, height, width,
+                (x + PADDING) as i32 - x0, (y + PADDING) as i32 + y1);
+            draw_label(&mut sheet, x + PADDING, y + glyph_height + 2 * PADDING, glyph.0);
+            for i in 0..cell_height {
+                sheet.as_mut_slice()[(y + i) * width + x + cell_width - 1] = GRID;
+            }
+            for i in 0..cell_width {
+                sheet.as_mut_slice()[(y + cell_height - 1) * width + x + i] = GRID;
+            }
+        }
+        sheet
+    }
+}
+
+/// Draws the digits of `number` at `(x, y)` of `sheet`.
+fn draw_label(sheet: &mut GrayBitmap, x: usize, y: usize, number: u16) {
+    let mut digits = vec![];
+    let mut rest = number as usize;
+    loop {
+        digits.insert(0, rest % 10);
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    let width = sheet.width();
+    for (i, &digit) in digits.iter().enumerate() {
+        for (row, &bits) in DIGITS[digit].iter().enumerate() {
+            for column in 0..3 {
+                if bits & (4 >> column) != 0 {
+                    sheet.as_mut_slice()[(y + row) * width + x + i * 4 + column] = 0xff;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+    use utils::read_file;
+
+    #[test]
+    fn specimen() {
+        let data = read_file("assets/Tuffy_Bold.ttf");
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        let glyphs: Vec<_> = font.glyphs().map(|(id, _)| id).collect();
+        let sheet = font.render_specimen(16.0, 20);
+        let (cell_width, cell_height) = (sheet.width() / 20, sheet.height() / ((glyphs.len() + 19) / 20));
+        expect!(sheet.width() % 20).to(be_equal_to(0));
+        expect!(sheet.height() % cell_height).to(be_equal_to(0));
+        expect!(cell_width > 16 && cell_height > 16).to(be_true());
+
+        // The cell of 'A' has the glyph, and its label reads its index.
+        let n = glyphs.iter().position(|&id| id == font.glyph_index_for_code('A')).unwrap();
+        let (x, y) = (n % 20 * cell_width, n / 20 * cell_height);
+        let cell = |dx: usize, dy: usize| sheet.as_slice()[(y + dy) * sheet.width() + x + dx];
+        let inked = (0..cell_height - 5 - 3 * PADDING).any(|dy| (0..cell_width - 1).any(|dx| cell(dx, dy) > 0));
+        expect!(inked).to(be_true());
+        let mut label = GrayBitmap::new(0, 0, LABEL_WIDTH, 5);
+        draw_label(&mut label, 0, 0, glyphs[n].0);
+        let top = cell_height - 1 - PADDING - 5;
+        for dy in 0..5 {
+            for dx in 0..LABEL_WIDTH {
+                expect!(cell(PADDING + dx, top + dy)).to(be_equal_to(label.as_slice()[dy * LABEL_WIDTH + dx]));
+            }
+        }
+        expect!(cell(cell_width - 1, 0)).to(be_equal_to(GRID));
+
+        expect!(font.render_specimen(16.0, 0).width()).to(be_equal_to(cell_width));
+    }
+}
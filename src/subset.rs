@@ -0,0 +1,390 @@
+//! Font subsetting.
+//!
+//! Writes a new font containing only the glyphs needed to display a given set
+//! of characters. Glyph outlines and instructions are copied byte for byte,
+//! so rendering of the retained characters is not affected.
+
+use prelude::*;
+use alloc::collections::BTreeMap;
+use Error;
+use Result;
+use FontInfo;
+use GlyphId;
+use tables::GlyphData;
+use types::BBox;
+use utils;
+use byteorder::{BigEndian, ByteOrder};
+use utils::WriteBytesExt;
+
+/// Tables that don't depend on glyph indices and are copied as is.
+const COPIED_TABLES: [&'static [u8; 4]; 6] = [b"OS/2", b"name", b"cvt ", b"fpgm", b"prep", b"gasp"];
+
+/// A subset of a font.
+#[derive(Debug, Clone)]
+pub struct Subset {
+    data: Vec<u8>,
+    glyphs: Vec<GlyphId>,
+}
+
+impl Subset {
+    /// Returns the font file of the subset.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes the subset and returns its font file.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Returns the glyph of the original font for each glyph of the subset.
+    pub fn original_glyph_indices(&self) -> &[GlyphId] {
+        &self.glyphs
+    }
+
+    /// Returns the glyph in the subset of `glyph` of the original font or
+    /// `None` if the glyph was not retained.
+    pub fn glyph_index_for_original(&self, glyph: GlyphId) -> Option<GlyphId> {
+        // The subset has fewer glyphs than the original font.
+        self.glyphs.binary_search(&glyph).ok().map(|i| GlyphId(i as u16))
+    }
+}
+
+/// Creates a new font containing only glyphs for `codepoints` (and glyphs
+/// they are composed of) of the `font`.
+///
+/// The subset keeps the `cmap`, `glyf`, `head`, `hhea`, `hmtx`, `loca`,
+/// `maxp` and `post` tables (rebuilt for the retained glyphs, glyph names are
+/// dropped), as well as `OS/2`, `name`, `gasp` and hinting tables. Glyph 0
+/// (the missing character glyph) is always retained, codepoints not mapped
+/// by the font are skipped.
+///
+/// # Errors
+/// Returns error if one of the required tables is missing or malformed.
+pub fn subset(font: &FontInfo, codepoints: &[char]) -> Result<Subset> {
+    let data = font.data();
+    let records = try!(utils::read_table_records(data, font.fontstart));
+    let table = |tag: &[u8; 4]| {
+        records.iter().find(|r| &r.tag == tag).map(|r| &data[r.offset..r.offset + r.length])
+    };
+
+    // Collect codepoints mapped by the font and all glyphs they need.
+    let mut mapping = BTreeMap::new();
+    for &c in codepoints {
+        let glyph = font.glyph_index_for_code(c);
+        if glyph != GlyphId(0) {
+            mapping.insert(c as u32, glyph.index());
+        }
+    }
+    let mut glyphs: Vec<usize> = Some(0).into_iter().chain(mapping.values().cloned()).collect();
+    let mut i = 0;
+    while i < glyphs.len() {
+        for (_, component) in try!(GlyphData::new(glyph_bytes(font, glyphs[i]), 0).component_offsets()) {
+            if !glyphs.contains(&component) {
+                glyphs.push(component);
+            }
+        }
+        i += 1;
+    }
+    glyphs.sort();
+    glyphs.dedup();
+
+    let new_index = |old: usize| glyphs.binary_search(&old).unwrap();
+
+    // glyf & loca
+    let mut glyf = vec![];
+    let mut offsets = vec![];
+    let mut bbox: Option<BBox> = None;
+    for &old in &glyphs {
+        offsets.push(glyf.len() as u32);
+        let bytes = glyph_bytes(font, old);
+        let start = glyf.len();
+        glyf.extend_from_slice(bytes);
+        for (at, component) in try!(GlyphData::new(bytes, 0).component_offsets()) {
+            BigEndian::write_u16(&mut glyf[start + at..], new_index(component) as u16);
+        }
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+        if let Some(b) = font.glyph_data(old).bounding_box() {
+            if bytes.is_empty() {
+                continue;
+            }
+            bbox = Some(bbox.map_or(b, |a| BBox {
+                x0: a.x0.min(b.x0), y0: a.y0.min(b.y0), x1: a.x1.max(b.x1), y1: a.y1.max(b.y1)
+            }));
+        }
+    }
+    offsets.push(glyf.len() as u32);
+
+    let long_offsets = glyf.len() > 0x1fffe;
+    let mut loca = vec![];
+    for &offset in &offsets {
+        if long_offsets {
+            loca.write_u32::<BigEndian>(offset).unwrap();
+        } else {
+            loca.write_u16::<BigEndian>((offset / 2) as u16).unwrap();
+        }
+    }
+
+    // hmtx & hhea
+    let mut hmtx = vec![];
+    let mut advance_width_max = 0;
+    let mut min_lsb = i16::max_value();
+    let mut min_rsb = i16::max_value();
+    let mut x_max_extent = i16::min_value();
+    for &old in &glyphs {
+        let metric = font.hmtx.hmetric_for_glyph_at_index(old);
+        hmtx.write_u16::<BigEndian>(metric.advance_width).unwrap();
+        hmtx.write_i16::<BigEndian>(metric.left_side_bearing).unwrap();
+
+        advance_width_max = advance_width_max.max(metric.advance_width);
+        if glyph_bytes(font, old).is_empty() {
+            continue;
+        }
+        if let Some(b) = font.glyph_data(old).bounding_box() {
+            let lsb = metric.left_side_bearing as i32;
+            let extent = lsb + b.x1 - b.x0;
+            min_lsb = min_lsb.min(lsb as i16);
+            min_rsb = min_rsb.min((metric.advance_width as i32 - extent) as i16);
+            x_max_extent = x_max_extent.max(extent as i16);
+        }
+    }
+
+    let mut hhea = try!(table(b"hhea").ok_or(Error::MissingTable)).to_owned();
+    if hhea.len() < 36 {
+        return Err(Error::Malformed);
+    }
+    BigEndian::write_u16(&mut hhea[10..], advance_width_max);
+    if bbox.is_some() {
+        BigEndian::write_i16(&mut hhea[12..], min_lsb);
+        BigEndian::write_i16(&mut hhea[14..], min_rsb);
+        BigEndian::write_i16(&mut hhea[16..], x_max_extent);
+    }
+    BigEndian::write_u16(&mut hhea[34..], glyphs.len() as u16);
+
+    // head
+    let mut head = try!(table(b"head").ok_or(Error::MissingTable)).to_owned();
+    if head.len() < 54 {
+        return Err(Error::Malformed);
+    }
+    if let Some(bbox) = bbox {
+        BigEndian::write_i16(&mut head[36..], bbox.x0 as i16);
+        BigEndian::write_i16(&mut head[38..], bbox.y0 as i16);
+        BigEndian::write_i16(&mut head[40..], bbox.x1 as i16);
+        BigEndian::write_i16(&mut head[42..], bbox.y1 as i16);
+    }
+    BigEndian::write_u16(&mut head[50..], if long_offsets { 1 } else { 0 });
+
+    // maxp
+    let mut maxp = try!(table(b"maxp").ok_or(Error::MissingTable)).to_owned();
+    if maxp.len() < 6 {
+        return Err(Error::Malformed);
+    }
+    BigEndian::write_u16(&mut maxp[4..], glyphs.len() as u16);
+
+    // cmap
+    let cmap = build_cmap(&mapping.iter().map(|(&c, &g)| (c, new_index(g) as u16)).collect::<Vec<_>>());
+
+    let mut tables = vec![
+        (*b"cmap", cmap),
+        (*b"glyf", glyf),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", loca),
+        (*b"maxp", maxp),
+    ];
+
+    // post, version 3.0 doesn't contain glyph names.
+    if let Some(post) = table(b"post") {
+        if post.len() >= 32 {
+            let mut post = post[..32].to_owned();
+            BigEndian::write_u32(&mut post[0..], 0x00030000);
+            tables.push((*b"post", post));
+        }
+    }
+
+    for tag in COPIED_TABLES.iter() {
+        if let Some(t) = table(tag) {
+            tables.push((**tag, t.to_owned()));
+        }
+    }
+
+    // The first and last characters of OS/2 are those of the subset.
+    let codes = (mapping.keys().next(), mapping.keys().last());
+    if let (Some(&first), Some(&last)) = codes {
+        if let Some(&mut (_, ref mut os2)) = tables.iter_mut().find(|t| &t.0 == b"OS/2" && t.1.len() >= 68) {
+            BigEndian::write_u16(&mut os2[64..], ::core::cmp::min(first, 0xffff) as u16);
+            BigEndian::write_u16(&mut os2[66..], ::core::cmp::min(last, 0xffff) as u16);
+        }
+    }
+
+    Ok(Subset {
+        data: utils::assemble_font(tables),
+        // Components are 16-bit glyph indices like those of the `cmap`.
+        glyphs: glyphs.iter().map(|&i| GlyphId(i as u16)).collect(),
+    })
+}
+
+/// Returns raw data of the glyph at index `i` in the `glyf` table.
+fn glyph_bytes<'a>(font: &'a FontInfo, i: usize) -> &'a [u8] {
+    font.glyph_bytes(i).unwrap_or(&[])
+}
+
+/// Builds a `cmap` table with a format 4 subtable for the Basic Multilingual
+/// Plane and a format 12 subtable if there are characters outside of it.
+///
+/// `mapping` must be sorted by codepoint.
+pub fn build_cmap(mapping: &[(u32, u16)]) -> Vec<u8> {
+    // Runs of consecutive codepoints mapped to consecutive glyphs.
+    let mut runs: Vec<(u32, u32, u16)> = vec![];
+    for &(c, g) in mapping {
+        if let Some(last) = runs.last_mut() {
+            if last.1 + 1 == c && last.2 as u32 + (c - last.0) == g as u32 {
+                last.1 = c;
+                continue;
+            }
+        }
+        runs.push((c, c, g));
+    }
+    let bmp: Vec<_> = runs.iter().filter(|r| r.0 < 0xffff)
+        .map(|&(s, e, g)| (s, ::core::cmp::min(e, 0xfffe), g)).collect();
+    let full_unicode = runs.last().map_or(false, |r| r.1 >= 0xffff);
+
+    // Format 4
+    let seg_count = bmp.len() + 1;
+    let mut entry_selector = 0;
+    while (2 << entry_selector) <= seg_count {
+        entry_selector += 1;
+    }
+    let search_range = 2 << entry_selector;
+    let mut f4 = vec![];
+    f4.write_u16::<BigEndian>(4).unwrap();
+    f4.write_u16::<BigEndian>((16 + 8 * seg_count) as u16).unwrap();
+    f4.write_u16::<BigEndian>(0).unwrap();
+    f4.write_u16::<BigEndian>((seg_count * 2) as u16).unwrap();
+    f4.write_u16::<BigEndian>(search_range as u16).unwrap();
+    f4.write_u16::<BigEndian>(entry_selector as u16).unwrap();
+    f4.write_u16::<BigEndian>((seg_count * 2 - search_range) as u16).unwrap();
+    for &(_, e, _) in &bmp {
+        f4.write_u16::<BigEndian>(e as u16).unwrap();
+    }
+    f4.write_u16::<BigEndian>(0xffff).unwrap();
+    f4.write_u16::<BigEndian>(0).unwrap(); // reservedPad
+    for &(s, _, _) in &bmp {
+        f4.write_u16::<BigEndian>(s as u16).unwrap();
+    }
+    f4.write_u16::<BigEndian>(0xffff).unwrap();
+    for &(s, _, g) in &bmp {
+        f4.write_u16::<BigEndian>((g as u32).wrapping_sub(s) as u16).unwrap();
+    }
+    f4.write_u16::<BigEndian>(1).unwrap();
+    for _ in 0..seg_count {
+        f4.write_u16::<BigEndian>(0).unwrap();
+    }
+
+    let mut subtables = vec![(3u16, 1u16, f4)];
+
+    // Format 12
+    if full_unicode {
+        let mut f12 = vec![];
+        f12.write_u16::<BigEndian>(12).unwrap();
+        f12.write_u16::<BigEndian>(0).unwrap();
+        f12.write_u32::<BigEndian>((16 + 12 * runs.len()) as u32).unwrap();
+        f12.write_u32::<BigEndian>(0).unwrap();
+        f12.write_u32::<BigEndian>(runs.len() as u32).unwrap();
+        for &(s, e, g) in &runs {
+            f12.write_u32::<BigEndian>(s).unwrap();
+            f12.write_u32::<BigEndian>(e).unwrap();
+            f12.write_u32::<BigEndian>(g as u32).unwrap();
+        }
+        subtables.push((3, 10, f12));
+    }
+
+    let mut cmap = vec![];
+    cmap.write_u16::<BigEndian>(0).unwrap();
+    cmap.write_u16::<BigEndian>(subtables.len() as u16).unwrap();
+    let mut offset = 4 + 8 * subtables.len();
+    for &(platform, encoding, ref subtable) in &subtables {
+        cmap.write_u16::<BigEndian>(platform).unwrap();
+        cmap.write_u16::<BigEndian>(encoding).unwrap();
+        cmap.write_u32::<BigEndian>(offset as u32).unwrap();
+        offset += subtable.len();
+    }
+    for &(_, _, ref subtable) in &subtables {
+        cmap.extend_from_slice(subtable);
+    }
+    cmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FontInfo;
+    use FontCollection;
+    use tables::CMAP;
+    use expectest::prelude::*;
+
+    fn render(font: &FontInfo, c: char) -> (Vec<u8>, isize, isize) {
+        let (mut w, mut h) = (0, 0);
+        unsafe {
+            let scale = font.scale_for_pixel_height(20.0);
+            let bitmap = ::get_codepoint_bitmap(font, scale, scale, c,
+                &mut w, &mut h, ::std::ptr::null_mut(), ::std::ptr::null_mut());
+            let pixels = ::std::slice::from_raw_parts(bitmap, (w * h) as usize).to_owned();
+            ::free_bitmap(bitmap);
+            (pixels, w, h)
+        }
+    }
+
+    #[test]
+    fn smoke() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+
+        let subset = subset(&font, &['A', 'b', 'л', '\u{10000}']).unwrap();
+        expect!(subset.original_glyph_indices())
+            .to(be_equal_to(&[GlyphId(0), GlyphId(36), GlyphId(69), GlyphId(487)][..]));
+        expect!(subset.glyph_index_for_original(GlyphId(69))).to(be_some().value(GlyphId(2)));
+        expect!(subset.glyph_index_for_original(GlyphId(68))).to(be_none());
+        expect!(subset.data().len() < data.len() / 4).to(be_equal_to(true));
+
+        // Valid checksums.
+        let collection = FontCollection::from_data(subset.data()).unwrap();
+        expect!(collection.validate_font_at_index(0)).to(be_ok().value(0));
+
+        // The first and last characters are those of the subset.
+        let os2 = ::utils::find_table_offset(subset.data(), 0, b"OS/2").unwrap().unwrap();
+        expect!(::utils::read_u16_at(subset.data(), os2 + 64)).to(be_ok().value(0x41));
+        expect!(::utils::read_u16_at(subset.data(), os2 + 66)).to(be_ok().value(0x43b));
+
+        let small = FontInfo::new_with_offset(subset.data(), 0).unwrap();
+        expect!(small.glyph_index_for_code('A')).to(be_equal_to(GlyphId(1)));
+        expect!(small.glyph_index_for_code('a')).to(be_equal_to(GlyphId(0)));
+        for &c in &['A', 'b', 'л'] {
+            expect!(render(&small, c)).to(be_equal_to(render(&font, c)));
+        }
+    }
+
+    #[test]
+    fn build_cmap_formats() {
+        let mapping = [(0x41, 1), (0x42, 2), (0x44, 3), (0x9000, 4), (0x1F600, 5), (0x1F601, 6)];
+        let cmap = build_cmap(&mapping);
+
+        // Format 12 is preferred when present.
+        let full = CMAP::from_data(&cmap, 0).unwrap();
+        for &(c, g) in &mapping {
+            expect!(full.index_for_code(c as usize)).to(be_some().value(g as usize));
+        }
+        expect!(full.index_for_code(0x43)).to(be_none());
+
+        // Format 4 only.
+        let bmp = build_cmap(&mapping[..4]);
+        let f4 = CMAP::from_data(&bmp, 0).unwrap();
+        for &(c, g) in &mapping[..4] {
+            expect!(f4.index_for_code(c as usize)).to(be_some().value(g as usize));
+        }
+        expect!(f4.index_for_code(0x43)).to(be_none());
+    }
+}
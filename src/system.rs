@@ -0,0 +1,185 @@
+//! Finding the fonts installed on the system, with the `system-fonts`
+//! feature.
+//!
+//! The font directories of the platform are searched directly, without
+//! asking fontconfig, DirectWrite or CoreText, so fonts configured in other
+//! places aren't found.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use collection::FontCollection;
+use tables::{MacStyle, NAME};
+use {FontInfo, OwnedFontInfo};
+
+/// Families tried by `find_default_font`, most common first.
+#[cfg(target_os = "windows")]
+const DEFAULT_FAMILIES: &[&str] = &["Segoe UI", "Tahoma", "Arial"];
+#[cfg(target_os = "macos")]
+const DEFAULT_FAMILIES: &[&str] = &["Helvetica Neue", "Helvetica", "Arial"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const DEFAULT_FAMILIES: &[&str] = &["DejaVu Sans", "Noto Sans", "Liberation Sans",
+    "Ubuntu", "Cantarell", "FreeSans"];
+
+/// Returns the directories fonts are installed in on this platform, the
+/// ones of the user first. Some of them may not exist.
+pub fn system_font_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    let home = env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        if let Some(local) = env::var_os("LOCALAPPDATA") {
+            dirs.push(Path::new(&local).join("Microsoft").join("Windows").join("Fonts"));
+        }
+        let windows = env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+        dirs.push(Path::new(&windows).join("Fonts"));
+    } else if cfg!(target_os = "macos") {
+        dirs.extend(home.map(|home| home.join("Library").join("Fonts")));
+        dirs.push(PathBuf::from("/Library/Fonts"));
+        dirs.push(PathBuf::from("/System/Library/Fonts"));
+    } else {
+        match env::var_os("XDG_DATA_HOME") {
+            Some(data) => dirs.push(Path::new(&data).join("fonts")),
+            None => dirs.extend(home.as_ref().map(|home| home.join(".local/share/fonts"))),
+        }
+        dirs.extend(home.map(|home| home.join(".fonts")));
+        dirs.push(PathBuf::from("/usr/local/share/fonts"));
+        dirs.push(PathBuf::from("/usr/share/fonts"));
+    }
+    dirs
+}
+
+/// Returns the path of an installed font of `family` with `style`, and the
+/// offset of the font in the file for `FontInfo::new_with_offset`.
+///
+/// Only `MacStyle::BOLD` and `MacStyle::ITALIC` of `style` are compared, the
+/// default style finds the regular font. Families are compared ignoring case
+/// with the family names of the `name` table. Only fonts with TrueType
+/// outlines are found.
+///
+/// Files are read until a font matches, those whose name looks like the
+/// family first, so it can take a while with many fonts installed.
+pub fn find_system_font(family: &str, style: MacStyle) -> Option<(PathBuf, usize)> {
+    find_font_in(&system_font_dirs(), family, style)
+}
+
+/// Same as `find_system_font`, but reads the font.
+pub fn load_system_font(family: &str, style: MacStyle) -> Option<OwnedFontInfo> {
+    find_system_font(family, style)
+        .and_then(|(path, offset)| fs::read(path).ok().map(|data| (data, offset)))
+        .and_then(|(data, offset)| FontInfo::from_vec(data, offset).ok())
+}
+
+/// Returns the first installed font of the families user interfaces of the
+/// platform use, e.g. Segoe UI on Windows, with `style`.
+pub fn find_default_font(style: MacStyle) -> Option<(PathBuf, usize)> {
+    let dirs = system_font_dirs();
+    DEFAULT_FAMILIES.iter().filter_map(|family| find_font_in(&dirs, family, style)).next()
+}
+
+fn find_font_in(dirs: &[PathBuf], family: &str, style: MacStyle) -> Option<(PathBuf, usize)> {
+    let mut files = vec![];
+    for dir in dirs {
+        font_files(dir, &mut files);
+    }
+    // Files are usually named after the family, without spaces.
+    let key: String = family.chars().filter(|c| !c.is_whitespace()).collect::<String>()
+        .to_lowercase();
+    let (likely, others): (Vec<_>, Vec<_>) = files.into_iter().partition(|path| {
+        path.file_stem().map_or(false, |stem| stem.to_string_lossy().to_lowercase().contains(&key))
+    });
+
+    // Fonts of another weight can have the family as the typographic
+    // family, they are only taken if no font has it as the family.
+    let mut typographic = None;
+    for path in likely.into_iter().chain(others) {
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+        let collection = match FontCollection::from_data(&data) {
+            Ok(collection) => collection,
+            Err(_) => continue,
+        };
+        // The checksum adjustments of collections may be 0, fonts that can't
+        // be read are skipped.
+        for offset in (0..collection.len()).filter_map(|i| collection.offset_for_font_at_index(i)) {
+            let font = match FontInfo::new_with_offset(&data, offset) {
+                Ok(font) => font,
+                Err(_) => continue,
+            };
+            let checked = MacStyle::BOLD | MacStyle::ITALIC;
+            if font.head().mac_style() & checked != style & checked {
+                continue;
+            }
+            if font.name().has_string(NAME::FAMILY, family) {
+                return Some((path, offset));
+            }
+            if typographic.is_none() && font.name().has_string(NAME::TYPOGRAPHIC_FAMILY, family) {
+                typographic = Some((path.clone(), offset));
+            }
+        }
+    }
+    typographic
+}
+
+/// Adds the font files in `dir` and its subdirectories to `files`.
+fn font_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            font_files(&path, files);
+            continue;
+        }
+        // Linked fonts are found, linked directories not, they may loop.
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_ref().map(|e| &e[..]) {
+            Some("ttf") | Some("ttc") | Some("otf") => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn find_in_dirs() {
+        let dir = env::temp_dir().join(format!("truetype-system-fonts-{}", ::std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested").join("Tuffy_Bold.ttf"),
+            ::utils::read_file("assets/Tuffy_Bold.ttf")).unwrap();
+        fs::write(dir.join("notes.txt"), b"not a font").unwrap();
+        // A link back up isn't followed.
+        #[cfg(unix)]
+        ::std::os::unix::fs::symlink(&dir, dir.join("nested").join("loop")).unwrap();
+        let dirs = [PathBuf::from("/nonexistent"), dir.clone()];
+
+        expect!(find_font_in(&dirs, "tuffy", MacStyle::BOLD))
+            .to(be_some().value((dir.join("nested").join("Tuffy_Bold.ttf"), 0)));
+        expect!(find_font_in(&dirs, "Tuffy", MacStyle::default())).to(be_none());
+        expect!(find_font_in(&dirs, "Tuffy", MacStyle::BOLD | MacStyle::ITALIC)).to(be_none());
+        expect!(find_font_in(&dirs, "Tuff", MacStyle::BOLD)).to(be_none());
+
+        // Fonts without a checksum adjustment are found too.
+        let mut data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let head = ::utils::find_table_offset(&data, 0, b"head").unwrap().unwrap();
+        data[head + 8..head + 12].copy_from_slice(&[0, 0, 0, 0]);
+        fs::write(dir.join("nested").join("Tuffy_Bold.ttf"), data).unwrap();
+        expect!(find_font_in(&dirs, "tuffy", MacStyle::BOLD))
+            .to(be_some().value((dir.join("nested").join("Tuffy_Bold.ttf"), 0)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+//! Tables shared by the Apple Advanced Typography tables `kerx` and `morx`.
+
+use prelude::*;
+use Error;
+use Result;
+use utils::{HeapSize, read_u16_at};
+
+/// A lookup table mapping glyphs to `u16` values.
+#[derive(Debug, Clone)]
+pub enum LookupTable {
+    /// The values of the glyphs from the first.
+    Array(u16, Vec<u16>),
+    /// The first and last glyph of the segments and the value of their
+    /// glyphs.
+    Segments(Vec<(u16, u16, u16)>),
+    /// The first and last glyph of the segments and the values of every
+    /// glyph in them.
+    SegmentArrays(Vec<(u16, u16, Vec<u16>)>),
+}
+
+impl LookupTable {
+    /// Reads the lookup table at `offset`, `glyphs` being the number of
+    /// glyphs of the font for simple arrays.
+    pub fn from_data(data: &[u8], offset: usize, glyphs: usize) -> Result<LookupTable> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let format = try!(u16_at(offset));
+        // Formats 2, 4 and 6 have units of a size after a header for binary
+        // searches, which may end with a unit of glyph 0xffff.
+        let units = || -> Result<Vec<usize>> {
+            let (unit_size, count) = (try!(u16_at(offset + 2)) as usize, try!(u16_at(offset + 4)));
+            let mut units = vec![];
+            for i in 0..count as usize {
+                let unit = offset + 12 + i * unit_size;
+                if try!(u16_at(unit)) != 0xffff {
+                    units.push(unit);
+                }
+            }
+            Ok(units)
+        };
+        match format {
+            0 => {
+                let mut values = Vec::with_capacity(glyphs);
+                for i in 0..glyphs {
+                    values.push(try!(u16_at(offset + 2 + i * 2)));
+                }
+                Ok(LookupTable::Array(0, values))
+            }
+            2 | 6 => {
+                let mut segments = vec![];
+                for unit in try!(units()) {
+                    // Single glyphs are segments of one glyph.
+                    segments.push(if format == 2 {
+                        (try!(u16_at(unit + 2)), try!(u16_at(unit)), try!(u16_at(unit + 4)))
+                    } else {
+                        (try!(u16_at(unit)), try!(u16_at(unit)), try!(u16_at(unit + 2)))
+                    });
+                }
+                Ok(LookupTable::Segments(segments))
+            }
+            4 => {
+                let mut segments = vec![];
+                for unit in try!(units()) {
+                    let (last, first) = (try!(u16_at(unit)), try!(u16_at(unit + 2)));
+                    let start = offset + try!(u16_at(unit + 4)) as usize;
+                    let mut values = vec![];
+                    for i in 0..(last.saturating_sub(first) as usize + 1) {
+                        values.push(try!(u16_at(start + i * 2)));
+                    }
+                    segments.push((first, last, values));
+                }
+                Ok(LookupTable::SegmentArrays(segments))
+            }
+            8 => {
+                let first = try!(u16_at(offset + 2));
+                let mut values = vec![];
+                for i in 0..try!(u16_at(offset + 4)) as usize {
+                    values.push(try!(u16_at(offset + 6 + i * 2)));
+                }
+                Ok(LookupTable::Array(first, values))
+            }
+            _ => Err(Error::Malformed),
+        }
+    }
+
+    /// Returns the glyphs the table has values for with their values, in
+    /// the order of the table.
+    pub fn entries(&self) -> Vec<(u16, u16)> {
+        match *self {
+            LookupTable::Array(first, ref values) => {
+                values.iter().enumerate().map(|(i, &value)| ((first as usize + i) as u16, value))
+                    .collect()
+            }
+            LookupTable::Segments(ref segments) => {
+                segments.iter()
+                    .flat_map(|&(first, last, value)| (first..=last).map(move |glyph| (glyph, value)))
+                    .collect()
+            }
+            LookupTable::SegmentArrays(ref segments) => {
+                segments.iter().flat_map(|&(first, _, ref values)| {
+                    values.iter().enumerate().map(move |(i, &value)| ((first as usize + i) as u16, value))
+                }).collect()
+            }
+        }
+    }
+
+    /// Returns the value of `glyph`, `None` if the table doesn't have one.
+    pub fn get(&self, glyph: usize) -> Option<u16> {
+        let contains = |first: u16, last: u16| first as usize <= glyph && glyph <= last as usize;
+        match *self {
+            LookupTable::Array(first, ref values) => {
+                glyph.checked_sub(first as usize).and_then(|i| values.get(i)).cloned()
+            }
+            LookupTable::Segments(ref segments) => {
+                segments.iter().find(|&&(first, last, _)| contains(first, last))
+                    .map(|&(_, _, value)| value)
+            }
+            LookupTable::SegmentArrays(ref segments) => {
+                segments.iter().find(|&&(first, last, _)| contains(first, last))
+                    .and_then(|&(first, _, ref values)| values.get(glyph - first as usize).cloned())
+            }
+        }
+    }
+}
+
+impl HeapSize for LookupTable {
+    fn heap_size(&self) -> usize {
+        match *self {
+            LookupTable::Array(_, ref values) => values.heap_size(),
+            LookupTable::Segments(ref segments) => segments.heap_size(),
+            LookupTable::SegmentArrays(ref segments) => segments.heap_size(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn lookup_tables() {
+        let simple = LookupTable::from_data(&[0, 0, 0, 5, 0, 6, 0, 7], 0, 3).unwrap();
+        expect!((simple.get(0), simple.get(2), simple.get(3))).to(be_equal_to((Some(5), Some(7), None)));
+
+        let trimmed = LookupTable::from_data(&[0, 8, 0, 10, 0, 2, 0, 1, 0, 2], 0, 0).unwrap();
+        expect!((trimmed.get(9), trimmed.get(11), trimmed.get(12))).to(be_equal_to((None, Some(2), None)));
+
+        // Glyphs 3 to 5 have the value 9, and the end of the search.
+        let segments = LookupTable::from_data(&[0, 2, 0, 6, 0, 2, 0, 12, 0, 1, 0, 0,
+            0, 5, 0, 3, 0, 9, 0xff, 0xff, 0xff, 0xff, 0, 0], 0, 0).unwrap();
+        expect!((segments.get(2), segments.get(3), segments.get(5), segments.get(0xffff)))
+            .to(be_equal_to((None, Some(9), Some(9), None)));
+
+        // Glyphs 3 and 4 have the values at 18.
+        let arrays = LookupTable::from_data(&[0, 4, 0, 6, 0, 1, 0, 6, 0, 0, 0, 0,
+            0, 4, 0, 3, 0, 18, 0, 7, 0, 8], 0, 0).unwrap();
+        expect!((arrays.get(3), arrays.get(4), arrays.get(5))).to(be_equal_to((Some(7), Some(8), None)));
+
+        let single = LookupTable::from_data(&[0, 6, 0, 4, 0, 2, 0, 8, 0, 1, 0, 0,
+            0, 4, 0, 40, 0, 9, 0, 90], 0, 0).unwrap();
+        expect!((single.get(4), single.get(9), single.get(5))).to(be_equal_to((Some(40), Some(90), None)));
+
+        expect!(LookupTable::from_data(&[0, 3, 0, 0, 0, 0], 0, 0).err()).to(be_some().value(Malformed));
+        expect!(LookupTable::from_data(&[0, 0, 0, 5], 0, 3).err()).to(be_some().value(Malformed));
+        expect!(LookupTable::from_data(&[0, 0, 0, 5], 0, 1).unwrap().get(0)).to(be_some().value(5));
+    }
+}
@@ -0,0 +1,179 @@
+use prelude::*;
+use Error;
+use Result;
+use utils::{HeapSize, read_tag_at, read_u16_at};
+
+/// The baselines of a script in a `BASE` table, in font units from the
+/// baseline the glyphs are drawn on.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ScriptBaselines {
+    /// The script, e.g. `latn`, `hani` or `deva`.
+    pub script: [u8; 4],
+    /// The baseline the script is aligned to, e.g. `BASE::HANGING` for
+    /// Devanagari.
+    pub default_baseline: [u8; 4],
+    /// The baselines of the script and their positions.
+    pub baselines: Vec<([u8; 4], i16)>,
+}
+
+impl ScriptBaselines {
+    /// Returns the position of the baseline `tag`, `None` if the font doesn't
+    /// give it for the script.
+    pub fn offset(&self, tag: &[u8; 4]) -> Option<i16> {
+        self.baselines.iter().find(|&&(t, _)| &t == tag).map(|&(_, offset)| offset)
+    }
+}
+
+/// A baseline table.
+///
+/// Aligns the glyphs of scripts with different baselines on a line, e.g.
+/// Latin letters sit on the roman baseline, Chinese characters on the
+/// ideographic one below it and Devanagari hangs from the hanging one.
+/// Only the baselines of the scripts are read, not the extents of their
+/// languages.
+#[derive(Debug, Default, Clone)]
+pub struct BASE {
+    horizontal: Vec<ScriptBaselines>,
+    vertical: Vec<ScriptBaselines>,
+}
+
+impl BASE {
+    /// The baseline of Latin, Greek and Cyrillic.
+    pub const ROMAN: [u8; 4] = *b"romn";
+    /// The bottom of the em box of ideographs.
+    pub const IDEOGRAPHIC: [u8; 4] = *b"ideo";
+    /// The top of the em box of ideographs.
+    pub const IDEOGRAPHIC_TOP: [u8; 4] = *b"idtp";
+    /// The line Devanagari, Bengali and Tibetan hang from.
+    pub const HANGING: [u8; 4] = *b"hang";
+    /// The center of math operators.
+    pub const MATH: [u8; 4] = *b"math";
+
+    /// Returns `BASE` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the baselines.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<BASE> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let _major_version = try!(u16_at(offset));
+        let axis = |position: usize| -> Result<Vec<ScriptBaselines>> {
+            match try!(u16_at(offset + position)) {
+                0 => Ok(vec![]),
+                axis => read_axis(data, offset + axis as usize),
+            }
+        };
+        Ok(BASE { horizontal: try!(axis(4)), vertical: try!(axis(6)) })
+    }
+
+    /// Returns the baselines of the scripts written horizontally.
+    pub fn horizontal(&self) -> &[ScriptBaselines] {
+        &self.horizontal
+    }
+
+    /// Returns the baselines of the scripts written vertically, which are
+    /// positions on the x axis.
+    pub fn vertical(&self) -> &[ScriptBaselines] {
+        &self.vertical
+    }
+
+    /// Returns the baselines of `script` written horizontally, `None` if
+    /// the font doesn't give them.
+    pub fn script(&self, script: &[u8; 4]) -> Option<&ScriptBaselines> {
+        self.horizontal.iter().find(|baselines| &baselines.script == script)
+    }
+}
+
+/// Reads the baselines of the scripts of the axis table at `offset`.
+fn read_axis(data: &[u8], offset: usize) -> Result<Vec<ScriptBaselines>> {
+    let u16_at = |i: usize| read_u16_at(data, i);
+    let tag_at = |i: usize| read_tag_at(data, i);
+
+    let tag_list = match try!(u16_at(offset)) {
+        0 => return Ok(vec![]),
+        tag_list => offset + tag_list as usize,
+    };
+    let mut tags = vec![];
+    for i in 0..try!(u16_at(tag_list)) as usize {
+        tags.push(try!(tag_at(tag_list + 2 + i * 4)));
+    }
+
+    let script_list = offset + try!(u16_at(offset + 2)) as usize;
+    let mut scripts = vec![];
+    for i in 0..try!(u16_at(script_list)) as usize {
+        let record = script_list + 2 + i * 6;
+        let script = try!(tag_at(record));
+        let base_script = script_list + try!(u16_at(record + 4)) as usize;
+        let values = match try!(u16_at(base_script)) {
+            0 => continue,
+            values => base_script + values as usize,
+        };
+        let default_index = try!(u16_at(values)) as usize;
+        let mut baselines = vec![];
+        for j in 0..try!(u16_at(values + 2)) as usize {
+            // All formats of BaseCoord start with the coordinate.
+            let coord = values + try!(u16_at(values + 4 + j * 2)) as usize;
+            let coordinate = try!(u16_at(coord + 2)) as i16;
+            let tag = try!(tags.get(j).ok_or(Error::Malformed));
+            baselines.push((*tag, coordinate));
+        }
+        scripts.push(ScriptBaselines {
+            script: script,
+            default_baseline: try!(tags.get(default_index).cloned().ok_or(Error::Malformed)),
+            baselines: baselines,
+        });
+    }
+    Ok(scripts)
+}
+
+impl HeapSize for ScriptBaselines {
+    fn heap_size(&self) -> usize {
+        self.baselines.heap_size()
+    }
+}
+
+impl HeapSize for BASE {
+    fn heap_size(&self) -> usize {
+        self.horizontal.heap_size() + self.vertical.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn baselines() {
+        let data = vec![
+            // Header with a horizontal axis.
+            0, 1, 0, 0, 0, 8, 0, 0,
+            // Axis at 8: tags at 12, scripts at 26.
+            0, 4, 0, 18,
+            0, 3, b'h', b'a', b'n', b'g', b'i', b'd', b'e', b'o', b'r', b'o', b'm', b'n',
+            // Scripts at 26: deva and latn.
+            0, 2, b'd', b'e', b'v', b'a', 0, 14, b'l', b'a', b't', b'n', 0, 46,
+            // BaseScript of deva at 40 with values at 46.
+            0, 6, 0, 0, 0, 0,
+            0, 0, 0, 3, 0, 10, 0, 14, 0, 18,
+            0, 1, 0x02, 0x58, 0, 1, 0xff, 0x88, 0, 2, 0, 0, 0, 0, 0, 0,
+            // BaseScript of latn at 72 without values.
+            0, 0, 0, 0, 0, 0,
+        ];
+        let base = BASE::from_data(&data, 0).unwrap();
+        expect!(base.vertical().is_empty()).to(be_true());
+        expect!(base.horizontal().len()).to(be_equal_to(1));
+        let deva = base.script(b"deva").unwrap();
+        expect!(deva.default_baseline).to(be_equal_to(BASE::HANGING));
+        expect!(deva.offset(&BASE::HANGING)).to(be_some().value(600));
+        expect!(deva.offset(&BASE::IDEOGRAPHIC)).to(be_some().value(-120));
+        expect!(deva.offset(&BASE::ROMAN)).to(be_some().value(0));
+        expect!(deva.offset(&BASE::MATH)).to(be_none());
+        expect!(base.script(b"latn")).to(be_none());
+
+        expect!(BASE::from_data(&data[..50], 0)).to(be_err().value(Malformed));
+        expect!(BASE::from_data(&data, data.len())).to(be_err().value(Malformed));
+    }
+}
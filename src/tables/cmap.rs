@@ -1,19 +1,55 @@
 
+use prelude::*;
 use Error;
 use Result;
-use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
+use byteorder::{BigEndian, ByteOrder};
+use core::ops::Range;
+use lazy::Lazy;
+use utils::{HeapSize, WriteBytesExt};
 use utils::{read_u16_from_raw_data, read_i16_from_raw_data};
+use super::Serialize;
 
-#[derive(Debug)]
-pub struct CMAP {
+/// A character code mapping table.
+///
+/// The `cmap` table maps character codes to glyph indices.
+///
+/// The table borrows its data from the font file, use `into_owned` to detach
+/// it from the file, or `without_data` if the file is kept anyway.
+#[derive(Debug, Clone)]
+pub struct CMAP<'a> {
+    // The data the table was read from and the offset of the format of the
+    // selected encoding subtable in it.
+    data: Cow<'a, [u8]>,
+    offset: usize,
     encoding_subtable: EncodingSubtable,
     format: Format,
+    // The glyphs of the ASCII characters, looked up the first time one is
+    // needed so that Latin text doesn't search the segments of the format
+    // every time.
+    ascii: Lazy<Vec<u16>>,
 }
 
-/// A character code mapping table.
-///
-/// The `cmap` table maps character codes to glyph indices.
-impl CMAP {
+// Marks ASCII characters that are looked up in the format, those without a
+// glyph or with one beyond `u16`.
+const NOT_CACHED: u16 = 0xffff;
+
+/// A table that maps no characters.
+impl<'a> Default for CMAP<'a> {
+    fn default() -> Self {
+        CMAP {
+            data: Cow::Borrowed(&[]),
+            offset: 0,
+            encoding_subtable: EncodingSubtable {
+                platform: Platform::Microsoft(MicrosoftEncodingId::UnicodeUCS4),
+                offset: 12,
+            },
+            format: Format::F1213(Format1213 { format: 12, length: 16, ..Format1213::default() }),
+            ascii: Lazy::new(Some(0)),
+        }
+    }
+}
+
+impl<'a> CMAP<'a> {
     /// Returns `cmap` font table.
     ///
     /// Attempts to read `data` starting from `offset` position.
@@ -23,7 +59,7 @@ impl CMAP {
     /// - if there is not enough data to read
     /// - if encoding subtable is not supported
     /// - if format is not supported
-    pub fn from_data(data: &[u8], offset: usize) -> Result<Self> {
+    pub fn from_data(data: &'a [u8], offset: usize) -> Result<CMAP<'a>> {
 
         if offset >= data.len() || offset + 4 > data.len() {
             return Err(Error::Malformed);
@@ -32,7 +68,7 @@ impl CMAP {
         // +2 skip version field.
         let number_subtables = BigEndian::read_u16(&data[offset + 2..]) as usize;
         let subtables_data = &data[offset + 4..];
-        if number_subtables * (2 + 2 + 4) > data.len() {
+        if number_subtables * (2 + 2 + 4) > subtables_data.len() {
             return Err(Error::Malformed);
         }
 
@@ -53,17 +89,132 @@ impl CMAP {
         }
 
         let encoding_subtable = encoding_subtables.first().unwrap().clone();
-        let format = try!(Format::from_data(data, offset + encoding_subtable.offset as usize));
-
+        let offset = offset + encoding_subtable.offset as usize;
+        let format = try!(Format::from_data(data, offset));
         Ok(CMAP {
+            data: Cow::Borrowed(data),
+            offset: offset,
             encoding_subtable: encoding_subtable,
             format: format,
+            ascii: Lazy::new(Some(0)),
         })
     }
 
     /// Returns an index for character `code` in a `loca` font table.
+    ///
+    /// ASCII characters are looked up in a table built the first time one
+    /// is asked for.
     pub fn index_for_code(&self, code: usize) -> Option<usize> {
-        self.format.index_for_code(code)
+        self.index_for_code_in(&self.data, code)
+    }
+
+    /// Same as `index_for_code`, but for a table returned by `without_data`,
+    /// `data` is the data it was read from.
+    pub fn index_for_code_in(&self, data: &[u8], code: usize) -> Option<usize> {
+        let subtable = self.subtable(data);
+        if code < 128 {
+            let ascii = self.ascii.get(|_| Ok((0..128).map(|code| {
+                match self.format.index_for_code(subtable, code) {
+                    Some(index) if index < NOT_CACHED as usize => index as u16,
+                    _ => NOT_CACHED,
+                }
+            }).collect()));
+            if ascii[code] != NOT_CACHED {
+                return Some(ascii[code] as usize);
+            }
+        }
+        self.format.index_for_code(subtable, code)
+    }
+
+    /// Returns the sorted, non-overlapping ranges of character codes the
+    /// table may map, with both ends included.
+    ///
+    /// Codes in the ranges can still map to no glyph or glyph 0.
+    pub fn code_ranges(&self) -> Vec<(u32, u32)> {
+        self.code_ranges_in(&self.data)
+    }
+
+    fn code_ranges_in(&self, data: &[u8]) -> Vec<(u32, u32)> {
+        let mut ranges = self.format.code_ranges(self.subtable(data));
+        ranges.sort();
+        let mut next = 0;
+        ranges.into_iter().filter_map(|(start, end)| {
+            let start = ::core::cmp::max(start, next);
+            if start > end {
+                return None;
+            }
+            next = end.saturating_add(1);
+            Some((start, end))
+        }).collect()
+    }
+
+    /// Returns the characters the table maps to a glyph other than the
+    /// missing glyph, in ascending order.
+    pub fn codes<'b>(&'b self) -> impl Iterator<Item = char> + 'b {
+        self.codes_in(&self.data)
+    }
+
+    /// Same as `codes`, but for a table returned by `without_data`, `data`
+    /// is the data it was read from.
+    pub fn codes_in<'b>(&'b self, data: &'b [u8]) -> impl Iterator<Item = char> + 'b {
+        self.code_ranges_in(data).into_iter()
+            .flat_map(|(start, end)| start..=end)
+            .filter(move |&code| self.index_for_code_in(data, code as usize).unwrap_or(0) != 0)
+            .filter_map(::core::char::from_u32)
+    }
+
+    /// Returns a copy of the table that owns its data.
+    ///
+    /// Only the selected encoding subtable is copied, and nothing if the
+    /// table already owns its data.
+    pub fn into_owned(self) -> CMAP<'static> {
+        let (data, offset) = match self.data {
+            Cow::Owned(data) => (data, self.offset),
+            Cow::Borrowed(data) => {
+                let subtable = data.get(self.offset..).unwrap_or(&[]);
+                let size = ::core::cmp::min(self.format.size(), subtable.len());
+                (subtable[..size].to_vec(), 0)
+            }
+        };
+        CMAP {
+            data: Cow::Owned(data),
+            offset: offset,
+            encoding_subtable: self.encoding_subtable,
+            format: self.format,
+            ascii: self.ascii,
+        }
+    }
+
+    /// Returns the table without the data it was read from, for callers that
+    /// keep the data anyway, like `FontInfo`. Codes must then be looked up
+    /// with `index_for_code_in` and `codes_in`, passing that data.
+    pub fn without_data(self) -> CMAP<'static> {
+        CMAP {
+            data: Cow::Borrowed(&[]),
+            offset: self.offset,
+            encoding_subtable: self.encoding_subtable,
+            format: self.format,
+            ascii: self.ascii,
+        }
+    }
+
+    fn subtable<'b>(&self, data: &'b [u8]) -> &'b [u8] {
+        data.get(self.offset..).unwrap_or(&[])
+    }
+}
+
+/// Only the selected encoding subtable is written.
+impl<'a> Serialize for CMAP<'a> {
+    fn bytes(&self) -> Vec<u8> {
+        let (platform_id, platform_specific_id) = self.encoding_subtable.platform.ids();
+        let mut data = vec![];
+        data.write_u16::<BigEndian>(0).unwrap(); // version
+        data.write_u16::<BigEndian>(1).unwrap();
+        data.write_u16::<BigEndian>(platform_id).unwrap();
+        data.write_u16::<BigEndian>(platform_specific_id).unwrap();
+        data.write_u32::<BigEndian>(4 + 8).unwrap();
+        data.extend_from_slice(&self.format.bytes(self.subtable(&self.data)));
+        data
     }
 }
 
@@ -128,6 +279,13 @@ impl Platform {
             _ => None,
         }
     }
+
+    fn ids(&self) -> (u16, u16) {
+        match *self {
+            Platform::Unicode(id) => (0, id as u16),
+            Platform::Microsoft(id) => (3, id as u16),
+        }
+    }
 }
 
 #[repr(u16)]
@@ -153,7 +311,10 @@ enum MicrosoftEncodingId {
     UnicodeUCS4 = 10
 }
 
-#[derive(Debug)]
+// The formats keep the ranges of their arrays in the data of the subtable,
+// which is passed to their methods, so that a `CMAP` can be used without
+// borrowing or copying the font data.
+#[derive(Debug, Clone)]
 enum Format {
     F0(Format0),
     F4(Format4),
@@ -162,70 +323,126 @@ enum Format {
 }
 
 impl Format {
-    fn from_data(data: &[u8], offset: usize) -> Result<Self> {
+    fn from_data(data: &[u8], offset: usize) -> Result<Format> {
         use self::Format::*;
         if offset + 2 > data.len() {
             return Err(Error::Malformed);
         }
 
-        let format = BigEndian::read_u16(&data[offset..]);
+        let data = &data[offset..];
+        let format = BigEndian::read_u16(data);
         match format {
-            0 => Ok(F0(try!(Format0::from_data(data, offset)))),
-            4 => Ok(F4(try!(Format4::from_data(data, offset)))),
-            6 => Ok(F6(try!(Format6::from_data(data, offset)))),
-            12 | 13 => Ok(F1213(try!(Format1213::from_data(data, offset)))),
+            0 => Ok(F0(try!(Format0::from_data(data)))),
+            4 => Ok(F4(try!(Format4::from_data(data)))),
+            6 => Ok(F6(try!(Format6::from_data(data)))),
+            12 | 13 => Ok(F1213(try!(Format1213::from_data(data, 0)))),
             _ => Err(Error::CMAPFormatIsNotSupported),
         }
     }
 
-    fn index_for_code(&self, code: usize) -> Option<usize> {
+    fn index_for_code(&self, data: &[u8], code: usize) -> Option<usize> {
         use self::Format::*;
         match *self {
-            F0(ref f) => f.index_for_code(code),
-            F4(ref f) => f.index_for_code(code),
-            F6(ref f) => f.index_for_code(code),
+            F0(ref f) => f.index_for_code(data, code),
+            F4(ref f) => f.index_for_code(data, code),
+            F6(ref f) => f.index_for_code(data, code),
             F1213(ref f) => f.index_for_code(code),
         }
     }
+
+    fn code_ranges(&self, data: &[u8]) -> Vec<(u32, u32)> {
+        use self::Format::*;
+        match *self {
+            F0(ref f) => vec![(0, f.glyph_index_array.len() as u32 - 1)],
+            F4(ref f) => (0..f.seg_count()).filter_map(|i| f.segment_at_index(data, i))
+                .filter(|s| s.start_code <= s.end_code && s.start_code < 0xffff)
+                .map(|s| (s.start_code as u32, ::core::cmp::min(s.end_code, 0xfffe) as u32))
+                .collect(),
+            F6(ref f) if f.entry_count > 0 => {
+                vec![(f.first_code as u32, f.first_code as u32 + f.entry_count as u32 - 1)]
+            }
+            F6(_) => vec![],
+            // Groups can reach beyond the last Unicode character.
+            F1213(ref f) => f.groups.iter()
+                .filter(|g| g.start_char_code <= g.end_char_code && g.start_char_code <= 0x10ffff)
+                .map(|g| (g.start_char_code, ::core::cmp::min(g.end_char_code, 0x10ffff)))
+                .collect(),
+        }
+    }
+
+    fn bytes(&self, data: &[u8]) -> Vec<u8> {
+        use self::Format::*;
+        match *self {
+            F0(ref f) => f.bytes(data),
+            F4(ref f) => f.bytes(data),
+            F6(ref f) => f.bytes(data),
+            F1213(ref f) => f.bytes(),
+        }
+    }
+
+    /// Returns the size of the data of the subtable the format needs.
+    fn size(&self) -> usize {
+        use self::Format::*;
+        match *self {
+            F0(ref f) => f.glyph_index_array.end,
+            F4(ref f) => f.glyph_index_array.end,
+            F6(ref f) => f.raw_glyph_index_array.end,
+            F1213(_) => 0,
+        }
+    }
 }
 
-#[derive(Debug)]
+/// Returns the bytes of `data` in `range`, empty if they are out of bounds.
+fn slice<'a>(data: &'a [u8], range: &Range<usize>) -> &'a [u8] {
+    data.get(range.clone()).unwrap_or(&[])
+}
+
+#[derive(Debug, Clone)]
 struct Format0 {
     format: u16,
     length: u16,
     language: u16,
-    glyph_index_array: Vec<u8>,
+    glyph_index_array: Range<usize>,
 }
 
 impl Format0 {
-    fn from_data(data: &[u8], offset: usize) -> Result<Self> {
+    fn from_data(data: &[u8]) -> Result<Format0> {
         const SIZE: usize = 262;
-        if offset + SIZE > data.len() {
+        if SIZE > data.len() {
             return Err(Error::Malformed);
         }
 
-        let format = BigEndian::read_u16(&data[offset..]);
-        let length = BigEndian::read_u16(&data[offset + 2..]);
+        let format = BigEndian::read_u16(data);
+        let length = BigEndian::read_u16(&data[2..]);
 
         if length as usize != SIZE {
             return Err(Error::Malformed);
         }
-        let language = BigEndian::read_u16(&data[offset + 4..]);
+        let language = BigEndian::read_u16(&data[4..]);
 
         Ok(Format0 {
             format: format,
             length: length,
             language: language,
-            glyph_index_array: data[offset + 6..SIZE].to_owned(),
+            glyph_index_array: 6..SIZE,
         })
     }
 
-    fn index_for_code(&self, code: usize) -> Option<usize> {
-        self.glyph_index_array.get(code).map(|&i| i as usize)
+    fn index_for_code(&self, data: &[u8], code: usize) -> Option<usize> {
+        slice(data, &self.glyph_index_array).get(code).map(|&i| i as usize)
+    }
+
+    fn bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.write_u16::<BigEndian>(self.format).unwrap();
+        bytes.write_u16::<BigEndian>(self.length).unwrap();
+        bytes.write_u16::<BigEndian>(self.language).unwrap();
+        bytes.extend_from_slice(slice(data, &self.glyph_index_array));
+        bytes
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct Format4 {
     format: u16,
     length: u16,
@@ -234,21 +451,21 @@ struct Format4 {
     search_range: u16,
     entry_selector: u16,
     range_shift: u16,
-    end_code: Vec<u8>,
+    end_code: Range<usize>,
     reserved_pad: u16,
-    start_code: Vec<u8>,
-    id_delta: Vec<u8>,
-    id_range_offset: Vec<u8>,
-    glyph_index_array: Vec<u8>,
+    start_code: Range<usize>,
+    id_delta: Range<usize>,
+    id_range_offset: Range<usize>,
+    glyph_index_array: Range<usize>,
 }
 
 impl Format4 {
-    fn from_data(data: &[u8], offset: usize) -> Result<Self> {
-        if offset + 2 * 8 > data.len() {
+    fn from_data(data: &[u8]) -> Result<Format4> {
+        if 2 * 8 > data.len() {
             return Err(Error::Malformed);
         }
 
-        let mut z = offset;
+        let mut z = 0;
         let mut f = Format4::default();
         f.format = BigEndian::read_u16(&data[z..]);
         z += 2;
@@ -270,45 +487,54 @@ impl Format4 {
         if (f.length as usize) < 2 * 8 + f.seg_count_x2 as usize * 4 {
             return Err(Error::Malformed);
         }
+        let end = f.length as usize;
+        if end > data.len() {
+            return Err(Error::Malformed);
+        }
 
-        f.end_code = data[z..z + f.seg_count_x2 as usize].to_owned();
-        z += f.seg_count_x2 as usize;
+        let size = f.seg_count_x2 as usize;
+        f.end_code = z..z + size;
+        z += size;
         f.reserved_pad = BigEndian::read_u16(&data[z..]);
         z += 2;
-        f.start_code = data[z..z + f.seg_count_x2 as usize].to_owned();
-        z += f.seg_count_x2 as usize;
-        f.id_delta = data[z..z + f.seg_count_x2 as usize].to_owned();
-        z += f.seg_count_x2 as usize;
-        f.id_range_offset = data[z..z + f.seg_count_x2 as usize].to_owned();
-        z += f.seg_count_x2 as usize;
-        f.glyph_index_array = data[z..z + f.length as usize].to_owned();
+        f.start_code = z..z + size;
+        z += size;
+        f.id_delta = z..z + size;
+        z += size;
+        f.id_range_offset = z..z + size;
+        z += size;
+        f.glyph_index_array = z..end;
 
         Ok(f)
     }
 
-    fn index_for_code(&self, code: usize) -> Option<usize> {
+    fn index_for_code(&self, data: &[u8], code: usize) -> Option<usize> {
         if code >= 0xffff {
             return None;
         }
 
+        let end_code = slice(data, &self.end_code);
         let mut r = (None, None); // Just to reduce indentation.
-        for i in 0..self.end_code.len() / 2 {
-            if BigEndian::read_u16(&self.end_code[i * 2..]) as usize >= code {
-                r = (self.segment_at_index(i), Some(i));
+        for i in 0..end_code.len() / 2 {
+            if BigEndian::read_u16(&end_code[i * 2..]) as usize >= code {
+                r = (self.segment_at_index(data, i), Some(i));
                 break;
             }
         }
 
         if let (Some(s), Some(i)) = r {
             if s.start_code <= code {
+                // All arithmetic on `id_delta` is modulo 65536.
                 if s.id_range_offset == 0 {
-                    // TODO: Investigate, probably should be: (id_delta + code) % 0xffff
-                    return Some((s.id_delta + code as isize) as usize);
+                    return Some(((s.id_delta + code as isize) & 0xffff) as usize);
                 }
-                let index = s.id_range_offset / 2 + (code - s.start_code) - (self.seg_count() - i);
-                if let Some(glyph_id) = read_u16_from_raw_data(&self.glyph_index_array, index) {
+                let index = (s.id_range_offset / 2 + (code - s.start_code))
+                    .checked_sub(self.seg_count() - i);
+                if let Some(glyph_id) = index.and_then(|index| {
+                    read_u16_from_raw_data(slice(data, &self.glyph_index_array), index)
+                }) {
                     if glyph_id != 0 {
-                        return Some((glyph_id as isize + s.id_delta) as usize);
+                        return Some(((glyph_id as isize + s.id_delta) & 0xffff) as usize);
                     }
                 }
             }
@@ -317,15 +543,33 @@ impl Format4 {
         None
     }
 
+    fn bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.write_u16::<BigEndian>(self.format).unwrap();
+        bytes.write_u16::<BigEndian>(self.length).unwrap();
+        bytes.write_u16::<BigEndian>(self.language).unwrap();
+        bytes.write_u16::<BigEndian>(self.seg_count_x2).unwrap();
+        bytes.write_u16::<BigEndian>(self.search_range).unwrap();
+        bytes.write_u16::<BigEndian>(self.entry_selector).unwrap();
+        bytes.write_u16::<BigEndian>(self.range_shift).unwrap();
+        bytes.extend_from_slice(slice(data, &self.end_code));
+        bytes.write_u16::<BigEndian>(self.reserved_pad).unwrap();
+        bytes.extend_from_slice(slice(data, &self.start_code));
+        bytes.extend_from_slice(slice(data, &self.id_delta));
+        bytes.extend_from_slice(slice(data, &self.id_range_offset));
+        bytes.extend_from_slice(slice(data, &self.glyph_index_array));
+        bytes
+    }
+
     fn seg_count(&self) -> usize {
         self.seg_count_x2 as usize / 2
     }
 
-    fn segment_at_index(&self, i: usize) -> Option<Format4Segment> {
-        let s = read_u16_from_raw_data(&self.start_code, i);
-        let e = read_u16_from_raw_data(&self.end_code, i);
-        let d = read_i16_from_raw_data(&self.id_delta, i);
-        let r = read_u16_from_raw_data(&self.id_range_offset, i);
+    fn segment_at_index(&self, data: &[u8], i: usize) -> Option<Format4Segment> {
+        let s = read_u16_from_raw_data(slice(data, &self.start_code), i);
+        let e = read_u16_from_raw_data(slice(data, &self.end_code), i);
+        let d = read_i16_from_raw_data(slice(data, &self.id_delta), i);
+        let r = read_u16_from_raw_data(slice(data, &self.id_range_offset), i);
         if let (Some(s), Some(e), Some(d), Some(r)) = (s, e, d, r) {
             Some(Format4Segment {
                 start_code: s as usize,
@@ -347,30 +591,30 @@ struct Format4Segment {
     id_range_offset: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Format6 {
     format: u16,
     length: u16,
     language: u16,
     first_code: u16,
     entry_count: u16,
-    raw_glyph_index_array: Vec<u8>,
+    raw_glyph_index_array: Range<usize>,
 }
 
 impl Format6 {
-    fn from_data(data: &[u8], offset: usize) -> Result<Self> {
-        if offset + 2 * 5 > data.len() {
+    fn from_data(data: &[u8]) -> Result<Format6> {
+        if 2 * 5 > data.len() {
             return Err(Error::Malformed);
         }
 
-        let format = BigEndian::read_u16(&data[offset..]);
-        let length = BigEndian::read_u16(&data[offset + 2..]);
-        let language = BigEndian::read_u16(&data[offset + 4..]);
-        let first_code = BigEndian::read_u16(&data[offset + 6..]);
-        let entry_count = BigEndian::read_u16(&data[offset + 8..]);
+        let format = BigEndian::read_u16(data);
+        let length = BigEndian::read_u16(&data[2..]);
+        let language = BigEndian::read_u16(&data[4..]);
+        let first_code = BigEndian::read_u16(&data[6..]);
+        let entry_count = BigEndian::read_u16(&data[8..]);
 
         let size = entry_count as usize * 2;
-        if offset + 2 * 5 + size > data.len() {
+        if 2 * 5 + size > data.len() {
             return Err(Error::Malformed);
         }
 
@@ -380,24 +624,36 @@ impl Format6 {
             language: language,
             first_code: first_code,
             entry_count: entry_count,
-            raw_glyph_index_array: data[offset + 2 * 5..size].to_owned(),
+            raw_glyph_index_array: 2 * 5..2 * 5 + size,
         })
     }
 
-    fn index_for_code(&self, code: usize) -> Option<usize> {
+    fn index_for_code(&self, data: &[u8], code: usize) -> Option<usize> {
         let first_code = self.first_code as usize;
         let entry_count = self.entry_count as usize;
+        let raw_glyph_index_array = slice(data, &self.raw_glyph_index_array);
         if code < first_code || code >= first_code + entry_count {
             None
         } else {
             let offset = (code - first_code) * 2;
-            if offset >= self.raw_glyph_index_array.len() {
+            if offset >= raw_glyph_index_array.len() {
                 None
             } else {
-                Some(BigEndian::read_u16(&self.raw_glyph_index_array[offset..]) as usize)
+                Some(BigEndian::read_u16(&raw_glyph_index_array[offset..]) as usize)
             }
         }
     }
+
+    fn bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.write_u16::<BigEndian>(self.format).unwrap();
+        bytes.write_u16::<BigEndian>(self.length).unwrap();
+        bytes.write_u16::<BigEndian>(self.language).unwrap();
+        bytes.write_u16::<BigEndian>(self.first_code).unwrap();
+        bytes.write_u16::<BigEndian>(self.entry_count).unwrap();
+        bytes.extend_from_slice(slice(data, &self.raw_glyph_index_array));
+        bytes
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -407,7 +663,7 @@ struct GroupFormat1213 {
     start_glyph_code: u32,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct Format1213 {
     format: u32,
     length: u32,
@@ -428,7 +684,7 @@ impl Format1213 {
         f.language = BigEndian::read_u32(&data[offset + 8..]);
         f.n_groups = BigEndian::read_u32(&data[offset + 12..]);
 
-        if offset + f.n_groups as usize * 12 > data.len() {
+        if offset + 4 * 4 + f.n_groups as usize * 12 > data.len() {
             return Err(Error::Malformed);
         }
 
@@ -449,7 +705,7 @@ impl Format1213 {
     }
 
     fn index_for_code(&self, code: usize) -> Option<usize> {
-        use std::cmp::Ordering::*;
+        use core::cmp::Ordering::*;
 
         let group = self.groups.binary_search_by(|group| {
             if code < group.start_char_code as usize {
@@ -469,6 +725,30 @@ impl Format1213 {
             }
         })
     }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut data = vec![];
+        data.write_u32::<BigEndian>(self.format).unwrap();
+        data.write_u32::<BigEndian>(self.length).unwrap();
+        data.write_u32::<BigEndian>(self.language).unwrap();
+        data.write_u32::<BigEndian>(self.n_groups).unwrap();
+        for group in &self.groups {
+            data.write_u32::<BigEndian>(group.start_char_code).unwrap();
+            data.write_u32::<BigEndian>(group.end_char_code).unwrap();
+            data.write_u32::<BigEndian>(group.start_glyph_code).unwrap();
+        }
+        data
+    }
+}
+
+impl<'a> HeapSize for CMAP<'a> {
+    fn heap_size(&self) -> usize {
+        let groups = match self.format {
+            Format::F1213(ref f) => f.groups.capacity() * ::core::mem::size_of::<GroupFormat1213>(),
+            _ => 0,
+        };
+        self.data.heap_size() + groups + self.ascii.heap_size()
+    }
 }
 
 #[cfg(test)]
@@ -478,12 +758,89 @@ mod tests {
 
     #[test]
     fn smoke() {
-        let data = ::utils::read_file("tests/Tuffy_Bold.ttf");
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
         let offset = ::utils::find_table_offset(&data, 0, b"cmap").unwrap().unwrap();
 
         let cmap = CMAP::from_data(&data, offset).unwrap();
 
         expect!(cmap.index_for_code('a' as usize)).to(be_some().value(68));
         expect!(cmap.index_for_code('л' as usize)).to(be_some().value(487));
+        for code in 0..256 {
+            let index = cmap.format.index_for_code(cmap.subtable(&data), code);
+            expect!(cmap.index_for_code(code)).to(be_equal_to(index));
+        }
+
+        let codes: Vec<_> = cmap.codes().collect();
+        expect!(codes.contains(&'a')).to(be_true());
+        expect!(codes.contains(&'л')).to(be_true());
+        expect!(codes.windows(2).all(|w| w[0] < w[1])).to(be_true());
+        let mapped = (0..0x110000).filter(|&code| cmap.index_for_code(code).unwrap_or(0) != 0);
+        expect!(codes.len()).to(be_equal_to(mapped.count()));
+
+        let copy = CMAP::from_data(&cmap.bytes(), 0).unwrap().into_owned();
+        for code in 0..0x10000 {
+            expect!(copy.index_for_code(code)).to(be_equal_to(cmap.index_for_code(code)));
+        }
+
+        // Only the subtable is copied, and a table that owns its data keeps it.
+        let owned = cmap.clone().into_owned();
+        expect!(owned.heap_size() < data.len() / 10).to(be_true());
+        let bytes = owned.data.as_ptr();
+        expect!(owned.into_owned().data.as_ptr()).to(be_equal_to(bytes));
+
+        // The glyphs of ASCII characters are kept once one is looked up.
+        let detached = CMAP::from_data(&data, offset).unwrap().without_data();
+        expect!(detached.heap_size()).to(be_equal_to(0));
+        for code in 0..0x10000 {
+            expect!(detached.index_for_code_in(&data, code)).to(be_equal_to(cmap.index_for_code(code)));
+        }
+        expect!(detached.heap_size()).to(be_equal_to(128 * 2));
+        expect!(detached.codes_in(&data).eq(cmap.codes())).to(be_true());
+    }
+
+    #[test]
+    fn groups_beyond_unicode() {
+        let mut data = vec![];
+        for &value in &[0, 1, 3, 10] {
+            data.write_u16::<BigEndian>(value).unwrap();
+        }
+        data.write_u32::<BigEndian>(12).unwrap();
+        for &value in &[12 << 16, 16 + 2 * 12, 0, 2, 0x10fff0, 0xffffffff, 5, 0x110000, 0x110010, 5] {
+            data.write_u32::<BigEndian>(value).unwrap();
+        }
+        let cmap = CMAP::from_data(&data, 0).unwrap();
+        expect!(cmap.code_ranges()).to(be_equal_to(vec![(0x10fff0, 0x10ffff)]));
+        expect!(cmap.codes().count()).to(be_equal_to(16));
+    }
+
+    #[test]
+    fn unknown_platforms() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let offset = ::utils::find_table_offset(&data, 0, b"cmap").unwrap().unwrap();
+        let cmap = CMAP::from_data(&data, offset).unwrap();
+        let format = cmap.format.bytes(cmap.subtable(&data));
+
+        // Subtables of unknown platforms and encodings are skipped.
+        let table = |ids: &[(u16, u16)]| {
+            let mut table = vec![];
+            table.write_u16::<BigEndian>(0).unwrap();
+            table.write_u16::<BigEndian>(ids.len() as u16).unwrap();
+            for &(platform_id, platform_specific_id) in ids {
+                table.write_u16::<BigEndian>(platform_id).unwrap();
+                table.write_u16::<BigEndian>(platform_specific_id).unwrap();
+                table.write_u32::<BigEndian>(4 + 8 * ids.len() as u32).unwrap();
+            }
+            table.extend_from_slice(&format);
+            table
+        };
+        let data = table(&[(7, 0), (3, 99), (0, 3), (0xffff, 0xffff)]);
+        let copy = CMAP::from_data(&data, 0).unwrap();
+        expect!(copy.encoding_subtable.platform.ids()).to(be_equal_to((0, 3)));
+        expect!(copy.index_for_code('a' as usize)).to(be_some().value(68));
+
+        expect!(CMAP::from_data(&table(&[(7, 0), (3, 99)]), 0))
+            .to(be_err().value(Error::CMAPEncodingSubtableIsNotSupported));
+        expect!(Platform::new(0, 7)).to(be_none());
+        expect!(Platform::new(3, 10)).to(be_some().value(Platform::Microsoft(MicrosoftEncodingId::UnicodeUCS4)));
     }
 }
@@ -0,0 +1,291 @@
+//! Tables shared by `GDEF`, `GPOS` and `GSUB`.
+
+use prelude::*;
+use Error;
+use Result;
+use utils::{HeapSize, read_tag_at, read_u16_at, read_u32_at};
+
+/// The features of a `GPOS` or `GSUB` table with the indices of their
+/// lookups.
+#[derive(Debug, Default, Clone)]
+pub struct FeatureList(Vec<([u8; 4], Vec<u16>)>);
+
+impl FeatureList {
+    pub fn from_data(data: &[u8], offset: usize) -> Result<FeatureList> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let mut features = vec![];
+        for i in 0..try!(u16_at(offset)) as usize {
+            let record = offset + 2 + i * 6;
+            let feature = offset + try!(u16_at(record + 4)) as usize;
+            let mut lookups = vec![];
+            for j in 0..try!(u16_at(feature + 2)) as usize {
+                lookups.push(try!(u16_at(feature + 4 + j * 2)));
+            }
+            features.push((try!(read_tag_at(data, record)), lookups));
+        }
+        Ok(FeatureList(features))
+    }
+
+    /// Returns the tags of the features in order.
+    pub fn tags(&self) -> Vec<[u8; 4]> {
+        self.0.iter().map(|&(tag, _)| tag).collect()
+    }
+
+    /// Returns the indices of the lookups of `features` in the order they
+    /// are applied.
+    pub fn lookups(&self, features: &[[u8; 4]]) -> Vec<u16> {
+        let mut lookups: Vec<u16> = self.0.iter()
+            .filter(|&&(ref tag, _)| features.contains(tag))
+            .flat_map(|&(_, ref lookups)| lookups.iter().cloned())
+            .collect();
+        lookups.sort();
+        lookups.dedup();
+        lookups
+    }
+}
+
+/// Reads the subtables of the lookups of the lookup list at `offset`, with
+/// `read` taking the type and the start of a subtable and returning `None`
+/// for the types it doesn't know.
+///
+/// Subtables of lookups of the type `extension` are replaced by the
+/// subtables they point to.
+pub fn read_lookups<T, F>(data: &[u8], offset: usize, extension: u16, read: F)
+    -> Result<Vec<Vec<T>>> where F: Fn(u16, usize) -> Result<Option<T>>
+{
+    let u16_at = |i: usize| read_u16_at(data, i);
+    let mut lookups = vec![];
+    for i in 0..try!(u16_at(offset)) as usize {
+        let lookup = offset + try!(u16_at(offset + 2 + i * 2)) as usize;
+        let lookup_type = try!(u16_at(lookup));
+        let mut subtables = vec![];
+        for j in 0..try!(u16_at(lookup + 4)) as usize {
+            let mut subtable = lookup + try!(u16_at(lookup + 6 + j * 2)) as usize;
+            let mut subtable_type = lookup_type;
+            // Extension subtables point to subtables farther away.
+            if lookup_type == extension {
+                subtable_type = try!(u16_at(subtable + 2));
+                subtable += try!(read_u32_at(data, subtable + 4)) as usize;
+            }
+            if let Some(subtable) = try!(read(subtable_type, subtable)) {
+                subtables.push(subtable);
+            }
+        }
+        lookups.push(subtables);
+    }
+    Ok(lookups)
+}
+
+/// The glyphs a subtable applies to, in the order of their records.
+#[derive(Debug, Clone)]
+pub enum Coverage {
+    Glyphs(Vec<u16>),
+    /// The first and last glyph of the ranges, and the index of the first.
+    Ranges(Vec<(u16, u16, u16)>),
+}
+
+impl Coverage {
+    pub fn from_data(data: &[u8], offset: usize) -> Result<Coverage> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let count = try!(u16_at(offset + 2)) as usize;
+        match try!(u16_at(offset)) {
+            1 => {
+                let mut glyphs = Vec::with_capacity(count);
+                for i in 0..count {
+                    glyphs.push(try!(u16_at(offset + 4 + i * 2)));
+                }
+                Ok(Coverage::Glyphs(glyphs))
+            }
+            2 => {
+                let mut ranges = Vec::with_capacity(count);
+                for i in 0..count {
+                    let record = offset + 4 + i * 6;
+                    ranges.push((try!(u16_at(record)), try!(u16_at(record + 2)),
+                        try!(u16_at(record + 4))));
+                }
+                Ok(Coverage::Ranges(ranges))
+            }
+            _ => Err(Error::Malformed),
+        }
+    }
+
+    /// Returns the index of the record of `glyph`, `None` if it isn't covered.
+    pub fn index(&self, glyph: usize) -> Option<usize> {
+        if glyph > 0xffff {
+            return None;
+        }
+        let glyph = glyph as u16;
+        match *self {
+            Coverage::Glyphs(ref glyphs) => glyphs.binary_search(&glyph).ok(),
+            Coverage::Ranges(ref ranges) => {
+                ranges.iter().find(|&&(first, last, _)| first <= glyph && glyph <= last)
+                    .map(|&(first, _, index)| index as usize + (glyph - first) as usize)
+            }
+        }
+    }
+}
+
+/// The classes of glyphs, 0 for the glyphs that aren't listed.
+#[derive(Debug, Clone)]
+pub enum ClassDef {
+    /// The first glyph and the classes of the glyphs from it.
+    Array(u16, Vec<u16>),
+    /// The first and last glyph of the ranges, and their class.
+    Ranges(Vec<(u16, u16, u16)>),
+}
+
+impl ClassDef {
+    pub fn from_data(data: &[u8], offset: usize) -> Result<ClassDef> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        match try!(u16_at(offset)) {
+            1 => {
+                let first = try!(u16_at(offset + 2));
+                let count = try!(u16_at(offset + 4)) as usize;
+                let mut classes = Vec::with_capacity(count);
+                for i in 0..count {
+                    classes.push(try!(u16_at(offset + 6 + i * 2)));
+                }
+                Ok(ClassDef::Array(first, classes))
+            }
+            2 => {
+                let count = try!(u16_at(offset + 2)) as usize;
+                let mut ranges = Vec::with_capacity(count);
+                for i in 0..count {
+                    let record = offset + 4 + i * 6;
+                    ranges.push((try!(u16_at(record)), try!(u16_at(record + 2)),
+                        try!(u16_at(record + 4))));
+                }
+                Ok(ClassDef::Ranges(ranges))
+            }
+            _ => Err(Error::Malformed),
+        }
+    }
+
+    /// Returns the class of `glyph`.
+    pub fn class(&self, glyph: usize) -> u16 {
+        match *self {
+            ClassDef::Array(first, ref classes) => {
+                glyph.checked_sub(first as usize).and_then(|i| classes.get(i)).cloned()
+                    .unwrap_or(0)
+            }
+            ClassDef::Ranges(ref ranges) => {
+                ranges.iter().find(|&&(first, last, _)| {
+                    first as usize <= glyph && glyph <= last as usize
+                }).map_or(0, |&(_, _, class)| class)
+            }
+        }
+    }
+}
+
+impl HeapSize for FeatureList {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size()
+    }
+}
+
+impl HeapSize for Coverage {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Coverage::Glyphs(ref glyphs) => glyphs.heap_size(),
+            Coverage::Ranges(ref ranges) => ranges.heap_size(),
+        }
+    }
+}
+
+impl HeapSize for ClassDef {
+    fn heap_size(&self) -> usize {
+        match *self {
+            ClassDef::Array(_, ref classes) => classes.heap_size(),
+            ClassDef::Ranges(ref ranges) => ranges.heap_size(),
+        }
+    }
+}
+
+/// Returns a coverage table of the sorted `glyphs`.
+#[cfg(test)]
+pub fn coverage_table(glyphs: &[u16]) -> Vec<u8> {
+    use byteorder::BigEndian;
+    use utils::WriteBytesExt;
+
+    let mut data = vec![];
+    data.write_u16::<BigEndian>(1).unwrap();
+    data.write_u16::<BigEndian>(glyphs.len() as u16).unwrap();
+    for &glyph in glyphs {
+        data.write_u16::<BigEndian>(glyph).unwrap();
+    }
+    data
+}
+
+/// Returns a `GPOS` or `GSUB` table without scripts, with `features` of
+/// a lookup each and `lookups` of a type and a subtable each.
+#[cfg(test)]
+pub fn layout_table(features: &[([u8; 4], u16)], lookups: Vec<(u16, Vec<u8>)>) -> Vec<u8> {
+    use byteorder::BigEndian;
+    use utils::WriteBytesExt;
+
+    let mut data = vec![];
+    let lookup_list = 14 + features.len() * 12;
+    // The script list at 10 is empty.
+    for &v in &[1, 0, 10, 12, lookup_list, 0] {
+        data.write_u16::<BigEndian>(v as u16).unwrap();
+    }
+    data.write_u16::<BigEndian>(features.len() as u16).unwrap();
+    for (i, &(tag, _)) in features.iter().enumerate() {
+        data.extend_from_slice(&tag);
+        data.write_u16::<BigEndian>((2 + features.len() * 6 + i * 6) as u16).unwrap();
+    }
+    for &(_, lookup) in features {
+        for &v in &[0, 1, lookup] {
+            data.write_u16::<BigEndian>(v).unwrap();
+        }
+    }
+    data.write_u16::<BigEndian>(lookups.len() as u16).unwrap();
+    let mut offset = 2 + lookups.len() * 2;
+    for &(_, ref subtable) in &lookups {
+        data.write_u16::<BigEndian>(offset as u16).unwrap();
+        offset += 8 + subtable.len();
+    }
+    for (lookup_type, subtable) in lookups {
+        for &v in &[lookup_type, 0, 1, 8] {
+            data.write_u16::<BigEndian>(v).unwrap();
+        }
+        data.extend(subtable);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn coverage() {
+        let data = [0, 2, 0, 2, 0, 10, 0, 12, 0, 0, 0, 20, 0, 20, 0, 3];
+        let coverage = Coverage::from_data(&data, 0).unwrap();
+        expect!(coverage.index(9)).to(be_none());
+        expect!(coverage.index(11)).to(be_some().value(1));
+        expect!(coverage.index(20)).to(be_some().value(3));
+        expect!(coverage.index(0x10014)).to(be_none());
+        let coverage = Coverage::from_data(&[0, 1, 0, 2, 0, 4, 0, 9], 0).unwrap();
+        expect!(coverage.index(9)).to(be_some().value(1));
+        expect!(coverage.index(5)).to(be_none());
+        expect!(Coverage::from_data(&[0, 3, 0, 0], 0).err()).to(be_some().value(Malformed));
+        // The indices of a range can go past 65535.
+        let coverage = Coverage::from_data(&[0, 2, 0, 1, 0, 0, 0, 0x20, 0xff, 0xf0], 0).unwrap();
+        expect!(coverage.index(0x1f)).to(be_some().value(0x1000f));
+    }
+
+    #[test]
+    fn class_def() {
+        let classes = ClassDef::from_data(&[0, 1, 0, 4, 0, 2, 0, 1, 0, 3], 0).unwrap();
+        expect!((classes.class(3), classes.class(4), classes.class(5), classes.class(6)))
+            .to(be_equal_to((0, 1, 3, 0)));
+        let classes = ClassDef::from_data(&[0, 2, 0, 1, 0, 10, 0, 12, 0, 2], 0).unwrap();
+        expect!((classes.class(9), classes.class(10), classes.class(12), classes.class(13)))
+            .to(be_equal_to((0, 2, 2, 0)));
+        expect!(ClassDef::from_data(&[0, 1, 0, 4, 0, 2, 0, 1], 0).err())
+            .to(be_some().value(Malformed));
+    }
+}
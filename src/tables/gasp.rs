@@ -0,0 +1,136 @@
+use prelude::*;
+use Error;
+use Result;
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize};
+
+const GRIDFIT: u16 = 1 << 0;
+const DOGRAY: u16 = 1 << 1;
+const SYMMETRIC_GRIDFIT: u16 = 1 << 2;
+const SYMMETRIC_SMOOTHING: u16 = 1 << 3;
+
+/// How to render a font at a size, see `GASP::rendering_behavior`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RenderingBehavior {
+    /// Hint the glyphs.
+    pub gridfit: bool,
+    /// Antialias the glyphs.
+    pub do_gray: bool,
+    /// Hint the glyphs for ClearType, only in version 1 of the table.
+    pub symmetric_gridfit: bool,
+    /// Smooth the glyphs in the y direction for ClearType, only in
+    /// version 1 of the table.
+    pub symmetric_smoothing: bool,
+}
+
+impl Default for RenderingBehavior {
+    /// Hints and antialiases, what Windows does for fonts without
+    /// a `gasp` table.
+    fn default() -> RenderingBehavior {
+        RenderingBehavior::from_flags(GRIDFIT | DOGRAY)
+    }
+}
+
+impl RenderingBehavior {
+    fn from_flags(flags: u16) -> RenderingBehavior {
+        RenderingBehavior {
+            gridfit: flags & GRIDFIT != 0,
+            do_gray: flags & DOGRAY != 0,
+            symmetric_gridfit: flags & SYMMETRIC_GRIDFIT != 0,
+            symmetric_smoothing: flags & SYMMETRIC_SMOOTHING != 0,
+        }
+    }
+}
+
+/// A grid-fitting and scan-conversion procedure table.
+///
+/// The 'gasp' table tells up to which sizes in pixels per em a font
+/// should be hinted and antialiased.
+#[derive(Debug, Default, Clone)]
+pub struct GASP {
+    version: u16,
+    // The largest size of every range and its behavior, sorted by size.
+    ranges: Vec<(u16, u16)>,
+}
+
+impl GASP {
+    /// Returns `gasp` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<GASP> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let mut gasp = GASP::default();
+        gasp.version = try!(cursor.read_u16::<BigEndian>());
+        let ranges = try!(cursor.read_u16::<BigEndian>());
+        for _ in 0..ranges {
+            let max_ppem = try!(cursor.read_u16::<BigEndian>());
+            let behavior = try!(cursor.read_u16::<BigEndian>());
+            gasp.ranges.push((max_ppem, behavior));
+        }
+        Ok(gasp)
+    }
+
+    /// Returns how to render the font at `ppem` pixels per em.
+    ///
+    /// Sizes above all ranges get the default behavior.
+    pub fn rendering_behavior(&self, ppem: u16) -> RenderingBehavior {
+        // The ClearType flags are undefined in version 0.
+        let mask = if self.version == 0 { GRIDFIT | DOGRAY } else { !0 };
+        self.ranges.iter()
+            .find(|&&(max_ppem, _)| ppem <= max_ppem)
+            .map(|&(_, flags)| RenderingBehavior::from_flags(flags & mask))
+            .unwrap_or_default()
+    }
+}
+
+impl HeapSize for GASP {
+    fn heap_size(&self) -> usize {
+        self.ranges.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn smoke() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let offset = ::utils::find_table_offset(&data, 0, b"gasp").unwrap().unwrap();
+
+        // Antialiased at all sizes, never hinted.
+        let gasp = GASP::from_data(&data, offset).unwrap();
+        let behavior = gasp.rendering_behavior(12);
+        expect!(behavior.gridfit).to(be_false());
+        expect!(behavior.do_gray).to(be_true());
+        expect!(gasp.rendering_behavior(0xffff)).to(be_equal_to(behavior));
+
+        expect!(GASP::from_data(&data[..offset + 6], offset)).to(be_err().value(Malformed));
+        expect!(GASP::from_data(&data, data.len())).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn ranges() {
+        // Hinted up to 8 ppem, then antialiased up to 16 ppem.
+        let data = [0, 1, 0, 2, 0, 8, 0, 1, 0, 16, 0, 2 | 8];
+        let gasp = GASP::from_data(&data, 0).unwrap();
+        expect!(gasp.rendering_behavior(8)).to(be_equal_to(RenderingBehavior::from_flags(GRIDFIT)));
+        expect!(gasp.rendering_behavior(9))
+            .to(be_equal_to(RenderingBehavior::from_flags(DOGRAY | SYMMETRIC_SMOOTHING)));
+        expect!(gasp.rendering_behavior(17)).to(be_equal_to(RenderingBehavior::default()));
+
+        // Version 0 has no ClearType flags.
+        let data = [0, 0, 0, 1, 0xff, 0xff, 0, 2 | 8];
+        let gasp = GASP::from_data(&data, 0).unwrap();
+        expect!(gasp.rendering_behavior(9)).to(be_equal_to(RenderingBehavior::from_flags(DOGRAY)));
+    }
+}
@@ -0,0 +1,207 @@
+use prelude::*;
+use Result;
+use utils::{HeapSize, read_u16_at};
+use super::common::{ClassDef, Coverage};
+
+/// What a glyph is used for, which lookups of `GPOS` tell apart.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GlyphClass {
+    /// A glyph of a single character, like a letter.
+    Base,
+    /// A glyph of several characters, like "fi".
+    Ligature,
+    /// A glyph attached to another one, like a combining accent.
+    Mark,
+    /// A part of a glyph of a single character.
+    Component,
+}
+
+/// A position of a caret between the components of a ligature.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Caret {
+    /// The x coordinate of the caret in font units.
+    Coordinate(i16),
+    /// The index of the point of the outline the caret goes through.
+    Point(u16),
+}
+
+/// A glyph definition table.
+///
+/// Classifies the glyphs for positioning them, and gives the points
+/// marks are attached to and the carets in ligatures. The mark glyph sets
+/// and the variations of newer versions are ignored.
+#[derive(Debug, Default, Clone)]
+pub struct GDEF {
+    glyph_classes: Option<ClassDef>,
+    attach_points: Option<(Coverage, Vec<Vec<u16>>)>,
+    carets: Option<(Coverage, Vec<Vec<Caret>>)>,
+    mark_attach_classes: Option<ClassDef>,
+}
+
+impl GDEF {
+    /// Returns `GDEF` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the tables, or
+    /// they have an unknown format.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<GDEF> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let _major_version = try!(u16_at(offset));
+        // Returns the start of the table at `position` of the header.
+        let table = |position: usize| -> Result<Option<usize>> {
+            Ok(match try!(u16_at(offset + position)) {
+                0 => None,
+                table => Some(offset + table as usize),
+            })
+        };
+
+        let glyph_classes = match try!(table(4)) {
+            Some(start) => Some(try!(ClassDef::from_data(data, start))),
+            None => None,
+        };
+
+        let attach_points = match try!(table(6)) {
+            Some(start) => {
+                let coverage = try!(Coverage::from_data(data, start + try!(u16_at(start)) as usize));
+                let mut points = vec![];
+                for i in 0..try!(u16_at(start + 2)) as usize {
+                    let list = start + try!(u16_at(start + 4 + i * 2)) as usize;
+                    let mut indices = vec![];
+                    for j in 0..try!(u16_at(list)) as usize {
+                        indices.push(try!(u16_at(list + 2 + j * 2)));
+                    }
+                    points.push(indices);
+                }
+                Some((coverage, points))
+            }
+            None => None,
+        };
+
+        let carets = match try!(table(8)) {
+            Some(start) => {
+                let coverage = try!(Coverage::from_data(data, start + try!(u16_at(start)) as usize));
+                let mut ligatures = vec![];
+                for i in 0..try!(u16_at(start + 2)) as usize {
+                    let ligature = start + try!(u16_at(start + 4 + i * 2)) as usize;
+                    let mut carets = vec![];
+                    for j in 0..try!(u16_at(ligature)) as usize {
+                        let caret = ligature + try!(u16_at(ligature + 2 + j * 2)) as usize;
+                        let value = try!(u16_at(caret + 2));
+                        // The third format adds device adjustments to the
+                        // coordinate of the first.
+                        carets.push(match try!(u16_at(caret)) {
+                            2 => Caret::Point(value),
+                            _ => Caret::Coordinate(value as i16),
+                        });
+                    }
+                    ligatures.push(carets);
+                }
+                Some((coverage, ligatures))
+            }
+            None => None,
+        };
+
+        let mark_attach_classes = match try!(table(10)) {
+            Some(start) => Some(try!(ClassDef::from_data(data, start))),
+            None => None,
+        };
+
+        Ok(GDEF {
+            glyph_classes: glyph_classes,
+            attach_points: attach_points,
+            carets: carets,
+            mark_attach_classes: mark_attach_classes,
+        })
+    }
+
+    /// Returns `true` if the font classifies its glyphs, see `glyph_class`.
+    pub fn has_glyph_classes(&self) -> bool {
+        self.glyph_classes.is_some()
+    }
+
+    /// Returns the class of the glyph at index `i`, `None` if it isn't
+    /// classified.
+    pub fn glyph_class(&self, i: usize) -> Option<GlyphClass> {
+        match self.glyph_classes.as_ref().map_or(0, |classes| classes.class(i)) {
+            1 => Some(GlyphClass::Base),
+            2 => Some(GlyphClass::Ligature),
+            3 => Some(GlyphClass::Mark),
+            4 => Some(GlyphClass::Component),
+            _ => None,
+        }
+    }
+
+    /// Returns the class of the mark at index `i` that lookups can be
+    /// limited to, 0 if it has none.
+    pub fn mark_attach_class(&self, i: usize) -> u16 {
+        self.mark_attach_classes.as_ref().map_or(0, |classes| classes.class(i))
+    }
+
+    /// Returns the indices of the points of the outline of the glyph at
+    /// index `i` that marks are attached to.
+    pub fn attach_points(&self, i: usize) -> &[u16] {
+        self.attach_points.as_ref()
+            .and_then(|&(ref coverage, ref points)| coverage.index(i).and_then(|j| points.get(j)))
+            .map_or(&[], |points| &points[..])
+    }
+
+    /// Returns the carets between the components of the ligature at index
+    /// `i`, from left to right, empty for other glyphs.
+    pub fn ligature_carets(&self, i: usize) -> &[Caret] {
+        self.carets.as_ref()
+            .and_then(|&(ref coverage, ref carets)| coverage.index(i).and_then(|j| carets.get(j)))
+            .map_or(&[], |carets| &carets[..])
+    }
+}
+
+impl HeapSize for Caret {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl HeapSize for GDEF {
+    fn heap_size(&self) -> usize {
+        self.glyph_classes.heap_size() + self.attach_points.heap_size() + self.carets.heap_size()
+            + self.mark_attach_classes.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn classes_and_carets() {
+        let data = vec![
+            // Header with glyph classes at 12, attachment points at 26 and
+            // carets at 44.
+            0, 1, 0, 0, 0, 12, 0, 26, 0, 44, 0, 0,
+            // Glyphs 2 to 5 are bases, a ligature, a mark and unclassified.
+            0, 1, 0, 2, 0, 4, 0, 1, 0, 2, 0, 3, 0, 0,
+            // Glyph 2 has points 7 and 9.
+            0, 6, 0, 1, 0, 12, 0, 1, 0, 1, 0, 2, 0, 2, 0, 7, 0, 9,
+            // Glyph 3 has a caret at 500 and one on point 4.
+            0, 6, 0, 1, 0, 12, 0, 1, 0, 1, 0, 3, 0, 2, 0, 6, 0, 10,
+            0, 1, 0x01, 0xf4, 0, 2, 0, 4,
+        ];
+        let gdef = GDEF::from_data(&data, 0).unwrap();
+        expect!(gdef.has_glyph_classes()).to(be_true());
+        expect!((0..7).map(|i| gdef.glyph_class(i)).collect::<Vec<_>>()).to(be_equal_to(vec![
+            None, None, Some(GlyphClass::Base), Some(GlyphClass::Ligature),
+            Some(GlyphClass::Mark), None, None,
+        ]));
+        expect!(gdef.mark_attach_class(4)).to(be_equal_to(0));
+        expect!(gdef.attach_points(2)).to(be_equal_to(&[7u16, 9][..]));
+        expect!(gdef.attach_points(3).is_empty()).to(be_true());
+        expect!(gdef.ligature_carets(3)).to(be_equal_to(&[Caret::Coordinate(500), Caret::Point(4)][..]));
+        expect!(gdef.ligature_carets(2).is_empty()).to(be_true());
+
+        expect!(GDEF::from_data(&data[..data.len() - 1], 0).err()).to(be_some().value(Malformed));
+        expect!(GDEF::default().glyph_class(2)).to(be_none());
+    }
+}
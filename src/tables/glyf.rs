@@ -1,42 +1,170 @@
 
+use prelude::*;
 use Error;
 use Result;
 use types::BBox;
-use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use builder::OutlinePoint;
+use byteorder::BigEndian;
+use utils::Cursor;
 
-#[derive(Debug)]
-pub struct GLYF {
-    bytes: Vec<u8>,
+/// A glyph data table.
+///
+/// The table borrows its data from the font file, use `into_owned` to detach
+/// it from the file.
+#[derive(Debug, Clone)]
+pub struct GLYF<'a> {
+    bytes: Cow<'a, [u8]>,
 }
 
-impl GLYF {
-    pub fn from_data(data: &[u8], offset: usize, size: usize) -> Result<Self> {
+impl<'a> GLYF<'a> {
+    /// Returns `glyf` font table of `size` bytes starting from `offset`
+    /// position of `data`.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read.
+    pub fn from_data(data: &'a [u8], offset: usize, size: usize) -> Result<GLYF<'a>> {
         if offset + size > data.len() {
             return Err(Error::Malformed);
         }
 
         Ok(GLYF {
-            bytes: data[offset..offset + size].to_owned(),
+            bytes: Cow::Borrowed(&data[offset..offset + size]),
         })
     }
 
+    /// Returns a copy of the table that owns its data.
+    pub fn into_owned(self) -> GLYF<'static> {
+        GLYF { bytes: Cow::Owned(self.bytes.into_owned()) }
+    }
+
     /// Returns instance of `GlyphData` starting from `offset` position.
     ///
     /// `offset` could be taken from the `loca` font table.
     pub fn glyph_data(&self, offset: usize) -> GlyphData {
-        let z = if offset >= self.bytes.len() { 0 } else { offset };
-        GlyphData { bytes: &self.bytes[z..] }
+        GlyphData::new(&self.bytes, offset)
+    }
+}
+
+const ARG_1_AND_2_ARE_WORDS: u16 = 1 << 0;
+const ARGS_ARE_XY_VALUES: u16 = 1 << 1;
+const ROUND_XY_TO_GRID: u16 = 1 << 2;
+const WE_HAVE_A_SCALE: u16 = 1 << 3;
+const MORE_COMPONENTS: u16 = 1 << 5;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 1 << 6;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 1 << 7;
+const SCALED_COMPONENT_OFFSET: u16 = 1 << 11;
+const UNSCALED_COMPONENT_OFFSET: u16 = 1 << 12;
+
+/// The outline of a glyph as stored in the `glyf` table, see
+/// `GlyphData::outline`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outline {
+    /// The closed contours of a simple glyph, with their points in the
+    /// order they are numbered. Empty glyphs have no contours.
+    Simple(Vec<Vec<OutlinePoint>>),
+    /// The components of a composite glyph.
+    Composite(Vec<Component>),
+}
+
+/// A component of a composite glyph, another glyph of the font placed
+/// with an affine transform.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Component {
+    /// The flags of the component as stored in the font.
+    pub flags: u16,
+    /// The index of the glyph.
+    pub glyph_index: usize,
+    /// The transform `[a, b, c, d, e, f]` taking the point `(x, y)` of the
+    /// glyph to `(a * x + c * y + e, b * x + d * y + f)`.
+    ///
+    /// The offsets `e` and `f` are 0 for components matched by points.
+    pub transform: [f32; 6],
+    /// The numbers of a point of the composite glyph so far and of a point
+    /// of the component that are moved onto each other, instead of
+    /// offsetting the component.
+    pub matched_points: Option<(usize, usize)>,
+}
+
+impl Component {
+    fn read(cursor: &mut Cursor) -> Result<Component> {
+        let flags = try!(cursor.read_u16::<BigEndian>());
+        let glyph_index = try!(cursor.read_u16::<BigEndian>()) as usize;
+        let xy_values = flags & ARGS_ARE_XY_VALUES != 0;
+        let args = match (flags & ARG_1_AND_2_ARE_WORDS != 0, xy_values) {
+            (true, true) => (try!(cursor.read_i16::<BigEndian>()) as i32,
+                try!(cursor.read_i16::<BigEndian>()) as i32),
+            (true, false) => (try!(cursor.read_u16::<BigEndian>()) as i32,
+                try!(cursor.read_u16::<BigEndian>()) as i32),
+            (false, true) => (try!(cursor.read_i8()) as i32, try!(cursor.read_i8()) as i32),
+            (false, false) => (try!(cursor.read_u8()) as i32, try!(cursor.read_u8()) as i32),
+        };
+
+        // Scales are 2.14 fixed point numbers.
+        let mut read_scale = || -> Result<f32> {
+            Ok(try!(cursor.read_i16::<BigEndian>()) as f32 / 16384.0)
+        };
+        let mut transform = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        if flags & WE_HAVE_A_SCALE != 0 {
+            let scale = try!(read_scale());
+            transform[0] = scale;
+            transform[3] = scale;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            transform[0] = try!(read_scale());
+            transform[3] = try!(read_scale());
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            for value in transform[..4].iter_mut() {
+                *value = try!(read_scale());
+            }
+        }
+
+        if !xy_values {
+            return Ok(Component {
+                flags: flags,
+                glyph_index: glyph_index,
+                transform: transform,
+                matched_points: Some((args.0 as usize, args.1 as usize)),
+            });
+        }
+
+        // Offsets are in the coordinates of the composite glyph, unless
+        // SCALED_COMPONENT_OFFSET asks to transform them like the points.
+        let (x, y) = (args.0 as f32, args.1 as f32);
+        if flags & SCALED_COMPONENT_OFFSET != 0 && flags & UNSCALED_COMPONENT_OFFSET == 0 {
+            transform[4] = transform[0] * x + transform[2] * y;
+            transform[5] = transform[1] * x + transform[3] * y;
+        } else {
+            transform[4] = x;
+            transform[5] = y;
+        }
+        // There is no hinting, so ROUND_XY_TO_GRID rounds to whole font
+        // units, which only matters for scaled offsets.
+        if flags & ROUND_XY_TO_GRID != 0 {
+            transform[4] = libm::roundf(transform[4]);
+            transform[5] = libm::roundf(transform[5]);
+        }
+        Ok(Component {
+            flags: flags,
+            glyph_index: glyph_index,
+            transform: transform,
+            matched_points: None,
+        })
     }
 }
 
 /// Contains data for the glyph.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GlyphData<'a> {
     bytes: &'a [u8],
 }
 
 impl<'a> GlyphData<'a> {
+    /// Returns the glyph starting from `offset` position of the data
+    /// of the `glyf` font table.
+    pub fn new(glyf: &'a [u8], offset: usize) -> GlyphData<'a> {
+        let z = if offset >= glyf.len() { 0 } else { offset };
+        GlyphData { bytes: &glyf[z..] }
+    }
+
     /// Returns the number of contours in the glyph.
     pub fn number_of_contours(&self) -> isize {
         Cursor::new(self.bytes).read_i16::<BigEndian>().ok().unwrap_or(0) as isize
@@ -63,6 +191,151 @@ impl<'a> GlyphData<'a> {
         Some(BBox { x0: x0, y0: y0, x1: x1, y1: y1 })
     }
 
+    /// Returns the coordinates of the points of a simple glyph in the order
+    /// they are numbered, or no points for other glyphs.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if the point data is truncated.
+    pub fn points(&self) -> Result<Vec<(i32, i32)>> {
+        self.flags_and_points().map(|(_, points)| points)
+    }
+
+    fn flags_and_points(&self) -> Result<(Vec<u8>, Vec<(i32, i32)>)> {
+        let mut points = vec![];
+        let number_of_contours = self.number_of_contours();
+        if number_of_contours <= 0 {
+            return Ok((vec![], points));
+        }
+
+        let last_end_point = 10 + 2 * (number_of_contours as usize - 1);
+        let mut cursor = Cursor::new(try!(self.bytes.get(last_end_point..).ok_or(Error::Malformed)));
+        let n = try!(cursor.read_u16::<BigEndian>()) as usize + 1;
+        let instructions = try!(cursor.read_u16::<BigEndian>()) as usize;
+        let mut z = 10 + 2 * number_of_contours as usize + 2 + instructions;
+
+        let mut flags = Vec::with_capacity(n);
+        while flags.len() < n {
+            let flag = *try!(self.bytes.get(z).ok_or(Error::Malformed));
+            z += 1;
+            let mut count = 1;
+            if flag & 8 != 0 {
+                count += *try!(self.bytes.get(z).ok_or(Error::Malformed)) as usize;
+                z += 1;
+            }
+            for _ in 0..::core::cmp::min(count, n - flags.len()) {
+                flags.push(flag);
+            }
+        }
+
+        // Coordinates are deltas, x and y are stored separately.
+        fn read(bytes: &[u8], z: &mut usize, flag: u8, short: u8, same: u8) -> Result<i32> {
+            if flag & short != 0 {
+                let delta = *try!(bytes.get(*z).ok_or(Error::Malformed)) as i32;
+                *z += 1;
+                Ok(if flag & same != 0 { delta } else { -delta })
+            } else if flag & same == 0 {
+                let delta = try!(Cursor::new(try!(bytes.get(*z..).ok_or(Error::Malformed)))
+                    .read_i16::<BigEndian>());
+                *z += 2;
+                Ok(delta as i32)
+            } else {
+                Ok(0)
+            }
+        }
+        let mut x = 0;
+        for &flag in &flags {
+            x += try!(read(self.bytes, &mut z, flag, 2, 16));
+            points.push((x, 0));
+        }
+        let mut y = 0;
+        for (point, &flag) in points.iter_mut().zip(&flags) {
+            y += try!(read(self.bytes, &mut z, flag, 4, 32));
+            point.1 = y;
+        }
+        Ok((flags, points))
+    }
+
+    /// Decodes the outline of the glyph, the contours of a simple glyph or
+    /// the components of a composite glyph.
+    ///
+    /// Coordinates beyond the range of `i16`, which only corrupt glyphs
+    /// have, are clamped to it.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if the glyph is truncated or the end
+    /// points of its contours are not increasing.
+    pub fn outline(&self) -> Result<Outline> {
+        let number_of_contours = self.number_of_contours();
+        if number_of_contours == -1 {
+            return self.read_components().map(Outline::Composite);
+        }
+
+        let mut contours = vec![];
+        if number_of_contours <= 0 {
+            return Ok(Outline::Simple(contours));
+        }
+        let (flags, points) = try!(self.flags_and_points());
+        let mut cursor = Cursor::new(&self.bytes[10..]);
+        let mut start = 0;
+        for _ in 0..number_of_contours {
+            let end = try!(cursor.read_u16::<BigEndian>()) as usize + 1;
+            if end <= start || end > points.len() {
+                return Err(Error::Malformed);
+            }
+            contours.push(points[start..end].iter().zip(&flags[start..end])
+                .map(|(&(x, y), &flag)| OutlinePoint { x: clamp(x), y: clamp(y), on_curve: flag & 1 != 0 })
+                .collect());
+            start = end;
+        }
+        Ok(Outline::Simple(contours))
+    }
+
+    fn read_components(&self) -> Result<Vec<Component>> {
+        Ok(try!(self.read_components_at()).into_iter().map(|(_, component)| component).collect())
+    }
+
+    // Returns the components with the offsets of their glyph indices.
+    fn read_components_at(&self) -> Result<Vec<(usize, Component)>> {
+        let mut components = vec![];
+        let mut cursor = Cursor::new(try!(self.bytes.get(10..).ok_or(Error::Malformed)));
+        loop {
+            // The glyph index follows the flags.
+            let offset = 10 + cursor.position() + 2;
+            let component = try!(Component::read(&mut cursor));
+            components.push((offset, component));
+            if component.flags & MORE_COMPONENTS == 0 {
+                return Ok(components);
+            }
+        }
+    }
+
+    /// Returns the flags and glyph indices of the components of a compound
+    /// glyph, or no components for other glyphs.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if the component data is truncated.
+    pub fn components(&self) -> Result<Vec<(u16, usize)>> {
+        if self.number_of_contours() != -1 {
+            return Ok(vec![]);
+        }
+        let components = try!(self.read_components());
+        Ok(components.iter().map(|c| (c.flags, c.glyph_index)).collect())
+    }
+
+    /// Returns the offsets in the data of the glyph of the glyph indices of
+    /// the components of a compound glyph with the indices, or nothing for
+    /// other glyphs, e.g. to renumber the components of a subset.
+    ///
+    /// # Errors
+    /// Returns `Error::Malformed` if the component data is truncated.
+    pub fn component_offsets(&self) -> Result<Vec<(usize, usize)>> {
+        if self.number_of_contours() != -1 {
+            return Ok(vec![]);
+        }
+        let components = try!(self.read_components_at());
+        Ok(components.iter().map(|&(offset, ref c)| (offset, c.glyph_index)).collect())
+    }
+
     /// Same as `bitmap_box`, but you can specify a subpixel shift
     /// for the character.
     pub fn bitmap_box_subpixel(&self, scale_x: f32, scale_y: f32,
@@ -72,10 +345,35 @@ impl<'a> GlyphData<'a> {
             // Move to integral bboxes (treating pixels as little squares,
             // what pixels get touched)?
             BBox {
-                x0: (bbox.x0 as f32 * scale_x + shift_x).floor() as i32,
-                y0: (-bbox.y1 as f32 * scale_y + shift_y).floor() as i32,
-                x1: (bbox.x1 as f32 * scale_x + shift_x).ceil() as i32,
-                y1: (-bbox.y0 as f32 * scale_y + shift_y).ceil() as i32,
+                x0: libm::floorf(bbox.x0 as f32 * scale_x + shift_x) as i32,
+                y0: libm::floorf(-bbox.y1 as f32 * scale_y + shift_y) as i32,
+                x1: libm::ceilf(bbox.x1 as f32 * scale_x + shift_x) as i32,
+                y1: libm::ceilf(-bbox.y0 as f32 * scale_y + shift_y) as i32,
+            }
+        })
+    }
+
+    /// Returns the bbox of the bitmap of the glyph mapped with the affine
+    /// `transform` `[a, b, c, d, e, f]` taking `(x, y)` to
+    /// `(a * x + c * y + e, b * x + d * y + f)`.
+    pub fn bitmap_box_transformed(&self, transform: &[f32; 6]) -> Option<BBox> {
+        self.bounding_box().map(|bbox| {
+            let corners = [(bbox.x0, bbox.y0), (bbox.x0, bbox.y1),
+                (bbox.x1, bbox.y0), (bbox.x1, bbox.y1)];
+            let mut x = (::core::f32::MAX, ::core::f32::MIN);
+            let mut y = (::core::f32::MAX, ::core::f32::MIN);
+            for &(cx, cy) in &corners {
+                let (cx, cy) = (cx as f32, cy as f32);
+                let tx = transform[0] * cx + transform[2] * cy + transform[4];
+                let ty = transform[1] * cx + transform[3] * cy + transform[5];
+                x = (x.0.min(tx), x.1.max(tx));
+                y = (y.0.min(ty), y.1.max(ty));
+            }
+            BBox {
+                x0: libm::floorf(x.0) as i32,
+                y0: libm::floorf(y.0) as i32,
+                x1: libm::ceilf(x.1) as i32,
+                y1: libm::ceilf(y.1) as i32,
             }
         })
     }
@@ -90,14 +388,79 @@ impl<'a> GlyphData<'a> {
     }
 }
 
+// Returns the coordinate clamped to the range of `i16`.
+fn clamp(coordinate: i32) -> i16 {
+    coordinate.max(i16::min_value() as i32).min(i16::max_value() as i32) as i16
+}
+
+/// Checks that a glyph outline can be decoded without reading past its end.
+///
+/// `glyph` is the data of a single glyph as located by the `loca` table.
+/// Returns indices of the components of a composite glyph.
+///
+/// # Errors
+/// Returns `Error::Malformed` if contour end points are not increasing
+/// or point data doesn't fit into the glyph.
+pub fn check_glyph(glyph: &[u8]) -> Result<Vec<usize>> {
+    let mut components = vec![];
+    if glyph.is_empty() {
+        return Ok(components);
+    }
+
+    let mut cursor = Cursor::new(glyph);
+    let number_of_contours = try!(cursor.read_i16::<BigEndian>());
+    for _ in 0..4 {
+        try!(cursor.read_i16::<BigEndian>()); // bounding box
+    }
+
+    if number_of_contours > 0 {
+        let mut points = 0;
+        for i in 0..number_of_contours {
+            let end = try!(cursor.read_u16::<BigEndian>()) as usize + 1;
+            if i > 0 && end <= points {
+                return Err(Error::Malformed);
+            }
+            points = end;
+        }
+        let instructions = try!(cursor.read_u16::<BigEndian>()) as usize;
+        let mut z = 10 + 2 * number_of_contours as usize + 2 + instructions;
+
+        // Flags may be repeated, count coordinate sizes as they are read.
+        let mut coordinates = 0;
+        let mut i = 0;
+        while i < points {
+            let flags = *try!(glyph.get(z).ok_or(Error::Malformed));
+            z += 1;
+            let mut count = 1;
+            if flags & 8 != 0 {
+                count += *try!(glyph.get(z).ok_or(Error::Malformed)) as usize;
+                z += 1;
+            }
+            let count = ::core::cmp::min(count, points - i);
+            let x = if flags & 2 != 0 { 1 } else if flags & 16 == 0 { 2 } else { 0 };
+            let y = if flags & 4 != 0 { 1 } else if flags & 32 == 0 { 2 } else { 0 };
+            coordinates += count * (x + y);
+            i += count;
+        }
+        if z + coordinates > glyph.len() {
+            return Err(Error::Malformed);
+        }
+    } else if number_of_contours == -1 {
+        let glyph = GlyphData { bytes: glyph };
+        components = try!(glyph.components()).into_iter().map(|(_, index)| index).collect();
+    }
+    Ok(components)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tables::{MAXP, HEAD, LOCA};
+    use expectest::prelude::*;
 
     #[test]
     fn smoke() {
-        let data = ::utils::read_file("tests/Tuffy_Bold.ttf");
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
         let maxp_offset = ::utils::find_table_offset(&data, 0, b"maxp").unwrap().unwrap();
         let glyphs = MAXP::from_data(&data, maxp_offset).unwrap().num_glyphs();
         let head_offset = ::utils::find_table_offset(&data, 0, b"head").unwrap().unwrap();
@@ -106,6 +469,92 @@ mod tests {
         let loca = LOCA::from_data(&data, loca_offset, glyphs, format).unwrap();
 
         let glyf_offset = ::utils::find_table_offset(&data, 0, b"glyf").unwrap().unwrap();
-        let _ = GLYF::from_data(&data, glyf_offset, loca.size_of_glyf_table()).unwrap();
+        let glyf = GLYF::from_data(&data, glyf_offset, loca.size_of_glyf_table()).unwrap();
+        let offset = loca.offset_for_glyph_at_index(36).unwrap();
+        assert_eq!(glyf.glyph_data(offset).bytes.as_ptr(), data[glyf_offset + offset..].as_ptr());
+
+        let owned = glyf.clone().into_owned();
+        assert!(owned.glyph_data(offset).bytes.as_ptr() != data[glyf_offset + offset..].as_ptr());
+        assert_eq!(owned.glyph_data(offset).bounding_box(), glyf.glyph_data(offset).bounding_box());
+    }
+
+    #[test]
+    fn points() {
+        // A repeated flag, short and long coordinates and a truncated glyph.
+        let data = [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0,
+            1 | 2 | 4 | 16 | 8, 1, 1 | 32, 5, 10, 0, 0, 10, 1];
+        expect!(GlyphData::new(&data, 0).points()).to(be_ok().value(vec![(5, -10), (15, -11), (15, -11)]));
+        expect!(GlyphData::new(&data[..22], 0).points()).to(be_err().value(Error::Malformed));
+        expect!(GlyphData::new(&[], 0).points()).to(be_ok().value(vec![]));
+    }
+
+    #[test]
+    fn outline() {
+        // The last point is off the curve.
+        let data = [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0,
+            1 | 2 | 4 | 16 | 8, 1, 32, 5, 10, 0, 0, 10, 1];
+        let contour = vec![OutlinePoint::on(5, -10), OutlinePoint::on(15, -11),
+            OutlinePoint::off(15, -11)];
+        expect!(GlyphData::new(&data, 0).outline()).to(be_ok().value(Outline::Simple(vec![contour])));
+        expect!(GlyphData::new(&data[..22], 0).outline()).to(be_err().value(Error::Malformed));
+        expect!(GlyphData::new(&[], 0).outline()).to(be_ok().value(Outline::Simple(vec![])));
+
+        // Points 30000 units apart, the second one beyond the range of i16.
+        let data = [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1 | 32, 1 | 32, 0x75, 0x30, 0x75, 0x30];
+        let contour = vec![OutlinePoint::on(30000, 0), OutlinePoint::on(32767, 0)];
+        expect!(GlyphData::new(&data, 0).outline()).to(be_ok().value(Outline::Simple(vec![contour])));
+
+        // Glyph 3 at half the size moved by (-2, 4), then glyph 5 with its
+        // point 200 on point 1.
+        let data = [0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x2b, 0, 3, 0xff, 0xfe, 0, 4, 0x20, 0,
+            0, 0, 0, 5, 1, 200];
+        let components = vec![
+            Component { flags: 0x2b, glyph_index: 3, transform: [0.5, 0.0, 0.0, 0.5, -2.0, 4.0],
+                matched_points: None },
+            Component { flags: 0, glyph_index: 5, transform: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                matched_points: Some((1, 200)) },
+        ];
+        expect!(GlyphData::new(&data, 0).outline()).to(be_ok().value(Outline::Composite(components)));
+        expect!(GlyphData::new(&data, 0).components()).to(be_ok().value(vec![(0x2b, 3), (0, 5)]));
+        expect!(GlyphData::new(&data[..25], 0).outline()).to(be_err().value(Error::Malformed));
+    }
+
+    #[test]
+    fn bitmap_boxes() {
+        let data = [0, 1, 0, 0, 0, 0, 0, 10, 0, 20];
+        let glyph = GlyphData::new(&data, 0);
+        let bbox = BBox { x0: 0, y0: -4, x1: 2, y1: 0 };
+        expect!(glyph.bitmap_box(0.15, 0.2)).to(be_some().value(bbox));
+        expect!(glyph.bitmap_box_transformed(&[0.15, 0.0, 0.0, -0.2, 0.0, 0.0]))
+            .to(be_some().value(bbox));
+        // Rotated by 90 degrees.
+        expect!(glyph.bitmap_box_transformed(&[0.0, 1.0, 1.0, 0.0, 0.5, 0.0]))
+            .to(be_some().value(BBox { x0: 0, y0: 0, x1: 21, y1: 10 }));
+    }
+
+    #[test]
+    fn check() {
+        // Two contours of two and one points, 'A' is composed of glyph 5.
+        let simple = [0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 0, 1 | 2 | 4 | 8, 2, 0, 0, 0, 0, 0, 0];
+        let composite = [0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 5, 1, 2];
+
+        expect!(check_glyph(&[])).to(be_ok().value(vec![]));
+        expect!(check_glyph(&simple)).to(be_ok().value(vec![]));
+        expect!(check_glyph(&simple[..simple.len() - 1])).to(be_err().value(Error::Malformed));
+        expect!(check_glyph(&composite)).to(be_ok().value(vec![5]));
+        expect!(check_glyph(&composite[..15])).to(be_err().value(Error::Malformed));
+        expect!(GlyphData::new(&composite, 0).components()).to(be_ok().value(vec![(2, 5)]));
+        expect!(GlyphData::new(&composite, 0).component_offsets()).to(be_ok().value(vec![(12, 5)]));
+        expect!(GlyphData::new(&simple, 0).components()).to(be_ok().value(vec![]));
+
+        // Decreasing end points.
+        let mut data = simple;
+        data[13] = 0;
+        expect!(check_glyph(&data)).to(be_err().value(Error::Malformed));
+
+        // Matching points.
+        let mut data = composite;
+        data[11] = 0;
+        expect!(check_glyph(&data)).to(be_ok().value(vec![5]));
     }
 }
@@ -0,0 +1,357 @@
+use prelude::*;
+use Error;
+use Result;
+use utils::{HeapSize, read_u16_at};
+use super::common::{Coverage, FeatureList, read_lookups};
+
+/// Where a mark is drawn, given by `GPOS::attach_marks`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MarkAttachment {
+    /// The index of the glyph the mark is attached to, a base, a ligature
+    /// or another mark.
+    pub base: usize,
+    /// The offset of the origin of the mark from the origin of the glyph it
+    /// is attached to, in font units with the y axis pointing up.
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A point on a glyph marks are attached by, in font units.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Anchor {
+    x: i16,
+    y: i16,
+}
+
+impl Anchor {
+    /// Reads the anchor at `offset`, `None` for the null offset. The device
+    /// adjustments and contour points of the other formats are ignored.
+    fn from_data(data: &[u8], offset: usize, relative_to: usize) -> Result<Option<Anchor>> {
+        if offset == 0 {
+            return Ok(None);
+        }
+        let start = relative_to + offset;
+        Ok(Some(Anchor {
+            x: try!(read_u16_at(data, start + 2)) as i16,
+            y: try!(read_u16_at(data, start + 4)) as i16,
+        }))
+    }
+}
+
+/// What marks of a mark attachment subtable are attached to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum AttachTo {
+    Base,
+    Ligature,
+    Mark,
+}
+
+/// A subtable of a lookup of type 4, 5 or 6.
+#[derive(Debug, Clone)]
+struct MarkSubtable {
+    attach_to: AttachTo,
+    marks: Coverage,
+    /// The class and the anchor of every mark.
+    mark_anchors: Vec<(u16, Anchor)>,
+    bases: Coverage,
+    /// The anchors of every class on every component of every base, bases
+    /// and marks having a single component.
+    base_anchors: Vec<Vec<Vec<Option<Anchor>>>>,
+}
+
+impl MarkSubtable {
+    fn from_data(data: &[u8], offset: usize, attach_to: AttachTo) -> Result<MarkSubtable> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        if try!(u16_at(offset)) != 1 {
+            return Err(Error::Malformed);
+        }
+        let marks = try!(Coverage::from_data(data, offset + try!(u16_at(offset + 2)) as usize));
+        let bases = try!(Coverage::from_data(data, offset + try!(u16_at(offset + 4)) as usize));
+        let classes = try!(u16_at(offset + 6)) as usize;
+
+        let mark_array = offset + try!(u16_at(offset + 8)) as usize;
+        let mut mark_anchors = vec![];
+        for i in 0..try!(u16_at(mark_array)) as usize {
+            let record = mark_array + 2 + i * 4;
+            let anchor = try!(Anchor::from_data(data, try!(u16_at(record + 2)) as usize, mark_array));
+            mark_anchors.push((try!(u16_at(record)), try!(anchor.ok_or(Error::Malformed))));
+        }
+
+        // Reads a component record of an anchor for every class.
+        let component = |record: usize, relative_to: usize| -> Result<Vec<Option<Anchor>>> {
+            let mut anchors = Vec::with_capacity(classes);
+            for class in 0..classes {
+                let offset = try!(u16_at(record + class * 2)) as usize;
+                anchors.push(try!(Anchor::from_data(data, offset, relative_to)));
+            }
+            Ok(anchors)
+        };
+        let base_array = offset + try!(u16_at(offset + 10)) as usize;
+        let mut base_anchors = vec![];
+        for i in 0..try!(u16_at(base_array)) as usize {
+            base_anchors.push(if attach_to == AttachTo::Ligature {
+                let attach = base_array + try!(u16_at(base_array + 2 + i * 2)) as usize;
+                let mut components = vec![];
+                for j in 0..try!(u16_at(attach)) as usize {
+                    components.push(try!(component(attach + 2 + j * classes * 2, attach)));
+                }
+                components
+            } else {
+                vec![try!(component(base_array + 2 + i * classes * 2, base_array))]
+            });
+        }
+
+        Ok(MarkSubtable {
+            attach_to: attach_to,
+            marks: marks,
+            mark_anchors: mark_anchors,
+            bases: bases,
+            base_anchors: base_anchors,
+        })
+    }
+
+    /// Returns the attachment of the mark at index `i` of `glyphs`, `None`
+    /// if the subtable doesn't apply to it.
+    fn attach<F>(&self, glyphs: &[usize], i: usize, is_mark: &F) -> Option<MarkAttachment>
+        where F: Fn(usize) -> bool
+    {
+        let &(class, mark_anchor) = match self.marks.index(glyphs[i]) {
+            Some(index) => match self.mark_anchors.get(index) {
+                Some(mark) => mark,
+                None => return None,
+            },
+            None => return None,
+        };
+        let base = match self.attach_to {
+            // Marks attach to the mark right before them.
+            AttachTo::Mark => match i.checked_sub(1) {
+                Some(base) if is_mark(glyphs[base]) => base,
+                _ => return None,
+            },
+            _ => match (0..i).rev().find(|&j| !is_mark(glyphs[j])) {
+                Some(base) => base,
+                None => return None,
+            },
+        };
+        let components = match self.bases.index(glyphs[base]) {
+            Some(index) => match self.base_anchors.get(index) {
+                Some(components) => components,
+                None => return None,
+            },
+            None => return None,
+        };
+        // Without ligature substitution the component a mark belongs to is
+        // unknown, it goes on the last one that has an anchor for it.
+        components.iter().rev()
+            .filter_map(|anchors| anchors.get(class as usize).cloned().and_then(|a| a))
+            .next()
+            .map(|anchor| MarkAttachment {
+                base: base,
+                x: anchor.x as i32 - mark_anchor.x as i32,
+                y: anchor.y as i32 - mark_anchor.y as i32,
+            })
+    }
+}
+
+/// A glyph positioning table.
+///
+/// Only the mark attachment lookups are read, which put diacritics on
+/// letters, ligatures and other diacritics. The features are read
+/// regardless of the script and the language.
+#[derive(Debug, Default, Clone)]
+pub struct GPOS {
+    features: FeatureList,
+    /// The subtables of every lookup, empty for other types.
+    lookups: Vec<Vec<MarkSubtable>>,
+}
+
+impl GPOS {
+    /// The feature attaching marks to bases and ligatures.
+    pub const MARK: [u8; 4] = *b"mark";
+    /// The feature attaching marks to marks.
+    pub const MARK_TO_MARK: [u8; 4] = *b"mkmk";
+
+    /// Returns `GPOS` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the features and
+    /// the mark attachment lookups, or they have an unknown format.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<GPOS> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let _major_version = try!(u16_at(offset));
+
+        let features = try!(FeatureList::from_data(data, offset + try!(u16_at(offset + 6)) as usize));
+        let lookup_list = offset + try!(u16_at(offset + 8)) as usize;
+        let lookups = try!(read_lookups(data, lookup_list, 9, |lookup_type, subtable| {
+            let attach_to = match lookup_type {
+                4 => AttachTo::Base,
+                5 => AttachTo::Ligature,
+                6 => AttachTo::Mark,
+                _ => return Ok(None),
+            };
+            MarkSubtable::from_data(data, subtable, attach_to).map(Some)
+        }));
+
+        Ok(GPOS { features: features, lookups: lookups })
+    }
+
+    /// Returns the tags of the features of the font, in order, which may
+    /// repeat for different scripts.
+    pub fn features(&self) -> Vec<[u8; 4]> {
+        self.features.tags()
+    }
+
+    /// Returns the indices of the lookups of `features` in the order they
+    /// are applied.
+    pub fn feature_lookups(&self, features: &[[u8; 4]]) -> Vec<u16> {
+        self.features.lookups(features)
+    }
+
+    /// Returns `true` if a mark attachment lookup attaches the glyph at
+    /// index `i` as a mark, for fonts that don't classify their glyphs.
+    pub fn is_mark(&self, i: usize) -> bool {
+        self.lookups.iter().flat_map(|subtables| subtables.iter())
+            .any(|subtable| subtable.marks.index(i).is_some())
+    }
+
+    /// Returns where the marks among `glyphs` are attached by `lookups`,
+    /// `None` for the glyphs that aren't attached.
+    ///
+    /// Marks are attached to the glyph before them that isn't a mark, or
+    /// to the mark right before them, `is_mark` tells which glyphs are
+    /// marks. A mark attached again by a later lookup takes the later
+    /// position.
+    pub fn attach_marks<F>(&self, glyphs: &[usize], lookups: &[u16], is_mark: F)
+        -> Vec<Option<MarkAttachment>> where F: Fn(usize) -> bool
+    {
+        let mut attachments = vec![None; glyphs.len()];
+        for &lookup in lookups {
+            let subtables = match self.lookups.get(lookup as usize) {
+                Some(subtables) => subtables,
+                None => continue,
+            };
+            for i in 0..glyphs.len() {
+                if let Some(attachment) = subtables.iter()
+                    .filter_map(|subtable| subtable.attach(glyphs, i, &is_mark)).next() {
+                    attachments[i] = Some(attachment);
+                }
+            }
+        }
+        attachments
+    }
+}
+
+impl HeapSize for Anchor {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl HeapSize for MarkSubtable {
+    fn heap_size(&self) -> usize {
+        self.marks.heap_size() + self.mark_anchors.heap_size() + self.bases.heap_size()
+            + self.base_anchors.heap_size()
+    }
+}
+
+impl HeapSize for GPOS {
+    fn heap_size(&self) -> usize {
+        self.features.heap_size() + self.lookups.heap_size()
+    }
+}
+
+/// Returns a `GPOS` table attaching the marks `marks` to the bases `bases`
+/// by the `mark` feature, and to each other by the `mkmk` feature, with the
+/// anchors of the marks at (0, 0), anchors of bases at `(x, 500)` and of
+/// marks at `(0, 200)`.
+#[cfg(test)]
+pub fn mark_table(bases: &[(u16, i16)], marks: &[u16]) -> Vec<u8> {
+    use byteorder::BigEndian;
+    use utils::WriteBytesExt;
+    use super::common::{coverage_table, layout_table};
+
+    fn anchor(data: &mut Vec<u8>, x: i16, y: i16) {
+        for &v in &[1, x, y] {
+            data.write_i16::<BigEndian>(v).unwrap();
+        }
+    }
+    // A subtable attaching `marks` of class 0 to `bases` with an anchor
+    // each.
+    fn subtable(bases: &[(u16, i16, i16)], marks: &[u16]) -> Vec<u8> {
+        let mark_coverage = coverage_table(marks);
+        let base_coverage = coverage_table(&bases.iter().map(|b| b.0).collect::<Vec<_>>());
+        let mut mark_array = vec![];
+        mark_array.write_u16::<BigEndian>(marks.len() as u16).unwrap();
+        for i in 0..marks.len() {
+            mark_array.write_u16::<BigEndian>(0).unwrap();
+            mark_array.write_u16::<BigEndian>((2 + marks.len() * 4 + i * 6) as u16).unwrap();
+        }
+        for _ in marks {
+            anchor(&mut mark_array, 0, 0);
+        }
+        let mut base_array = vec![];
+        base_array.write_u16::<BigEndian>(bases.len() as u16).unwrap();
+        for i in 0..bases.len() {
+            base_array.write_u16::<BigEndian>((2 + bases.len() * 2 + i * 6) as u16).unwrap();
+        }
+        for &(_, x, y) in bases {
+            anchor(&mut base_array, x, y);
+        }
+
+        let mut data = vec![];
+        let marks_at = 12;
+        let bases_at = marks_at + mark_coverage.len();
+        let mark_array_at = bases_at + base_coverage.len();
+        let base_array_at = mark_array_at + mark_array.len();
+        for &v in &[1, marks_at, bases_at, 1, mark_array_at, base_array_at] {
+            data.write_u16::<BigEndian>(v as u16).unwrap();
+        }
+        data.extend(mark_coverage);
+        data.extend(base_coverage);
+        data.extend(mark_array);
+        data.extend(base_array);
+        data
+    }
+
+    layout_table(&[(GPOS::MARK, 0), (GPOS::MARK_TO_MARK, 1)], vec![
+        (4, subtable(&bases.iter().map(|&(g, x)| (g, x, 500)).collect::<Vec<_>>(), marks)),
+        (6, subtable(&marks.iter().map(|&g| (g, 0, 200)).collect::<Vec<_>>(), marks)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn mark_attachment() {
+        let data = mark_table(&[(1, 300), (2, 450)], &[5, 6]);
+        let gpos = GPOS::from_data(&data, 0).unwrap();
+        expect!(gpos.features()).to(be_equal_to(vec![GPOS::MARK, GPOS::MARK_TO_MARK]));
+        let lookups = gpos.feature_lookups(&[GPOS::MARK_TO_MARK, GPOS::MARK]);
+        expect!(lookups.clone()).to(be_equal_to(vec![0, 1]));
+        expect!((gpos.is_mark(5), gpos.is_mark(1))).to(be_equal_to((true, false)));
+
+        // A base with two marks, the second on the first, and a mark on a
+        // glyph it can't be attached to.
+        let glyphs = [2, 5, 6, 3, 5];
+        let attachments = gpos.attach_marks(&glyphs, &lookups, |g| gpos.is_mark(g));
+        expect!(attachments).to(be_equal_to(vec![
+            None,
+            Some(MarkAttachment { base: 0, x: 450, y: 500 }),
+            Some(MarkAttachment { base: 1, x: 0, y: 200 }),
+            None,
+            None,
+        ]));
+        // Without mark-to-mark both marks go on the base.
+        let attachments = gpos.attach_marks(&glyphs[..3], &[0], |g| gpos.is_mark(g));
+        expect!(attachments[2]).to(be_some().value(MarkAttachment { base: 0, x: 450, y: 500 }));
+
+        expect!(GPOS::from_data(&data[..data.len() - 1], 0).err()).to(be_some().value(Malformed));
+        expect!(GPOS::from_data(&data, data.len()).err()).to(be_some().value(Malformed));
+    }
+}
@@ -0,0 +1,261 @@
+use prelude::*;
+use Error;
+use Result;
+use utils::{HeapSize, read_u16_at};
+use super::common::{Coverage, FeatureList, read_lookups};
+
+/// A subtable of a lookup of type 1 or 4.
+#[derive(Debug, Clone)]
+enum Substitution {
+    /// Adds a delta to the covered glyphs.
+    Delta(Coverage, u16),
+    /// Replaces the covered glyphs by those of the same index.
+    Single(Coverage, Vec<u16>),
+    /// Replaces a covered glyph followed by the other components of a
+    /// ligature by the ligature, the first that matches of every set.
+    Ligature(Coverage, Vec<Vec<(Vec<u16>, u16)>>),
+}
+
+impl Substitution {
+    fn from_data(data: &[u8], offset: usize, lookup_type: u16) -> Result<Substitution> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let format = try!(u16_at(offset));
+        let coverage = try!(Coverage::from_data(data, offset + try!(u16_at(offset + 2)) as usize));
+        match (lookup_type, format) {
+            (1, 1) => Ok(Substitution::Delta(coverage, try!(u16_at(offset + 4)))),
+            (1, 2) => {
+                let mut glyphs = vec![];
+                for i in 0..try!(u16_at(offset + 4)) as usize {
+                    glyphs.push(try!(u16_at(offset + 6 + i * 2)));
+                }
+                Ok(Substitution::Single(coverage, glyphs))
+            }
+            (4, 1) => {
+                let mut sets = vec![];
+                for i in 0..try!(u16_at(offset + 4)) as usize {
+                    let set = offset + try!(u16_at(offset + 6 + i * 2)) as usize;
+                    let mut ligatures = vec![];
+                    for j in 0..try!(u16_at(set)) as usize {
+                        let ligature = set + try!(u16_at(set + 2 + j * 2)) as usize;
+                        let components = try!(u16_at(ligature + 2)) as usize;
+                        let mut rest = vec![];
+                        for k in 1..components {
+                            rest.push(try!(u16_at(ligature + 2 + k * 2)));
+                        }
+                        ligatures.push((rest, try!(u16_at(ligature))));
+                    }
+                    sets.push(ligatures);
+                }
+                Ok(Substitution::Ligature(coverage, sets))
+            }
+            _ => Err(Error::Malformed),
+        }
+    }
+
+    /// Replaces the glyph at index `i` of `glyphs` and the glyphs it forms a
+    /// ligature with, returns `false` if the subtable doesn't apply to it.
+    fn apply(&self, glyphs: &mut Vec<(usize, usize)>, i: usize) -> bool {
+        let glyph = glyphs[i].0;
+        match *self {
+            Substitution::Delta(ref coverage, delta) => {
+                if coverage.index(glyph).is_none() {
+                    return false;
+                }
+                glyphs[i].0 = (glyph as u16).wrapping_add(delta) as usize;
+            }
+            Substitution::Single(ref coverage, ref substitutes) => {
+                match coverage.index(glyph).and_then(|j| substitutes.get(j)) {
+                    Some(&substitute) => glyphs[i].0 = substitute as usize,
+                    None => return false,
+                }
+            }
+            Substitution::Ligature(ref coverage, ref sets) => {
+                let set = match coverage.index(glyph).and_then(|j| sets.get(j)) {
+                    Some(set) => set,
+                    None => return false,
+                };
+                let following = &glyphs[i + 1..];
+                let ligature = set.iter().find(|&&(ref rest, _)| {
+                    rest.len() <= following.len()
+                        && rest.iter().zip(following).all(|(&a, b)| a as usize == b.0)
+                });
+                match ligature {
+                    Some(&(ref rest, ligature)) => {
+                        glyphs[i].0 = ligature as usize;
+                        glyphs.drain(i + 1..i + 1 + rest.len());
+                    }
+                    None => return false,
+                }
+            }
+        }
+        true
+    }
+}
+
+/// A glyph substitution table.
+///
+/// Only the single and ligature substitutions are read, which replace
+/// glyphs by alternates like small capitals, and sequences of glyphs by
+/// ligatures. The features are read regardless of the script and the
+/// language.
+#[derive(Debug, Default, Clone)]
+pub struct GSUB {
+    features: FeatureList,
+    /// The subtables of every lookup, empty for other types.
+    lookups: Vec<Vec<Substitution>>,
+}
+
+impl GSUB {
+    /// Returns `GSUB` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the features and
+    /// the substitution lookups, or they have an unknown format.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<GSUB> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let _major_version = try!(u16_at(offset));
+        let features = try!(FeatureList::from_data(data, offset + try!(u16_at(offset + 6)) as usize));
+        let lookup_list = offset + try!(u16_at(offset + 8)) as usize;
+        let lookups = try!(read_lookups(data, lookup_list, 7, |lookup_type, subtable| {
+            match lookup_type {
+                1 | 4 => Substitution::from_data(data, subtable, lookup_type).map(Some),
+                _ => Ok(None),
+            }
+        }));
+        Ok(GSUB { features: features, lookups: lookups })
+    }
+
+    /// Returns the tags of the features of the font, in order, which may
+    /// repeat for different scripts.
+    pub fn features(&self) -> Vec<[u8; 4]> {
+        self.features.tags()
+    }
+
+    /// Returns the indices of the lookups of `features` in the order they
+    /// are applied.
+    pub fn feature_lookups(&self, features: &[[u8; 4]]) -> Vec<u16> {
+        self.features.lookups(features)
+    }
+
+    /// Returns `glyphs` with the substitutions of `lookups` applied, with
+    /// the index in `glyphs` of the first glyph every glyph replaces.
+    pub fn substitute(&self, glyphs: &[usize], lookups: &[u16]) -> Vec<(usize, usize)> {
+        let mut result: Vec<(usize, usize)> = glyphs.iter().cloned().zip(0..).collect();
+        for &lookup in lookups {
+            let subtables = match self.lookups.get(lookup as usize) {
+                Some(subtables) => subtables,
+                None => continue,
+            };
+            let mut i = 0;
+            while i < result.len() {
+                for subtable in subtables {
+                    if subtable.apply(&mut result, i) {
+                        break;
+                    }
+                }
+                i += 1;
+            }
+        }
+        result
+    }
+}
+
+impl HeapSize for Substitution {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Substitution::Delta(ref coverage, _) => coverage.heap_size(),
+            Substitution::Single(ref coverage, ref glyphs) => coverage.heap_size() + glyphs.heap_size(),
+            Substitution::Ligature(ref coverage, ref sets) => coverage.heap_size() + sets.heap_size(),
+        }
+    }
+}
+
+impl HeapSize for GSUB {
+    fn heap_size(&self) -> usize {
+        self.features.heap_size() + self.lookups.heap_size()
+    }
+}
+
+/// Returns a `GSUB` table replacing the glyphs of `singles` by the `smcp`
+/// feature and forming the ligatures of `ligatures` by the `liga` feature.
+#[cfg(test)]
+pub fn substitution_table(singles: &[(u16, u16)], ligatures: &[(&[u16], u16)]) -> Vec<u8> {
+    use byteorder::BigEndian;
+    use utils::WriteBytesExt;
+    use super::common::{coverage_table, layout_table};
+
+    let mut single = vec![];
+    let coverage = coverage_table(&singles.iter().map(|s| s.0).collect::<Vec<_>>());
+    for &v in &[2, 6 + singles.len() * 2, singles.len()] {
+        single.write_u16::<BigEndian>(v as u16).unwrap();
+    }
+    for &(_, substitute) in singles {
+        single.write_u16::<BigEndian>(substitute).unwrap();
+    }
+    single.extend(coverage);
+
+    // A set of a single ligature for every first glyph.
+    let mut ligature = vec![];
+    let coverage = coverage_table(&ligatures.iter().map(|l| l.0[0]).collect::<Vec<_>>());
+    let mut sets = vec![];
+    let mut offset = 6 + ligatures.len() * 2;
+    for &(components, glyph) in ligatures {
+        let mut set = vec![];
+        for &v in [1, 4, glyph, components.len() as u16].iter().chain(&components[1..]) {
+            set.write_u16::<BigEndian>(v).unwrap();
+        }
+        sets.push(offset);
+        offset += set.len();
+        ligature.extend(set);
+    }
+    let mut header = vec![];
+    for &v in [1, offset, ligatures.len()].iter().chain(&sets) {
+        header.write_u16::<BigEndian>(v as u16).unwrap();
+    }
+    header.extend(ligature);
+    header.extend(coverage);
+
+    layout_table(&[(*b"smcp", 0), (*b"liga", 1)], vec![(1, single), (4, header)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn substitutions() {
+        // 'f', 'i' and 'l' are glyphs 3, 4 and 5, 'fi' 6, 'ffi' 7 and
+        // small capitals 'I' and 'L' 8 and 9.
+        let data = substitution_table(&[(4, 8), (5, 9)], &[(&[3, 3, 4], 7), (&[4, 4], 10)]);
+        let gsub = GSUB::from_data(&data, 0).unwrap();
+        expect!(gsub.features()).to(be_equal_to(vec![*b"smcp", *b"liga"]));
+        expect!(gsub.feature_lookups(&[*b"liga"])).to(be_equal_to(vec![1]));
+
+        let glyphs = [3, 3, 4, 5, 4, 4];
+        expect!(gsub.substitute(&glyphs, &[1])).to(be_equal_to(vec![(7, 0), (5, 3), (10, 4)]));
+        expect!(gsub.substitute(&glyphs, &[0])).to(be_equal_to(vec![(3, 0), (3, 1), (8, 2),
+            (9, 3), (8, 4), (8, 5)]));
+        // Small capitals don't form ligatures.
+        expect!(gsub.substitute(&glyphs, &[0, 1])).to(be_equal_to(vec![(3, 0), (3, 1), (8, 2),
+            (9, 3), (8, 4), (8, 5)]));
+        expect!(gsub.substitute(&glyphs[..2], &[1])).to(be_equal_to(vec![(3, 0), (3, 1)]));
+        expect!(gsub.substitute(&glyphs, &[])).to(be_equal_to(vec![(3, 0), (3, 1), (4, 2),
+            (5, 3), (4, 4), (4, 5)]));
+
+        let data = delta_table();
+        let gsub = GSUB::from_data(&data, 0).unwrap();
+        expect!(gsub.substitute(&[2, 3, 9], &[0])).to(be_equal_to(vec![(2, 0), (13, 1), (9, 2)]));
+
+        expect!(GSUB::from_data(&data[..data.len() - 1], 0).err()).to(be_some().value(Malformed));
+    }
+
+    /// Returns a `GSUB` table adding 10 to glyph 3.
+    fn delta_table() -> Vec<u8> {
+        let subtable = vec![0, 1, 0, 6, 0, 10, 0, 1, 0, 1, 0, 3];
+        ::tables::common::layout_table(&[(*b"onum", 0)], vec![(1, subtable)])
+    }
+}
@@ -0,0 +1,94 @@
+use prelude::*;
+use Error;
+use Result;
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize};
+
+/// A horizontal device metrics table.
+///
+/// The 'hdmx' table stores the advance widths of all glyphs in whole
+/// pixels for some sizes, as they are after hinting.
+#[derive(Debug, Default, Clone)]
+pub struct HDMX {
+    // The size in pixels per em and the widths of every record.
+    records: Vec<(u8, Vec<u8>)>,
+}
+
+impl HDMX {
+    /// Returns `hdmx` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    /// `glyphs` is a number of glyphs in the font.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read.
+    pub fn from_data(data: &[u8], offset: usize, glyphs: u32) -> Result<HDMX> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let _version = try!(cursor.read_u16::<BigEndian>());
+        let records = try!(cursor.read_i16::<BigEndian>());
+        let record_size = try!(cursor.read_i32::<BigEndian>());
+        // Records are padded to a multiple of 4 bytes, so they can be longer
+        // than the size and widths.
+        if records < 0 || record_size < 2 + glyphs as i32 {
+            return Err(Error::Malformed);
+        }
+
+        let start = offset + 8;
+        let mut hdmx = HDMX::default();
+        for i in 0..records as usize {
+            let record = start + i * record_size as usize;
+            let bytes = try!(data.get(record..record + 2 + glyphs as usize).ok_or(Error::Malformed));
+            hdmx.records.push((bytes[0], bytes[2..].to_vec()));
+        }
+        Ok(hdmx)
+    }
+
+    /// Returns the sizes in pixels per em the table has widths for.
+    pub fn sizes(&self) -> Vec<u16> {
+        self.records.iter().map(|&(ppem, _)| ppem as u16).collect()
+    }
+
+    /// Returns the advance width in pixels of the glyph at index `i` at
+    /// `ppem` pixels per em.
+    ///
+    /// Returns `None` if the table has no record for the size or `i` is out
+    /// of bounds.
+    pub fn advance_width(&self, i: usize, ppem: u16) -> Option<u8> {
+        self.records.iter()
+            .find(|&&(size, _)| size as u16 == ppem)
+            .and_then(|&(_, ref widths)| widths.get(i).cloned())
+    }
+}
+
+impl HeapSize for HDMX {
+    fn heap_size(&self) -> usize {
+        self.records.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn advance_widths() {
+        // Two glyphs at 8 and 12 pixels per em, padded to 4 bytes.
+        let data = [0, 0, 0, 2, 0, 0, 0, 4, 8, 6, 3, 6, 12, 9, 5, 9];
+        let hdmx = HDMX::from_data(&data, 0, 2).unwrap();
+        expect!(hdmx.sizes()).to(be_equal_to(vec![8, 12]));
+        expect!(hdmx.advance_width(1, 8)).to(be_some().value(6));
+        expect!(hdmx.advance_width(0, 12)).to(be_some().value(5));
+        expect!(hdmx.advance_width(2, 12)).to(be_none());
+        expect!(hdmx.advance_width(0, 10)).to(be_none());
+
+        expect!(HDMX::from_data(&data[..15], 0, 2)).to(be_err().value(Malformed));
+        expect!(HDMX::from_data(&data, 0, 3)).to(be_err().value(Malformed));
+        expect!(HDMX::from_data(&data, data.len(), 2)).to(be_err().value(Malformed));
+    }
+}
@@ -1,15 +1,71 @@
 
+use prelude::*;
+use core::ops::{BitAnd, BitOr};
 use types::Fixed;
 use Error;
 use Result;
 use types::{BBox, LocationFormat};
-use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::BigEndian;
+use utils::{Cursor, WriteBytesExt};
+use super::Serialize;
+
+/// The style of a font, from the `macStyle` field of its header.
+///
+/// A set of flags that can be combined with `|` and intersected with `&`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct MacStyle(u16);
+
+impl MacStyle {
+    pub const BOLD: MacStyle = MacStyle(1 << 0);
+    pub const ITALIC: MacStyle = MacStyle(1 << 1);
+    pub const UNDERLINE: MacStyle = MacStyle(1 << 2);
+    pub const OUTLINE: MacStyle = MacStyle(1 << 3);
+    pub const SHADOW: MacStyle = MacStyle(1 << 4);
+    pub const CONDENSED: MacStyle = MacStyle(1 << 5);
+    pub const EXTENDED: MacStyle = MacStyle(1 << 6);
+
+    /// Returns the style with the flags set in `bits`, including undefined
+    /// ones.
+    pub fn from_bits(bits: u16) -> MacStyle {
+        MacStyle(bits)
+    }
+
+    /// Returns the flags as stored in the font.
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Returns `true` if no flags are set, for a regular font.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all flags of `other` are set.
+    pub fn contains(&self, other: MacStyle) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for MacStyle {
+    type Output = MacStyle;
+
+    fn bitor(self, other: MacStyle) -> MacStyle {
+        MacStyle(self.0 | other.0)
+    }
+}
+
+impl BitAnd for MacStyle {
+    type Output = MacStyle;
+
+    fn bitand(self, other: MacStyle) -> MacStyle {
+        MacStyle(self.0 & other.0)
+    }
+}
 
 /// A font header.
 ///
 /// The 'head' table contains global information about the font.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct HEAD {
     version: Fixed,
     font_revision: Fixed,
@@ -76,29 +132,26 @@ impl HEAD {
         Ok(head)
     }
 
-    #[cfg(test)]
-    fn bytes(&self) -> Vec<u8> {
-        use byteorder::WriteBytesExt;
+    /// Returns the value that makes the checksum of the whole font
+    /// equal to `0xB1B0AFBA`.
+    pub fn check_sum_adjustment(&self) -> u32 {
+        self.check_sum_adjustment
+    }
 
-        let mut data = vec![];
-        data.write_i32::<BigEndian>(self.version.0).unwrap();
-        data.write_i32::<BigEndian>(self.font_revision.0).unwrap();
-        data.write_u32::<BigEndian>(self.check_sum_adjustment).unwrap();
-        data.write_u32::<BigEndian>(self.magic_number).unwrap();
-        data.write_u16::<BigEndian>(self.flags).unwrap();
-        data.write_u16::<BigEndian>(self.units_per_em).unwrap();
-        data.write_i64::<BigEndian>(self.created).unwrap();
-        data.write_i64::<BigEndian>(self.modified).unwrap();
-        data.write_i16::<BigEndian>(self.x_min).unwrap();
-        data.write_i16::<BigEndian>(self.y_min).unwrap();
-        data.write_i16::<BigEndian>(self.x_max).unwrap();
-        data.write_i16::<BigEndian>(self.y_max).unwrap();
-        data.write_u16::<BigEndian>(self.mac_style).unwrap();
-        data.write_u16::<BigEndian>(self.lowest_rec_ppem).unwrap();
-        data.write_i16::<BigEndian>(self.font_direction_hint).unwrap();
-        data.write_u16::<BigEndian>(self.index_to_loc_format).unwrap();
-        data.write_i16::<BigEndian>(self.glyph_data_format).unwrap();
-        data
+    /// Returns the flags of the font, such as bit 0 for a baseline at
+    /// `y = 0` or bit 3 for integer scaling when hinting.
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// Returns the style of the font.
+    pub fn mac_style(&self) -> MacStyle {
+        MacStyle(self.mac_style)
+    }
+
+    /// Returns the smallest readable size of the font in pixels per em.
+    pub fn lowest_rec_ppem(&self) -> u16 {
+        self.lowest_rec_ppem
     }
 
     /// Returns the number of units per em for the font.
@@ -129,6 +182,30 @@ impl HEAD {
     }
 }
 
+impl Serialize for HEAD {
+    fn bytes(&self) -> Vec<u8> {
+        let mut data = vec![];
+        data.write_i32::<BigEndian>(self.version.0).unwrap();
+        data.write_i32::<BigEndian>(self.font_revision.0).unwrap();
+        data.write_u32::<BigEndian>(self.check_sum_adjustment).unwrap();
+        data.write_u32::<BigEndian>(self.magic_number).unwrap();
+        data.write_u16::<BigEndian>(self.flags).unwrap();
+        data.write_u16::<BigEndian>(self.units_per_em).unwrap();
+        data.write_i64::<BigEndian>(self.created).unwrap();
+        data.write_i64::<BigEndian>(self.modified).unwrap();
+        data.write_i16::<BigEndian>(self.x_min).unwrap();
+        data.write_i16::<BigEndian>(self.y_min).unwrap();
+        data.write_i16::<BigEndian>(self.x_max).unwrap();
+        data.write_i16::<BigEndian>(self.y_max).unwrap();
+        data.write_u16::<BigEndian>(self.mac_style).unwrap();
+        data.write_u16::<BigEndian>(self.lowest_rec_ppem).unwrap();
+        data.write_i16::<BigEndian>(self.font_direction_hint).unwrap();
+        data.write_u16::<BigEndian>(self.index_to_loc_format).unwrap();
+        data.write_i16::<BigEndian>(self.glyph_data_format).unwrap();
+        data
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,7 +216,7 @@ mod tests {
 
     #[test]
     fn smoke() {
-        let data = ::utils::read_file("tests/Tuffy_Bold.ttf");
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
         let offset = ::utils::find_table_offset(&data, 0, b"head").unwrap().unwrap();
 
         let head = HEAD::from_data(&data, offset).unwrap();
@@ -154,4 +231,21 @@ mod tests {
 
         expect!(HEAD::from_data(&data, data.len())).to(be_err().value(Malformed));
     }
+
+    #[test]
+    fn mac_style() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let offset = ::utils::find_table_offset(&data, 0, b"head").unwrap().unwrap();
+        let head = HEAD::from_data(&data, offset).unwrap();
+        expect!(head.mac_style()).to(be_equal_to(MacStyle::BOLD));
+        expect!(head.flags() & 1).to(be_equal_to(1));
+        expect!(head.lowest_rec_ppem()).to(be_equal_to(8));
+
+        let style = MacStyle::BOLD | MacStyle::ITALIC;
+        expect!(style.bits()).to(be_equal_to(3));
+        expect!(style.contains(MacStyle::ITALIC)).to(be_true());
+        expect!(style.contains(MacStyle::ITALIC | MacStyle::UNDERLINE)).to(be_false());
+        expect!(style & MacStyle::UNDERLINE).to(be_equal_to(MacStyle::default()));
+        expect!(MacStyle::default().is_empty()).to(be_true());
+    }
 }
@@ -1,9 +1,11 @@
 
+use prelude::*;
 use types::Fixed;
 use Error;
 use Result;
-use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::BigEndian;
+use utils::{Cursor, WriteBytesExt};
+use super::Serialize;
 
 /// A horizontal header.
 ///
@@ -14,7 +16,7 @@ use byteorder::{BigEndian, ReadBytesExt};
 /// these are expressed in unscaled coordinates, so you must multiply by
 /// the scale factor for a given size. You can advance the vertical position by
 /// `ascent - descent + line_gap`.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct HHEA {
     version: Fixed,
     ascent: i16,
@@ -71,17 +73,67 @@ impl HHEA {
         hhea.reserved3 = try!(cursor.read_i16::<BigEndian>());
         hhea.reserved4 = try!(cursor.read_i16::<BigEndian>());
         hhea.metric_data_format = try!(cursor.read_i16::<BigEndian>());
-        // TODO: Add error handling. Seems like `num_of_long_hor_metrics`
-        // should be >= 1. This is requirement for `hmtx` font table.
+        // `hmtx` checks that there is at least one long horizontal metric.
         hhea.num_of_long_hor_metrics = try!(cursor.read_u16::<BigEndian>());
 
         Ok(hhea)
     }
 
-    #[cfg(test)]
-    fn bytes(&self) -> Vec<u8> {
-        use byteorder::WriteBytesExt;
+    /// Distance from baseline of highest ascender.
+    pub fn ascent(&self) -> i32 {
+        self.ascent as i32
+    }
+
+    /// Distance from baseline of lowest descender (i.e. it is typically negative).
+    pub fn descent(&self) -> i32 {
+        self.descent as i32
+    }
+
+    /// The spacing between one row's descent and the next row's ascent.
+    #[allow(dead_code)]
+    pub fn line_gap(&self) -> i32 {
+        self.line_gap as i32
+    }
+
+    /// Returns the slope of the caret as `(rise, run, offset)`.
+    ///
+    /// The caret goes `run` units right for every `rise` units up, so
+    /// `(1, 0)` is vertical, and is moved right by `offset` units, which
+    /// is 0 for non-slanted fonts.
+    pub fn caret_slope(&self) -> (i16, i16, i16) {
+        (self.caret_slope_rise, self.caret_slope_run, self.caret_offset)
+    }
+
+    /// The number of advance widths in metrics table.
+    pub fn num_of_long_hor_metrics(&self) -> u32 {
+        self.num_of_long_hor_metrics as u32
+    }
+
+    /// Sets the distance from baseline of highest ascender.
+    pub fn set_ascent(&mut self, ascent: i16) {
+        self.ascent = ascent;
+    }
+
+    /// Sets the distance from baseline of lowest descender.
+    pub fn set_descent(&mut self, descent: i16) {
+        self.descent = descent;
+    }
 
+    /// Sets the spacing between one row's descent and the next row's ascent.
+    pub fn set_line_gap(&mut self, line_gap: i16) {
+        self.line_gap = line_gap;
+    }
+
+    /// Sets the number of advance widths in metrics table.
+    ///
+    /// Must be kept in sync with `HMTX::num_of_long_hor_metrics`.
+    pub fn set_num_of_long_hor_metrics(&mut self, metrics: u16) {
+        self.num_of_long_hor_metrics = metrics;
+    }
+}
+
+impl Serialize for HHEA {
+    fn bytes(&self) -> Vec<u8> {
         let mut data = vec![];
         data.write_i32::<BigEndian>(self.version.0).unwrap();
         data.write_i16::<BigEndian>(self.ascent).unwrap();
@@ -102,27 +154,6 @@ impl HHEA {
         data.write_u16::<BigEndian>(self.num_of_long_hor_metrics).unwrap();
         data
     }
-
-    /// Distance from baseline of highest ascender.
-    pub fn ascent(&self) -> i32 {
-        self.ascent as i32
-    }
-
-    /// Distance from baseline of lowest descender (i.e. it is typically negative).
-    pub fn descent(&self) -> i32 {
-        self.descent as i32
-    }
-
-    /// The spacing between one row's descent and the next row's ascent.
-    #[allow(dead_code)]
-    pub fn line_gap(&self) -> i32 {
-        self.line_gap as i32
-    }
-
-    /// The number of advance widths in metrics table.
-    pub fn num_of_long_hor_metrics(&self) -> u32 {
-        self.num_of_long_hor_metrics as u32
-    }
 }
 
 #[cfg(test)]
@@ -135,11 +166,12 @@ mod tests {
 
     #[test]
     fn smoke() {
-        let data = ::utils::read_file("tests/Tuffy_Bold.ttf");
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
         let offset = ::utils::find_table_offset(&data, 0, b"hhea").unwrap().unwrap();
 
         let hhea = HHEA::from_data(&data, offset).unwrap();
         assert_eq!(hhea.bytes(), &data[offset..offset + SIZE]);
+        expect!(hhea.caret_slope()).to(be_equal_to((1, 0, 0)));
 
         let hhea = HHEA::default();
         expect!(HHEA::from_data(&hhea.bytes(), 0)).to(be_err().value(HHEAVersionIsNotSupported));
@@ -1,11 +1,14 @@
 
+use prelude::*;
 use Error;
 use Result;
-use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize, WriteBytesExt};
+use super::Serialize;
 
 /// A record of horizontal metrics.
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LongHorizontalMetric {
     /// The offset from the current horizontal position to the next horizontal
     /// position.
@@ -19,7 +22,7 @@ pub struct LongHorizontalMetric {
 ///
 /// The 'hmtx' table contains metric information for the horizontal layout
 /// each of the glyphs in the font.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct HMTX {
     metrics: Vec<LongHorizontalMetric>,
     left_side_bearings: Vec<i16>,
@@ -34,13 +37,14 @@ impl HMTX {
     /// `glyphs` is a number of glyphs in the font.
     ///
     /// # Errors
-    /// Returns error if there is not enough data to read or the number of
-    /// `metrics` is greater than the number of `glyphs`.
+    /// Returns error if there is not enough data to read, the number of
+    /// `metrics` is greater than the number of `glyphs` or there are glyphs
+    /// but no `metrics`.
     pub fn from_data(data: &[u8], offset: usize, metrics: u32, glyphs: u32) -> Result<HMTX> {
         if offset >= data.len() {
             return Err(Error::Malformed);
         }
-        if metrics > glyphs {
+        if metrics > glyphs || metrics == 0 && glyphs > 0 {
             return Err(Error::Malformed);
         }
         let bearings = glyphs - metrics;
@@ -64,27 +68,12 @@ impl HMTX {
         Ok(hmtx)
     }
 
-    #[cfg(test)]
-    fn bytes(&self) -> Vec<u8> {
-        use byteorder::WriteBytesExt;
-
-        let mut data = vec![];
-        for metric in &self.metrics {
-            data.write_u16::<BigEndian>(metric.advance_width).unwrap();
-            data.write_i16::<BigEndian>(metric.left_side_bearing).unwrap();
-        }
-        for &bearing in &self.left_side_bearings {
-            data.write_i16::<BigEndian>(bearing).unwrap();
-        }
-        data
-    }
-
     /// Returns a horizontal metric for a glyph at a given index.
     pub fn hmetric_for_glyph_at_index(&self, i: usize) -> LongHorizontalMetric {
         if let Some(&metric) = self.metrics.get(i) {
             metric
         } else {
-            // It's safe to `unwrap` here, since valid font should contain
+            // It's safe to `unwrap` here, since `from_data` requires
             // at least one entry of horizontal metrics.
             let mut metric = *self.metrics.last().unwrap();
             if let Some(&lsb) = self.left_side_bearings.get(i - self.metrics.len()) {
@@ -93,6 +82,75 @@ impl HMTX {
             metric
         }
     }
+
+    /// Returns the advance width of the glyph at index `i`, without looking
+    /// up its left side bearing.
+    pub fn advance_width_for_glyph_at_index(&self, i: usize) -> u16 {
+        // Glyphs past the long metrics have the advance width of the last.
+        self.metrics.get(i).or_else(|| self.metrics.last()).map_or(0, |m| m.advance_width)
+    }
+
+    /// Returns the number of long horizontal metrics in the table.
+    pub fn num_of_long_hor_metrics(&self) -> u32 {
+        self.metrics.len() as u32
+    }
+
+    /// Replaces the horizontal metric of the glyph at index `i`.
+    ///
+    /// Glyphs past the last long metric share its advance width, so setting
+    /// a different advance width for such a glyph converts the bearings up to
+    /// it into long metrics. The number of long metrics in `hhea` has to be
+    /// updated accordingly.
+    ///
+    /// # Panics
+    /// Panics if `i` is not less than the number of glyphs.
+    pub fn set_hmetric_for_glyph_at_index(&mut self, i: usize, metric: LongHorizontalMetric) {
+        if i < self.metrics.len() {
+            self.metrics[i] = metric;
+            return;
+        }
+
+        let j = i - self.metrics.len();
+        assert!(j < self.left_side_bearings.len(), "glyph index is out of bounds");
+        let advance_width = self.metrics.last().map_or(0, |m| m.advance_width);
+        if metric.advance_width == advance_width {
+            self.left_side_bearings[j] = metric.left_side_bearing;
+        } else {
+            for b in self.left_side_bearings.drain(..j + 1) {
+                self.metrics.push(LongHorizontalMetric {
+                    advance_width: advance_width,
+                    left_side_bearing: b,
+                });
+            }
+            self.metrics[i] = metric;
+        }
+    }
+}
+
+impl Serialize for HMTX {
+    fn bytes(&self) -> Vec<u8> {
+        let mut data = vec![];
+        for metric in &self.metrics {
+            data.write_u16::<BigEndian>(metric.advance_width).unwrap();
+            data.write_i16::<BigEndian>(metric.left_side_bearing).unwrap();
+        }
+        for &bearing in &self.left_side_bearings {
+            data.write_i16::<BigEndian>(bearing).unwrap();
+        }
+        data
+    }
+}
+
+impl HeapSize for LongHorizontalMetric {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl HeapSize for HMTX {
+    fn heap_size(&self) -> usize {
+        self.metrics.heap_size() + self.left_side_bearings.heap_size()
+    }
 }
 
 #[cfg(test)]
@@ -104,7 +162,7 @@ mod tests {
 
     #[test]
     fn smoke() {
-        let data = ::utils::read_file("tests/Tuffy_Bold.ttf");
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
         let hhea_offset = ::utils::find_table_offset(&data, 0, b"hhea").unwrap().unwrap();
         let metrics = HHEA::from_data(&data, hhea_offset).unwrap().num_of_long_hor_metrics();
         let maxp_offset = ::utils::find_table_offset(&data, 0, b"maxp").unwrap().unwrap();
@@ -117,5 +175,33 @@ mod tests {
 
         expect!(HMTX::from_data(&data, data.len(), metrics, glyphs)).to(be_err().value(Malformed));
         expect!(HMTX::from_data(&data, hmtx_offset, 1, 0)).to(be_err().value(Malformed));
+        expect!(HMTX::from_data(&data, hmtx_offset, 0, glyphs)).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn set_hmetric() {
+        let data = [0, 10, 0, 1, 0, 2, 0, 3];
+        let mut hmtx = HMTX::from_data(&data, 0, 1, 3).unwrap();
+
+        let metric = LongHorizontalMetric { advance_width: 10, left_side_bearing: 5 };
+        hmtx.set_hmetric_for_glyph_at_index(2, metric);
+        expect!(hmtx.num_of_long_hor_metrics()).to(be_equal_to(1));
+        expect!(hmtx.hmetric_for_glyph_at_index(2)).to(be_equal_to(metric));
+
+        let metric = LongHorizontalMetric { advance_width: 20, left_side_bearing: 6 };
+        hmtx.set_hmetric_for_glyph_at_index(1, metric);
+        expect!(hmtx.num_of_long_hor_metrics()).to(be_equal_to(2));
+        expect!(hmtx.hmetric_for_glyph_at_index(1)).to(be_equal_to(metric));
+        expect!(hmtx.bytes()).to(be_equal_to(vec![0, 10, 0, 1, 0, 20, 0, 6, 0, 5]));
+    }
+
+    #[test]
+    fn advance_width() {
+        let data = [0, 10, 0, 1, 0, 20, 0, 2, 0, 3];
+        let hmtx = HMTX::from_data(&data, 0, 2, 3).unwrap();
+        expect!(hmtx.advance_width_for_glyph_at_index(0)).to(be_equal_to(10));
+        expect!(hmtx.advance_width_for_glyph_at_index(2)).to(be_equal_to(20));
+        expect!(hmtx.advance_width_for_glyph_at_index(100)).to(be_equal_to(20));
+        expect!(HMTX::default().advance_width_for_glyph_at_index(0)).to(be_equal_to(0));
     }
 }
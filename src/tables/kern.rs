@@ -0,0 +1,115 @@
+use prelude::*;
+use Error;
+use Result;
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize};
+
+/// A kerning table.
+///
+/// Only the first subtable is read, and only if it holds horizontal kerning
+/// pairs (format 0), like stb_truetype does. Other fonts have no kerning.
+#[derive(Debug, Default, Clone)]
+pub struct KERN {
+    // Sorted by the pair of glyphs, the left glyph in the high 16 bits.
+    pairs: Vec<(u32, i16)>,
+}
+
+impl KERN {
+    /// Returns `kern` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<KERN> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+
+        let mut kern = KERN::default();
+        let mut cursor = Cursor::new(&data[offset..]);
+        let _version = try!(cursor.read_u16::<BigEndian>());
+        let tables = try!(cursor.read_u16::<BigEndian>());
+        if tables == 0 {
+            return Ok(kern);
+        }
+
+        let _version = try!(cursor.read_u16::<BigEndian>());
+        let _length = try!(cursor.read_u16::<BigEndian>());
+        let coverage = try!(cursor.read_u16::<BigEndian>());
+        let pairs = try!(cursor.read_u16::<BigEndian>());
+        let _search_range = try!(cursor.read_u16::<BigEndian>());
+        let _entry_selector = try!(cursor.read_u16::<BigEndian>());
+        let _range_shift = try!(cursor.read_u16::<BigEndian>());
+        // Horizontal, format 0, without the minimum, cross-stream and
+        // override flags.
+        if coverage != 1 {
+            return Ok(kern);
+        }
+
+        kern.pairs.reserve(pairs as usize);
+        for _ in 0..pairs {
+            let pair = try!(cursor.read_u32::<BigEndian>());
+            let value = try!(cursor.read_i16::<BigEndian>());
+            kern.pairs.push((pair, value));
+        }
+        Ok(kern)
+    }
+
+    /// Returns `true` if the table has no kerning pairs.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns the kerned pairs of glyphs as the left and right glyph and
+    /// the kerning in unscaled coordinates, sorted by the glyphs.
+    pub fn pairs<'a>(&'a self) -> impl Iterator<Item = (u16, u16, i16)> + 'a {
+        self.pairs.iter().map(|&(pair, value)| ((pair >> 16) as u16, pair as u16, value))
+    }
+
+    /// Returns the kerning between the glyphs at indices `left` and `right`,
+    /// in unscaled coordinates.
+    ///
+    /// Returns 0 if the pair isn't kerned.
+    pub fn kerning(&self, left: usize, right: usize) -> i16 {
+        if left > 0xffff || right > 0xffff {
+            return 0;
+        }
+        let needle = (left as u32) << 16 | right as u32;
+        self.pairs.binary_search_by(|&(pair, _)| pair.cmp(&needle))
+            .map(|i| self.pairs[i].1)
+            .unwrap_or(0)
+    }
+}
+
+impl HeapSize for KERN {
+    fn heap_size(&self) -> usize {
+        self.pairs.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn smoke() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let offset = ::utils::find_table_offset(&data, 0, b"kern").unwrap().unwrap();
+
+        let kern = KERN::from_data(&data, offset).unwrap();
+        expect!(kern.is_empty()).to(be_false());
+        let &(pair, value) = kern.pairs.last().unwrap();
+        expect!(kern.kerning((pair >> 16) as usize, (pair & 0xffff) as usize)).to(be_equal_to(value));
+        expect!(kern.kerning(0, 0)).to(be_equal_to(0));
+        expect!(kern.pairs().count()).to(be_equal_to(kern.pairs.len()));
+        expect!(kern.pairs().all(|(left, right, value)| {
+            kern.kerning(left as usize, right as usize) == value
+        })).to(be_true());
+
+        expect!(KERN::from_data(&data[..offset + 20], offset)).to(be_err().value(Malformed));
+        expect!(KERN::from_data(&data, data.len())).to(be_err().value(Malformed));
+    }
+}
@@ -0,0 +1,222 @@
+use prelude::*;
+use alloc::collections::BTreeMap;
+use Error;
+use Result;
+use utils::{HeapSize, read_u16_at, read_u32_at};
+use super::aat::LookupTable;
+
+/// A subtable of format 0 or 2.
+#[derive(Debug, Clone)]
+enum Subtable {
+    /// The kerning of pairs of glyphs sorted by the glyphs, the left glyph
+    /// in the high 16 bits.
+    Pairs(Vec<(u32, i16)>),
+    /// The kerning of pairs of classes, the sum of the values of the left
+    /// and the right glyph being the index of the kerning.
+    Classes(LookupTable, LookupTable, Vec<i16>),
+}
+
+impl Subtable {
+    fn kerning(&self, left: usize, right: usize) -> i16 {
+        match *self {
+            Subtable::Pairs(ref pairs) => {
+                if left > 0xffff || right > 0xffff {
+                    return 0;
+                }
+                let needle = (left as u32) << 16 | right as u32;
+                pairs.binary_search_by(|&(pair, _)| pair.cmp(&needle))
+                    .map(|i| pairs[i].1)
+                    .unwrap_or(0)
+            }
+            Subtable::Classes(ref left_classes, ref right_classes, ref values) => {
+                let index = left_classes.get(left).unwrap_or(0) as usize
+                    + right_classes.get(right).unwrap_or(0) as usize;
+                values.get(index).cloned().unwrap_or(0)
+            }
+        }
+    }
+}
+
+/// An extended kerning table of Apple fonts.
+///
+/// Only the horizontal subtables of pairs (format 0) and classes (format 2)
+/// are read, the kerning of all of them is added up. Cross-stream and
+/// variation subtables and the state machines of the other formats are
+/// ignored.
+#[derive(Debug, Default, Clone)]
+pub struct KERX {
+    subtables: Vec<Subtable>,
+}
+
+impl KERX {
+    /// Returns `kerx` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position, `glyphs`
+    /// being the number of glyphs of the font.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the subtables, a
+    /// subtable is shorter than its header, or their class tables have an
+    /// unknown format.
+    pub fn from_data(data: &[u8], offset: usize, glyphs: u32) -> Result<KERX> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let u32_at = |i: usize| read_u32_at(data, i).map(|v| v as usize);
+        let _version = try!(u16_at(offset));
+        let mut subtables = vec![];
+        let mut subtable = offset + 8;
+        for _ in 0..try!(u32_at(offset + 4)) {
+            let length = try!(u32_at(subtable));
+            let end = subtable.saturating_add(length);
+            if length < 12 || end > data.len() {
+                return Err(Error::Malformed);
+            }
+            let coverage = try!(u32_at(subtable + 4));
+            // Vertical, cross-stream and variation subtables.
+            if coverage & 0xe000_0000 == 0 {
+                match coverage & 0xff {
+                    0 => {
+                        let pairs = try!(u32_at(subtable + 12));
+                        let mut kerning = Vec::with_capacity(pairs.min(length / 6));
+                        for i in 0..pairs {
+                            let pair = subtable + 28 + i * 6;
+                            kerning.push((try!(read_u32_at(data, pair)), try!(u16_at(pair + 4)) as i16));
+                        }
+                        kerning.sort_by_key(|&(pair, _)| pair);
+                        subtables.push(Subtable::Pairs(kerning));
+                    }
+                    2 => {
+                        let class_table = |position: usize| -> Result<LookupTable> {
+                            let start = subtable + try!(u32_at(subtable + position));
+                            LookupTable::from_data(data, start, glyphs as usize)
+                        };
+                        let (left, right) = (try!(class_table(16)), try!(class_table(20)));
+                        let array = subtable + try!(u32_at(subtable + 24));
+                        if end < array {
+                            return Err(Error::Malformed);
+                        }
+                        let mut values = Vec::with_capacity((end - array) / 2);
+                        for i in 0..(end - array) / 2 {
+                            values.push(try!(u16_at(array + i * 2)) as i16);
+                        }
+                        subtables.push(Subtable::Classes(left, right, values));
+                    }
+                    _ => {}
+                }
+            }
+            subtable = end;
+        }
+        Ok(KERX { subtables: subtables })
+    }
+
+    /// Returns `true` if the table has no subtables it can kern with.
+    pub fn is_empty(&self) -> bool {
+        self.subtables.is_empty()
+    }
+
+    /// Returns the kerned pairs of glyphs as the left and right glyph and
+    /// the kerning of all subtables in unscaled coordinates, sorted by the
+    /// glyphs.
+    ///
+    /// Class subtables kern the pairs of the glyphs in their class tables,
+    /// glyphs missing from them aren't paired.
+    pub fn pairs(&self) -> Vec<(u16, u16, i16)> {
+        let mut pairs = BTreeMap::new();
+        for subtable in &self.subtables {
+            match *subtable {
+                Subtable::Pairs(ref kerning) => {
+                    for &(pair, value) in kerning {
+                        let sum = pairs.entry(pair).or_insert(0i16);
+                        *sum = sum.wrapping_add(value);
+                    }
+                }
+                Subtable::Classes(ref left_classes, ref right_classes, ref values) => {
+                    let rights = right_classes.entries();
+                    for (left, left_class) in left_classes.entries() {
+                        for &(right, right_class) in &rights {
+                            let index = left_class as usize + right_class as usize;
+                            let value = values.get(index).cloned().unwrap_or(0);
+                            if value != 0 {
+                                let sum = pairs.entry((left as u32) << 16 | right as u32).or_insert(0i16);
+                                *sum = sum.wrapping_add(value);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        pairs.into_iter().filter(|&(_, value)| value != 0)
+            .map(|(pair, value)| ((pair >> 16) as u16, pair as u16, value))
+            .collect()
+    }
+
+    /// Returns the kerning between the glyphs at indices `left` and `right`,
+    /// in unscaled coordinates.
+    ///
+    /// Returns 0 if the pair isn't kerned.
+    pub fn kerning(&self, left: usize, right: usize) -> i16 {
+        self.subtables.iter()
+            .fold(0i16, |sum, subtable| sum.wrapping_add(subtable.kerning(left, right)))
+    }
+}
+
+impl HeapSize for Subtable {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Subtable::Pairs(ref pairs) => pairs.heap_size(),
+            Subtable::Classes(ref left, ref right, ref values) => {
+                left.heap_size() + right.heap_size() + values.heap_size()
+            }
+        }
+    }
+}
+
+impl HeapSize for KERX {
+    fn heap_size(&self) -> usize {
+        self.subtables.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn pairs_and_classes() {
+        let data = vec![
+            // Version 2 with 3 subtables.
+            0, 2, 0, 0, 0, 0, 0, 3,
+            // Format 0 with glyphs 4 and 5 kerned by -50, 4 and 6 by 20.
+            0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 2, 0, 0, 0, 12, 0, 0, 0, 1, 0, 0, 0, 0,
+            0, 4, 0, 6, 0, 20, 0, 4, 0, 5, 0xff, 0xce,
+            // A vertical subtable, ignored.
+            0, 0, 0, 12, 0x80, 0, 0, 0, 0, 0, 0, 0,
+            // Format 2 with glyphs 4 and 5 of the left classes 0 and 2 and
+            // glyph 5 of the right class 1, tables at 28, 40 and 48.
+            0, 0, 0, 56, 0, 0, 0, 2, 0, 0, 0, 0,
+            0, 0, 0, 4, 0, 0, 0, 28, 0, 0, 0, 40, 0, 0, 0, 48,
+            0, 8, 0, 4, 0, 2, 0, 0, 0, 2, 0, 0,
+            0, 8, 0, 5, 0, 1, 0, 1,
+            0, 0, 0, 0, 0, 0, 0, 10,
+        ];
+        let kerx = KERX::from_data(&data, 0, 8).unwrap();
+        expect!(kerx.is_empty()).to(be_false());
+        expect!(kerx.kerning(4, 5)).to(be_equal_to(-50));
+        expect!(kerx.kerning(4, 6)).to(be_equal_to(20));
+        expect!(kerx.kerning(5, 5)).to(be_equal_to(10));
+        expect!(kerx.kerning(5, 4)).to(be_equal_to(0));
+        expect!(kerx.kerning(6, 4)).to(be_equal_to(0));
+
+        expect!(kerx.pairs()).to(be_equal_to(vec![(4, 5, -50), (4, 6, 20), (5, 5, 10)]));
+
+        expect!(KERX::from_data(&data[..data.len() - 4], 0, 8).err()).to(be_some().value(Malformed));
+        // Subtables can't be empty, or be longer than the data.
+        let empty = [0, 2, 0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        expect!(KERX::from_data(&empty, 0, 8).err()).to(be_some().value(Malformed));
+        let long = [0, 2, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        expect!(KERX::from_data(&long, 0, 8).err()).to(be_some().value(Malformed));
+        expect!(KERX::default().kerning(4, 5)).to(be_equal_to(0));
+    }
+}
@@ -1,15 +1,18 @@
 
+use prelude::*;
+use core::ops::Range;
 use Error;
 use Result;
 use types::LocationFormat;
-use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize, WriteBytesExt};
+use super::Serialize;
 
 /// A location table.
 ///
 /// The 'loca' table stores the offsets to the locations of the glyphs
 /// in the font relative to the beginning of the 'glyf' table.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LOCA {
     offsets: Vec<u32>,
     format: LocationFormat,
@@ -58,10 +61,40 @@ impl LOCA {
         Ok(loca)
     }
 
-    #[cfg(test)]
-    fn bytes(&self) -> Vec<u8> {
-        use byteorder::WriteBytesExt;
+    /// Returns the offset to the location of the glyph in the font
+    /// relative to the beginning of the 'glyf' table.
+    ///
+    /// Returns `None` if `i` is out of bounds or if the font does not contain
+    /// an outline for the glyph at index `i`.
+    pub fn offset_for_glyph_at_index(&self, i: usize) -> Option<usize> {
+        self.offset(i).map(|range| range.start)
+    }
+
+    /// Returns the bytes of the glyph at index `i` in the 'glyf' table.
+    ///
+    /// Returns `None` if `i` is out of bounds or if the glyph is empty,
+    /// like a space. Glyphs that end before they start are empty too.
+    pub fn offset(&self, i: usize) -> Option<Range<usize>> {
+        match (self.offsets.get(i), self.offsets.get(i + 1)) {
+            (Some(&c), Some(&n)) if c < n => Some(c as usize..n as usize),
+            _ => None,
+        }
+    }
+
+    /// Returns offsets of all glyphs relative to the beginning of the 'glyf'
+    /// table. The last offset marks the end of the last glyph.
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
 
+    /// Returns the size of the `glyf` font table in bytes.
+    pub fn size_of_glyf_table(&self) -> usize {
+        self.offsets.last().map_or(0, |&n| n as usize)
+    }
+}
+
+impl Serialize for LOCA {
+    fn bytes(&self) -> Vec<u8> {
         let mut data = vec![];
         match self.format {
             LocationFormat::Short => {
@@ -77,23 +110,11 @@ impl LOCA {
         }
         data
     }
+}
 
-    /// Returns the offset to the location of the glyph in the font
-    /// relative to the beginning of the 'glyf' table.
-    ///
-    /// Returns `None` if `i` is out of bounds or if the font does not contain
-    /// an outline for the glyph at index `i`.
-    pub fn offset_for_glyph_at_index(&self, i: usize) -> Option<usize> {
-        if let (Some(c), Some(n)) = (self.offsets.get(i), self.offsets.get(i + 1)) {
-            if c == n { None } else { Some(*c as usize) }
-        } else {
-            None
-        }
-    }
-
-    /// Returns the size of the `glyf` font table in bytes.
-    pub fn size_of_glyf_table(&self) -> usize {
-        self.offsets.get(self.offsets.len() - 1).map(|&n| n as usize).unwrap_or(0)
+impl HeapSize for LOCA {
+    fn heap_size(&self) -> usize {
+        self.offsets.heap_size()
     }
 }
 
@@ -107,7 +128,7 @@ mod tests {
 
     #[test]
     fn smoke() {
-        let data = ::utils::read_file("tests/Tuffy_Bold.ttf");
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
         let maxp_offset = ::utils::find_table_offset(&data, 0, b"maxp").unwrap().unwrap();
         let glyphs = MAXP::from_data(&data, maxp_offset).unwrap().num_glyphs();
         let head_offset = ::utils::find_table_offset(&data, 0, b"head").unwrap().unwrap();
@@ -126,6 +147,8 @@ mod tests {
         let data = &[0, 50, 0, 100, 0, 200];
         let loca = LOCA::from_data(data, 0, 2, LocationFormat::Short).unwrap();
         expect!(loca.bytes()).to(be_equal_to(data));
+        expect!(loca.offset(1)).to(be_some().value(200..400));
+        expect!(loca.offset(2)).to(be_none());
         expect!(loca.offsets).to(be_equal_to([50 * 2, 100 * 2, 200 * 2]));
     }
 
@@ -134,6 +157,18 @@ mod tests {
         let data = &[0, 0, 0, 50, 0, 0, 0, 100, 0, 0, 0, 200];
         let loca = LOCA::from_data(data, 0, 2, LocationFormat::Long).unwrap();
         expect!(loca.bytes()).to(be_equal_to(data));
+        expect!(loca.offset(0)).to(be_some().value(50..100));
         expect!(loca.offsets).to(be_equal_to([50, 100, 200]));
     }
+
+    #[test]
+    fn empty_and_unsorted_glyphs() {
+        let data = &[0, 0, 0, 0, 0, 10, 0, 5];
+        let loca = LOCA::from_data(data, 0, 3, LocationFormat::Short).unwrap();
+        expect!(loca.offset(0)).to(be_none());
+        expect!(loca.offset(1)).to(be_some().value(0..20));
+        expect!(loca.offset(2)).to(be_none());
+        expect!(loca.offset(3)).to(be_none());
+        expect!(loca.offset_for_glyph_at_index(1)).to(be_some().value(0));
+    }
 }
@@ -1,18 +1,35 @@
 
+use prelude::*;
 use types::Fixed;
 use Error;
 use Result;
-use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::BigEndian;
+use utils::{Cursor, WriteBytesExt};
+use super::Serialize;
 
 /// A maximum profile.
 ///
 /// The 'maxp' table establishes the memory requirements for a font.
-/// TODO: implement parsing of 1.0 version of the table.
-#[derive(Debug, Default)]
+/// Version 0.5 of the table is used by fonts with CFF outlines and contains
+/// only the number of glyphs, version 1.0 adds limits for TrueType outlines
+/// and instructions.
+#[derive(Debug, Default, Clone)]
 pub struct MAXP {
     version: Fixed,
     num_glyphs: u16,
+    max_points: u16,
+    max_contours: u16,
+    max_composite_points: u16,
+    max_composite_contours: u16,
+    max_zones: u16,
+    max_twilight_points: u16,
+    max_storage: u16,
+    max_function_defs: u16,
+    max_instruction_defs: u16,
+    max_stack_elements: u16,
+    max_size_of_instructions: u16,
+    max_component_elements: u16,
+    max_component_depth: u16,
 }
 
 impl MAXP {
@@ -35,26 +52,55 @@ impl MAXP {
                 let mut maxp = MAXP::default();
                 maxp.version = version;
                 maxp.num_glyphs = try!(cursor.read_u16::<BigEndian>());
+                if version == Fixed(0x00010000) {
+                    maxp.max_points = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_contours = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_composite_points = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_composite_contours = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_zones = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_twilight_points = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_storage = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_function_defs = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_instruction_defs = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_stack_elements = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_size_of_instructions = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_component_elements = try!(cursor.read_u16::<BigEndian>());
+                    maxp.max_component_depth = try!(cursor.read_u16::<BigEndian>());
+                }
                 Ok(maxp)
             },
             _ => Err(Error::MAXPVersionIsNotSupported),
         }
     }
 
-    #[cfg(test)]
-    fn bytes(&self) -> Vec<u8> {
-        use byteorder::WriteBytesExt;
+    /// Returns the number of glyphs in the font.
+    pub fn num_glyphs(&self) -> u32 {
+        self.num_glyphs as u32
+    }
+}
 
+impl Serialize for MAXP {
+    fn bytes(&self) -> Vec<u8> {
         let mut data = vec![];
         data.write_i32::<BigEndian>(self.version.0).unwrap();
         data.write_u16::<BigEndian>(self.num_glyphs).unwrap();
+        if self.version == Fixed(0x00010000) {
+            data.write_u16::<BigEndian>(self.max_points).unwrap();
+            data.write_u16::<BigEndian>(self.max_contours).unwrap();
+            data.write_u16::<BigEndian>(self.max_composite_points).unwrap();
+            data.write_u16::<BigEndian>(self.max_composite_contours).unwrap();
+            data.write_u16::<BigEndian>(self.max_zones).unwrap();
+            data.write_u16::<BigEndian>(self.max_twilight_points).unwrap();
+            data.write_u16::<BigEndian>(self.max_storage).unwrap();
+            data.write_u16::<BigEndian>(self.max_function_defs).unwrap();
+            data.write_u16::<BigEndian>(self.max_instruction_defs).unwrap();
+            data.write_u16::<BigEndian>(self.max_stack_elements).unwrap();
+            data.write_u16::<BigEndian>(self.max_size_of_instructions).unwrap();
+            data.write_u16::<BigEndian>(self.max_component_elements).unwrap();
+            data.write_u16::<BigEndian>(self.max_component_depth).unwrap();
+        }
         data
     }
-
-    /// Returns the number of glyphs in the font.
-    pub fn num_glyphs(&self) -> u32 {
-        self.num_glyphs as u32
-    }
 }
 
 #[cfg(test)]
@@ -63,11 +109,11 @@ mod tests {
     use Error::*;
     use expectest::prelude::*;
 
-    const SIZE: usize = 4 + 2;
+    const SIZE: usize = 4 + 2 + 13 * 2;
 
     #[test]
     fn smoke() {
-        let data = ::utils::read_file("tests/Tuffy_Bold.ttf");
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
         let offset = ::utils::find_table_offset(&data, 0, b"maxp").unwrap().unwrap();
 
         let maxp = MAXP::from_data(&data, offset).unwrap();
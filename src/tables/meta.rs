@@ -0,0 +1,99 @@
+use prelude::*;
+use Error;
+use Result;
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize};
+
+/// A metadata table.
+///
+/// Only the design and supported languages are read, as lists of
+/// ScriptLangTags like `Latn`, `en-Latn` or `zh-Hant`.
+#[derive(Debug, Default, Clone)]
+pub struct META {
+    design_languages: Vec<String>,
+    supported_languages: Vec<String>,
+}
+
+impl META {
+    /// Returns `meta` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read or a list of
+    /// languages isn't UTF-8.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<META> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let _version = try!(cursor.read_u32::<BigEndian>());
+        let _flags = try!(cursor.read_u32::<BigEndian>());
+        let _reserved = try!(cursor.read_u32::<BigEndian>());
+        let maps = try!(cursor.read_u32::<BigEndian>());
+
+        let mut meta = META::default();
+        for _ in 0..maps {
+            let mut tag = [0; 4];
+            for byte in tag.iter_mut() {
+                *byte = try!(cursor.read_u8());
+            }
+            let start = offset + try!(cursor.read_u32::<BigEndian>()) as usize;
+            let end = start + try!(cursor.read_u32::<BigEndian>()) as usize;
+            let languages = match &tag {
+                b"dlng" => &mut meta.design_languages,
+                b"slng" => &mut meta.supported_languages,
+                _ => continue,
+            };
+            let bytes = try!(data.get(start..end).ok_or(Error::Malformed));
+            let text = try!(::core::str::from_utf8(bytes).map_err(|_| Error::Malformed));
+            languages.extend(text.split(',').map(|tag| tag.trim())
+                .filter(|tag| !tag.is_empty()).map(String::from));
+        }
+        Ok(meta)
+    }
+
+    /// Returns the languages the font was designed for.
+    pub fn design_languages(&self) -> &[String] {
+        &self.design_languages
+    }
+
+    /// Returns the languages the font can display.
+    pub fn supported_languages(&self) -> &[String] {
+        &self.supported_languages
+    }
+}
+
+impl HeapSize for META {
+    fn heap_size(&self) -> usize {
+        self.design_languages.heap_size() + self.supported_languages.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn languages() {
+        let mut data = vec![
+            0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3,
+            b'd', b'l', b'n', b'g', 0, 0, 0, 52, 0, 0, 0, 4,
+            b'a', b'p', b'p', b'l', 0, 0, 0, 52, 0, 0, 0, 4,
+            b's', b'l', b'n', b'g', 0, 0, 0, 56, 0, 0, 0, 18,
+        ];
+        data.extend_from_slice(b"LatnCyrl, en-Latn,Grek");
+        let meta = META::from_data(&data, 0).unwrap();
+        expect!(meta.design_languages()).to(be_equal_to(&["Latn".to_string()][..]));
+        expect!(meta.supported_languages())
+            .to(be_equal_to(&["Cyrl".to_string(), "en-Latn".to_string(), "Grek".to_string()][..]));
+
+        expect!(META::from_data(&data[..data.len() - 1], 0)).to(be_err().value(Malformed));
+        data[57] = 0xff;
+        expect!(META::from_data(&data, 0)).to(be_err().value(Malformed));
+        expect!(META::from_data(&data, data.len())).to(be_err().value(Malformed));
+    }
+}
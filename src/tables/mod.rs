@@ -1,3 +1,4 @@
+use prelude::*;
 
 mod hhea;
 mod head;
@@ -6,12 +7,65 @@ mod hmtx;
 mod loca;
 mod cmap;
 mod glyf;
+mod ttcf;
+mod kern;
+mod gasp;
+mod hdmx;
+mod vdmx;
+mod os2;
+mod meta;
+mod name;
+mod post;
+mod base;
+mod common;
+mod gdef;
+mod gpos;
+mod gsub;
+mod aat;
+mod kerx;
+mod morx;
+mod trak;
 
 pub use self::hhea::HHEA;
-pub use self::head::HEAD;
+pub use self::head::{HEAD, MacStyle};
 pub use self::maxp::MAXP;
 pub use self::hmtx::{HMTX, LongHorizontalMetric};
 pub use self::loca::LOCA;
 pub use self::cmap::CMAP;
-pub use self::glyf::{GLYF, GlyphData};
+pub use self::glyf::{GLYF, GlyphData, Outline, Component, check_glyph};
+pub use self::ttcf::TTCF;
+pub use self::kern::KERN;
+pub use self::gasp::{GASP, RenderingBehavior};
+pub use self::hdmx::HDMX;
+pub use self::vdmx::VDMX;
+pub use self::os2::{OS2, Embedding, Permissions, ScriptMetrics};
+pub use self::meta::META;
+pub use self::name::{NAME, Name};
+pub use self::post::POST;
+pub use self::base::{BASE, ScriptBaselines};
+pub use self::gdef::{GDEF, GlyphClass, Caret};
+pub use self::gpos::{GPOS, MarkAttachment};
+pub use self::gsub::GSUB;
+pub use self::kerx::KERX;
+pub use self::morx::MORX;
+pub use self::trak::TRAK;
+#[cfg(test)]
+pub use self::gpos::mark_table;
+#[cfg(test)]
+pub use self::gsub::substitution_table;
 
+/// Returns the font file `font` with the `extra` tables added.
+#[cfg(test)]
+pub fn with_tables(font: &[u8], extra: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    let mut tables: Vec<_> = ::utils::read_table_records(font, 0).unwrap().into_iter()
+        .map(|r| (r.tag, font[r.offset..r.offset + r.length].to_vec())).collect();
+    tables.extend(extra);
+    ::utils::assemble_font(tables)
+}
+
+
+/// A font table that can be written back into a font file.
+pub trait Serialize {
+    /// Returns the binary representation of the table.
+    fn bytes(&self) -> Vec<u8>;
+}
@@ -0,0 +1,130 @@
+use prelude::*;
+use Error;
+use Result;
+use utils::{HeapSize, read_u16_at, read_u32_at};
+use super::aat::LookupTable;
+
+/// An extended glyph metamorphosis table of Apple fonts.
+///
+/// Only the non-contextual subtables are read, which replace glyphs one
+/// by one, and only those of the features the chains turn on by default.
+/// The state machines of the other types, e.g. for ligatures, are
+/// ignored.
+#[derive(Debug, Default, Clone)]
+pub struct MORX {
+    substitutions: Vec<LookupTable>,
+}
+
+impl MORX {
+    /// Returns `morx` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position, `glyphs`
+    /// being the number of glyphs of the font.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the chains, a chain
+    /// or subtable is shorter than its header or longer than its chain, the
+    /// version is older than 2 or a lookup table has an unknown format.
+    pub fn from_data(data: &[u8], offset: usize, glyphs: u32) -> Result<MORX> {
+        let u32_at = |i: usize| read_u32_at(data, i);
+        if try!(read_u16_at(data, offset)) < 2 {
+            return Err(Error::Malformed);
+        }
+        let mut substitutions = vec![];
+        let mut chain = offset + 8;
+        for _ in 0..try!(u32_at(offset + 4)) {
+            let default_flags = try!(u32_at(chain));
+            let chain_end = chain.saturating_add(try!(u32_at(chain + 4)) as usize);
+            if chain_end < chain + 16 || chain_end > data.len() {
+                return Err(Error::Malformed);
+            }
+            let features = try!(u32_at(chain + 8)) as usize;
+            let mut subtable = chain + 16 + features * 12;
+            for _ in 0..try!(u32_at(chain + 12)) {
+                let length = try!(u32_at(subtable)) as usize;
+                let end = subtable.saturating_add(length);
+                if length < 12 || end > chain_end {
+                    return Err(Error::Malformed);
+                }
+                let coverage = try!(u32_at(subtable + 4));
+                let flags = try!(u32_at(subtable + 8));
+                // Vertical subtables, unless they apply to both directions.
+                let vertical = coverage & 0x8000_0000 != 0 && coverage & 0x2000_0000 == 0;
+                if coverage & 0xff == 4 && flags & default_flags != 0 && !vertical {
+                    substitutions.push(try!(LookupTable::from_data(data, subtable + 12, glyphs as usize)));
+                }
+                subtable = end;
+            }
+            chain = chain_end;
+        }
+        Ok(MORX { substitutions: substitutions })
+    }
+
+    /// Returns `true` if the table has no substitutions.
+    pub fn is_empty(&self) -> bool {
+        self.substitutions.is_empty()
+    }
+
+    /// Replaces the `glyphs` by their substitutes, in the order of the
+    /// subtables.
+    pub fn substitute(&self, glyphs: &mut [usize]) {
+        for substitution in &self.substitutions {
+            for glyph in glyphs.iter_mut() {
+                if let Some(substitute) = substitution.get(*glyph) {
+                    *glyph = substitute as usize;
+                }
+            }
+        }
+    }
+}
+
+impl HeapSize for MORX {
+    fn heap_size(&self) -> usize {
+        self.substitutions.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn non_contextual() {
+        let data = vec![
+            // Version 2 with a chain.
+            0, 2, 0, 0, 0, 0, 0, 1,
+            // Default flags 1, a feature and 3 subtables.
+            0, 0, 0, 1, 0, 0, 0, 82, 0, 0, 0, 1, 0, 0, 0, 3,
+            0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1,
+            // Glyphs 3 and 4 become 7 and 8.
+            0, 0, 0, 22, 0, 0, 0, 4, 0, 0, 0, 1,
+            0, 8, 0, 3, 0, 2, 0, 7, 0, 8,
+            // Glyph 7 becomes 9 with a feature that is off.
+            0, 0, 0, 20, 0, 0, 0, 4, 0, 0, 0, 2,
+            0, 8, 0, 7, 0, 1, 0, 9,
+            // A ligature subtable, ignored.
+            0, 0, 0, 12, 0, 0, 0, 2, 0, 0, 0, 1,
+        ];
+        let morx = MORX::from_data(&data, 0, 10).unwrap();
+        expect!(morx.is_empty()).to(be_false());
+        let mut glyphs = [2, 3, 4, 7];
+        morx.substitute(&mut glyphs);
+        expect!(glyphs).to(be_equal_to([2, 7, 8, 7]));
+
+        expect!(MORX::from_data(&data[..40], 0, 10).err()).to(be_some().value(Malformed));
+        expect!(MORX::from_data(&[0, 1, 0, 0, 0, 0, 0, 0], 0, 10).err()).to(be_some().value(Malformed));
+        // Chains and subtables can't be empty, or be longer than the data.
+        let mut empty = data.clone();
+        empty[4..8].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        empty[12..16].copy_from_slice(&[0, 0, 0, 0]);
+        expect!(MORX::from_data(&empty, 0, 10).err()).to(be_some().value(Malformed));
+        let mut long = data.clone();
+        long[15] = 83;
+        expect!(MORX::from_data(&long, 0, 10).err()).to(be_some().value(Malformed));
+        let mut empty = data.clone();
+        empty[81] = 0;
+        expect!(MORX::from_data(&empty, 0, 10).err()).to(be_some().value(Malformed));
+    }
+}
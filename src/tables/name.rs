@@ -0,0 +1,248 @@
+use prelude::*;
+use Error;
+use Result;
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize};
+
+/// A naming table.
+///
+/// Only the names in Unicode and in Mac Roman are read, other encodings are
+/// skipped.
+#[derive(Debug, Default, Clone)]
+pub struct NAME {
+    names: Vec<Name>,
+}
+
+/// A string of the naming table.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Name {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub language_id: u16,
+    /// What the string names, e.g. `NAME::FAMILY`.
+    pub name_id: u16,
+    pub string: String,
+}
+
+impl NAME {
+    pub const COPYRIGHT: u16 = 0;
+    pub const FAMILY: u16 = 1;
+    pub const SUBFAMILY: u16 = 2;
+    pub const UNIQUE_ID: u16 = 3;
+    pub const FULL_NAME: u16 = 4;
+    pub const VERSION: u16 = 5;
+    pub const POSTSCRIPT_NAME: u16 = 6;
+    /// The family of fonts with more styles than regular, italic, bold and
+    /// bold italic, which have other weights as part of the `FAMILY`.
+    pub const TYPOGRAPHIC_FAMILY: u16 = 16;
+    pub const TYPOGRAPHIC_SUBFAMILY: u16 = 17;
+
+    /// Returns `name` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the records. Strings
+    /// outside of the data are skipped.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<NAME> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let _format = try!(cursor.read_u16::<BigEndian>());
+        let count = try!(cursor.read_u16::<BigEndian>());
+        let strings = offset + try!(cursor.read_u16::<BigEndian>()) as usize;
+
+        let mut names = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let platform_id = try!(cursor.read_u16::<BigEndian>());
+            let encoding_id = try!(cursor.read_u16::<BigEndian>());
+            let language_id = try!(cursor.read_u16::<BigEndian>());
+            let name_id = try!(cursor.read_u16::<BigEndian>());
+            let length = try!(cursor.read_u16::<BigEndian>()) as usize;
+            let start = strings + try!(cursor.read_u16::<BigEndian>()) as usize;
+            let bytes = match data.get(start..start + length) {
+                Some(bytes) => bytes,
+                None => continue,
+            };
+            let string = match (platform_id, encoding_id) {
+                (0, _) | (3, 0) | (3, 1) | (3, 10) => decode_utf16(bytes),
+                (1, 0) => decode_mac_roman(bytes),
+                _ => continue,
+            };
+            names.push(Name {
+                platform_id: platform_id,
+                encoding_id: encoding_id,
+                language_id: language_id,
+                name_id: name_id,
+                string: string,
+            });
+        }
+        Ok(NAME { names: names })
+    }
+
+    /// Returns all names the table has in a known encoding.
+    pub fn names(&self) -> &[Name] {
+        &self.names
+    }
+
+    /// Returns the strings with `name_id` in all languages.
+    pub fn strings<'a>(&'a self, name_id: u16) -> impl Iterator<Item = &'a str> + 'a {
+        self.names.iter().filter(move |name| name.name_id == name_id)
+            .map(|name| &name.string[..])
+    }
+
+    /// Returns the string with `name_id` in American English, or else in
+    /// another English or any other language.
+    pub fn string(&self, name_id: u16) -> Option<&str> {
+        self.names.iter().filter(|name| name.name_id == name_id)
+            .min_by_key(|name| match (name.platform_id, name.language_id) {
+                (3, 0x409) => 0,
+                // Mac English.
+                (1, 0) => 1,
+                // Unicode has no languages.
+                (0, _) => 2,
+                // English of other countries.
+                (3, language) if language & 0x3ff == 0x09 => 3,
+                _ => 4,
+            })
+            .map(|name| &name.string[..])
+    }
+
+    /// Checks whether one of the strings with `name_id` in any language is
+    /// `string`, ignoring case.
+    pub fn has_string(&self, name_id: u16, string: &str) -> bool {
+        let string = fold_case(string);
+        self.strings(name_id).any(|s| fold_case(s) == string)
+    }
+}
+
+/// Returns `s` in lower case, with the characters that only differ in case
+/// from others in upper case too mapped to them, like "ß" to "ss", so that
+/// strings equal ignoring case are equal.
+fn fold_case(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ſ' => folded.push('s'),
+            'ς' => folded.push('σ'),
+            'ϐ' => folded.push('β'),
+            'ϑ' => folded.push('θ'),
+            'ϕ' => folded.push('φ'),
+            'ϖ' => folded.push('π'),
+            'ϰ' => folded.push('κ'),
+            'ϱ' => folded.push('ρ'),
+            'ϵ' => folded.push('ε'),
+            'ﬀ' => folded.push_str("ff"),
+            'ﬁ' => folded.push_str("fi"),
+            'ﬂ' => folded.push_str("fl"),
+            'ﬃ' => folded.push_str("ffi"),
+            'ﬄ' => folded.push_str("ffl"),
+            'ﬅ' | 'ﬆ' => folded.push_str("st"),
+            c => folded.extend(c.to_lowercase()),
+        }
+    }
+    folded
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units = bytes.chunks(2).filter(|c| c.len() == 2)
+        .map(|c| (c[0] as u16) << 8 | c[1] as u16);
+    ::core::char::decode_utf16(units)
+        .map(|c| c.unwrap_or(::core::char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn decode_mac_roman(bytes: &[u8]) -> String {
+    // The characters from 0x80, the others are ASCII.
+    const HIGH: &str = "ÄÅÇÉÑÖÜáàâäãåçéèêëíìîïñóòôöõúùûü†°¢£§•¶ß®©™´¨≠ÆØ∞±≤≥¥µ∂∑∏π∫ªºΩæø\
+        ¿¡¬√ƒ≈∆«»…\u{a0}ÀÃÕŒœ–—“”‘’÷◊ÿŸ⁄€‹›ﬁﬂ‡·‚„‰ÂÊÁËÈÍÎÏÌÓÔ\u{f8ff}ÒÚÛÙıˆ˜¯˘˙˚¸˝˛ˇ";
+    bytes.iter().map(|&b| {
+        if b < 0x80 { b as char } else { HIGH.chars().nth(b as usize - 0x80).unwrap() }
+    }).collect()
+}
+
+impl HeapSize for Name {
+    fn heap_size(&self) -> usize {
+        self.string.heap_size()
+    }
+}
+
+impl HeapSize for NAME {
+    fn heap_size(&self) -> usize {
+        self.names.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn smoke() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let offset = ::utils::find_table_offset(&data, 0, b"name").unwrap().unwrap();
+
+        let name = NAME::from_data(&data, offset).unwrap();
+        expect!(name.strings(NAME::FAMILY).all(|s| s == "Tuffy")).to(be_true());
+        expect!(name.strings(NAME::SUBFAMILY).next()).to(be_some().value("Bold"));
+        expect!(name.has_string(NAME::FAMILY, "TUFFY")).to(be_true());
+        expect!(name.has_string(NAME::FAMILY, "Tuff")).to(be_false());
+
+        expect!(NAME::from_data(&data, data.len())).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn encodings() {
+        let mut data = vec![
+            0, 0, 0, 3, 0, 42,
+            0, 1, 0, 0, 0, 0, 0, 1, 0, 4, 0, 0,
+            0, 3, 0, 1, 4, 9, 0, 1, 0, 6, 0, 4,
+            // Out of bounds.
+            0, 3, 0, 1, 4, 9, 0, 2, 0, 6, 0, 10,
+        ];
+        data.extend_from_slice(b"Caf\x8e");
+        data.extend_from_slice(&[0, b'C', 0, b'a', 0, 0xe9]);
+        let name = NAME::from_data(&data, 0).unwrap();
+        expect!(name.names().len()).to(be_equal_to(2));
+        expect!(name.names()[0].string.clone()).to(be_equal_to("Café".to_string()));
+        expect!(name.names()[1].clone()).to(be_equal_to(Name {
+            platform_id: 3, encoding_id: 1, language_id: 0x409, name_id: 1,
+            string: "Caé".to_string(),
+        }));
+        expect!(name.has_string(NAME::FAMILY, "CAFÉ")).to(be_true());
+
+        expect!(NAME::from_data(&data[..20], 0)).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn languages() {
+        let name = |platform_id, language_id, string: &str| Name {
+            platform_id: platform_id, encoding_id: 1, language_id: language_id,
+            name_id: NAME::FAMILY, string: string.to_string(),
+        };
+        let mut table = NAME { names: vec![
+            name(3, 0x407, "Straße"), name(3, 0x809, "Street"), name(1, 0, "Mac"),
+            name(3, 0x409, "Street US"),
+        ] };
+        expect!(table.string(NAME::FAMILY)).to(be_some().value("Street US"));
+        table.names.pop();
+        expect!(table.string(NAME::FAMILY)).to(be_some().value("Mac"));
+        table.names.pop();
+        expect!(table.string(NAME::FAMILY)).to(be_some().value("Street"));
+        table.names.pop();
+        expect!(table.string(NAME::FAMILY)).to(be_some().value("Straße"));
+        expect!(table.string(NAME::SUBFAMILY)).to(be_none());
+
+        // Matching compares all languages and folds case.
+        expect!(table.has_string(NAME::FAMILY, "STRASSE")).to(be_true());
+        expect!(table.has_string(NAME::FAMILY, "strasse")).to(be_true());
+        expect!(table.has_string(NAME::FAMILY, "Strase")).to(be_false());
+        expect!(fold_case("ΣΟΦΟΣ")).to(be_equal_to(fold_case("σοφος")));
+        expect!(fold_case("ﬁne")).to(be_equal_to("fine".to_string()));
+    }
+}
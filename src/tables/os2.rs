@@ -0,0 +1,351 @@
+use Error;
+use Result;
+use byteorder::{BigEndian, ByteOrder};
+use utils::Cursor;
+
+const RESTRICTED_LICENSE: u16 = 0x0002;
+const PREVIEW_AND_PRINT: u16 = 0x0004;
+const EDITABLE: u16 = 0x0008;
+const NO_SUBSETTING: u16 = 0x0100;
+const BITMAP_ONLY: u16 = 0x0200;
+
+// Flags of fsSelection.
+const ITALIC: u16 = 0x0001;
+const BOLD: u16 = 0x0020;
+const USE_TYPO_METRICS: u16 = 0x0080;
+const OBLIQUE: u16 = 0x0200;
+
+/// How a font may be embedded in documents, from the `fsType` field of its
+/// `OS/2` table.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Embedding {
+    /// The font may be embedded and installed permanently.
+    Installable,
+    /// The font must not be embedded without permission of the owner.
+    RestrictedLicense,
+    /// The font may be embedded to view and print documents, which are
+    /// read-only.
+    PreviewAndPrint,
+    /// The font may be embedded in documents that can be edited.
+    Editable,
+}
+
+/// The embedding permissions of a font, see `FontInfo::permissions`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Permissions {
+    /// How the font may be embedded.
+    pub embedding: Embedding,
+    /// Only the whole font may be embedded, not a subset.
+    pub no_subsetting: bool,
+    /// Only the bitmaps of the font may be embedded, not its outlines.
+    pub bitmap_only: bool,
+}
+
+impl Default for Permissions {
+    /// No restrictions, what fonts without an `OS/2` table have.
+    fn default() -> Permissions {
+        Permissions::from_fs_type(0)
+    }
+}
+
+impl Permissions {
+    /// Returns the permissions of the `fsType` field of an `OS/2` table.
+    ///
+    /// Old fonts may set several embedding flags, the least restrictive one
+    /// is used.
+    pub fn from_fs_type(fs_type: u16) -> Permissions {
+        let embedding = if fs_type & EDITABLE != 0 {
+            Embedding::Editable
+        } else if fs_type & PREVIEW_AND_PRINT != 0 {
+            Embedding::PreviewAndPrint
+        } else if fs_type & RESTRICTED_LICENSE != 0 {
+            Embedding::RestrictedLicense
+        } else {
+            Embedding::Installable
+        };
+        Permissions {
+            embedding: embedding,
+            no_subsetting: fs_type & NO_SUBSETTING != 0,
+            bitmap_only: fs_type & BITMAP_ONLY != 0,
+        }
+    }
+}
+
+/// The size and position of subscripts or superscripts in font units, see
+/// `OS2::subscript` and `OS2::superscript`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ScriptMetrics {
+    /// The horizontal size of the em of the glyphs.
+    pub x_size: i16,
+    /// The vertical size of the em of the glyphs.
+    pub y_size: i16,
+    /// How far to move the glyphs right, e.g. for italics.
+    pub x_offset: i16,
+    /// How far to move the glyphs from the baseline, down for subscripts and
+    /// up for superscripts.
+    pub y_offset: i16,
+}
+
+impl ScriptMetrics {
+    fn from_bytes(bytes: &[u8]) -> ScriptMetrics {
+        ScriptMetrics {
+            x_size: BigEndian::read_i16(bytes),
+            y_size: BigEndian::read_i16(&bytes[2..]),
+            x_offset: BigEndian::read_i16(&bytes[4..]),
+            y_offset: BigEndian::read_i16(&bytes[6..]),
+        }
+    }
+}
+
+/// An OS/2 and Windows specific metrics table.
+///
+/// Only the fields up to `fsType`, the subscripts, superscripts and
+/// strikeout, the Unicode ranges, `fsSelection`, the vertical metrics and
+/// the heights of letters are read for now.
+#[derive(Debug, Default, Clone)]
+pub struct OS2 {
+    version: u16,
+    weight_class: u16,
+    width_class: u16,
+    fs_type: u16,
+    fs_selection: u16,
+    scripts: Option<(ScriptMetrics, ScriptMetrics)>,
+    strikeout: Option<(i16, i16)>,
+    unicode_ranges: Option<[u32; 4]>,
+    typo_metrics: Option<(i16, i16, i16)>,
+    win_metrics: Option<(u16, u16)>,
+    heights: Option<(i16, i16)>,
+}
+
+impl OS2 {
+    /// Returns `OS/2` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position, `length`
+    /// being the length of the table in the table directory. Fields past it
+    /// are missing, even if `data` goes on.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read.
+    pub fn from_data(data: &[u8], offset: usize, length: usize) -> Result<OS2> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+        let data = &data[..::core::cmp::min(offset.saturating_add(length), data.len())];
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let mut os2 = OS2::default();
+        os2.version = try!(cursor.read_u16::<BigEndian>());
+        let _x_avg_char_width = try!(cursor.read_i16::<BigEndian>());
+        os2.weight_class = try!(cursor.read_u16::<BigEndian>());
+        os2.width_class = try!(cursor.read_u16::<BigEndian>());
+        os2.fs_type = try!(cursor.read_u16::<BigEndian>());
+        // Some old fonts end the table early, `fsSelection` is 0 then.
+        if let Some(bytes) = data.get(offset + 10..offset + 26) {
+            os2.scripts = Some((ScriptMetrics::from_bytes(bytes),
+                ScriptMetrics::from_bytes(&bytes[8..])));
+        }
+        if let Some(bytes) = data.get(offset + 26..offset + 30) {
+            os2.strikeout = Some((BigEndian::read_i16(&bytes[2..]), BigEndian::read_i16(bytes)));
+        }
+        if let Some(bytes) = data.get(offset + 42..offset + 58) {
+            let mut ranges = [0; 4];
+            BigEndian::read_u32_into(bytes, &mut ranges);
+            os2.unicode_ranges = Some(ranges);
+        }
+        if let Some(bytes) = data.get(offset + 62..offset + 64) {
+            os2.fs_selection = BigEndian::read_u16(bytes);
+        }
+        if let Some(bytes) = data.get(offset + 68..offset + 78) {
+            os2.typo_metrics = Some((BigEndian::read_i16(bytes), BigEndian::read_i16(&bytes[2..]),
+                BigEndian::read_i16(&bytes[4..])));
+            os2.win_metrics = Some((BigEndian::read_u16(&bytes[6..]),
+                BigEndian::read_u16(&bytes[8..])));
+        }
+        // Only from version 2.
+        match data.get(offset + 86..offset + 90) {
+            Some(bytes) if os2.version >= 2 => {
+                os2.heights = Some((BigEndian::read_i16(bytes), BigEndian::read_i16(&bytes[2..])));
+            }
+            _ => {}
+        }
+        Ok(os2)
+    }
+
+    /// Returns the version of the table.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns the weight of the font from 1 to 1000, 400 is regular and
+    /// 700 bold.
+    pub fn weight_class(&self) -> u16 {
+        self.weight_class
+    }
+
+    /// Returns the width of the font from 1 for ultra-condensed to 9 for
+    /// ultra-expanded, 5 is normal.
+    pub fn width_class(&self) -> u16 {
+        self.width_class
+    }
+
+    /// Returns the style flags as stored in the font.
+    pub fn fs_selection(&self) -> u16 {
+        self.fs_selection
+    }
+
+    /// Returns `true` if the font is italic or oblique.
+    pub fn is_italic(&self) -> bool {
+        self.fs_selection & (ITALIC | OBLIQUE) != 0
+    }
+
+    /// Returns `true` if the font is the bold style of its family.
+    pub fn is_bold(&self) -> bool {
+        self.fs_selection & BOLD != 0
+    }
+
+    /// Returns the size and offset of subscripts the font recommends, `None`
+    /// if the table ends before them.
+    pub fn subscript(&self) -> Option<ScriptMetrics> {
+        self.scripts.map(|scripts| scripts.0)
+    }
+
+    /// Returns the size and offset of superscripts the font recommends,
+    /// `None` if the table ends before them.
+    pub fn superscript(&self) -> Option<ScriptMetrics> {
+        self.scripts.map(|scripts| scripts.1)
+    }
+
+    /// Returns the distance from the baseline to the top of the strikeout
+    /// and its thickness, `None` if the table ends before them.
+    pub fn strikeout(&self) -> Option<(i16, i16)> {
+        self.strikeout
+    }
+
+    /// Returns the bits of the Unicode blocks the font claims to cover,
+    /// `ulUnicodeRange1` to `ulUnicodeRange4`. `None` if the table ends
+    /// before them.
+    pub fn unicode_ranges(&self) -> Option<[u32; 4]> {
+        self.unicode_ranges
+    }
+
+    /// Returns `true` if the font asks to lay out lines with the typographic
+    /// metrics instead of those of `hhea` or the Windows ones.
+    pub fn use_typo_metrics(&self) -> bool {
+        self.fs_selection & USE_TYPO_METRICS != 0
+    }
+
+    /// Returns the typographic ascender, descender and line gap, `None` if
+    /// the table ends before them.
+    pub fn typo_metrics(&self) -> Option<(i16, i16, i16)> {
+        self.typo_metrics
+    }
+
+    /// Returns the Windows ascent and descent, both positive, the extents
+    /// outside of which Windows clips glyphs. `None` if the table ends
+    /// before them.
+    pub fn win_metrics(&self) -> Option<(u16, u16)> {
+        self.win_metrics
+    }
+
+    /// Returns the height of lowercase letters like 'x', `None` before
+    /// version 2 of the table or if the font leaves it 0.
+    pub fn x_height(&self) -> Option<i16> {
+        self.heights.map(|heights| heights.0).filter(|&height| height > 0)
+    }
+
+    /// Returns the height of capital letters like 'H', `None` before
+    /// version 2 of the table or if the font leaves it 0.
+    pub fn cap_height(&self) -> Option<i16> {
+        self.heights.map(|heights| heights.1).filter(|&height| height > 0)
+    }
+
+    /// Returns the embedding licensing rights as stored in the font.
+    pub fn fs_type(&self) -> u16 {
+        self.fs_type
+    }
+
+    /// Returns the embedding permissions, see `Permissions::from_fs_type`.
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_fs_type(self.fs_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn smoke() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let offset = ::utils::find_table_offset(&data, 0, b"OS/2").unwrap().unwrap();
+
+        let length = ::utils::find_table_with_length(&data, 0, b"OS/2").unwrap().unwrap().1;
+        let os2 = OS2::from_data(&data, offset, length).unwrap();
+        expect!(os2.version()).to(be_equal_to(4));
+        expect!(os2.permissions()).to(be_equal_to(Permissions::default()));
+        expect!(os2.weight_class()).to(be_equal_to(700));
+        expect!(os2.width_class()).to(be_equal_to(5));
+        expect!(os2.is_bold()).to(be_true());
+        expect!(os2.is_italic()).to(be_false());
+        expect!(os2.use_typo_metrics()).to(be_true());
+        expect!(os2.typo_metrics()).to(be_some().value((1597, -505, 0)));
+        expect!(os2.win_metrics()).to(be_some().value((1950, 451)));
+        expect!(os2.strikeout()).to(be_some().value((530, 102)));
+        expect!(os2.subscript()).to(be_some().value(ScriptMetrics {
+            x_size: 1331, y_size: 1433, x_offset: 0, y_offset: 286,
+        }));
+        expect!(os2.superscript().map(|s| s.y_offset)).to(be_some().value(983));
+        expect!(os2.x_height()).to(be_some().value(1073));
+        expect!(os2.cap_height()).to(be_some().value(1456));
+        expect!(os2.unicode_ranges()).to(be_some().value([0xa00002ef, 0x1000207b, 0, 0]));
+
+        // Fields past fsType are optional.
+        let short = OS2::from_data(&data[..offset + 70], offset, length).unwrap();
+        expect!(short.is_bold()).to(be_true());
+        expect!(short.typo_metrics()).to(be_none());
+        expect!(short.x_height()).to(be_none());
+        expect!(OS2::from_data(&data[..offset + 25], offset, length).unwrap().superscript())
+            .to(be_none());
+        expect!(OS2::from_data(&data[..offset + 10], offset, length).unwrap().fs_selection())
+            .to(be_equal_to(0));
+        // The table can end before the next one starts.
+        expect!(OS2::from_data(&data, offset, 62).unwrap().fs_selection()).to(be_equal_to(0));
+        expect!(OS2::from_data(&data, offset, 64).unwrap().is_bold()).to(be_true());
+        let scripts = OS2::from_data(&data, offset, 29).unwrap();
+        expect!(scripts.superscript().map(|s| s.y_offset)).to(be_some().value(983));
+        expect!(scripts.strikeout()).to(be_none());
+        expect!(OS2::from_data(&data, offset, 25).unwrap().subscript()).to(be_none());
+        let typo = OS2::from_data(&data, offset, 77).unwrap();
+        expect!(typo.typo_metrics()).to(be_none());
+        expect!(typo.win_metrics()).to(be_none());
+        expect!(OS2::from_data(&data, offset, 78).unwrap().win_metrics()).to(be_some().value((1950, 451)));
+        expect!(OS2::from_data(&data, offset, 57).unwrap().unicode_ranges()).to(be_none());
+        expect!(OS2::from_data(&data, offset, 58).unwrap().unicode_ranges())
+            .to(be_some().value([0xa00002ef, 0x1000207b, 0, 0]));
+        let heights = OS2::from_data(&data, offset, 89).unwrap();
+        expect!(heights.x_height()).to(be_none());
+        expect!(heights.cap_height()).to(be_none());
+        expect!(OS2::from_data(&data, offset, 90).unwrap().cap_height()).to(be_some().value(1456));
+
+        expect!(OS2::from_data(&data[..offset + 9], offset, length)).to(be_err().value(Malformed));
+        expect!(OS2::from_data(&data, offset, 9)).to(be_err().value(Malformed));
+        expect!(OS2::from_data(&data, data.len(), length)).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn permissions() {
+        let permissions = Permissions::from_fs_type(PREVIEW_AND_PRINT | NO_SUBSETTING);
+        expect!(permissions.embedding).to(be_equal_to(Embedding::PreviewAndPrint));
+        expect!(permissions.no_subsetting).to(be_true());
+        expect!(permissions.bitmap_only).to(be_false());
+
+        // The least restrictive flag wins.
+        let permissions = Permissions::from_fs_type(RESTRICTED_LICENSE | EDITABLE | BITMAP_ONLY);
+        expect!(permissions.embedding).to(be_equal_to(Embedding::Editable));
+        expect!(permissions.bitmap_only).to(be_true());
+        expect!(Permissions::from_fs_type(RESTRICTED_LICENSE).embedding)
+            .to(be_equal_to(Embedding::RestrictedLicense));
+    }
+}
@@ -0,0 +1,85 @@
+use types::Fixed;
+use Error;
+use Result;
+use byteorder::BigEndian;
+use utils::Cursor;
+
+/// A PostScript table.
+///
+/// Only the header is read, the names of the glyphs that follow it are
+/// skipped.
+#[derive(Debug, Default, Clone)]
+pub struct POST {
+    version: Fixed,
+    italic_angle: Fixed,
+    underline_position: i16,
+    underline_thickness: i16,
+    is_fixed_pitch: u32,
+}
+
+impl POST {
+    /// Returns `post` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read the header.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<POST> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let mut post = POST::default();
+        post.version = Fixed(try!(cursor.read_i32::<BigEndian>()));
+        post.italic_angle = Fixed(try!(cursor.read_i32::<BigEndian>()));
+        post.underline_position = try!(cursor.read_i16::<BigEndian>());
+        post.underline_thickness = try!(cursor.read_i16::<BigEndian>());
+        post.is_fixed_pitch = try!(cursor.read_u32::<BigEndian>());
+        Ok(post)
+    }
+
+    /// Returns the angle of italics in degrees counter-clockwise from the
+    /// vertical, negative for fonts leaning to the right.
+    pub fn italic_angle(&self) -> f32 {
+        self.italic_angle.0 as f32 / 65536.0
+    }
+
+    /// Returns the distance from the baseline to the top of the underline,
+    /// negative below the baseline.
+    pub fn underline_position(&self) -> i16 {
+        self.underline_position
+    }
+
+    /// Returns the thickness of the underline.
+    pub fn underline_thickness(&self) -> i16 {
+        self.underline_thickness
+    }
+
+    /// Returns `true` if all glyphs of the font are as wide.
+    pub fn is_fixed_pitch(&self) -> bool {
+        self.is_fixed_pitch != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn smoke() {
+        let data = ::utils::read_file("assets/Tuffy_Bold.ttf");
+        let offset = ::utils::find_table_offset(&data, 0, b"post").unwrap().unwrap();
+
+        let post = POST::from_data(&data, offset).unwrap();
+        expect!(post.underline_position()).to(be_equal_to(-255));
+        expect!(post.underline_thickness()).to(be_equal_to(102));
+        expect!(post.italic_angle()).to(be_equal_to(0.0));
+        expect!(post.is_fixed_pitch()).to(be_false());
+
+        expect!(POST::from_data(&data[..offset + 15], offset)).to(be_err().value(Malformed));
+        expect!(POST::from_data(&data, data.len())).to(be_err().value(Malformed));
+    }
+}
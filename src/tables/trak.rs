@@ -0,0 +1,128 @@
+use prelude::*;
+use Error;
+use Result;
+use utils::{HeapSize, read_u16_at, read_u32_at};
+
+/// A tracking table of Apple fonts.
+///
+/// Gives the space to add between the glyphs at point sizes, usually
+/// positive at small sizes to keep text legible and negative at large
+/// ones. Only the horizontal tracks are read.
+#[derive(Debug, Default, Clone)]
+pub struct TRAK {
+    /// The point sizes the values are given for, in increasing order.
+    sizes: Vec<f32>,
+    /// The tracks and their value at every size, in font units.
+    tracks: Vec<(f32, Vec<i16>)>,
+}
+
+impl TRAK {
+    /// Returns `trak` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read, or the sizes
+    /// aren't in increasing order.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<TRAK> {
+        let u16_at = |i: usize| read_u16_at(data, i);
+        let fixed_at = |i: usize| read_u32_at(data, i).map(|v| v as i32 as f32 / 65536.0);
+        let _version = try!(read_u32_at(data, offset));
+        let horizontal = try!(u16_at(offset + 6)) as usize;
+        if horizontal == 0 {
+            return Ok(TRAK::default());
+        }
+        let horizontal = offset + horizontal;
+        let count = try!(u16_at(horizontal + 2)) as usize;
+        let size_table = offset + try!(read_u32_at(data, horizontal + 4)) as usize;
+        let mut sizes = Vec::with_capacity(count);
+        for i in 0..count {
+            sizes.push(try!(fixed_at(size_table + i * 4)));
+        }
+        // Interpolating between equal sizes would divide by zero.
+        if sizes.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(Error::Malformed);
+        }
+        let mut tracks = vec![];
+        for i in 0..try!(u16_at(horizontal)) as usize {
+            let entry = horizontal + 8 + i * 8;
+            let values = offset + try!(u16_at(entry + 6)) as usize;
+            let mut track = Vec::with_capacity(count);
+            for j in 0..count {
+                track.push(try!(u16_at(values + j * 2)) as i16);
+            }
+            tracks.push((try!(fixed_at(entry)), track));
+        }
+        Ok(TRAK { sizes: sizes, tracks: tracks })
+    }
+
+    /// Returns `true` if the table has no horizontal tracks.
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Returns the tracking of `track` at the point size `size` in font
+    /// units, 0 if the font has no such track.
+    ///
+    /// The normal track is 0, looser tracks are positive and tighter ones
+    /// negative. Sizes between those of the table are interpolated, those
+    /// beyond them get the value of the nearest.
+    pub fn tracking(&self, track: f32, size: f32) -> f32 {
+        let values = match self.tracks.iter().find(|&&(t, _)| t == track) {
+            Some(&(_, ref values)) if !values.is_empty() => values,
+            _ => return 0.0,
+        };
+        let value = |i: usize| values[i] as f32;
+        match self.sizes.iter().position(|&s| s >= size) {
+            Some(0) => value(0),
+            Some(i) => {
+                let (below, above) = (self.sizes[i - 1], self.sizes[i]);
+                let t = (size - below) / (above - below);
+                value(i - 1) + t * (value(i) - value(i - 1))
+            }
+            None => value(self.sizes.len() - 1),
+        }
+    }
+}
+
+impl HeapSize for TRAK {
+    fn heap_size(&self) -> usize {
+        self.sizes.heap_size() + self.tracks.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn tracking() {
+        let data = [
+            0, 1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0,
+            // 2 tracks of 2 sizes at 36.
+            0, 2, 0, 2, 0, 0, 0, 36,
+            // The normal track with values at 44, and a tight one at 48.
+            0, 0, 0, 0, 0, 0, 0, 44, 0xff, 0xff, 0, 0, 0, 0, 0, 48,
+            // 12 and 24 points.
+            0, 12, 0, 0, 0, 24, 0, 0,
+            0, 20, 0xff, 0xf6, 0xff, 0xe2, 0xff, 0xc4,
+        ];
+        let trak = TRAK::from_data(&data, 0).unwrap();
+        expect!(trak.is_empty()).to(be_false());
+        expect!(trak.tracking(0.0, 12.0)).to(be_equal_to(20.0));
+        expect!(trak.tracking(0.0, 18.0)).to(be_equal_to(5.0));
+        expect!(trak.tracking(0.0, 24.0)).to(be_equal_to(-10.0));
+        expect!(trak.tracking(0.0, 6.0)).to(be_equal_to(20.0));
+        expect!(trak.tracking(0.0, 48.0)).to(be_equal_to(-10.0));
+        expect!(trak.tracking(-1.0, 12.0)).to(be_equal_to(-30.0));
+        expect!(trak.tracking(1.0, 12.0)).to(be_equal_to(0.0));
+
+        expect!(TRAK::from_data(&data[..data.len() - 1], 0).err()).to(be_some().value(Malformed));
+        let mut unsorted = data;
+        unsorted[41] = 12;
+        expect!(TRAK::from_data(&unsorted, 0).err()).to(be_some().value(Malformed));
+        expect!(TRAK::default().tracking(0.0, 12.0)).to(be_equal_to(0.0));
+    }
+}
@@ -0,0 +1,176 @@
+use prelude::*;
+use types::Fixed;
+use Error;
+use Result;
+use utils::prefix_is_tag;
+use byteorder::BigEndian;
+use utils::Cursor;
+
+/// A font collection header.
+///
+/// The 'ttcf' header is located at the very beginning of a TrueType Collection
+/// file and lists the offsets to the table directories of all fonts in the
+/// collection. Version 2.0 of the header additionally locates a digital
+/// signature ('DSIG') table that covers the whole collection.
+#[derive(Debug, Default, Clone)]
+pub struct TTCF {
+    tag: u32,
+    version: Fixed,
+    num_fonts: u32,
+    offsets: Vec<u32>,
+    dsig_tag: u32,
+    dsig_length: u32,
+    dsig_offset: u32,
+}
+
+impl TTCF {
+    /// Returns `ttcf` collection header.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read, if the data doesn't
+    /// start with the `ttcf` tag, if the version of the header is not supported
+    /// or if the `DSIG` table of a version 2.0 header lies outside of `data`.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<TTCF> {
+        if offset >= data.len() || !prefix_is_tag(&data[offset..], b"ttcf") {
+            return Err(Error::Malformed);
+        }
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let mut ttcf = TTCF::default();
+        ttcf.tag = try!(cursor.read_u32::<BigEndian>());
+        ttcf.version = Fixed(try!(cursor.read_i32::<BigEndian>()));
+        if ttcf.version != Fixed(0x00010000) && ttcf.version != Fixed(0x00020000) {
+            return Err(Error::TTCFVersionIsNotSupported);
+        }
+
+        ttcf.num_fonts = try!(cursor.read_u32::<BigEndian>());
+        // Check the count before allocating, every offset takes 4 bytes.
+        if ttcf.num_fonts as usize > (data.len() - offset) / 4 {
+            return Err(Error::Malformed);
+        }
+        ttcf.offsets = Vec::with_capacity(ttcf.num_fonts as usize);
+        for _ in 0..ttcf.num_fonts {
+            ttcf.offsets.push(try!(cursor.read_u32::<BigEndian>()));
+        }
+
+        if ttcf.version == Fixed(0x00020000) {
+            ttcf.dsig_tag = try!(cursor.read_u32::<BigEndian>());
+            ttcf.dsig_length = try!(cursor.read_u32::<BigEndian>());
+            ttcf.dsig_offset = try!(cursor.read_u32::<BigEndian>());
+            if let Some((start, length)) = ttcf.dsig() {
+                if start.checked_add(length).map_or(true, |end| end > data.len()) {
+                    return Err(Error::Malformed);
+                }
+            }
+        }
+
+        Ok(ttcf)
+    }
+
+    #[cfg(test)]
+    fn bytes(&self) -> Vec<u8> {
+        use utils::WriteBytesExt;
+
+        let mut data = vec![];
+        data.write_u32::<BigEndian>(self.tag).unwrap();
+        data.write_i32::<BigEndian>(self.version.0).unwrap();
+        data.write_u32::<BigEndian>(self.num_fonts).unwrap();
+        for &offset in &self.offsets {
+            data.write_u32::<BigEndian>(offset).unwrap();
+        }
+        if self.version == Fixed(0x00020000) {
+            data.write_u32::<BigEndian>(self.dsig_tag).unwrap();
+            data.write_u32::<BigEndian>(self.dsig_length).unwrap();
+            data.write_u32::<BigEndian>(self.dsig_offset).unwrap();
+        }
+        data
+    }
+
+    /// Returns the number of fonts in the collection.
+    pub fn num_fonts(&self) -> usize {
+        self.num_fonts as usize
+    }
+
+    /// Returns the offset to the table directory of the font at index `i`.
+    ///
+    /// Returns `None` if `i` is out of bounds. The offset itself is not
+    /// validated.
+    pub fn offset_for_font_at_index(&self, i: usize) -> Option<usize> {
+        self.offsets.get(i).map(|&offset| offset as usize)
+    }
+
+    /// Returns the offset and the length of the `DSIG` table of the collection.
+    ///
+    /// Returns `None` if the header has version 1.0 or if the collection
+    /// is not signed.
+    pub fn dsig(&self) -> Option<(usize, usize)> {
+        if self.version == Fixed(0x00020000) && &u32_tag(self.dsig_tag) == b"DSIG" {
+            Some((self.dsig_offset as usize, self.dsig_length as usize))
+        } else {
+            None
+        }
+    }
+}
+
+fn u32_tag(tag: u32) -> [u8; 4] {
+    [(tag >> 24) as u8, (tag >> 16) as u8, (tag >> 8) as u8, tag as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use types::Fixed;
+    use expectest::prelude::*;
+
+    #[test]
+    fn smoke() {
+        let mut ttcf = TTCF::default();
+        ttcf.tag = 0x74746366; // 'ttcf'
+        ttcf.version = Fixed(0x00010000);
+        ttcf.num_fonts = 2;
+        ttcf.offsets = vec![20, 120];
+        let data = ttcf.bytes();
+
+        let parsed = TTCF::from_data(&data, 0).unwrap();
+        assert_eq!(parsed.bytes(), data);
+        expect!(parsed.num_fonts()).to(be_equal_to(2));
+        expect!(parsed.offset_for_font_at_index(1)).to(be_some().value(120));
+        expect!(parsed.offset_for_font_at_index(2)).to(be_none());
+        expect!(parsed.dsig()).to(be_none());
+
+        ttcf.version = Fixed(0x00030000);
+        expect!(TTCF::from_data(&ttcf.bytes(), 0)).to(be_err().value(TTCFVersionIsNotSupported));
+
+        ttcf.version = Fixed(0x00010000);
+        ttcf.num_fonts = 3;
+        expect!(TTCF::from_data(&ttcf.bytes(), 0)).to(be_err().value(Malformed));
+
+        expect!(TTCF::from_data(&data, data.len())).to(be_err().value(Malformed));
+        expect!(TTCF::from_data(b"true\0\0\0\0", 0)).to(be_err().value(Malformed));
+    }
+
+    #[test]
+    fn version_2_dsig() {
+        let mut ttcf = TTCF::default();
+        ttcf.tag = 0x74746366;
+        ttcf.version = Fixed(0x00020000);
+        ttcf.num_fonts = 1;
+        ttcf.offsets = vec![24];
+        ttcf.dsig_tag = 0x44534947; // 'DSIG'
+        ttcf.dsig_length = 8;
+        ttcf.dsig_offset = 24;
+
+        let mut data = ttcf.bytes();
+        expect!(TTCF::from_data(&data, 0)).to(be_err().value(Malformed));
+
+        data.extend_from_slice(&[0; 8]);
+        let parsed = TTCF::from_data(&data, 0).unwrap();
+        expect!(parsed.dsig()).to(be_some().value((24, 8)));
+
+        ttcf.dsig_tag = 0;
+        expect!(TTCF::from_data(&ttcf.bytes(), 0).unwrap().dsig()).to(be_none());
+    }
+}
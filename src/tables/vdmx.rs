@@ -0,0 +1,112 @@
+use prelude::*;
+use Error;
+use Result;
+use byteorder::BigEndian;
+use utils::{Cursor, HeapSize};
+
+/// A vertical device metrics table.
+///
+/// The 'VDMX' table stores the largest and smallest y of all glyphs in whole
+/// pixels for some sizes and aspect ratios, as they are after hinting.
+#[derive(Debug, Default, Clone)]
+pub struct VDMX {
+    // The size in pixels per em, maximum and minimum y of every entry of the
+    // groups for square pixels.
+    groups: Vec<Vec<(u16, i16, i16)>>,
+}
+
+impl VDMX {
+    /// Returns `VDMX` font table.
+    ///
+    /// Attempts to read `data` starting from `offset` position.
+    ///
+    /// # Errors
+    /// Returns error if there is not enough data to read.
+    pub fn from_data(data: &[u8], offset: usize) -> Result<VDMX> {
+        if offset >= data.len() {
+            return Err(Error::Malformed);
+        }
+
+        let mut cursor = Cursor::new(&data[offset..]);
+        let _version = try!(cursor.read_u16::<BigEndian>());
+        let _groups = try!(cursor.read_u16::<BigEndian>());
+        let ratios = try!(cursor.read_u16::<BigEndian>());
+        let mut ranges = Vec::with_capacity(ratios as usize);
+        for _ in 0..ratios {
+            let _charset = try!(cursor.read_u8());
+            let x = try!(cursor.read_u8());
+            let start = try!(cursor.read_u8());
+            let end = try!(cursor.read_u8());
+            ranges.push((x, start, end));
+        }
+
+        let mut vdmx = VDMX::default();
+        for &(x, start, end) in &ranges {
+            let group = try!(cursor.read_u16::<BigEndian>()) as usize;
+            let mut group = Cursor::new(try!(data.get(offset + group..).ok_or(Error::Malformed)));
+            let entries = try!(group.read_u16::<BigEndian>());
+            let _start = try!(group.read_u8());
+            let _end = try!(group.read_u8());
+            let mut heights = Vec::with_capacity(entries as usize);
+            for _ in 0..entries {
+                let ppem = try!(group.read_u16::<BigEndian>());
+                let y_max = try!(group.read_i16::<BigEndian>());
+                let y_min = try!(group.read_i16::<BigEndian>());
+                heights.push((ppem, y_max, y_min));
+            }
+            // A ratio covers x : start..end, all zero matches any ratio.
+            if (x == 0 && start == 0 && end == 0) || (start <= x && x <= end) {
+                vdmx.groups.push(heights);
+            }
+        }
+        Ok(vdmx)
+    }
+
+    /// Returns the largest and smallest y of all glyphs in pixels at `ppem`
+    /// pixels per em, for square pixels.
+    ///
+    /// Returns `None` if the table has no entry for the size.
+    pub fn extents(&self, ppem: u16) -> Option<(i16, i16)> {
+        // The first matching ratio is used, like on Windows.
+        self.groups.first().and_then(|heights| {
+            heights.iter()
+                .find(|&&(size, _, _)| size == ppem)
+                .map(|&(_, y_max, y_min)| (y_max, y_min))
+        })
+    }
+}
+
+impl HeapSize for VDMX {
+    fn heap_size(&self) -> usize {
+        self.groups.heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error::*;
+    use expectest::prelude::*;
+
+    #[test]
+    fn extents() {
+        let data = [
+            0, 1, 0, 2, 0, 2,
+            // 2 : 1..1, then any ratio.
+            1, 2, 1, 1,
+            0, 0, 0, 0,
+            0, 18, 0, 18,
+            // A group with entries at 8 and 9 pixels per em.
+            0, 2, 8, 9,
+            0, 8, 0, 7, 0xff, 0xfe,
+            0, 9, 0, 8, 0xff, 0xfd,
+        ];
+        let vdmx = VDMX::from_data(&data, 0).unwrap();
+        expect!(vdmx.extents(8)).to(be_some().value((7, -2)));
+        expect!(vdmx.extents(9)).to(be_some().value((8, -3)));
+        expect!(vdmx.extents(10)).to(be_none());
+
+        expect!(VDMX::from_data(&data[..data.len() - 1], 0)).to(be_err().value(Malformed));
+        expect!(VDMX::from_data(&data, data.len())).to(be_err().value(Malformed));
+    }
+}
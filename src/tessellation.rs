@@ -0,0 +1,530 @@
+//! Triangulates outlines into meshes, for rendering text as geometry.
+
+use prelude::*;
+use {FontInfo, GlyphId, PathSegment, Result};
+use raster::{flatten_path, signed_area};
+
+/// Triangles covering a filled outline.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Mesh {
+    /// The corners of the triangles, most shared by several triangles.
+    pub positions: Vec<(f32, f32)>,
+    /// The indices of the corners of every triangle in `positions`, three
+    /// by three. All triangles go clockwise with the y axis pointing down.
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// Returns the corners of every triangle.
+    pub fn triangles<'a>(&'a self) -> impl Iterator<Item = [(f32, f32); 3]> + 'a {
+        self.indices.chunks(3).map(move |t| {
+            [self.positions[t[0] as usize], self.positions[t[1] as usize],
+                self.positions[t[2] as usize]]
+        })
+    }
+}
+
+/// Returns triangles covering `path` filled with the nonzero rule, curves
+/// being approximated with lines at most `tolerance` away from them.
+///
+/// Contours inside an odd number of other contours are holes, whatever
+/// way they go, and are cut out of the contour right around them. Contours
+/// of fonts don't cross, crossing contours give overlapping triangles.
+pub fn tessellate_path(path: &[PathSegment], tolerance: f32) -> Mesh {
+    triangulate(path, tolerance).0
+}
+
+/// Returns the triangles of `tessellate_path` and the contours as indices
+/// of the positions, the outer ones clockwise and the holes the other way.
+fn triangulate(path: &[PathSegment], tolerance: f32) -> (Mesh, Vec<Vec<u32>>) {
+    let mut contours = flatten_path(path, tolerance);
+    for contour in &mut contours {
+        contour.dedup();
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+    }
+    contours.retain(|contour| contour.len() >= 3 && signed_area(contour) != 0.0);
+
+    // How many contours every contour is in.
+    let depths: Vec<usize> = contours.iter().enumerate().map(|(i, contour)| {
+        contours.iter().enumerate()
+            .filter(|&(j, other)| j != i && contains(other, contour[0]))
+            .count()
+    }).collect();
+
+    let mut mesh = Mesh::default();
+    let mut polygons = vec![];
+    for (i, contour) in contours.iter().enumerate() {
+        // Outer contours go clockwise, holes the other way.
+        let hole = depths[i] % 2 == 1;
+        let start = mesh.positions.len() as u32;
+        let mut indices: Vec<u32> = (start..start + contour.len() as u32).collect();
+        if (signed_area(contour) > 0.0) == hole {
+            indices.reverse();
+        }
+        mesh.positions.extend_from_slice(contour);
+        polygons.push(indices);
+    }
+
+    for (i, contour) in contours.iter().enumerate() {
+        if depths[i] % 2 == 1 {
+            continue;
+        }
+        let mut holes: Vec<usize> = (0..contours.len())
+            .filter(|&j| depths[j] == depths[i] + 1 && contains(contour, contours[j][0]))
+            .collect();
+        // The rightmost holes first, so that the others can be joined to
+        // them.
+        let right = |j: usize| contours[j].iter().fold(::core::f32::MIN, |m, p| m.max(p.0));
+        holes.sort_by(|&a, &b| right(b).partial_cmp(&right(a)).unwrap_or(::core::cmp::Ordering::Equal));
+        let mut polygon = polygons[i].clone();
+        for hole in holes {
+            join_hole(&mesh.positions, &mut polygon, &polygons[hole]);
+        }
+        clip_ears(&mesh.positions, polygon, &mut mesh.indices);
+    }
+    (mesh, polygons)
+}
+
+impl<'a> FontInfo<'a> {
+    /// Returns triangles covering `glyph` scaled by `scale`, in the
+    /// coordinates of `glyph_path`, with curves approximated with
+    /// lines at most `tolerance` away from them.
+    pub fn glyph_mesh(&self, glyph: GlyphId, scale: f32, tolerance: f32) -> Result<Mesh> {
+        Ok(tessellate_path(&try!(self.glyph_path(glyph, scale)), tolerance))
+    }
+
+    /// Returns a solid of `glyph` scaled by `scale` and `depth` thick, e.g.
+    /// for titles in 3D scenes.
+    ///
+    /// Unlike `glyph_mesh`, the y axis points up. The front of the glyph is
+    /// at z = 0 facing towards positive z, the back at z = -`depth`, and
+    /// the side walls join their contours. Curves are approximated with
+    /// lines at most a thousandth of the em away from them, and every face
+    /// has its own corners so that the edges between them stay sharp.
+    pub fn extrude_glyph(&self, glyph: GlyphId, scale: f32, depth: f32) -> Result<Mesh3D> {
+        let tolerance = scale * self.head.units_per_em() / 1000.0;
+        let (mesh, contours) = triangulate(&try!(self.glyph_path(glyph, scale)), tolerance);
+        Ok(extrude(&mesh, &contours, depth))
+    }
+
+    /// Returns the triangles of `curve_mesh` for `glyph` scaled by `scale`,
+    /// in the coordinates of `glyph_path`.
+    ///
+    /// The scale only sets the units of the positions, the curves stay
+    /// smooth at any size the mesh is drawn at.
+    pub fn glyph_curve_mesh(&self, glyph: GlyphId, scale: f32) -> Result<CurveMesh> {
+        Ok(curve_mesh(&try!(self.glyph_path(glyph, scale))))
+    }
+}
+
+/// Triangles of a solid in 3D.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Mesh3D {
+    pub positions: Vec<(f32, f32, f32)>,
+    /// The unit vector perpendicular to the face of every position.
+    pub normals: Vec<(f32, f32, f32)>,
+    /// The indices of the corners of every triangle, three by three,
+    /// counterclockwise seen from the outside.
+    pub indices: Vec<u32>,
+}
+
+/// Returns a solid of the triangles `mesh` with the y axis pointing down,
+/// `depth` thick, with walls along the `contours` of its positions.
+fn extrude(mesh: &Mesh, contours: &[Vec<u32>], depth: f32) -> Mesh3D {
+    let mut solid = Mesh3D::default();
+    let n = mesh.positions.len() as u32;
+    for &(z, nz) in &[(0.0, 1.0), (-depth, -1.0)] {
+        for &(x, y) in &mesh.positions {
+            solid.positions.push((x, -y, z));
+            solid.normals.push((0.0, 0.0, nz));
+        }
+    }
+    // The triangles still go clockwise seen from the front once the y axis
+    // points up, so they are turned around for the front.
+    for t in mesh.indices.chunks(3) {
+        solid.indices.extend_from_slice(&[t[0], t[2], t[1]]);
+    }
+    solid.indices.extend(mesh.indices.iter().map(|&i| n + i));
+
+    for contour in contours {
+        for (k, &b) in contour.iter().enumerate() {
+            let a = contour[if k == 0 { contour.len() - 1 } else { k - 1 }];
+            let (a, b) = (mesh.positions[a as usize], mesh.positions[b as usize]);
+            let (a, b) = ((a.0, -a.1), (b.0, -b.1));
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let length = libm::sqrtf(dx * dx + dy * dy);
+            // Outer contours go clockwise and holes the other way, so the
+            // left of every edge is outside.
+            let normal = (-dy / length, dx / length, 0.0);
+            let start = solid.positions.len() as u32;
+            for &p in &[(a.0, a.1, 0.0), (b.0, b.1, 0.0), (b.0, b.1, -depth), (a.0, a.1, -depth)] {
+                solid.positions.push(p);
+                solid.normals.push(normal);
+            }
+            solid.indices.extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+        }
+    }
+    solid
+}
+
+/// A corner of a triangle of a `CurveMesh`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct CurveVertex {
+    pub position: (f32, f32),
+    /// The coordinates of the corner in the space of the curve, which are
+    /// interpolated over the triangle.
+    pub uv: (f32, f32),
+    /// 1 if the triangle covers the side of its curve towards its ends, -1
+    /// if it covers the side towards its control point.
+    pub sign: f32,
+}
+
+/// Triangles of an outline for rendering it at any size on the GPU, by the
+/// technique of Loop and Blinn, instead of bitmaps in an atlas.
+///
+/// Every curve has a triangle between its ends and its control point whose
+/// `uv` are (0, 0), (0.5, 0) and (1, 1), so that the curve is where
+/// `u * u - v` is 0. A fragment shader keeps the fragments where
+/// `sign * (u * u - v) <= 0` and discards the others, with antialiasing
+/// from the screen space derivatives if needed. The triangles inside the
+/// outline have the `uv` (0, 1) and are always kept.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct CurveMesh {
+    pub vertices: Vec<CurveVertex>,
+    /// The indices of the corners of every triangle in `vertices`, three
+    /// by three, clockwise with the y axis pointing down.
+    pub indices: Vec<u32>,
+}
+
+/// Returns the triangles of `path` filled with the nonzero rule for
+/// rendering it with its curves on the GPU, see `CurveMesh`.
+///
+/// The triangles of curves may overlap those of other curves where curves
+/// are close to each other and bend a lot, like in sharp corners, which
+/// leaves holes or bumps at those places.
+pub fn curve_mesh(path: &[PathSegment]) -> CurveMesh {
+    // The points of every contour with the control point of the curve
+    // from the point before, if any.
+    let mut contours: Vec<Vec<((f32, f32), Option<(f32, f32)>)>> = vec![];
+    for segment in path {
+        let (point, control) = match *segment {
+            PathSegment::MoveTo(x, y) => {
+                contours.push(vec![]);
+                ((x, y), None)
+            }
+            PathSegment::LineTo(x, y) => ((x, y), None),
+            PathSegment::QuadTo(cx, cy, x, y) => ((x, y), Some((cx, cy))),
+        };
+        if contours.is_empty() {
+            contours.push(vec![((0.0, 0.0), None)]);
+        }
+        contours.last_mut().unwrap().push((point, control));
+    }
+    for contour in &mut contours {
+        if contour.len() > 1 && contour.first().map(|p| p.0) == contour.last().map(|p| p.0) {
+            let (_, control) = contour.pop().unwrap();
+            contour[0].1 = control;
+        }
+    }
+    let polygons: Vec<Vec<(f32, f32)>> = contours.iter().map(|contour| {
+        contour.iter().flat_map(|&(point, control)| control.into_iter().chain(Some(point)))
+            .collect()
+    }).collect();
+
+    let mut mesh = CurveMesh::default();
+    let mut inside = vec![];
+    for (i, contour) in contours.iter().enumerate() {
+        if contour.len() < 2 {
+            continue;
+        }
+        let depth = polygons.iter().enumerate()
+            .filter(|&(j, other)| j != i && contains(other, contour[0].0))
+            .count();
+        // Whether the contour goes around the filled area or a hole, and
+        // which way.
+        let outer = depth % 2 == 0;
+        let clockwise = signed_area(&polygons[i]) > 0.0;
+        let mut previous = contour[contour.len() - 1].0;
+        inside.push(PathSegment::MoveTo(previous.0, previous.1));
+        for &(point, control) in contour {
+            let turn = control.map_or(0.0, |c| cross(previous, c, point));
+            if let (Some(c), true) = (control, turn != 0.0) {
+                // The control point of a curve that bends the same way as
+                // the contour is out of the area the contour goes around,
+                // which is filled unless it's a hole. Either way the
+                // triangles inside stay out of the triangle of the curve.
+                let filled = ((turn > 0.0) == clockwise) != outer;
+                let sign = if filled { -1.0 } else { 1.0 };
+                if filled {
+                    inside.push(PathSegment::LineTo(c.0, c.1));
+                }
+                let start = mesh.vertices.len() as u32;
+                for &(position, uv) in &[(previous, (0.0, 0.0)), (c, (0.5, 0.0)), (point, (1.0, 1.0))] {
+                    mesh.vertices.push(CurveVertex { position: position, uv: uv, sign: sign });
+                }
+                mesh.indices.extend_from_slice(&if turn > 0.0 {
+                    [start, start + 1, start + 2]
+                } else {
+                    [start, start + 2, start + 1]
+                });
+            }
+            inside.push(PathSegment::LineTo(point.0, point.1));
+            previous = point;
+        }
+    }
+
+    let inside = tessellate_path(&inside, 1.0);
+    let start = mesh.vertices.len() as u32;
+    mesh.vertices.extend(inside.positions.into_iter().map(|position| {
+        CurveVertex { position: position, uv: (0.0, 1.0), sign: 1.0 }
+    }));
+    mesh.indices.extend(inside.indices.into_iter().map(|i| start + i));
+    mesh
+}
+
+/// Returns `true` if `p` is inside `contour` by the even-odd rule.
+fn contains(contour: &[(f32, f32)], p: (f32, f32)) -> bool {
+    let mut inside = false;
+    for (i, &b) in contour.iter().enumerate() {
+        let a = contour[if i == 0 { contour.len() - 1 } else { i - 1 }];
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Returns the cross product of the vectors from `a` to `b` and from `b` to
+/// `c`, positive if the corner at `b` turns clockwise with the y axis
+/// pointing down.
+fn cross(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0)
+}
+
+/// Returns `true` if `p` is inside the clockwise triangle `t` or on its
+/// edges.
+fn in_triangle(t: [(f32, f32); 3], p: (f32, f32)) -> bool {
+    cross(t[0], t[1], p) >= 0.0 && cross(t[1], t[2], p) >= 0.0 && cross(t[2], t[0], p) >= 0.0
+}
+
+/// Joins `hole` to the clockwise `polygon` around it by a pair of edges
+/// from its rightmost point to a point of the polygon it can see, which
+/// makes a single polygon of both.
+fn join_hole(positions: &[(f32, f32)], polygon: &mut Vec<u32>, hole: &[u32]) {
+    let at = |i: u32| positions[i as usize];
+    let m = (0..hole.len()).fold(0, |m, i| if at(hole[i]).0 > at(hole[m]).0 { i } else { m });
+    let mp = at(hole[m]);
+
+    // The closest edge to the right of the point, and the end of it
+    // farthest to the right.
+    let mut closest: Option<(f32, usize)> = None;
+    for i in 0..polygon.len() {
+        let j = (i + 1) % polygon.len();
+        let (a, b) = (at(polygon[i]), at(polygon[j]));
+        if (a.1 > mp.1) == (b.1 > mp.1) {
+            continue;
+        }
+        let x = a.0 + (mp.1 - a.1) / (b.1 - a.1) * (b.0 - a.0);
+        if x >= mp.0 && closest.map_or(true, |(closest, _)| x < closest) {
+            closest = Some((x, if a.0 > b.0 { i } else { j }));
+        }
+    }
+    let (x, mut bridge) = match closest {
+        Some(closest) => closest,
+        // Only for a hole that isn't inside the polygon after all.
+        None => return,
+    };
+
+    // Points of the polygon in the triangle between the point, the edge
+    // and its end hide the end, the one at the smallest angle to the
+    // point is visible instead.
+    let end = at(polygon[bridge]);
+    let triangle = if end.1 < mp.1 { [mp, end, (x, mp.1)] } else { [mp, (x, mp.1), end] };
+    let angle = |p: (f32, f32)| libm::fabsf(p.1 - mp.1) / (p.0 - mp.0);
+    for (i, &index) in polygon.iter().enumerate() {
+        let p = at(index);
+        if p != end && p.0 > mp.0 && in_triangle(triangle, p) && angle(p) < angle(at(polygon[bridge])) {
+            bridge = i;
+        }
+    }
+
+    let mut joined = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    joined.extend_from_slice(&polygon[..bridge + 1]);
+    joined.extend(hole[m..].iter().chain(&hole[..m + 1]).cloned());
+    joined.extend_from_slice(&polygon[bridge..]);
+    *polygon = joined;
+}
+
+/// Cuts off the convex corners of the clockwise `polygon` that have no
+/// other points in them as triangles, until it's a single triangle.
+fn clip_ears(positions: &[(f32, f32)], mut polygon: Vec<u32>, triangles: &mut Vec<u32>) {
+    let at = |i: u32| positions[i as usize];
+    let (mut i, mut misses) = (0, 0);
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        i %= n;
+        let (a, b, c) = (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+        let turn = cross(at(a), at(b), at(c));
+        let triangle = [at(a), at(b), at(c)];
+        let ear = turn > 0.0 && !polygon.iter().any(|&p| {
+            !triangle.contains(&at(p)) && in_triangle(triangle, at(p))
+        });
+        // Points on a straight line are dropped, and corners that aren't
+        // ears are cut off anyway if there are none left, which only
+        // happens with contours that cross.
+        if ear || turn == 0.0 || misses >= n {
+            if turn != 0.0 {
+                triangles.extend_from_slice(&[a, b, c]);
+            }
+            polygon.remove(i);
+            misses = 0;
+        } else {
+            i += 1;
+            misses += 1;
+        }
+    }
+    if polygon.len() == 3 && cross(at(polygon[0]), at(polygon[1]), at(polygon[2])) != 0.0 {
+        triangles.extend_from_slice(&polygon);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expectest::prelude::*;
+
+    fn square(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<PathSegment> {
+        vec![PathSegment::MoveTo(x0, y0), PathSegment::LineTo(x1, y0),
+            PathSegment::LineTo(x1, y1), PathSegment::LineTo(x0, y1), PathSegment::LineTo(x0, y0)]
+    }
+
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.triangles().map(|t| signed_area(&t) / 2.0).sum()
+    }
+
+    #[test]
+    fn squares() {
+        let mesh = tessellate_path(&square(0.0, 0.0, 10.0, 10.0), 0.35);
+        expect!(mesh.positions.len()).to(be_equal_to(4));
+        expect!(mesh.indices.len()).to(be_equal_to(6));
+        expect!(area(&mesh)).to(be_equal_to(100.0));
+
+        // Going the other way, with a hole and an island in it.
+        let mut path = square(0.0, 10.0, 10.0, 0.0);
+        path.extend(square(2.0, 2.0, 8.0, 8.0));
+        path.extend(square(4.0, 4.0, 6.0, 6.0));
+        let mesh = tessellate_path(&path, 0.35);
+        expect!(mesh.positions.len()).to(be_equal_to(12));
+        expect!(mesh.indices.len()).to(be_equal_to(3 * 10));
+        expect!(area(&mesh)).to(be_equal_to(100.0 - 36.0 + 4.0));
+        expect!(mesh.triangles().all(|t| signed_area(&t) > 0.0)).to(be_true());
+
+        expect!(tessellate_path(&[], 0.35)).to(be_equal_to(Mesh::default()));
+    }
+
+    #[test]
+    fn extrusion() {
+        let mut path = square(0.0, 0.0, 10.0, 10.0);
+        path.extend(square(2.0, 2.0, 8.0, 8.0));
+        let (mesh, contours) = triangulate(&path, 0.35);
+        let solid = extrude(&mesh, &contours, 2.0);
+        // The front and the back of 8 corners, and 8 walls of 4.
+        expect!(solid.positions.len()).to(be_equal_to(8 * 2 + 8 * 4));
+        expect!(solid.indices.len()).to(be_equal_to(3 * (8 * 2 + 8 * 2)));
+
+        let sub = |a: (f32, f32, f32), b: (f32, f32, f32)| (a.0 - b.0, a.1 - b.1, a.2 - b.2);
+        let mut volume = 0.0;
+        for t in solid.indices.chunks(3) {
+            let (p, q, r) = (solid.positions[t[0] as usize], solid.positions[t[1] as usize],
+                solid.positions[t[2] as usize]);
+            let (u, v) = (sub(q, p), sub(r, p));
+            let cross = (u.1 * v.2 - u.2 * v.1, u.2 * v.0 - u.0 * v.2, u.0 * v.1 - u.1 * v.0);
+            // The triangles face the way of their normals.
+            for &i in t {
+                let n = solid.normals[i as usize];
+                expect!(cross.0 * n.0 + cross.1 * n.1 + cross.2 * n.2 > 0.0).to(be_true());
+            }
+            volume += (p.0 * cross.0 + p.1 * cross.1 + p.2 * cross.2) / 6.0;
+        }
+        expect!((volume - (100.0 - 36.0) * 2.0).abs() < 0.001).to(be_true());
+        // The left walls of the square and of the hole face away from the
+        // solid.
+        expect!((solid.positions[16], solid.normals[16]))
+            .to(be_equal_to(((0.0, -10.0, 0.0), (-1.0, 0.0, 0.0))));
+        expect!((solid.positions[32], solid.normals[32]))
+            .to(be_equal_to(((2.0, -2.0, 0.0), (1.0, 0.0, 0.0))));
+    }
+
+    /// Returns `true` if a triangle of `mesh` keeps the fragment at `p`.
+    fn covers(mesh: &CurveMesh, p: (f32, f32)) -> bool {
+        mesh.indices.chunks(3).any(|t| {
+            let [a, b, c] = [mesh.vertices[t[0] as usize], mesh.vertices[t[1] as usize],
+                mesh.vertices[t[2] as usize]];
+            let area = cross(a.position, b.position, c.position);
+            let wa = cross(b.position, c.position, p) / area;
+            let wb = cross(c.position, a.position, p) / area;
+            let wc = 1.0 - wa - wb;
+            let (u, v) = (wa * a.uv.0 + wb * b.uv.0 + wc * c.uv.0, wa * a.uv.1 + wb * b.uv.1 + wc * c.uv.1);
+            wa >= 0.0 && wb >= 0.0 && wc >= 0.0 && a.sign * (u * u - v) <= 0.0
+        })
+    }
+
+    #[test]
+    fn curves() {
+        // A square bulging out at the top and in at the bottom.
+        let path = vec![
+            PathSegment::MoveTo(0.0, 0.0), PathSegment::QuadTo(5.0, -6.0, 10.0, 0.0),
+            PathSegment::LineTo(10.0, 10.0), PathSegment::QuadTo(5.0, 4.0, 0.0, 10.0),
+            PathSegment::LineTo(0.0, 0.0),
+        ];
+        let mesh = curve_mesh(&path);
+        expect!(mesh.vertices.len()).to(be_equal_to(3 + 3 + 5));
+        expect!(mesh.indices.len()).to(be_equal_to(3 * (2 + 3)));
+        // The curves are 3 pixels from their ends in the middle.
+        for &(p, covered) in &[((5.0, -2.9), true), ((5.0, -3.1), false), ((5.0, 5.0), true),
+            ((5.0, 6.9), true), ((5.0, 7.1), false), ((1.0, 8.85), true), ((1.0, 9.0), false),
+            ((-1.0, 5.0), false)]
+        {
+            expect!(covers(&mesh, p)).to(be_equal_to(covered));
+        }
+
+        // The same in a hole, going the other way.
+        let mut path = square(-10.0, 20.0, 20.0, -10.0);
+        path.extend(vec![
+            PathSegment::MoveTo(0.0, 0.0), PathSegment::LineTo(0.0, 10.0),
+            PathSegment::QuadTo(5.0, 4.0, 10.0, 10.0), PathSegment::LineTo(10.0, 0.0),
+            PathSegment::QuadTo(5.0, -6.0, 0.0, 0.0),
+        ]);
+        let mesh = curve_mesh(&path);
+        for &(p, covered) in &[((5.0, -2.9), false), ((5.0, -3.1), true), ((5.0, 5.0), false),
+            ((5.0, 6.9), false), ((5.0, 7.1), true), ((-5.0, 5.0), true)]
+        {
+            expect!(covers(&mesh, p)).to(be_equal_to(covered));
+        }
+        expect!(mesh.indices.chunks(3).all(|t| {
+            cross(mesh.vertices[t[0] as usize].position, mesh.vertices[t[1] as usize].position,
+                mesh.vertices[t[2] as usize].position) > 0.0
+        })).to(be_true());
+    }
+
+    #[test]
+    fn hidden_bridges() {
+        // The tip of a notch is in the way from the corner of the hole to
+        // the bottom right corner.
+        let path = vec![
+            PathSegment::MoveTo(0.0, 0.0), PathSegment::LineTo(10.0, 0.0),
+            PathSegment::LineTo(10.0, 10.0), PathSegment::LineTo(8.0, 10.0),
+            PathSegment::LineTo(7.0, 6.0), PathSegment::LineTo(6.0, 10.0),
+            PathSegment::LineTo(0.0, 10.0),
+        ];
+        let mut with_hole = path.clone();
+        with_hole.extend(square(1.0, 1.0, 4.0, 4.0));
+        let mesh = tessellate_path(&with_hole, 0.35);
+        expect!(area(&mesh)).to(be_equal_to(100.0 - 4.0 - 9.0));
+        expect!(mesh.triangles().all(|t| signed_area(&t) > 0.0)).to(be_true());
+        let mesh = tessellate_path(&path, 0.35);
+        expect!(area(&mesh)).to(be_equal_to(100.0 - 4.0));
+    }
+}
@@ -1,6 +1,10 @@
+use prelude::*;
+use eot;
+
 
 /// A bounding box type.
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BBox {
     pub x0: i32,
     pub y0: i32,
@@ -11,6 +15,24 @@ pub struct BBox {
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub struct Fixed(pub i32);
 
+/// The index of a glyph in a font, distinct from the characters it's the
+/// glyph of, see `FontInfo::glyph_index_for_code`.
+///
+/// Fonts have up to 65535 glyphs, glyph 0 being the one of missing
+/// characters. The methods of `FontInfo` and the functions ported from
+/// stb_truetype take glyphs as `GlyphId` and characters as `char`, the
+/// tables take glyph indices as `usize`.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlyphId(pub u16);
+
+impl GlyphId {
+    /// Returns the index of the glyph, e.g. to look it up in the tables.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// Indicates the type of offset format used in the index to loc ('loca') table.
 ///
 /// Taken from `indexToLocFormat` field of the `head` font table.
@@ -30,3 +52,172 @@ impl LocationFormat {
         }
     }
 }
+
+/// The container format of a font file, told by its first four bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FontKind {
+    /// A font with TrueType outlines.
+    TrueType,
+    /// An OpenType font with CFF outlines, which can't be rendered.
+    OpenTypeCFF,
+    /// A Type 1 font in an sfnt wrapper, which can't be read.
+    Type1,
+    /// A TrueType Collection of several fonts, see `FontCollection`.
+    TrueTypeCollection,
+    /// A compressed WOFF font, which has to be decompressed first.
+    Woff,
+    /// A compressed WOFF2 font, which has to be decompressed first.
+    Woff2,
+    /// An Embedded OpenType font, whose font `unwrap_eot` returns.
+    Eot,
+}
+
+impl FontKind {
+    /// Returns the kind of font in `data`, `None` if it isn't a font.
+    ///
+    /// Only the tag at the start, or the magic number in the header of EOT
+    /// files, is checked, like stb_truetype's `isfont`, so parsing may still
+    /// fail.
+    pub fn detect(data: &[u8]) -> Option<FontKind> {
+        if data.len() < 4 {
+            return None;
+        }
+        match &data[..4] {
+            b"1\0\0\0" | b"\0\x01\0\0" => Some(FontKind::TrueType),
+            b"OTTO" => Some(FontKind::OpenTypeCFF),
+            b"typ1" => Some(FontKind::Type1),
+            b"ttcf" => Some(FontKind::TrueTypeCollection),
+            b"wOFF" => Some(FontKind::Woff),
+            b"wOF2" => Some(FontKind::Woff2),
+            _ if eot::is_eot(data) => Some(FontKind::Eot),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` for a single font that isn't compressed, which
+    /// `FontInfo` can read.
+    pub fn is_font(&self) -> bool {
+        *self == FontKind::TrueType || *self == FontKind::OpenTypeCFF
+    }
+}
+
+/// An entry of the table directory of a font.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TableRecord {
+    pub tag: [u8; 4],
+    pub check_sum: u32,
+    pub offset: usize,
+    pub length: usize,
+}
+
+/// Checksums of a font, see `FontInfo::verify_checksums`.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct CheckSums {
+    /// Tags of the tables whose content doesn't match the checksum
+    /// stored in the table directory.
+    pub corrupt_tables: Vec<[u8; 4]>,
+    /// Whether `checkSumAdjustment` of the `head` table matches
+    /// the checksum of the whole font.
+    pub adjustment_matches: bool,
+}
+
+impl CheckSums {
+    /// Returns `true` if all checksums of the font are correct.
+    pub fn is_valid(&self) -> bool {
+        self.corrupt_tables.is_empty() && self.adjustment_matches
+    }
+}
+
+/// Which of the vertical metrics of a font lay out lines of text, see
+/// `FontInfo::line_metrics`.
+///
+/// Platforms disagree, so fonts often have different values in each.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MetricsSource {
+    /// The `hhea` table, what macOS and browsers on it use.
+    Hhea,
+    /// The typographic metrics of the `OS/2` table, what browsers use when
+    /// the font sets `OS2::use_typo_metrics`.
+    Typo,
+    /// The Windows metrics of the `OS/2` table, what GDI and browsers on
+    /// Windows use otherwise. They tend to be large enough to hold every
+    /// glyph.
+    Win,
+}
+
+/// The vertical metrics of lines of text in font units, see
+/// `FontInfo::line_metrics`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineMetrics {
+    /// The distance from the baseline to the top of the line.
+    pub ascent: i32,
+    /// The distance from the baseline to the bottom of the line, negative
+    /// below the baseline.
+    pub descent: i32,
+    /// The space to add between lines.
+    pub line_gap: i32,
+}
+
+impl LineMetrics {
+    /// Returns the distance from one baseline to the next.
+    pub fn line_height(&self) -> i32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+/// Where to draw lines through text in font units, see
+/// `FontInfo::decoration_metrics`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecorationMetrics {
+    /// The distance from the baseline to the top of the underline, negative
+    /// below the baseline.
+    pub underline_position: i32,
+    pub underline_thickness: i32,
+    /// The distance from the baseline to the top of the strikeout.
+    pub strikeout_position: i32,
+    pub strikeout_thickness: i32,
+}
+
+/// The direction in which the contours of a glyph go around it, with the
+/// y axis pointing up. TrueType outlines go clockwise.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Orientation {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Statistics of a glyph outline, see `FontInfo::glyph_stats`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlyphStats {
+    /// The number of contours, including those of components.
+    pub contours: usize,
+    /// The number of points, including those off the curve.
+    pub points: usize,
+    /// The signed area in square font units, positive if the outline goes
+    /// counter-clockwise.
+    pub area: f32,
+    /// The overall direction of the outline, `None` if it has no area.
+    pub orientation: Option<Orientation>,
+}
+
+/// The memory a font holds on to, see `FontInfo::memory_usage`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryUsage {
+    /// The bytes of the font file it owns, 0 if it borrows them.
+    pub data: usize,
+    /// The bytes of `FontInfo` and of the tables it read, like the metrics
+    /// and the kerning pairs.
+    pub tables: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the number of bytes in all.
+    pub fn total(&self) -> usize {
+        self.data + self.tables
+    }
+}
@@ -1,11 +1,22 @@
 
+use prelude::*;
+use alloc::collections::BTreeMap;
 use Error;
 use Result;
+use types::{CheckSums, FontKind, GlyphId, TableRecord};
 use byteorder::{BigEndian, ByteOrder};
 
 /// Attempts to find the table offset in `data` for a font table `tag`
 /// starting from a `fontstart` offset.
 pub fn find_table_offset(data: &[u8], fontstart: usize, tag: &[u8; 4]) -> Result<Option<usize>> {
+    find_table_with_length(data, fontstart, tag).map(|table| table.map(|(offset, _)| offset))
+}
+
+/// Same as `find_table_offset`, but returns the length of the table from the
+/// directory too, for tables whose size varies with their version.
+pub fn find_table_with_length(data: &[u8], fontstart: usize, tag: &[u8; 4])
+    -> Result<Option<(usize, usize)>>
+{
     let tabledir = fontstart + 12;
     if tabledir >= data.len() {
         return Err(Error::Malformed);
@@ -14,7 +25,8 @@ pub fn find_table_offset(data: &[u8], fontstart: usize, tag: &[u8; 4]) -> Result
     let num_tables = BigEndian::read_u16(&data[fontstart + 4..]) as usize;
     for table_chunk in data[tabledir..].chunks(16).take(num_tables) {
         if table_chunk.len()==16 && prefix_is_tag(table_chunk, tag) {
-            return Ok(Some(BigEndian::read_u32(&table_chunk[8..12]) as usize));
+            return Ok(Some((BigEndian::read_u32(&table_chunk[8..12]) as usize,
+                BigEndian::read_u32(&table_chunk[12..16]) as usize)));
         }
     }
     return Ok(None);
@@ -29,9 +41,170 @@ pub fn find_required_table_offset(data: &[u8], fontstart: usize, tag: &[u8; 4])
     }
 }
 
+/// Reads all entries of the table directory of a font starting from
+/// a `fontstart` offset.
+///
+/// # Errors
+/// Returns `Error::Malformed` if the directory or any of the tables it
+/// refers to doesn't fit into `data`.
+pub fn read_table_records(data: &[u8], fontstart: usize) -> Result<Vec<TableRecord>> {
+    if fontstart >= data.len() || data.len() - fontstart < 12 {
+        return Err(Error::Malformed);
+    }
+
+    let num_tables = BigEndian::read_u16(&data[fontstart + 4..]) as usize;
+    let tabledir = fontstart + 12;
+    if (data.len() - tabledir) / 16 < num_tables {
+        return Err(Error::Malformed);
+    }
+
+    let mut records = Vec::with_capacity(num_tables);
+    for table_chunk in data[tabledir..].chunks(16).take(num_tables) {
+        let record = TableRecord {
+            tag: [table_chunk[0], table_chunk[1], table_chunk[2], table_chunk[3]],
+            check_sum: BigEndian::read_u32(&table_chunk[4..]),
+            offset: BigEndian::read_u32(&table_chunk[8..]) as usize,
+            length: BigEndian::read_u32(&table_chunk[12..]) as usize,
+        };
+        if record.offset.checked_add(record.length).map_or(true, |end| end > data.len()) {
+            return Err(Error::Malformed);
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Calculates the checksum of a font table, i.e. the sum of its content
+/// interpreted as big-endian `u32` values. The last value is padded with zeros.
+pub fn table_check_sum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        for (w, &b) in word.iter_mut().zip(chunk) {
+            *w = b;
+        }
+        sum.wrapping_add(BigEndian::read_u32(&word))
+    })
+}
+
+/// Calculates the value the `checkSumAdjustment` field of the `head` table
+/// of a font starting from a `fontstart` offset should have.
+///
+/// The sum is computed over the table directory and all tables of the font
+/// as if it was a standalone file, which is also how fonts inside a TrueType
+/// Collection are checksummed.
+///
+/// # Errors
+/// Returns error if the table directory is malformed or the font has
+/// no `head` table.
+pub fn check_sum_adjustment(data: &[u8], fontstart: usize) -> Result<u32> {
+    let records = try!(read_table_records(data, fontstart));
+    let directory_end = fontstart + 12 + 16 * records.len();
+    let mut sum = table_check_sum(&data[fontstart..directory_end]);
+    let mut has_head = false;
+    for record in &records {
+        let table = &data[record.offset..record.offset + record.length];
+        sum = sum.wrapping_add(table_check_sum(table));
+        if &record.tag == b"head" {
+            // `checkSumAdjustment` itself must be treated as zero.
+            if table.len() < 12 {
+                return Err(Error::Malformed);
+            }
+            sum = sum.wrapping_sub(BigEndian::read_u32(&table[8..]));
+            has_head = true;
+        }
+    }
+
+    if !has_head {
+        return Err(Error::MissingTable);
+    }
+    Ok(0xB1B0AFBAu32.wrapping_sub(sum))
+}
+
+/// Recomputes the checksums of all tables of a font starting from
+/// a `fontstart` offset and its `checkSumAdjustment`, and compares them
+/// to the values stored in the font.
+///
+/// # Errors
+/// Returns error if the table directory is malformed or the font has
+/// no `head` table.
+pub fn verify_check_sums(data: &[u8], fontstart: usize) -> Result<CheckSums> {
+    let adjustment = try!(check_sum_adjustment(data, fontstart));
+    let mut check_sums = CheckSums::default();
+    for record in try!(read_table_records(data, fontstart)) {
+        let table = &data[record.offset..record.offset + record.length];
+        let mut sum = table_check_sum(table);
+        if &record.tag == b"head" {
+            // `checkSumAdjustment` isn't a part of the checksum of `head`.
+            let stored = BigEndian::read_u32(&table[8..]);
+            sum = sum.wrapping_sub(stored);
+            check_sums.adjustment_matches = stored == adjustment;
+        }
+        if sum != record.check_sum {
+            check_sums.corrupt_tables.push(record.tag);
+        }
+    }
+    Ok(check_sums)
+}
+
+/// Assembles a TrueType font file out of `tables` given as tag and content
+/// pairs.
+///
+/// Tables are sorted by tag and padded to four bytes, the table directory
+/// gets correct checksums and `checkSumAdjustment` of the `head` table
+/// (if any) is fixed up.
+pub fn assemble_font(mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let num_tables = tables.len();
+    let mut entry_selector = 0;
+    while (2 << entry_selector) <= num_tables {
+        entry_selector += 1;
+    }
+    let search_range = (1 << entry_selector) * 16;
+
+    let mut data = vec![0; 12 + 16 * num_tables];
+    BigEndian::write_u32(&mut data[0..], 0x00010000);
+    BigEndian::write_u16(&mut data[4..], num_tables as u16);
+    BigEndian::write_u16(&mut data[6..], search_range as u16);
+    BigEndian::write_u16(&mut data[8..], entry_selector as u16);
+    BigEndian::write_u16(&mut data[10..], (num_tables * 16 - search_range) as u16);
+
+    let mut head = None;
+    for (i, &mut (ref tag, ref mut table)) in tables.iter_mut().enumerate() {
+        if tag == b"head" && table.len() >= 12 {
+            // Must be zero while checksums are computed.
+            BigEndian::write_u32(&mut table[8..], 0);
+            head = Some(data.len());
+        }
+
+        let record = 12 + 16 * i;
+        let offset = data.len();
+        data[record..record + 4].copy_from_slice(tag);
+        BigEndian::write_u32(&mut data[record + 4..], table_check_sum(table));
+        BigEndian::write_u32(&mut data[record + 8..], offset as u32);
+        BigEndian::write_u32(&mut data[record + 12..], table.len() as u32);
+
+        data.extend_from_slice(table);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+    }
+
+    if let Some(head) = head {
+        let adjustment = 0xB1B0AFBAu32.wrapping_sub(table_check_sum(&data));
+        BigEndian::write_u32(&mut data[head + 8..], adjustment);
+    }
+    data
+}
+
+/// Checks that `data` starts with one of the known font version tags.
+pub fn is_font(data: &[u8]) -> bool {
+    FontKind::detect(data).map_or(false, |kind| kind.is_font())
+}
+
 /// Compatibility with unsafe code. TODO: Remove as soon as possible.
 pub unsafe fn find_table(data: *const u8, fontstart: u32, tag: &[u8; 4]) -> u32 {
-    let slice = ::std::slice::from_raw_parts(data, 1024); // DANGER: Don't care about size.
+    let slice = ::core::slice::from_raw_parts(data, 1024); // DANGER: Don't care about size.
     find_table_offset(slice, fontstart as usize, tag).unwrap_or(None).unwrap_or(0) as u32
 }
 
@@ -40,8 +213,24 @@ pub fn prefix_is_tag(bs: &[u8], tag: &[u8; 4]) -> bool {
     bs.len()>=4 && bs[0]==tag[0] && bs[1]==tag[1] && bs[2]==tag[2] && bs[3]==tag[3]
 }
 
+/// Reads the `u16` at byte `i` of `data`, for tables made of offsets.
+pub fn read_u16_at(data: &[u8], i: usize) -> Result<u16> {
+    data.get(i..i + 2).map(BigEndian::read_u16).ok_or(Error::Malformed)
+}
+
+/// Reads the `u32` at byte `i` of `data`.
+pub fn read_u32_at(data: &[u8], i: usize) -> Result<u32> {
+    data.get(i..i + 4).map(BigEndian::read_u32).ok_or(Error::Malformed)
+}
+
+/// Reads the tag at byte `i` of `data`.
+pub fn read_tag_at(data: &[u8], i: usize) -> Result<[u8; 4]> {
+    let bytes = try!(data.get(i..i + 4).ok_or(Error::Malformed));
+    Ok([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
 pub fn read_u16_from_raw_data(data: &[u8], index: usize) -> Option<u16> {
-    if index * 2 < data.len() {
+    if index * 2 + 1 < data.len() {
         Some(BigEndian::read_u16(&data[index * 2..]))
     } else {
         None
@@ -49,13 +238,166 @@ pub fn read_u16_from_raw_data(data: &[u8], index: usize) -> Option<u16> {
 }
 
 pub fn read_i16_from_raw_data(data: &[u8], index: usize) -> Option<i16> {
-    if index * 2 < data.len() {
+    if index * 2 + 1 < data.len() {
         Some(BigEndian::read_i16(&data[index * 2..]))
     } else {
         None
     }
 }
 
+/// Reads numbers from a slice, advancing through it.
+///
+/// Works like `std::io::Cursor` with `byteorder::ReadBytesExt`,
+/// but doesn't need `std`.
+pub struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+macro_rules! read_fn {
+    ($name:ident, $ty:ty, $size:expr) => {
+        pub fn $name<T: ByteOrder>(&mut self) -> Result<$ty> {
+            let bytes = try!(self.take($size));
+            Ok(T::$name(bytes))
+        }
+    }
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(data: &'a [u8]) -> Cursor<'a> {
+        Cursor { data: data, position: 0 }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn take(&mut self, size: usize) -> Result<&'a [u8]> {
+        if self.data.len() - self.position < size {
+            return Err(Error::Malformed);
+        }
+        self.position += size;
+        Ok(&self.data[self.position - size..self.position])
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(try!(self.take(1))[0])
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8> {
+        Ok(try!(self.take(1))[0] as i8)
+    }
+
+    read_fn!(read_u16, u16, 2);
+    read_fn!(read_i16, i16, 2);
+    read_fn!(read_u32, u32, 4);
+    read_fn!(read_i32, i32, 4);
+    read_fn!(read_i64, i64, 8);
+}
+
+/// Writes numbers to the end of a vector.
+///
+/// Works like `byteorder::WriteBytesExt`, but doesn't need `std`.
+pub trait WriteBytesExt {
+    fn write_u16<T: ByteOrder>(&mut self, n: u16) -> Result<()>;
+    fn write_i16<T: ByteOrder>(&mut self, n: i16) -> Result<()>;
+    fn write_u32<T: ByteOrder>(&mut self, n: u32) -> Result<()>;
+    fn write_i32<T: ByteOrder>(&mut self, n: i32) -> Result<()>;
+    fn write_i64<T: ByteOrder>(&mut self, n: i64) -> Result<()>;
+    fn write_f32<T: ByteOrder>(&mut self, n: f32) -> Result<()>;
+}
+
+macro_rules! write_fn {
+    ($name:ident, $ty:ty, $size:expr) => {
+        fn $name<T: ByteOrder>(&mut self, n: $ty) -> Result<()> {
+            let mut bytes = [0; $size];
+            T::$name(&mut bytes, n);
+            self.extend_from_slice(&bytes);
+            Ok(())
+        }
+    }
+}
+
+impl WriteBytesExt for Vec<u8> {
+    write_fn!(write_u16, u16, 2);
+    write_fn!(write_i16, i16, 2);
+    write_fn!(write_u32, u32, 4);
+    write_fn!(write_i32, i32, 4);
+    write_fn!(write_i64, i64, 8);
+    write_fn!(write_f32, f32, 4);
+}
+
+/// Tells the bytes a value allocates on the heap, for
+/// `FontInfo::memory_usage`.
+///
+/// Vectors count their capacity rather than their length, since that's
+/// what they hold on to.
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! no_heap {
+    ($($ty:ty),*) => {
+        $(impl HeapSize for $ty {
+            fn heap_size(&self) -> usize {
+                0
+            }
+        })*
+    }
+}
+
+no_heap!(u8, u16, u32, i16, i32, f32, usize, char, [u8; 4], GlyphId);
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * ::core::mem::size_of::<T>()
+            + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+/// Counts the entries, not the nodes of the tree.
+impl<K: HeapSize, V: HeapSize> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.len() * ::core::mem::size_of::<(K, V)>()
+            + self.iter().map(|(k, v)| k.heap_size() + v.heap_size()).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<A: HeapSize, B: HeapSize, C: HeapSize> HeapSize for (A, B, C) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size() + self.2.heap_size()
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// Only owned bytes count, borrowed ones belong to someone else.
+impl<'a> HeapSize for Cow<'a, [u8]> {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Cow::Borrowed(_) => 0,
+            Cow::Owned(ref bytes) => bytes.capacity(),
+        }
+    }
+}
+
 #[cfg(test)]
 pub fn read_file(path: &str) -> Vec<u8> {
     use std::fs::{self, File};
@@ -82,6 +424,64 @@ mod tests {
         assert!(!prefix_is_tag(b"abcc", b"abcd"));
     }
 
+    #[test]
+    fn test_table_check_sum() {
+        expect!(table_check_sum(&[])).to(be_equal_to(0));
+        expect!(table_check_sum(&[0, 0, 1, 0, 0, 0, 0, 2])).to(be_equal_to(258));
+        expect!(table_check_sum(&[0, 0, 0, 1, 1])).to(be_equal_to(0x01000001));
+        expect!(table_check_sum(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 2])).to(be_equal_to(1));
+    }
+
+    #[test]
+    fn test_check_sum_adjustment() {
+        let data = read_file("assets/Tuffy_Bold.ttf");
+        let head = find_table_offset(&data, 0, b"head").unwrap().unwrap();
+        let expected = BigEndian::read_u32(&data[head + 8..]);
+        expect!(check_sum_adjustment(&data, 0)).to(be_ok().value(expected));
+        expect!(table_check_sum(&data)).to(be_equal_to(0xB1B0AFBA));
+
+        expect!(read_table_records(&data, 0).unwrap().len()).to(be_equal_to(17));
+        expect!(read_table_records(&data[..100], 0)).to(be_err().value(Error::Malformed));
+    }
+
+    #[test]
+    fn test_verify_check_sums() {
+        let mut data = read_file("assets/Tuffy_Bold.ttf");
+        let valid = verify_check_sums(&data, 0).unwrap();
+        expect!(valid.is_valid()).to(be_true());
+
+        let glyf = find_table_offset(&data, 0, b"glyf").unwrap().unwrap();
+        data[glyf + 100] ^= 0xff;
+        let corrupt = verify_check_sums(&data, 0).unwrap();
+        expect!(corrupt.corrupt_tables).to(be_equal_to(vec![*b"glyf"]));
+        expect!(corrupt.adjustment_matches).to(be_false());
+
+        // Fixing only the adjustment leaves the table directory wrong.
+        let head = find_table_offset(&data, 0, b"head").unwrap().unwrap();
+        let adjustment = check_sum_adjustment(&data, 0).unwrap();
+        BigEndian::write_u32(&mut data[head + 8..], adjustment);
+        let corrupt = verify_check_sums(&data, 0).unwrap();
+        expect!(corrupt.corrupt_tables).to(be_equal_to(vec![*b"glyf"]));
+        expect!(corrupt.adjustment_matches).to(be_true());
+
+        expect!(verify_check_sums(&data[..100], 0)).to(be_err().value(Error::Malformed));
+    }
+
+    #[test]
+    fn test_assemble_font() {
+        let head = vec![0xaa; 54];
+        let data = assemble_font(vec![(*b"zzzz", vec![1, 2, 3]), (*b"head", head)]);
+        expect!(data.len()).to(be_equal_to(12 + 2 * 16 + 56 + 4));
+        expect!(table_check_sum(&data)).to(be_equal_to(0xB1B0AFBA));
+        expect!(check_sum_adjustment(&data, 0)).to(be_ok().value(BigEndian::read_u32(&data[44 + 8..])));
+
+        let records = read_table_records(&data, 0).unwrap();
+        expect!(records[0].tag).to(be_equal_to(*b"head"));
+        expect!(records[1].offset).to(be_equal_to(12 + 2 * 16 + 56));
+        expect!(records[1].length).to(be_equal_to(3));
+        expect!(records[1].check_sum).to(be_equal_to(0x01020300));
+    }
+
     #[test]
     fn test_read_u16_from_raw_data() {
         let data: &[u8] = &[0, 1, 0, 3];
@@ -0,0 +1,145 @@
+//! Compares lines of text rendered with every font at several sizes with
+//! the reference images in `tests/golden`, to check that changes to the
+//! rasterizer don't change what glyphs look like.
+//!
+//! The images are binary PGM files. After a change that's meant to change
+//! them, write them again with
+//!
+//! ```sh
+//! TRUETYPE_BLESS=1 cargo test --test golden
+//! ```
+//!
+//! and look at the differences before committing them.
+
+extern crate piston_truetype;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use piston_truetype::*;
+
+/// The fonts to render, by the name of their images.
+const FONTS: &[(&str, &[u8])] = &[
+    ("tuffy_bold", include_bytes!("../assets/Tuffy_Bold.ttf")),
+];
+
+/// The pixel heights to render at, from hinted sizes to display ones.
+const SIZES: &[f32] = &[9.0, 16.0, 32.0, 64.0];
+
+/// Curves, counters, diagonals, overlaps and a kerned pair.
+const TEXT: &str = "Ag@&%WAVe";
+
+/// The most a pixel may differ from its reference, to allow for floating
+/// point rounding on other platforms.
+const TOLERANCE: u8 = 2;
+
+/// Renders `TEXT` on one line with the pen starting at `x` of 0.25 pixels,
+/// so glyphs land on fractional positions.
+fn render_line(font: &FontInfo, pixel_height: f32, options: &RasterOptions) -> GrayBitmap {
+    let scale = font.scale_for_pixel_height(pixel_height);
+    let mut path = Vec::new();
+    let mut x = 0.25;
+    let mut previous = None;
+    for c in TEXT.chars() {
+        let glyph = font.glyph_index_for_code(c);
+        if let Some(previous) = previous {
+            x += font.kerning(previous, glyph) as f32 * scale;
+        }
+        for segment in font.glyph_path(glyph, scale).unwrap() {
+            path.push(match segment {
+                PathSegment::MoveTo(px, py) => PathSegment::MoveTo(px + x, py),
+                PathSegment::LineTo(px, py) => PathSegment::LineTo(px + x, py),
+                PathSegment::QuadTo(cx, cy, px, py) => PathSegment::QuadTo(cx + x, cy, px + x, py),
+            });
+        }
+        x += font.advance_width(glyph) as f32 * scale;
+        previous = Some(glyph);
+    }
+    rasterize_path(&path, options)
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+        .join(format!("{}.pgm", name))
+}
+
+fn write_pgm(path: &PathBuf, bitmap: &GrayBitmap) {
+    let mut file = File::create(path).unwrap();
+    write!(file, "P5\n{} {}\n255\n", bitmap.width(), bitmap.height()).unwrap();
+    file.write_all(bitmap.as_slice()).unwrap();
+}
+
+/// Returns the width, height and pixels of a binary PGM image.
+fn read_pgm(path: &PathBuf) -> (usize, usize, Vec<u8>) {
+    let mut data = Vec::new();
+    File::open(path).unwrap_or_else(|e| panic!("{}: {}, bless the images with \
+        TRUETYPE_BLESS=1", path.display(), e)).read_to_end(&mut data).unwrap();
+    // The header is three lines: the magic number, the size and the maximum.
+    let mut lines = data.splitn(4, |&b| b == b'\n');
+    assert_eq!(lines.next(), Some(&b"P5"[..]));
+    let size = String::from_utf8(lines.next().unwrap().to_vec()).unwrap();
+    let mut size = size.split(' ').map(|n| n.parse().unwrap());
+    let (width, height) = (size.next().unwrap(), size.next().unwrap());
+    assert_eq!(lines.next(), Some(&b"255"[..]));
+    (width, height, lines.next().unwrap().to_vec())
+}
+
+/// Compares `bitmap` with the image `name`, or writes it if blessing.
+/// Returns a description of the differences.
+fn check(name: &str, bitmap: &GrayBitmap) -> Option<String> {
+    let path = golden_path(name);
+    if env::var_os("TRUETYPE_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_pgm(&path, bitmap);
+        return None;
+    }
+    let (width, height, pixels) = read_pgm(&path);
+    if (width, height) != (bitmap.width(), bitmap.height()) {
+        return Some(format!("{}: {}x{} pixels instead of {}x{}", name, bitmap.width(),
+            bitmap.height(), width, height));
+    }
+    let differences: Vec<u8> = pixels.iter().zip(bitmap.as_slice())
+        .map(|(&a, &b)| if a > b { a - b } else { b - a })
+        .filter(|&d| d > TOLERANCE)
+        .collect();
+    if differences.is_empty() {
+        return None;
+    }
+    // Keep the rendering to compare it with the reference.
+    let actual = env::temp_dir().join(format!("{}.pgm", name));
+    write_pgm(&actual, bitmap);
+    Some(format!("{}: {} pixels differ by up to {}, see {}", name, differences.len(),
+        differences.iter().max().unwrap(), actual.display()))
+}
+
+#[test]
+fn golden_images() {
+    let deterministic = RasterOptions { deterministic: true, ..RasterOptions::default() };
+    let sampled = RasterOptions { overlap_correct: true, ..deterministic };
+    let mut failures = Vec::new();
+    for &(font_name, data) in FONTS {
+        let font = FontInfo::new_with_offset(data, 0).unwrap();
+        for &size in SIZES {
+            for &(options_name, ref options) in &[("area", deterministic), ("sampled", sampled)] {
+                let name = format!("{}_{}_{}", font_name, size, options_name);
+                failures.extend(check(&name, &render_line(&font, size, options)));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn deterministic_rendering() {
+    // With the scalar loops rendering is the same as with the vectors up to
+    // rounding.
+    let font = FontInfo::new_with_offset(FONTS[0].1, 0).unwrap();
+    let deterministic = RasterOptions { deterministic: true, ..RasterOptions::default() };
+    let exact = render_line(&font, 32.0, &deterministic);
+    let vectorized = render_line(&font, 32.0, &RasterOptions::default());
+    assert_eq!((exact.x, exact.y, exact.width(), exact.height()),
+        (vectorized.x, vectorized.y, vectorized.width(), vectorized.height()));
+    assert!(exact.as_slice().iter().zip(vectorized.as_slice())
+        .all(|(&a, &b)| (a as i32 - b as i32).abs() <= 1));
+}
@@ -1,11 +1,36 @@
 extern crate piston_truetype;
+#[cfg(feature = "piston")]
+extern crate texture;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
 use std::ptr::{null_mut};
+use std::sync::Arc;
+use std::thread;
 use piston_truetype::*;
 
+/// The font most tests use.
+fn tuffy() -> FontInfo<'static> {
+    FontInfo::new_with_offset(&include_bytes!("../assets/Tuffy_Bold.ttf")[..], 0).unwrap()
+}
+
+/// Packs `ranges` of Tuffy into `pixels` of `w` by `h` with `padding`,
+/// expecting them to fit.
+fn pack_tuffy(pixels: &mut [u8], w: usize, h: usize, padding: isize, ranges: &mut [PackRange]) {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    unsafe {
+        let mut spc = PackContext::default();
+        assert_eq!(pack_begin(&mut spc, pixels.as_mut_ptr(), w as isize, h as isize, 0, padding,
+            null_mut()), 1);
+        assert_eq!(pack_font_ranges(&mut spc, &bs[..], 0, ranges.as_mut_ptr(), ranges.len() as isize)
+            .unwrap(), 1);
+        pack_end(&mut spc);
+    }
+}
+
 fn expect_glyph(letter: char, expected: String) {
     unsafe {
-        let bs = include_bytes!("Tuffy_Bold.ttf");
+        let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
         let s = 20.0;
 
         let mut w = 0;
@@ -15,7 +40,7 @@ fn expect_glyph(letter: char, expected: String) {
 
         let font = FontInfo::new_with_offset(&bs[..], offset).ok().expect("Failed to load font");
         let scale = font.scale_for_pixel_height(s);
-        let bitmap = get_codepoint_bitmap(&font, 0.0,scale, letter as isize, &mut w, &mut h, null_mut(),null_mut());
+        let bitmap = get_codepoint_bitmap(&font, 0.0,scale, letter, &mut w, &mut h, null_mut(),null_mut());
 
         let mut result = String::new();
         for j in 0..h {
@@ -69,3 +94,1610 @@ fn draw_capital_g() {
         "   o@@@@:  \n" +
         "     .     \n" );
 }
+
+#[test]
+fn owned_font() {
+    fn load() -> OwnedFontInfo {
+        let data = include_bytes!("../assets/Tuffy_Bold.ttf").to_vec();
+        FontInfo::from_vec(data, 0).ok().expect("Failed to load font")
+    }
+
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let borrowed = FontInfo::new_with_offset(&bs[..], 0).ok().expect("Failed to load font");
+    let owned = load();
+    let copy = FontInfo::new_with_offset(&bs[..], 0).unwrap().into_owned();
+    assert_eq!(owned.data(), &bs[..]);
+    assert!(copy.data().as_ptr() != bs.as_ptr());
+
+    for font in &[&owned, &copy] {
+        let g = font.glyph_index_for_code('G');
+        assert_eq!(g, borrowed.glyph_index_for_code('G'));
+        assert_eq!(font.glyph_data_for_glyph_at_index(g).bounding_box(),
+                   borrowed.glyph_data_for_glyph_at_index(g).bounding_box());
+    }
+}
+
+#[test]
+fn render_from_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<FontInfo>();
+    assert_send_sync::<FontCollection>();
+
+    fn render(font: &FontInfo, c: char) -> Vec<u8> {
+        unsafe {
+            let (mut w, mut h) = (0, 0);
+            let scale = font.scale_for_pixel_height(20.0);
+            let bitmap = get_codepoint_bitmap(font, scale, scale, c,
+                &mut w, &mut h, null_mut(), null_mut());
+            let pixels = std::slice::from_raw_parts(bitmap, (w * h) as usize).to_vec();
+            free_bitmap(bitmap);
+            pixels
+        }
+    }
+
+    let data = include_bytes!("../assets/Tuffy_Bold.ttf").to_vec();
+    let font = Arc::new(FontInfo::from_vec(data, 0).ok().expect("Failed to load font"));
+    let threads: Vec<_> = "AGgx".chars().map(|c| {
+        let font = font.clone();
+        thread::spawn(move || (c, render(&font, c)))
+    }).collect();
+    for thread in threads {
+        let (c, pixels) = thread.join().unwrap();
+        assert_eq!(pixels, render(&font, c));
+    }
+}
+
+#[test]
+fn parse_corrupt_fonts() {
+    unsafe fn render(font: &FontInfo, glyph: GlyphId) {
+        let mut vertices = null_mut();
+        let n = get_glyph_shape(font, glyph, &mut vertices);
+        assert!(n == 0 || !vertices.is_null());
+        free_shape(font, vertices);
+
+        let (mut w, mut h) = (0, 0);
+        let bitmap = get_glyph_bitmap(font, 0.05, 0.05, glyph, &mut w, &mut h, null_mut(), null_mut());
+        free_bitmap(bitmap as *mut u8);
+        get_glyph_kern_advance(font, glyph, GlyphId(glyph.0.wrapping_add(1)));
+    }
+
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    assert!(FontInfo::parse_strict(&bs[..]).is_ok());
+    assert!(FontInfo::parse_strict(&bs[..1000]).is_err());
+    assert!(FontInfo::parse_strict(&[]).is_err());
+
+    // Deterministic xorshift, so that failures can be reproduced.
+    let mut state = 0x2545f491u32;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as usize
+    };
+
+    let mut parsed = 0;
+    for _ in 0..500 {
+        let mut data = bs.to_vec();
+        for _ in 0..1 + random() % 8 {
+            let i = random() % data.len();
+            data[i] = random() as u8;
+        }
+        let font = match FontInfo::parse_strict(&data) {
+            Ok(font) => font,
+            Err(_) => continue,
+        };
+        parsed += 1;
+        unsafe {
+            for c in "AGgxл".chars() {
+                render(&font, font.glyph_index_for_code(c));
+            }
+            for _ in 0..8 {
+                render(&font, GlyphId((random() % 1000) as u16));
+            }
+        }
+    }
+    assert!(parsed > 0);
+}
+
+#[test]
+fn detect_font_kind() {
+    let data = include_bytes!("../assets/Tuffy_Bold.ttf");
+    assert_eq!(FontKind::detect(data), Some(FontKind::TrueType));
+    assert_eq!(FontKind::detect(b"OTTO\0\x0a"), Some(FontKind::OpenTypeCFF));
+    assert_eq!(FontKind::detect(b"ttcf\0\x02\0\0"), Some(FontKind::TrueTypeCollection));
+    assert_eq!(FontKind::detect(b"wOFF\0\x01\0\0"), Some(FontKind::Woff));
+    assert_eq!(FontKind::detect(b"wOF2\0\x01\0\0"), Some(FontKind::Woff2));
+    assert_eq!(FontKind::detect(b"\x89PNG\r\n"), None);
+    assert_eq!(FontKind::detect(b"\0\x01\0"), None);
+    assert!(FontKind::TrueType.is_font() && !FontKind::Woff.is_font());
+
+    // Compressed fonts aren't taken for fonts.
+    let mut woff = data.to_vec();
+    woff[..4].copy_from_slice(b"wOFF");
+    assert!(FontCollection::from_data(&woff).is_err());
+    assert!(FontInfo::parse_strict(&woff).is_err());
+    assert_eq!(unsafe { get_font_offset_for_index(woff.as_ptr(), 0) }, -1);
+    assert_eq!(unsafe { get_font_offset_for_index(data.as_ptr(), 0) }, 0);
+}
+
+#[test]
+fn reject_type1_fonts() {
+    // The tables of a Type 1 font look like those of TrueType.
+    let mut data = include_bytes!("../assets/Tuffy_Bold.ttf").to_vec();
+    data[..4].copy_from_slice(b"typ1");
+    assert_eq!(FontKind::detect(&data), Some(FontKind::Type1));
+    assert_eq!(FontInfo::new_with_offset(&data, 0).err(), Some(Error::Type1NotSupported));
+    assert_eq!(FontInfo::parse_strict(&data).err(), Some(Error::Type1NotSupported));
+    assert_eq!(FontCollection::from_data(&data).err(), Some(Error::Type1NotSupported));
+    assert_eq!(unsafe { get_font_offset_for_index(data.as_ptr(), 0) }, -1);
+}
+
+/// Returns an EOT file of version 1 holding `font`.
+fn eot(font: &[u8], flags: u32) -> Vec<u8> {
+    // The fixed fields, then four empty names.
+    let mut header = vec![0; 82 + 4 * 4];
+    let size = (header.len() + font.len()) as u32;
+    header[0..4].copy_from_slice(&size.to_le_bytes());
+    header[4..8].copy_from_slice(&(font.len() as u32).to_le_bytes());
+    header[8..12].copy_from_slice(&0x10000u32.to_le_bytes());
+    header[12..16].copy_from_slice(&flags.to_le_bytes());
+    header[34..36].copy_from_slice(&0x504Cu16.to_le_bytes());
+    header.extend(font.iter().map(|b| if flags & 0x10000000 != 0 { b ^ 0x50 } else { *b }));
+    header
+}
+
+#[test]
+fn unwrap_eot_fonts() {
+    let data = include_bytes!("../assets/Tuffy_Bold.ttf");
+    for &flags in &[0, 0x10000000] {
+        let file = eot(data, flags);
+        assert_eq!(FontKind::detect(&file), Some(FontKind::Eot));
+        let font = unwrap_eot(&file).unwrap();
+        assert_eq!(&font[..], &data[..]);
+        let font = FontInfo::new_with_offset(&font, 0).unwrap();
+        assert_eq!(font.glyph_index_for_code('a'), GlyphId(68));
+    }
+
+    assert_eq!(unwrap_eot(&eot(data, 0x4)).err(), Some(Error::CompressedFontNotSupported));
+    let mut file = eot(data, 0);
+    file.pop();
+    assert_eq!(unwrap_eot(&file).err(), Some(Error::Malformed));
+    assert_eq!(unwrap_eot(data).err(), Some(Error::Malformed));
+}
+
+#[cfg(feature = "builtin-font")]
+#[test]
+fn builtin_font() {
+    let font = FontInfo::builtin();
+    assert_eq!(font.data(), BUILTIN_FONT);
+    assert_eq!(font.glyph_index_for_code('a'), GlyphId(68));
+}
+
+#[test]
+fn verify_checksums() {
+    let mut data = include_bytes!("../assets/Tuffy_Bold.ttf").to_vec();
+    assert!(FontInfo::new_with_offset(&data, 0).unwrap().verify_checksums().unwrap().is_valid());
+
+    // Patch the outline of a glyph without fixing the checksums.
+    let offset = FontInfo::new_with_offset(&data, 0).unwrap()
+        .offset_for_glyph_at_index(GlyphId(36)).unwrap();
+    data[offset + 20] ^= 0xff;
+    let check_sums = FontInfo::new_with_offset(&data, 0).unwrap().verify_checksums().unwrap();
+    assert_eq!(check_sums.corrupt_tables, vec![*b"glyf"]);
+    assert!(!check_sums.adjustment_matches);
+}
+
+/// Returns a font whose glyph 2 is a compound glyph made of `components`
+/// and glyph 1 is a square of 100 units advancing by 300 units.
+fn font_with_compound_glyph(components: &[u8]) -> Vec<u8> {
+    let square = vec![OutlinePoint::on(0, 0), OutlinePoint::on(0, 100),
+                      OutlinePoint::on(100, 100), OutlinePoint::on(100, 0)];
+    // Large enough to be overwritten with the compound glyph.
+    let placeholder = (0..16).map(|i| OutlinePoint::on(i % 2 * 1000, i * 1000)).collect();
+
+    let mut builder = FontBuilder::new("Compound", 1000);
+    builder.add_glyph(vec![], 500);
+    builder.add_glyph(vec![square], 300);
+    builder.add_glyph(vec![placeholder], 500);
+    let mut data = builder.build().unwrap();
+
+    let offset = FontInfo::new_with_offset(&data, 0).unwrap()
+        .offset_for_glyph_at_index(GlyphId(2)).unwrap();
+    data[offset..offset + 10].copy_from_slice(&[0xff, 0xff, 0, 0, 0, 0, 0, 200, 0, 200]);
+    data[offset + 10..offset + 10 + components.len()].copy_from_slice(components);
+    data
+}
+
+/// Renders glyph 2 of `data` at a tenth of its size, as rows of pixels.
+fn render_compound_glyph(data: &[u8]) -> Vec<Vec<u8>> {
+    let font = FontInfo::new_with_offset(data, 0).unwrap();
+    unsafe {
+        let (mut w, mut h) = (0, 0);
+        let bitmap = get_glyph_bitmap(&font, 0.1, 0.1, GlyphId(2), &mut w, &mut h, null_mut(), null_mut());
+        let pixels = std::slice::from_raw_parts(bitmap, (w * h) as usize).to_vec();
+        free_bitmap(bitmap as *mut u8);
+        pixels.chunks(w as usize).map(|row| row.to_vec()).collect()
+    }
+}
+
+#[test]
+fn compound_glyph_matching_points() {
+    // Point 0 of the second square is moved onto point 2 of the first one.
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0, 0, 1, 2, 0]);
+    assert!(FontInfo::parse_strict(&data).is_ok());
+    let rows = render_compound_glyph(&data);
+    assert_eq!(rows.len(), 20);
+    assert_eq!((rows[5][5], rows[5][15]), (0, 255));
+    assert_eq!((rows[15][5], rows[15][15]), (255, 0));
+
+    // Points that don't exist are ignored.
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0, 0, 1, 9, 0]);
+    let rows = render_compound_glyph(&data);
+    assert_eq!((rows[5][5], rows[5][15]), (0, 0));
+}
+
+#[test]
+fn compound_glyph_flags() {
+    // A square scaled by half and moved by 100 units.
+    let unscaled = font_with_compound_glyph(&[0, 0x0a, 0, 1, 100, 100, 0x20, 0]);
+    let rows = render_compound_glyph(&unscaled);
+    assert_eq!((rows[7][12], rows[12][7]), (255, 0));
+
+    // SCALED_COMPONENT_OFFSET scales the offset too.
+    let scaled = font_with_compound_glyph(&[0x08, 0x0a, 0, 1, 100, 100, 0x20, 0]);
+    let rows = render_compound_glyph(&scaled);
+    assert_eq!((rows[7][12], rows[12][7]), (0, 255));
+
+    // ROUND_XY_TO_GRID rounds the scaled offset of 50.5 units up.
+    let rounded = font_with_compound_glyph(&[0x08, 0x0e, 0, 1, 101, 101, 0x20, 0]);
+    let even = font_with_compound_glyph(&[0x08, 0x0a, 0, 1, 102, 102, 0x20, 0]);
+    let odd = font_with_compound_glyph(&[0x08, 0x0a, 0, 1, 101, 101, 0x20, 0]);
+    assert_eq!(render_compound_glyph(&rounded), render_compound_glyph(&even));
+    assert!(render_compound_glyph(&rounded) != render_compound_glyph(&odd));
+
+    // USE_MY_METRICS takes the advance of the square.
+    let font = FontInfo::new_with_offset(&scaled, 0).unwrap();
+    assert_eq!(font.hmetric_for_glyph_at_index(GlyphId(2)).advance_width, 500);
+    let data = font_with_compound_glyph(&[0x02, 0x0a, 0, 1, 100, 100, 0x20, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.hmetric_for_glyph_at_index(GlyphId(2)).advance_width, 300);
+}
+
+#[test]
+fn compound_glyph_cycles() {
+    unsafe fn shape(font: &FontInfo) -> piston_truetype::Result<isize> {
+        let mut vertices = null_mut();
+        let n = try_get_glyph_shape(font, GlyphId(2), &mut vertices);
+        free_shape(font, vertices);
+        n
+    }
+
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let mut font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.max_component_depth(), DEFAULT_MAX_COMPONENT_DEPTH);
+    unsafe {
+        assert_eq!(shape(&font), Ok(5));
+        font.set_max_component_depth(0);
+        assert_eq!(shape(&font), Err(Error::Malformed));
+    }
+
+    // Glyph 2 contains itself, directly or to match points.
+    for components in &[&[0, 0x02, 0, 2, 0, 0][..], &[0, 0x22, 0, 1, 0, 0, 0, 0, 0, 2, 0, 0]] {
+        let data = font_with_compound_glyph(components);
+        assert_eq!(FontInfo::parse_strict(&data).err(), Some(Error::Malformed));
+        let font = FontInfo::new_with_offset(&data, 0).unwrap();
+        unsafe {
+            assert_eq!(shape(&font), Err(Error::Malformed));
+            let mut vertices = null_mut();
+            assert_eq!(get_glyph_shape(&font, GlyphId(2), &mut vertices), 0);
+        }
+    }
+}
+
+/// Renders glyph 2 of `data` at a tenth of its size, shifted by half
+/// a pixel to the right.
+fn render_with_options(data: &[u8], options: &RasterOptions) -> Vec<u8> {
+    let font = FontInfo::new_with_offset(data, 0).unwrap();
+    unsafe {
+        let (mut w, mut h) = (0, 0);
+        let bitmap = get_glyph_bitmap_with_options(&font, 0.1, 0.1, 0.5, 0.0, GlyphId(2), options,
+            &mut w, &mut h, null_mut(), null_mut());
+        let pixels = std::slice::from_raw_parts(bitmap, (w * h) as usize).to_vec();
+        free_bitmap(bitmap);
+        pixels
+    }
+}
+
+#[test]
+fn overlap_correct_rasterization() {
+    // The same square twice, its left edge halfway through a pixel.
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0x02, 0, 1, 0, 0]);
+    let overlap_correct = RasterOptions { overlap_correct: true, ..RasterOptions::default() };
+    let sampled = render_with_options(&data, &overlap_correct);
+    let added = render_with_options(&data, &RasterOptions::default());
+    // The bitmap is 21 pixels wide, rows start with the left edge.
+    assert_eq!((added[21 * 15], added[21 * 15 + 5]), (255, 255));
+    assert_eq!((sampled[21 * 15], sampled[21 * 15 + 5]), (128, 255));
+
+    // Without overlaps both agree.
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    assert_eq!(render_with_options(&data, &overlap_correct),
+               render_with_options(&data, &RasterOptions::default()));
+}
+
+#[test]
+fn fill_rules() {
+    let even_odd = RasterOptions { fill_rule: FillRule::EvenOdd, ..RasterOptions::default() };
+
+    // The same square twice is empty inside with the even-odd rule.
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0x02, 0, 1, 0, 0]);
+    assert_eq!(render_with_options(&data, &RasterOptions::default())[21 * 15 + 5], 255);
+    assert_eq!(render_with_options(&data, &even_odd)[21 * 15 + 5], 0);
+
+    // Overlapping in a quarter of their area.
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0x02, 0, 1, 50, 50]);
+    let pixels = render_with_options(&data, &even_odd);
+    assert_eq!((pixels[21 * 17 + 2], pixels[21 * 12 + 7], pixels[21 * 7 + 12]), (255, 0, 255));
+}
+
+#[test]
+fn mono_rasterization() {
+    let data = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(data, 0).unwrap();
+    let mono = RasterOptions { mode: RasterMode::Mono, ..RasterOptions::default() };
+    let path = font.glyph_path(font.glyph_index_for_code('a'), 0.02).unwrap();
+    let bitmap = rasterize_path(&path, &mono);
+    assert!(bitmap.as_slice().iter().any(|&p| p == 255));
+    // Pixels are full where their center is inside the glyph.
+    for y in 0..bitmap.height() {
+        for x in 0..bitmap.width() {
+            let center = ((bitmap.x + x as i32) as f32 + 0.5, (bitmap.y + y as i32) as f32 + 0.5);
+            let inside = path_contains(&path, center.0, center.1, FillRule::NonZero);
+            assert_eq!(bitmap.as_slice()[y * bitmap.width() + x], if inside { 255 } else { 0 });
+        }
+    }
+    // Glyph bitmaps take the mode too.
+    unsafe {
+        let (mut w, mut h) = (0, 0);
+        let glyph = font.glyph_index_for_code('a');
+        let bitmap = get_glyph_bitmap_with_options(&font, 0.02, 0.02, 0.0, 0.0, glyph, &mono,
+            &mut w, &mut h, null_mut(), null_mut());
+        let pixels = std::slice::from_raw_parts(bitmap, (w * h) as usize).to_vec();
+        free_bitmap(bitmap);
+        assert!(pixels.iter().all(|&p| p == 0 || p == 255));
+        assert!(pixels.iter().any(|&p| p == 255));
+    }
+}
+
+#[test]
+fn rasterize_paths() {
+    let square = [PathSegment::MoveTo(0.5, 1.5), PathSegment::LineTo(2.5, 1.5),
+        PathSegment::LineTo(2.5, 3.5), PathSegment::LineTo(0.5, 3.5)];
+    let sampled = RasterOptions { overlap_correct: true, ..RasterOptions::default() };
+    for options in &[RasterOptions::default(), sampled] {
+        let bitmap = rasterize_path(&square, options);
+        assert_eq!((bitmap.x, bitmap.y, bitmap.width(), bitmap.height()), (0, 1, 3, 3));
+        assert_eq!(bitmap.as_slice(), vec![64, 128, 64, 128, 255, 128, 64, 128, 64]);
+    }
+
+    // A curve bulging to the right of the line from (0, 0) to (0, 8).
+    let bitmap = rasterize_path(&[PathSegment::QuadTo(8.0, 4.0, 0.0, 8.0)],
+        &RasterOptions::default());
+    assert_eq!((bitmap.x, bitmap.y, bitmap.width(), bitmap.height()), (0, 0, 4, 8));
+    assert_eq!(bitmap.as_slice()[4 * 4], 255);
+    assert_eq!(bitmap.as_slice()[3], 0);
+
+    assert_eq!(rasterize_path(&[], &RasterOptions::default()), GrayBitmap::default());
+}
+
+#[test]
+fn stroked_glyph() {
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+
+    assert_eq!(font.glyph_path(GlyphId(1), 0.1).unwrap(), vec![PathSegment::MoveTo(0.0, 0.0),
+        PathSegment::LineTo(0.0, -10.0), PathSegment::LineTo(10.0, -10.0),
+        PathSegment::LineTo(10.0, 0.0), PathSegment::LineTo(0.0, 0.0)]);
+
+    // The 10 pixel square with a line 2 pixels wide around it.
+    let bitmap = font.rasterize_stroked(GlyphId(1), 0.1, 2.0);
+    assert_eq!((bitmap.x, bitmap.y, bitmap.width(), bitmap.height()), (-1, -11, 12, 12));
+    assert_eq!((bitmap.as_slice()[12 * 6], bitmap.as_slice()[12 * 6 + 1], bitmap.as_slice()[12 * 6 + 6]),
+        (255, 255, 0));
+    assert_eq!(bitmap.as_slice()[12 + 1], 255);
+    assert_eq!(font.rasterize_stroked(GlyphId(0), 0.1, 2.0), GrayBitmap::default());
+}
+
+#[test]
+fn synthetic_bold_and_oblique() {
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    let path = font.glyph_path(GlyphId(1), 0.1).unwrap();
+
+    let bold = rasterize_path(&embolden(&path, 2.0), &RasterOptions::default());
+    assert_eq!((bold.x, bold.y, bold.width(), bold.height()), (-1, -11, 12, 12));
+    assert!(bold.as_slice().iter().all(|&p| p == 255));
+
+    // The top is 10 pixels to the right at a shear of 1.
+    let italic = rasterize_path(&oblique(&path, 1.0), &RasterOptions::default());
+    assert_eq!((italic.x, italic.y, italic.width(), italic.height()), (0, -10, 20, 10));
+    assert_eq!((italic.as_slice()[15], italic.as_slice()[20 * 9 + 5]), (255, 255));
+    assert_eq!((italic.as_slice()[5], italic.as_slice()[20 * 9 + 15]), (0, 0));
+}
+
+#[test]
+fn transformed_rasterization() {
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0x02, 0, 1, 50, 50]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    let render = |transform: &[f32; 6]| unsafe {
+        let (mut w, mut h, mut x, mut y) = (0, 0, 0, 0);
+        let bitmap = get_glyph_bitmap_transformed(&font, transform, GlyphId(2), &RasterOptions::default(),
+            &mut w, &mut h, &mut x, &mut y);
+        let pixels = std::slice::from_raw_parts(bitmap, (w * h) as usize).to_vec();
+        free_bitmap(bitmap);
+        (w, h, x, y, pixels)
+    };
+
+    let (w, h, x, y, pixels) = render(&[0.1, 0.0, 0.0, -0.1, 0.5, 0.0]);
+    assert_eq!((w, h, x, y), (21, 20, 0, -20));
+    assert_eq!(pixels, render_with_options(&data, &RasterOptions::default()));
+
+    // The first square rotated by 45 degrees stands on a corner.
+    let c = 0.1 * std::f32::consts::FRAC_1_SQRT_2;
+    let (w, h, x, y, pixels) = render(&[c, -c, -c, -c, 0.0, 0.0]);
+    assert_eq!((w, h, x, y), (30, 29, -15, -29));
+    assert_eq!((pixels[30 * 21 + 15], pixels[30 * 28], pixels[30 * 28 + 15]), (255, 0, 128));
+}
+
+#[test]
+fn glyph_hit_testing() {
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0x02, 0, 1, 50, 50]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert!(font.glyph_contains(GlyphId(2), 2.0, -2.0, 0.1));
+    assert!(font.glyph_contains(GlyphId(2), 7.0, -7.0, 0.1));
+    assert!(font.glyph_contains(GlyphId(2), 14.0, -14.0, 0.1));
+    assert!(!font.glyph_contains(GlyphId(2), 14.0, -2.0, 0.1));
+    assert!(!font.glyph_contains(GlyphId(2), 2.0, 2.0, 0.1));
+    assert!(!font.glyph_contains(GlyphId(0), 0.0, 0.0, 0.1));
+}
+
+#[test]
+fn glyph_stats() {
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0x02, 0, 1, 50, 50]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.glyph_stats(GlyphId(1)).unwrap(), GlyphStats {
+        contours: 1, points: 4, area: -10000.0, orientation: Some(Orientation::Clockwise) });
+    assert_eq!(font.glyph_stats(GlyphId(2)).unwrap(), GlyphStats {
+        contours: 2, points: 8, area: -20000.0, orientation: Some(Orientation::Clockwise) });
+    assert_eq!(font.glyph_stats(GlyphId(0)).unwrap(), GlyphStats::default());
+
+    // The area of curves matches the coverage of the bitmap.
+    let font = tuffy();
+    let glyph = font.glyph_index_for_code('o');
+    let stats = font.glyph_stats(glyph).unwrap();
+    assert_eq!(stats.orientation, Some(Orientation::Clockwise));
+    assert_eq!(stats.contours, 2);
+    unsafe {
+        let (mut w, mut h) = (0, 0);
+        let bitmap = get_glyph_bitmap(&font, 0.5, 0.5, glyph, &mut w, &mut h,
+            null_mut(), null_mut());
+        let pixels = std::slice::from_raw_parts(bitmap, (w * h) as usize);
+        let coverage = pixels.iter().map(|&p| p as f32 / 255.0).sum::<f32>() / 0.25;
+        free_bitmap(bitmap as *mut u8);
+        assert!((coverage + stats.area).abs() < stats.area.abs() * 0.01);
+    }
+}
+
+#[test]
+fn rasterize_into_gray_bitmap() {
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    unsafe {
+        let mut vertices = null_mut();
+        let n = get_glyph_shape(&font, GlyphId(1), &mut vertices);
+        // The square from (0, -10) to (10, 0) shifted by half a pixel.
+        let mut bitmap = GrayBitmap::new(0, -10, 11, 10);
+        rasterize(&mut bitmap, 0.35, vertices, n, 0.1, 0.1, 0.5, 0.0, 1);
+        free_shape(&font, vertices);
+        assert_eq!(bitmap.row(3)[..3], [128, 255, 255]);
+        assert_eq!(bitmap.row(3)[10], 128);
+        assert_eq!(bitmap.as_slice().iter().filter(|&&p| p == 255).count(), 90);
+        assert_eq!(bitmap.into_vec().len(), 110);
+    }
+}
+
+#[test]
+fn render_into_clipped_target() {
+    let font = tuffy();
+    let glyph = font.glyph_index_for_code('g');
+    let scale = font.scale_for_pixel_height(40.0);
+    let (mut w, mut h, mut xoff, mut yoff) = (0, 0, 0, 0);
+    let expected = unsafe {
+        let bitmap = get_glyph_bitmap(&font, scale, scale, glyph,
+            &mut w, &mut h, &mut xoff, &mut yoff);
+        let pixels = std::slice::from_raw_parts(bitmap, (w * h) as usize).to_vec();
+        free_bitmap(bitmap as *mut u8);
+        pixels
+    };
+    let (w, h, xoff, yoff) = (w as usize, h as usize, xoff as i32, yoff as i32);
+
+    // Every part of the glyph in a target with padding on each row.
+    for &(x, y) in &[(-xoff, -yoff), (-xoff - 5, -yoff - 7), (-xoff + 5, -yoff + 7)] {
+        let (width, height, stride) = (w, h, w + 3);
+        let mut target = vec![7; stride * height];
+        font.render_into(glyph, scale, &mut target, width, height, stride, x, y);
+        for row in 0..height {
+            assert_eq!(&target[row * stride + width..(row + 1) * stride], &[7, 7, 7]);
+            for column in 0..width {
+                let (gx, gy) = (column as i32 - x - xoff, row as i32 - y - yoff);
+                if gx >= 0 && gy >= 0 && (gx as usize) < w && (gy as usize) < h {
+                    assert_eq!(target[row * stride + column], expected[gy as usize * w + gx as usize]);
+                } else {
+                    assert_eq!(target[row * stride + column], 7);
+                }
+            }
+        }
+    }
+
+    // Entirely outside.
+    let mut target = vec![7; 16];
+    font.render_into(glyph, scale, &mut target, 4, 4, 4, 1000, 1000);
+    assert_eq!(target, vec![7; 16]);
+}
+
+#[test]
+fn rgba_conversion() {
+    let square = [PathSegment::MoveTo(0.0, 0.0), PathSegment::LineTo(1.5, 0.0),
+        PathSegment::LineTo(1.5, 1.0), PathSegment::LineTo(0.0, 1.0)];
+    let bitmap = rasterize_path(&square, &RasterOptions::default());
+    assert_eq!(bitmap.as_slice(), vec![255, 128]);
+    assert_eq!(bitmap.to_rgba([255, 0, 0, 255]), vec![255, 0, 0, 255, 255, 0, 0, 128]);
+    assert_eq!(bitmap.to_rgba_premultiplied([255, 0, 0, 255]), vec![255, 0, 0, 255, 128, 0, 0, 128]);
+    assert_eq!(bitmap.to_rgba_premultiplied([200, 100, 50, 128]),
+        vec![100, 50, 25, 128, 50, 25, 13, 64]);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn save_images() {
+    let mut bitmap = GrayBitmap::new(0, 0, 2, 2);
+    bitmap.as_mut_slice()[1] = 255;
+    let path = std::env::temp_dir().join("piston_truetype_save_images.pgm");
+    bitmap.save_pgm(&path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"P5\n2 2\n255\n\x00\xff\x00\x00".to_vec());
+
+    let path = path.with_extension("png");
+    bitmap.save_png(&path).unwrap();
+    assert_eq!(&std::fs::read(&path).unwrap()[..8], b"\x89PNG\r\n\x1a\n");
+}
+
+#[test]
+fn bake_atlas() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, ' ', 95).unwrap();
+    assert_eq!(atlas.len(), 95);
+    assert_eq!((atlas.bitmap.width(), atlas.bitmap.height()), (256, 256));
+    assert!(atlas.baked_char('A').is_some());
+    assert!(atlas.baked_char('\u{7f}').is_none());
+    assert!(atlas.baked_char('\u{1f}').is_none());
+
+    // Only some characters fit.
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 64, 64, ' ', 95).unwrap();
+    assert!(atlas.len() > 0 && atlas.len() < 95);
+    assert!(Atlas::bake(&bs[..10], 0, 32.0, 64, 64, ' ', 95).is_err());
+}
+
+#[cfg(feature = "piston")]
+#[test]
+fn piston_textures() {
+    use texture::{CreateTexture, Format, ImageSize, TextureSettings};
+
+    struct Texture(Vec<u8>, [u32; 2]);
+
+    impl ImageSize for Texture {
+        fn get_size(&self) -> (u32, u32) {
+            (self.1[0], self.1[1])
+        }
+    }
+
+    impl CreateTexture<()> for Texture {
+        type Error = ();
+
+        fn create<S: Into<[u32; 2]>>(_: &mut (), _: Format, memory: &[u8], size: S,
+            _: &TextureSettings) -> std::result::Result<Texture, ()>
+        {
+            Ok(Texture(memory.to_vec(), size.into()))
+        }
+    }
+
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, ' ', 95).unwrap();
+    let texture: Texture = atlas.create_texture(&mut (), &TextureSettings::new()).unwrap();
+    assert_eq!(texture.get_size(), (256, 256));
+    assert_eq!(texture.0.len(), 256 * 256 * 4);
+    assert!(texture.0.chunks(4).all(|p| p[..3] == [255, 255, 255]));
+    assert!(texture.0.chunks(4).any(|p| p[3] == 255));
+
+    let image = atlas.glyph_image('A', 10.0, 40.0).unwrap();
+    let [x, y, w, h] = image.src_rect;
+    assert!(w > 0.0 && h > 0.0 && x + w <= 256.0 && y + h <= 256.0);
+    assert_eq!([image.rect[2], image.rect[3]], [w, h]);
+    assert!(image.rect[1] < 40.0 && image.rect[1] + h <= 41.0);
+    assert!(image.advance > 0.0);
+    assert_eq!(atlas.glyph_image('\u{100}', 0.0, 0.0), None);
+}
+
+#[test]
+fn baked_quads() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, ' ', 95).unwrap();
+    let (quad, x) = atlas.quad('A', 10.0, 40.0).unwrap();
+    assert!(x > 10.0);
+    assert_eq!(quad.x0, quad.x0.floor());
+    assert!(quad.s0 < quad.s1 && quad.s1 <= 1.0 && quad.t0 < quad.t1 && quad.t1 <= 1.0);
+    assert_eq!((quad.x1 - quad.x0) / 256.0, quad.s1 - quad.s0);
+    assert_eq!(atlas.quad('\u{100}', 0.0, 0.0), None);
+
+    let baked = atlas.baked_char('A').unwrap();
+    let (mut q, mut xpos) = (AlignedQuad::default(), 10.0);
+    unsafe {
+        get_baked_quad(baked as *const _ as *mut _, 256, 256, 0, &mut xpos, &40.0, &mut q, 0);
+    }
+    assert_eq!((q, xpos), baked.quad(256, 256, 10.0, 40.0, false));
+    assert_eq!(q.x0, quad.x0 - 0.5);
+}
+
+#[test]
+fn public_fields() {
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap().clone();
+    let shape = unsafe {
+        let mut vertices = null_mut();
+        let n = get_glyph_shape(&font, GlyphId(1), &mut vertices);
+        let shape = std::slice::from_raw_parts(vertices, n as usize).to_vec();
+        free_shape(&font, vertices);
+        shape
+    };
+    assert_eq!(shape.iter().map(|v| (v.cmd(), v.x(), v.y())).collect::<Vec<_>>(),
+        vec![(Cmd::Move, 0, 0), (Cmd::Line, 0, 100), (Cmd::Line, 100, 100),
+            (Cmd::Line, 100, 0), (Cmd::Line, 0, 0)]);
+
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, ' ', 95).unwrap();
+    let baked = *atlas.baked_char('A').unwrap();
+    assert!(baked.x1 > baked.x0 && baked.y1 > baked.y0 && baked.xadvance > 0.0);
+    let packed = PackedChar { x0: 1, y0: 2, x1: 3, y1: 4, xoff2: 2.0, ..PackedChar::default() };
+    assert_eq!(packed.quad(4, 4, 0.0, 0.0, false).0.x1, 2.0);
+}
+
+#[test]
+fn compound_glyph_coordinates_beyond_i16() {
+    // The square scaled by 1.5 and moved by 32700 units in both directions.
+    let data = font_with_compound_glyph(&[0, 0x0b, 0, 1, 0x7f, 0xbc, 0x7f, 0xbc, 0x60, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    let shape = unsafe {
+        let mut vertices = null_mut();
+        let n = get_glyph_shape(&font, GlyphId(2), &mut vertices);
+        let shape = std::slice::from_raw_parts(vertices, n as usize).to_vec();
+        free_shape(&font, vertices);
+        shape
+    };
+    assert_eq!(shape.iter().map(|v| (v.x(), v.y())).collect::<Vec<_>>(),
+        vec![(32700, 32700), (32700, 32850), (32850, 32850), (32850, 32700), (32700, 32700)]);
+    assert_eq!(font.glyph_stats(GlyphId(2)).unwrap().area, -22500.0);
+    assert!(font.glyph_contains(GlyphId(2), 328.0, -328.0, 0.01));
+    assert!(!font.glyph_contains(GlyphId(2), 326.0, -326.0, 0.01));
+}
+
+#[test]
+fn scaled_glyph_shape() {
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.glyph_shape_scaled(GlyphId(1), 0.1, 0.2, 0.5, 0.25).unwrap(),
+        vec![PathSegment::MoveTo(0.5, 0.25), PathSegment::LineTo(0.5, -19.75),
+            PathSegment::LineTo(10.5, -19.75), PathSegment::LineTo(10.5, 0.25),
+            PathSegment::LineTo(0.5, 0.25)]);
+
+    // At the same place as in bitmaps.
+    let font = tuffy();
+    let glyph = font.glyph_index_for_code('S');
+    let path = font.glyph_shape_scaled(glyph, 0.02, 0.03, 0.5, 0.25).unwrap();
+    assert!(path.iter().any(|s| match *s { PathSegment::QuadTo(..) => true, _ => false }));
+    unsafe {
+        let (mut w, mut h, mut x, mut y) = (0, 0, 0, 0);
+        let bitmap = get_glyph_bitmap_subpixel(&font, 0.02, 0.03, 0.5, 0.25, glyph,
+            &mut w, &mut h, &mut x, &mut y);
+        free_bitmap(bitmap);
+        let from_path = rasterize_path(&path, &RasterOptions::default());
+        assert_eq!((from_path.x, from_path.y), (x as i32, y as i32));
+        assert!(from_path.width() <= w as usize && from_path.height() <= h as usize);
+    }
+}
+
+#[test]
+fn advance_widths() {
+    let font = tuffy();
+    for i in 0..2000 {
+        let glyph = GlyphId(i);
+        assert_eq!(font.advance_width(glyph), font.hmetric_for_glyph_at_index(glyph).advance_width);
+    }
+
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.advance_width(GlyphId(1)), 300);
+}
+
+#[test]
+fn kerning_pairs() {
+    let font = tuffy();
+    assert!(!font.kern().is_empty());
+    assert_eq!(font.kern().kerning(6, 7), -55);
+    assert_eq!(font.kern().kerning(7, 6), 0);
+    // Without a `kerx` table the kerning is that of the `kern` table, and
+    // the `morx` table only has ligatures, which aren't read.
+    assert!(font.kerx().is_empty());
+    assert_eq!(font.kerning(GlyphId(6), GlyphId(7)), -55);
+    assert!(font.morx().is_empty());
+    unsafe {
+        assert_eq!(get_glyph_kern_advance(&font, GlyphId(6), GlyphId(7)), -55);
+    }
+
+    // Fonts without a kerning table have no kerning.
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert!(font.kern().is_empty());
+}
+
+#[test]
+fn glyph_outlines() {
+    let data = font_with_compound_glyph(&[0, 0x22, 0, 1, 0, 0, 0, 0x02, 0, 1, 50, 50]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.glyph_outline(GlyphId(0)).unwrap(), Outline::Simple(vec![]));
+    // The contours the font was built from.
+    assert_eq!(font.glyph_outline(GlyphId(1)).unwrap(), Outline::Simple(vec![vec![
+        OutlinePoint::on(0, 0), OutlinePoint::on(0, 100),
+        OutlinePoint::on(100, 100), OutlinePoint::on(100, 0)]]));
+    match font.glyph_outline(GlyphId(2)).unwrap() {
+        Outline::Composite(components) => {
+            assert_eq!(components.len(), 2);
+            assert_eq!(components[1].glyph_index, 1);
+            assert_eq!(components[1].transform, [1.0, 0.0, 0.0, 1.0, 50.0, 50.0]);
+        }
+        outline => panic!("{:?}", outline),
+    }
+    assert_eq!(font.glyph_outline(GlyphId(3)), Ok(Outline::Simple(vec![])));
+
+    // The shapes are made of the same points.
+    let font = tuffy();
+    let glyph = font.glyph_index_for_code('B');
+    match font.glyph_outline(glyph).unwrap() {
+        Outline::Simple(contours) => {
+            assert_eq!(contours.len(), font.glyph_stats(glyph).unwrap().contours);
+            let points: usize = contours.iter().map(|c| c.len()).sum();
+            assert_eq!(points, font.glyph_stats(glyph).unwrap().points);
+        }
+        outline => panic!("{:?}", outline),
+    }
+}
+
+#[test]
+fn mac_style_matching() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    assert_eq!(font.head().mac_style(), MacStyle::BOLD);
+    assert_eq!(font.head().lowest_rec_ppem(), 8);
+    unsafe {
+        let bold = MacStyle::BOLD.bits() as i32;
+        let italic = (MacStyle::BOLD | MacStyle::ITALIC).bits() as i32;
+        assert_eq!(find_matching_font(bs.as_ptr(), b"Tuffy\0".as_ptr(), bold), 0);
+        assert_eq!(find_matching_font(bs.as_ptr(), b"Tuffy\0".as_ptr(), italic), -1);
+        // Other flags are ignored.
+        let outline = (MacStyle::BOLD | MacStyle::OUTLINE).bits() as i32;
+        assert_eq!(find_matching_font(bs.as_ptr(), b"Tuffy\0".as_ptr(), outline), 0);
+    }
+}
+
+#[test]
+fn caret_slope() {
+    let font = tuffy();
+    assert_eq!(font.hhea().caret_slope(), (1, 0, 0));
+
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.hhea().caret_slope(), (1, 0, 0));
+}
+
+#[test]
+fn rendering_behavior() {
+    let font = tuffy();
+    let behavior = font.rendering_behavior(16);
+    assert!(!behavior.gridfit && behavior.do_gray);
+
+    // Without a gasp table.
+    let data = font_with_compound_glyph(&[0, 0x02, 0, 1, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.rendering_behavior(16), RenderingBehavior::default());
+    assert!(font.rendering_behavior(16).gridfit);
+}
+
+#[test]
+fn pixel_metrics() {
+    let font = tuffy();
+
+    // Without hdmx and VDMX tables the scaled metrics are rounded.
+    let scale = font.scale_for_mapping_em_to_pixels(16.0);
+    let i = font.glyph_index_for_code('A');
+    let options = LayoutOptions { pixel_grid: true, ..LayoutOptions::default() };
+    let glyphs = font.layout("AVA", scale, &options);
+    assert_eq!(glyphs[0].advance, (font.advance_width(i) as f32 * scale).round());
+    assert!(glyphs.iter().all(|g| g.x == g.x.round() && g.advance == g.advance.round()));
+    let (ascent, descent) = font.pixel_vertical_extents(16);
+    assert_eq!(ascent, (font.hhea().ascent() as f32 * scale).round() as i32);
+    assert_eq!(descent, (font.hhea().descent() as f32 * scale).round() as i32);
+    assert!(descent < 0 && ascent > 0);
+}
+
+#[test]
+fn size_units() {
+    let font = tuffy();
+    let scale = font.scale_for_px(16.0);
+    assert_eq!(scale, font.scale_for_mapping_em_to_pixels(16.0));
+    assert_eq!(scale * font.head().units_per_em(), 16.0);
+    assert!(font.scale_for_pixel_height(16.0) < scale);
+    // 12 points are 16 pixels at 96 DPI, and 12 pixels at 72 DPI.
+    assert_eq!(font.scale_for_pt(12.0, 96.0), scale);
+    assert_eq!(font.scale_for_pt(12.0, 72.0), font.scale_for_px(12.0));
+    assert_eq!(font.px_per_em(scale), 16.0);
+}
+
+#[test]
+fn line_metrics() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    let hhea = font.line_metrics(MetricsSource::Hhea);
+    assert_eq!(hhea, LineMetrics { ascent: 1950, descent: -505, line_gap: 0 });
+    assert_eq!(hhea.line_height(), 2455);
+    assert_eq!(font.line_metrics(MetricsSource::Typo),
+        LineMetrics { ascent: 1597, descent: -505, line_gap: 0 });
+    // The Windows extents are smaller than those of hhea, the gap makes up
+    // for it.
+    let win = font.line_metrics(MetricsSource::Win);
+    assert_eq!(win, LineMetrics { ascent: 1950, descent: -451, line_gap: 54 });
+    assert_eq!(win.line_height(), hhea.line_height());
+    assert!(font.os2().unwrap().use_typo_metrics());
+
+    // Without an OS/2 table all sources are hhea.
+    let mut data = bs.to_vec();
+    let record = (0..data.len()).step_by(16).map(|i| i + 12)
+        .find(|&i| &data[i..i + 4] == b"OS/2").unwrap();
+    data[record + 3] = b'3';
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert!(font.os2().is_none());
+    assert_eq!(font.line_metrics(MetricsSource::Win), font.line_metrics(MetricsSource::Hhea));
+}
+
+#[test]
+fn decoration_metrics() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    assert_eq!(font.decoration_metrics(), DecorationMetrics {
+        underline_position: -255, underline_thickness: 102,
+        strikeout_position: 530, strikeout_thickness: 102,
+    });
+
+    // Without the tables the lines are guessed from the other metrics.
+    let mut data = bs.to_vec();
+    let num_tables = (data[4] as usize) << 8 | data[5] as usize;
+    for i in (12..12 + 16 * num_tables).step_by(16) {
+        if &data[i..i + 4] == b"OS/2" || &data[i..i + 4] == b"post" {
+            data[i] = b'x';
+        }
+    }
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert!(font.post().is_none() && font.os2().is_none());
+    assert_eq!(font.decoration_metrics(), DecorationMetrics {
+        underline_position: -252, underline_thickness: 146,
+        strikeout_position: 650, strikeout_thickness: 146,
+    });
+
+    // Tables that can't be read are the same, they don't reject the font.
+    let mut data = bs.to_vec();
+    let end = [(data.len() >> 24) as u8, (data.len() >> 16) as u8, (data.len() >> 8) as u8, data.len() as u8];
+    for i in (12..12 + 16 * num_tables).step_by(16) {
+        if &data[i..i + 4] == b"OS/2" || &data[i..i + 4] == b"post" {
+            data[i + 8..i + 12].copy_from_slice(&end);
+        }
+    }
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert!(font.post().is_none() && font.os2().is_none());
+    assert_eq!(font.decoration_metrics().underline_thickness, 146);
+    assert_eq!(font.load_tables(), Err(Error::Malformed));
+}
+
+#[test]
+fn letter_heights() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    assert_eq!(font.x_height(1.0), 1073.0);
+    assert_eq!(font.cap_height(0.5), 728.0);
+
+    // Without OS/2 the glyphs are measured.
+    let mut data = bs.to_vec();
+    let record = (0..data.len()).step_by(16).map(|i| i + 12)
+        .find(|&i| &data[i..i + 4] == b"OS/2").unwrap();
+    data[record] = b'x';
+    let measured = FontInfo::new_with_offset(&data, 0).unwrap();
+    let bbox = |c| font.glyph_data_for_glyph_at_index(font.glyph_index_for_code(c))
+        .bounding_box().unwrap();
+    assert_eq!(measured.x_height(1.0), bbox('x').y1 as f32);
+    assert_eq!(measured.cap_height(1.0), bbox('H').y1 as f32);
+    assert!((measured.x_height(1.0) - font.x_height(1.0)).abs() < 30.0);
+}
+
+#[test]
+fn font_without_baselines() {
+    let font = tuffy();
+    assert!(font.base().horizontal().is_empty());
+    assert_eq!(font.baseline_offset(b"latn", &BASE::ROMAN), None);
+}
+
+#[test]
+fn permissions_and_script_tags() {
+    let font = tuffy();
+    assert_eq!(font.os2().unwrap().fs_type(), 0);
+    let permissions = font.permissions();
+    assert_eq!(permissions.embedding, Embedding::Installable);
+    assert!(!permissions.no_subsetting && !permissions.bitmap_only);
+    assert!(font.script_tags().is_empty());
+}
+
+#[test]
+fn codepoints() {
+    let font = tuffy();
+    assert!(font.supports('a') && font.supports('л'));
+    assert!(!font.supports('\u{4e00}'));
+    let codepoints: Vec<_> = font.codepoints().collect();
+    assert!(codepoints.contains(&'a') && !codepoints.contains(&'\u{4e00}'));
+    assert!(codepoints.iter().all(|&c| font.supports(c)));
+}
+
+#[test]
+fn codepoints_for_glyph() {
+    let font = tuffy();
+    let i = font.glyph_index_for_code('a');
+    let before = font.memory_usage().tables;
+    assert!(font.codepoints_for_glyph(i).contains(&'a'));
+    // The map is kept for the next glyph.
+    assert!(font.memory_usage().tables > before);
+    assert!(font.codepoints_for_glyph(GlyphId(0)).is_empty());
+
+    let map = font.codepoints_by_glyph();
+    assert_eq!(map[&i], font.codepoints_for_glyph(i));
+    assert!(!map.contains_key(&GlyphId(0)));
+    assert_eq!(map.values().map(|codes| codes.len()).sum::<usize>(), font.codepoints().count());
+}
+
+#[test]
+fn glyphs_beyond_65535() {
+    let mut builder = FontBuilder::new("Beyond", 1000);
+    builder.add_glyph(vec![], 500);
+    let square = builder.add_glyph(vec![vec![OutlinePoint::on(0, 0), OutlinePoint::on(0, 100),
+        OutlinePoint::on(100, 100), OutlinePoint::on(100, 0)]], 300);
+    builder.map_code('\u{1f4a7}', square);
+    let mut data = builder.build().unwrap();
+    // Map the character to glyph 65537 in the format 12 subtable.
+    let group = [0, 1, 0xf4, 0xa7, 0, 1, 0xf4, 0xa7, 0, 0, 0, 1];
+    let at = data.windows(group.len()).position(|w| w == group).unwrap();
+    data[at + 8..at + 12].copy_from_slice(&[0, 1, 0, 1]);
+
+    // The glyph is missing rather than glyph 1.
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.glyph_index_for_code('\u{1f4a7}'), GlyphId(0));
+    let atlas = Atlas::bake(&data, 0, 20.0, 64, 64, '\u{1f4a7}', 1).unwrap();
+    let scale = font.scale_for_pixel_height(20.0);
+    assert_eq!(atlas.baked_char('\u{1f4a7}').unwrap().xadvance, scale * 500.0);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_atlas() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, ' ', 95).unwrap();
+    let json = serde_json::to_string(&atlas).unwrap();
+    let copy: Atlas = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy.bitmap, atlas.bitmap);
+    assert_eq!(copy.len(), atlas.len());
+    assert_eq!(copy.quad('A', 10.0, 20.0), atlas.quad('A', 10.0, 20.0));
+    // A bitmap whose size doesn't match its pixels is rejected.
+    let json = r#"{"x":0,"y":0,"width":3,"height":2,"pixels":[0,0,0]}"#;
+    assert!(serde_json::from_str::<GrayBitmap>(json).is_err());
+
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    let metric = font.hmetric_for_glyph_at_index(font.glyph_index_for_code('A'));
+    let json = serde_json::to_string(&metric).unwrap();
+    assert_eq!(serde_json::from_str::<LongHorizontalMetric>(&json).unwrap(), metric);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn atlas_metadata() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, 'A', 2).unwrap();
+    let mut meta = vec![];
+    atlas.write_metadata(&mut meta).unwrap();
+    let meta = String::from_utf8(meta).unwrap();
+    let lines: Vec<_> = meta.lines().collect();
+    assert_eq!(lines[0], "atlas 256 256 2");
+    let b = atlas.baked_char('B').unwrap();
+    assert_eq!(lines[2], format!("66 {} {} {} {} {} {} {}", b.x0, b.y0, b.x1, b.y1,
+        b.xoff, b.yoff, b.xadvance));
+    assert_eq!(lines.len(), 3);
+
+    // The surrogates have no lines.
+    let atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, '\u{d7ff}', 3).unwrap();
+    let mut meta = vec![];
+    atlas.write_metadata(&mut meta).unwrap();
+    let meta = String::from_utf8(meta).unwrap();
+    let lines: Vec<_> = meta.lines().collect();
+    assert_eq!(lines[0], "atlas 256 256 1");
+    assert_eq!(lines.len(), 2);
+}
+
+#[test]
+fn bake_oversampled() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let (w, h) = (256, 256);
+    let mut pixels = vec![0; w * h];
+    let mut baked = vec![BakedChar::default(); 95];
+    let mut oversampled = vec![0; w * h];
+    let mut packed = vec![PackedChar::default(); 95];
+    unsafe {
+        let rows = bake_font_bitmap(&bs[..], 0, 20.0, pixels.as_mut_ptr(), w as isize, h as isize,
+            ' ', 95, baked.as_mut_ptr()).unwrap();
+        let rows_1x = bake_font_bitmap_oversampled(&bs[..], 0, 20.0, oversampled.as_mut_ptr(),
+            w as isize, h as isize, ' ', 95, 1, 1, packed.as_mut_ptr()).unwrap();
+        assert_eq!(rows_1x, rows);
+    }
+    // Without oversampling the bitmaps are the same.
+    assert_eq!(oversampled, pixels);
+    for (b, p) in baked.iter().zip(&packed) {
+        assert_eq!((p.x0, p.y0, p.x1, p.y1), (b.x0, b.y0, b.x1, b.y1));
+        assert_eq!((p.xoff, p.yoff, p.xadvance), (b.xoff, b.yoff, b.xadvance));
+    }
+
+    unsafe {
+        assert!(bake_font_bitmap_oversampled(&bs[..], 0, 20.0, oversampled.as_mut_ptr(),
+            w as isize, h as isize, ' ', 95, 3, 1, packed.as_mut_ptr()).unwrap() > 0);
+    }
+    // The glyphs are 3 times as wide in the bitmap, but not on the screen.
+    let (b, p) = (&baked['H' as usize - 32], &packed['H' as usize - 32]);
+    assert!(p.x1 - p.x0 >= 3 * (b.x1 - b.x0) - 3);
+    assert_eq!(p.y1 - p.y0, b.y1 - b.y0);
+    let (quad, _) = p.quad(w, h, 0.0, 0.0, false);
+    assert!((quad.x1 - quad.x0 - (b.x1 - b.x0) as f32).abs() <= 1.0);
+    assert_eq!(p.xadvance, b.xadvance);
+}
+
+#[test]
+fn pack_ranges_with_settings() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let (w, h) = (512, 512);
+    let mut pixels = vec![0; w * h];
+    let mut plain = vec![PackedChar::default(); 26];
+    let mut oversampled = vec![PackedChar::default(); 26];
+    let settings = PackSettings { h_oversample: 3, v_oversample: 2, padding: Some(2), ..PackSettings::default() };
+    let mut ranges = [
+        PackRange::new(20.0, 'a', 26, plain.as_mut_ptr()),
+        PackRange::new(20.0, 'a', 26, oversampled.as_mut_ptr()).with_settings(settings),
+    ];
+    unsafe {
+        let mut spc = PackContext::default();
+        assert_eq!(pack_begin(&mut spc, pixels.as_mut_ptr(), w as isize, h as isize, 0, 1,
+            null_mut()), 1);
+        assert_eq!(pack_font_ranges(&mut spc, &bs[..], 0, ranges.as_mut_ptr(), 2).unwrap(), 1);
+
+        // Wrong checksums don't matter, wrong indices are errors.
+        let mut data = bs.to_vec();
+        let tables = (data[4] as usize) << 8 | data[5] as usize;
+        let record = (0..tables).map(|i| 12 + i * 16).find(|&r| &data[r..r + 4] == b"head").unwrap();
+        let head = (0..4).fold(0, |offset, i| offset << 8 | data[record + 8 + i] as usize);
+        data[head + 8] ^= 0xff;
+        assert_eq!(pack_font_ranges(&mut spc, &data, 0, ranges.as_mut_ptr(), 1).unwrap(), 1);
+        assert_eq!(pack_font_ranges(&mut spc, &data, -1, ranges.as_mut_ptr(), 1), Err(Error::Malformed));
+        assert_eq!(pack_font_ranges(&mut spc, &data, 1, ranges.as_mut_ptr(), 1), Err(Error::Malformed));
+        pack_end(&mut spc);
+    }
+
+    let (p, o) = (&plain['m' as usize - 'a' as usize], &oversampled['m' as usize - 'a' as usize]);
+    assert!(o.x1 - o.x0 >= 3 * (p.x1 - p.x0) - 3);
+    assert!(o.y1 - o.y0 >= 2 * (p.y1 - p.y0) - 2);
+    assert_eq!(o.xadvance, p.xadvance);
+    // On the screen both have about the same size.
+    assert!(((o.xoff2 - o.xoff) - (p.xoff2 - p.xoff)).abs() <= 1.0);
+    assert!(pixels.iter().any(|&p| p != 0));
+}
+
+#[test]
+fn pack_padding_of_context() {
+    let pack = |padding, settings| {
+        let (w, h) = (512, 512);
+        let mut pixels = vec![0; w * h];
+        let mut chars = vec![PackedChar::default(); 26];
+        let range = PackRange::new(20.0, 'a', 26, chars.as_mut_ptr()).with_settings(settings);
+        pack_tuffy(&mut pixels, w, h, padding, &mut [range]);
+        chars
+    };
+    let gaps = |chars: &[PackedChar]| {
+        let mut rects: Vec<_> = chars.iter().filter(|c| c.x1 > c.x0).collect();
+        rects.sort_by_key(|c| (c.y0, c.x0));
+        rects.windows(2).filter(|w| w[0].y0 == w[1].y0).map(|w| w[1].x0 - w[0].x1).min().unwrap()
+    };
+    // The characters are as far apart as the context says, unless the
+    // range sets its own padding.
+    assert!(gaps(&pack(4, PackSettings::default())) >= 4);
+    assert_eq!(gaps(&pack(4, PackSettings { padding: Some(1), ..PackSettings::default() })), 1);
+}
+
+#[test]
+fn tent_prefilter() {
+    // Shifted like the box filter, but weighing the middle twice.
+    let mut row = [0, 0, 255, 0, 0, 0];
+    unsafe { h_prefilter_tent(row.as_mut_ptr(), 6, 1, 6, 3) };
+    assert_eq!(row, [0, 0, 64, 128, 64, 0]);
+    let mut column = [0, 255, 255, 255, 0, 0];
+    unsafe { v_prefilter_tent(column.as_mut_ptr(), 1, 6, 1, 3) };
+    assert_eq!(column, [0, 64, 191, 255, 191, 64]);
+
+    let pack = |filter| {
+        let (w, h) = (256, 256);
+        let mut pixels = vec![0; w * h];
+        let mut chars = vec![PackedChar::default(); 26];
+        let settings = PackSettings { h_oversample: 3, v_oversample: 3, filter: filter,
+            ..PackSettings::default() };
+        let range = PackRange::new(12.0, 'a', 26, chars.as_mut_ptr())
+            .with_settings(settings);
+        pack_tuffy(&mut pixels, w, h, 1, &mut [range]);
+        (chars, pixels)
+    };
+    let (box_chars, box_pixels) = pack(Prefilter::Box);
+    let (tent_chars, tent_pixels) = pack(Prefilter::Tent);
+    assert_eq!(PackSettings::default().filter, Prefilter::Box);
+    assert_eq!(box_chars, tent_chars);
+    assert!(box_pixels != tent_pixels);
+    // The tent keeps more pixels fully covered or empty.
+    let sharp = |pixels: &[u8]| pixels.iter().filter(|&&p| p == 0 || p == 255).count();
+    assert!(sharp(&tent_pixels) > sharp(&box_pixels));
+}
+
+#[test]
+fn atlas_insert_glyph() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    let mut atlas = Atlas::bake(&bs[..], 0, 32.0, 256, 256, ' ', 95).unwrap();
+    let before = atlas.bitmap.clone();
+
+    // The glyph goes after the baked characters, which stay untouched.
+    let i = font.glyph_index_for_code('л');
+    let c = atlas.insert_glyph(&font, i, 32.0).unwrap();
+    let last = atlas.baked_char('~').unwrap();
+    assert!(c.y0 > last.y0 || (c.y0 == last.y0 && c.x0 > last.x1));
+    for y in 0..256 {
+        for x in 0..256 {
+            let inside = x >= c.x0 as usize && x < c.x1 as usize && y >= c.y0 as usize && y < c.y1 as usize;
+            if !inside {
+                assert_eq!(atlas.bitmap.as_slice()[y * 256 + x], before.as_slice()[y * 256 + x]);
+            }
+        }
+    }
+    assert!(atlas.bitmap != before);
+
+    // The same character baked has the same metrics.
+    let baked = *atlas.baked_char('A').unwrap();
+    let a = atlas.insert_glyph(&font, font.glyph_index_for_code('A'), 32.0).unwrap();
+    assert_eq!((a.xoff, a.yoff, a.xadvance), (baked.xoff, baked.yoff, baked.xadvance));
+    assert_eq!((a.x1 - a.x0, a.y1 - a.y0), (baked.x1 - baked.x0, baked.y1 - baked.y0));
+
+    // Until the atlas is full.
+    let mut inserted = 0;
+    while atlas.insert_glyph(&font, i, 32.0).is_some() {
+        inserted += 1;
+    }
+    assert!(inserted > 0);
+    assert!(atlas.insert_glyph(&font, i, 32.0).is_none());
+}
+
+#[test]
+fn paged_atlas() {
+    let font = tuffy();
+    let mut atlas = PagedAtlas::new(64, 64);
+    assert!(atlas.pages().is_empty());
+
+    let chars: Vec<_> = (33..127u8).map(|c| {
+        atlas.insert_glyph(&font, font.glyph_index_for_code(c as char), 24.0).unwrap()
+    }).collect();
+    assert!(atlas.pages().len() > 1);
+    assert_eq!(chars[0].page, 0);
+    assert_eq!(chars.last().unwrap().page as usize, atlas.pages().len() - 1);
+    // Pages fill up in order.
+    assert!(chars.windows(2).all(|w| w[0].page <= w[1].page));
+    assert!(atlas.pages().iter().all(|page| page.bitmap.as_slice().iter().any(|&p| p != 0)));
+
+    // Too large for any page.
+    let i = font.glyph_index_for_code('W');
+    assert!(atlas.insert_glyph(&font, i, 200.0).is_none());
+    assert_eq!(atlas.pages().len() - 1, chars.last().unwrap().page as usize);
+}
+
+#[test]
+fn pack_with_bleeding_edges() {
+    let (w, h) = (256, 256);
+    let mut pixels = vec![0; w * h];
+    let mut chars = vec![PackedChar::default(); 26];
+    let settings = PackSettings { padding: Some(2), bleed: true, ..PackSettings::default() };
+    let range = PackRange::new(-30.0, 'A', 26, chars.as_mut_ptr()).with_settings(settings);
+    pack_tuffy(&mut pixels, w, h, 1, &mut [range]);
+
+    // 'I' covers the whole left column of its bitmap, which bleeds left.
+    let c = &chars['I' as usize - 'A' as usize];
+    let (x, y) = (c.x0 as usize, c.y0 as usize + (c.y1 - c.y0) as usize / 2);
+    assert!(pixels[y * w + x] > 0);
+    assert_eq!(pixels[y * w + x - 1], pixels[y * w + x]);
+    assert_eq!(pixels[y * w + x - 2], pixels[y * w + x]);
+    // The gutters of neighbors don't overlap the characters.
+    for a in &chars {
+        for b in &chars {
+            if a as *const _ != b as *const _ {
+                let apart = a.x1 + 2 <= b.x0 - 2 || b.x1 + 2 <= a.x0 - 2
+                    || a.y1 + 2 <= b.y0 - 2 || b.y1 + 2 <= a.y0 - 2;
+                assert!(apart);
+            }
+        }
+    }
+}
+
+#[test]
+fn stride_alignment() {
+    assert_eq!(aligned_stride(61, 4), 64);
+    assert_eq!(aligned_stride(64, 4), 64);
+    assert_eq!(aligned_stride(61, 1), 61);
+    assert_eq!(aligned_stride(61, 0), 61);
+
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    let mut atlas = PagedAtlas::new(61, 64).with_stride_alignment(4);
+    for c in 33..127u8 {
+        atlas.insert_glyph(&font, font.glyph_index_for_code(c as char), 24.0).unwrap();
+    }
+    for page in atlas.pages() {
+        assert_eq!(page.bitmap.width(), 64);
+        assert!(page.bitmap.as_slice().chunks(64).all(|row| row[61..].iter().all(|&p| p == 0)));
+    }
+
+    // Packing into rows with a stride clears the padding.
+    let (w, h, stride) = (61, 64, aligned_stride(61, 4));
+    let mut pixels = vec![0xff; stride * h];
+    let mut chars = vec![PackedChar::default(); 3];
+    unsafe {
+        let mut spc = PackContext::default();
+        pack_begin(&mut spc, pixels.as_mut_ptr(), w as isize, h as isize, stride as isize, 1,
+            null_mut());
+        assert_eq!(pack_font_range(&mut spc, &bs[..], 0, 20.0, 'a', 3,
+            chars.as_mut_ptr(), PackSettings::default()).unwrap(), 1);
+        pack_end(&mut spc);
+    }
+    assert!(pixels.chunks(stride).all(|row| row[w..].iter().all(|&p| p == 0)));
+    assert!(chars.iter().all(|c| (c.x1 as usize) < w));
+}
+
+#[test]
+fn kerned_atlas_quads() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    let atlas = Atlas::bake(&bs[..], 0, 64.0, 512, 512, ' ', 95).unwrap();
+
+    // Find a kerned pair of printable characters.
+    let chars: Vec<char> = (33..127u8).map(|c| c as char).collect();
+    let glyph = |c: char| font.glyph_index_for_code(c);
+    let (a, b) = chars.iter().flat_map(|&a| chars.iter().map(move |&b| (a, b)))
+        .find(|&(a, b)| font.kerning(glyph(a), glyph(b)) < 0).unwrap();
+    let text: String = vec![a, b, '\u{4e00}', b].into_iter().collect();
+
+    let quads: Vec<_> = atlas.quads(&font, &text, 10.0, 100.0).collect();
+    assert_eq!(quads.len(), 3);
+    let (first, next) = atlas.quad(a, 10.0, 100.0).unwrap();
+    assert_eq!(quads[0], first);
+    // The second character moves left by the kerning.
+    let (unkerned, _) = atlas.quad(b, next, 100.0).unwrap();
+    assert!(quads[1].x0 < unkerned.x0);
+    assert_eq!(quads[1].s0, unkerned.s0);
+}
+
+#[test]
+fn pack_subpixel_phases() {
+    let (w, h) = (256, 256);
+    let mut pixels = vec![0; w * h];
+    let mut chars = vec![PackedChar::default(); 26 * 3];
+    let settings = PackSettings { subpixel_phases: 3, ..PackSettings::default() };
+    let range = PackRange::new(14.0, 'a', 26, chars.as_mut_ptr()).with_settings(settings);
+    pack_tuffy(&mut pixels, w, h, 1, &mut [range]);
+
+    // Every phase of 'o' is rendered on its own, shifted by a third of a pixel.
+    let o = 'o' as usize - 'a' as usize;
+    let phases = &chars[o * 3..o * 3 + 3];
+    let bitmap = |c: &PackedChar| -> Vec<u8> {
+        (c.y0..c.y1).flat_map(|y| (c.x0..c.x1).map(move |x| (x, y)))
+            .map(|(x, y)| pixels[y as usize * w + x as usize]).collect()
+    };
+    assert!(bitmap(&phases[0]) != bitmap(&phases[1]));
+    assert!(phases.iter().all(|c| c.xadvance == phases[0].xadvance));
+    assert!(phases[1].xoff < phases[0].xoff);
+
+    // The phase nearest to the fraction of the position is drawn.
+    for &(x, phase) in &[(10.0, 0), (10.3, 1), (10.7, 2), (10.9, 0)] {
+        let (mut xpos, mut ypos) = (x, 20.0);
+        let mut q = AlignedQuad::default();
+        unsafe {
+            get_packed_quad_phased(chars.as_mut_ptr(), w as isize, h as isize, o as isize, 3,
+                &mut xpos, &mut ypos, &mut q, 1);
+        }
+        let c = &chars[o * 3 + phase];
+        assert_eq!(q.s0, c.x0 as f32 / w as f32);
+        assert_eq!(q.x0, (x + c.xoff + 0.5).floor());
+        assert_eq!(xpos, x + c.xadvance);
+    }
+}
+
+#[test]
+fn kern_cache() {
+    let font = tuffy();
+    let cache = font.build_kern_cache();
+    assert_eq!(cache.len(), font.kern().pairs().count());
+    for left in 0..100 {
+        for right in 0..100 {
+            let pair = (GlyphId(left), GlyphId(right));
+            let kerning = cache.get(&pair).cloned().unwrap_or(0);
+            assert_eq!(kerning, font.kerning(pair.0, pair.1));
+        }
+    }
+    assert_eq!(cache.get(&(GlyphId(6), GlyphId(7))), Some(&-55));
+}
+
+#[test]
+fn right_to_left_layout() {
+    let font = tuffy();
+    let scale = font.scale_for_pixel_height(20.0);
+    let glyph = |c: char| font.glyph_index_for_code(c);
+
+    let ltr = font.layout("a(b", scale, &LayoutOptions::default());
+    assert_eq!(ltr.iter().map(|g| g.glyph).collect::<Vec<_>>(),
+        vec![glyph('a'), glyph('('), glyph('b')]);
+    assert_eq!(ltr[0].x, 0.0);
+    assert_eq!(ltr[1].x, ltr[0].advance);
+
+    // Reversed, with the bracket mirrored, keeping the offsets in the text.
+    let options = LayoutOptions { direction: Direction::RightToLeft, ..LayoutOptions::default() };
+    let rtl = font.layout("a(b", scale, &options);
+    assert_eq!(rtl.iter().map(|g| g.glyph).collect::<Vec<_>>(),
+        vec![glyph('b'), glyph(')'), glyph('a')]);
+    assert_eq!(rtl.iter().map(|g| g.cluster).collect::<Vec<_>>(), vec![2, 1, 0]);
+    assert_eq!(rtl[0].x, 0.0);
+    let width = |glyphs: &[PositionedGlyph]| glyphs.last().map(|g| g.x + g.advance).unwrap();
+    assert!((width(&rtl) - width(&ltr)).abs() < 1.0);
+
+    // Kerned like left-to-right text in visual order.
+    let ltr = font.layout("AV", scale, &LayoutOptions::default());
+    let rtl = font.layout("VA", scale, &options);
+    assert_eq!(rtl, ltr.iter().map(|&g| PositionedGlyph { cluster: 1 - g.cluster, ..g })
+        .collect::<Vec<_>>());
+    assert!(font.layout("", scale, &options).is_empty());
+}
+
+#[test]
+fn render_shaped_line() {
+    let font = tuffy();
+    let scale = font.scale_for_pixel_height(20.0);
+
+    // Shaping without changing anything gives the glyphs of the layout.
+    let glyphs = font.layout("Hi", scale, &LayoutOptions::default());
+    let shaped: Vec<ShapedGlyph> = glyphs.iter().map(|g| ShapedGlyph {
+        glyph: g.glyph.0 as u32,
+        cluster: g.cluster as u32,
+        x_advance: font.advance_width(g.glyph) as i32,
+        ..ShapedGlyph::default()
+    }).collect();
+    assert_eq!(from_shaped(&shaped, scale), glyphs);
+
+    let line = rasterize_path(&font.line_path(&glyphs, scale).unwrap(), &RasterOptions::default());
+    let h = rasterize_path(&font.glyph_path(glyphs[0].glyph, scale).unwrap(),
+        &RasterOptions::default());
+    // The dot of the 'i' is above the 'H'.
+    let top = (h.y - line.y) as usize;
+    assert_eq!((line.x, line.height()), (h.x, h.height() + top));
+    assert!(line.width() > h.width());
+    // The line starts with the 'H'.
+    for y in 0..h.height() {
+        assert_eq!(line.as_slice()[(y + top) * line.width()], h.as_slice()[y * h.width()]);
+    }
+}
+
+#[test]
+fn layout_without_kerning() {
+    let font = tuffy();
+    let kerned = font.layout("AV", 1.0, &LayoutOptions::default());
+    let options = LayoutOptions::default().features(&[("kern", false)]);
+    let unkerned = font.layout("AV", 1.0, &options);
+    assert_eq!(unkerned[1].x, kerned[0].advance);
+    let kerning = font.kerning(kerned[0].glyph, kerned[1].glyph);
+    assert_eq!(kerned[1].x - unkerned[1].x, kerning as f32);
+    assert!(kerned[1].x < unkerned[1].x);
+}
+
+#[cfg(feature = "tessellation")]
+#[test]
+fn glyph_meshes() {
+    let font = tuffy();
+    let scale = font.scale_for_pixel_height(100.0);
+    for c in "B8o%@g".chars() {
+        let glyph = font.glyph_index_for_code(c);
+        let path = font.glyph_path(glyph, scale).unwrap();
+        let mesh = font.glyph_mesh(glyph, scale, 0.35).unwrap();
+        let mut area = 0.0;
+        for [p, q, r] in mesh.triangles() {
+            // Clockwise with the y axis pointing down.
+            let doubled = (q.0 - p.0) * (r.1 - p.1) - (r.0 - p.0) * (q.1 - p.1);
+            assert!(doubled > 0.0, "{}", c);
+            area += doubled / 2.0;
+            let center = ((p.0 + q.0 + r.0) / 3.0, (p.1 + q.1 + r.1) / 3.0);
+            assert!(path_contains(&path, center.0, center.1, FillRule::NonZero), "{}", c);
+        }
+        // The triangles cover as much as the pixels of the bitmap.
+        let bitmap = rasterize_path(&path, &RasterOptions::default());
+        let covered = bitmap.as_slice().iter().map(|&p| p as f32 / 255.0).sum::<f32>();
+        assert!((area - covered).abs() < covered * 0.01, "{}: {} {}", c, area, covered);
+    }
+}
+
+#[cfg(feature = "tessellation")]
+#[test]
+fn extruded_glyphs() {
+    let font = tuffy();
+    let scale = font.scale_for_pixel_height(100.0);
+    let glyph = font.glyph_index_for_code('o');
+    let solid = font.extrude_glyph(glyph, scale, 10.0).unwrap();
+    assert_eq!(solid.positions.len(), solid.normals.len());
+    assert!(solid.positions.iter().all(|p| p.2 == 0.0 || p.2 == -10.0));
+
+    // The y axis points up, the glyph stands on the baseline.
+    let bitmap = rasterize_path(&font.glyph_path(glyph, scale).unwrap(), &RasterOptions::default());
+    let top = solid.positions.iter().fold(0.0f32, |m, p| m.max(p.1));
+    assert!((top + bitmap.y as f32).abs() < 1.0, "{} {}", top, bitmap.y);
+
+    // The solid is as big as the front times the depth.
+    let front = font.glyph_mesh(glyph, scale, 0.1).unwrap().triangles()
+        .map(|[p, q, r]| ((q.0 - p.0) * (r.1 - p.1) - (r.0 - p.0) * (q.1 - p.1)) / 2.0)
+        .sum::<f32>();
+    let mut volume = 0.0;
+    for t in solid.indices.chunks(3) {
+        let (p, q, r) = (solid.positions[t[0] as usize], solid.positions[t[1] as usize],
+            solid.positions[t[2] as usize]);
+        volume += (p.0 * (q.1 * r.2 - q.2 * r.1) + p.1 * (q.2 * r.0 - q.0 * r.2)
+            + p.2 * (q.0 * r.1 - q.1 * r.0)) / 6.0;
+    }
+    assert!((volume - front * 10.0).abs() < front * 0.01, "{} {}", volume, front);
+}
+
+#[cfg(feature = "tessellation")]
+#[test]
+fn curve_meshes() {
+    let font = tuffy();
+    let scale = font.scale_for_pixel_height(100.0);
+    let cross = |a: (f32, f32), b: (f32, f32), p: (f32, f32)| {
+        (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+    };
+    for c in "BOS8g@".chars() {
+        let glyph = font.glyph_index_for_code(c);
+        let path = font.glyph_path(glyph, scale).unwrap();
+        let mesh = font.glyph_curve_mesh(glyph, scale).unwrap();
+        // What a fragment shader keeps at the middle of every pixel.
+        let kept = |p: (f32, f32)| mesh.indices.chunks(3).any(|t| {
+            let corners: Vec<CurveVertex> = t.iter().map(|&i| mesh.vertices[i as usize]).collect();
+            let [a, b, c] = [corners[0], corners[1], corners[2]];
+            let area = cross(a.position, b.position, c.position);
+            let w = [cross(b.position, c.position, p) / area,
+                cross(c.position, a.position, p) / area,
+                cross(a.position, b.position, p) / area];
+            let u = w[0] * a.uv.0 + w[1] * b.uv.0 + w[2] * c.uv.0;
+            let v = w[0] * a.uv.1 + w[1] * b.uv.1 + w[2] * c.uv.1;
+            w.iter().all(|&w| w >= 0.0) && a.sign * (u * u - v) <= 0.0
+        });
+        let bitmap = rasterize_path(&path, &RasterOptions::default());
+        let mut wrong = 0;
+        for y in 0..bitmap.height() {
+            for x in 0..bitmap.width() {
+                let p = (bitmap.x as f32 + x as f32 + 0.5, bitmap.y as f32 + y as f32 + 0.5);
+                // The bitmap is only sure about pixels it covers fully or not at all.
+                let coverage = bitmap.as_slice()[y * bitmap.width() + x];
+                if (coverage == 255 || coverage == 0) && kept(p) != (coverage == 255) {
+                    wrong += 1;
+                }
+            }
+        }
+        assert_eq!(wrong, 0, "{}", c);
+    }
+}
+
+#[test]
+fn memory_usage() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    let borrowed = font.memory_usage();
+    assert_eq!(borrowed.data, 0);
+    // At least the locations of the glyphs.
+    assert!(borrowed.tables > 400 * 4);
+    let owned = FontInfo::from_vec(bs.to_vec(), 0).unwrap().memory_usage();
+    assert_eq!(owned.data, bs.len());
+    assert_eq!(owned.total(), bs.len() + owned.tables);
+    assert!(owned.tables >= borrowed.tables);
+
+    let mut cache = GlyphCache::new(1 << 16);
+    for c in "memory".chars() {
+        cache.get(&font, font.glyph_index_for_code(c), 0.05).unwrap();
+    }
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses), (1, 5));
+    assert!(stats.bytes <= 1 << 16 && stats.evictions == 0);
+}
+
+#[test]
+fn lazy_tables() {
+    let bs = include_bytes!("../assets/Tuffy_Bold.ttf");
+    let font = FontInfo::new_with_offset(&bs[..], 0).unwrap();
+    let loaded = font.memory_usage().tables;
+    let glyph = font.glyph_index_for_code('A');
+    assert!(glyph != GlyphId(0));
+    // The character map doesn't copy the data, it only keeps the glyphs of
+    // ASCII characters, the glyph locations are read.
+    assert_eq!(font.memory_usage().tables, loaded + 128 * 2);
+    font.glyph_data_for_glyph_at_index(glyph);
+    assert!(font.memory_usage().tables > loaded);
+    assert_eq!(font.load_tables(), Ok(()));
+
+    // A character map without subtables is only read on first use.
+    let mut data = bs.to_vec();
+    let tables = (data[4] as usize) << 8 | data[5] as usize;
+    let record = (0..tables).map(|i| 12 + i * 16).find(|&r| &data[r..r + 4] == b"cmap").unwrap();
+    let cmap = (0..4).fold(0, |offset, i| offset << 8 | data[record + 8 + i] as usize);
+    data[cmap + 2..cmap + 4].copy_from_slice(&[0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.glyph_index_for_code('A'), GlyphId(0));
+    assert_eq!(font.load_tables(), Err(Error::CMAPEncodingSubtableIsNotSupported));
+    assert!(FontInfo::parse_strict(&data).is_err());
+    assert!(font.glyph_path(GlyphId(5), 1.0).is_ok());
+
+    // Glyph outlines past the end of the data are empty.
+    let mut data = bs.to_vec();
+    let record = (0..tables).map(|i| 12 + i * 16).find(|&r| &data[r..r + 4] == b"glyf").unwrap();
+    data[record + 8..record + 12].copy_from_slice(&[0x47, 0, 0, 0]);
+    let font = FontInfo::new_with_offset(&data, 0).unwrap();
+    assert_eq!(font.load_tables(), Err(Error::Malformed));
+    let glyph = font.glyph_index_for_code('A');
+    assert_eq!(font.glyph_data_for_glyph_at_index(glyph).number_of_contours(), 0);
+    assert_eq!(font.glyph_path(glyph, 1.0), Ok(vec![]));
+    assert_eq!(font.layout("A", 1.0, &LayoutOptions::default()).len(), 1);
+    assert_eq!(font.glyphs().count(), 0);
+}
+
+#[test]
+fn glyphs_with_outlines() {
+    let font = tuffy();
+    let glyphs: Vec<_> = font.glyphs().collect();
+    assert!(glyphs.iter().any(|&(id, _)| id == font.glyph_index_for_code('A')));
+    assert!(glyphs.iter().all(|&(id, _)| id != font.glyph_index_for_code(' ')));
+    assert!(glyphs.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    for &(id, ref data) in &glyphs {
+        assert!(data.number_of_contours() != 0);
+        assert_eq!(data.outline().unwrap(), font.glyph_outline(id).unwrap());
+    }
+    let empty = (0..890).filter(|&i| font.glyph_path(GlyphId(i), 1.0).unwrap().is_empty()).count();
+    assert_eq!(glyphs.len(), 890 - empty);
+}